@@ -847,6 +847,16 @@ impl FakeFs {
         });
     }
 
+    pub fn set_diff_for_repo(&self, dot_git: &Path, staged: bool, diff: impl Into<String>) {
+        self.with_git_state(dot_git, true, |state| {
+            if staged {
+                state.staged_diff = diff.into();
+            } else {
+                state.unstaged_diff = diff.into();
+            }
+        });
+    }
+
     pub fn set_status_for_repo_via_working_copy_change(
         &self,
         dot_git: &Path,
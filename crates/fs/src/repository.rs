@@ -60,6 +60,9 @@ pub trait GitRepository: Send {
     fn create_branch(&self, _: &str) -> Result<()>;
 
     fn blame(&self, path: &Path, content: Rope) -> Result<git::blame::Blame>;
+
+    /// Returns the diff of the working directory against the index or, if `staged`, of the index against the HEAD commit.
+    fn diff(&self, staged: bool) -> Result<String>;
 }
 
 impl std::fmt::Debug for dyn GitRepository {
@@ -248,6 +251,25 @@ impl GitRepository for RealGitRepository {
             remote_url,
         )
     }
+
+    fn diff(&self, staged: bool) -> Result<String> {
+        let working_directory = self
+            .repository
+            .workdir()
+            .context("failed to get git working directory")?;
+        let mut command = std::process::Command::new(&self.git_binary_path);
+        command.current_dir(working_directory).arg("diff");
+        if staged {
+            command.arg("--cached");
+        }
+        let output = command.output().context("failed to run git diff")?;
+        anyhow::ensure!(
+            output.status.success(),
+            "git diff failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
 }
 
 fn matches_index(repo: &LibGitRepository, path: &RepoPath, mtime: SystemTime) -> bool {
@@ -293,6 +315,8 @@ pub struct FakeGitRepositoryState {
     pub blames: HashMap<PathBuf, Blame>,
     pub worktree_statuses: HashMap<RepoPath, GitFileStatus>,
     pub branch_name: Option<String>,
+    pub unstaged_diff: String,
+    pub staged_diff: String,
 }
 
 impl FakeGitRepository {
@@ -366,6 +390,15 @@ impl GitRepository for FakeGitRepository {
             .with_context(|| format!("failed to get blame for {:?}", path))
             .cloned()
     }
+
+    fn diff(&self, staged: bool) -> Result<String> {
+        let state = self.state.lock();
+        Ok(if staged {
+            state.staged_diff.clone()
+        } else {
+            state.unstaged_diff.clone()
+        })
+    }
 }
 
 fn check_path_to_repo_path_errors(relative_file_path: &Path) -> Result<()> {
@@ -1,8 +1,13 @@
+use std::path::PathBuf;
+
 use crate::Location;
 
 use anyhow::Result;
 use gpui::AppContext;
-use task::{static_source::TaskDefinitions, TaskVariables, VariableName};
+use task::{
+    static_source::{Definition, TaskDefinitions},
+    TaskVariables, VariableName, VariableResolver,
+};
 
 /// Language Contexts are used by Zed tasks to extract information about source file.
 pub trait ContextProvider: Send + Sync {
@@ -13,6 +18,28 @@ pub trait ContextProvider: Send + Sync {
     fn associated_tasks(&self) -> Option<TaskDefinitions> {
         None
     }
+
+    /// Suggests a directory to spawn tasks in for the given location, used instead of the worktree root for the tasks with no `cwd` specified.
+    fn suggested_cwd(&self, _: &Location, _: &mut AppContext) -> Option<PathBuf> {
+        None
+    }
+
+    /// Finds the name of the test that encloses the location, even if the location is deep inside of the test's body.
+    /// Found name is available to the tasks as `$ZED_NEAREST_TEST`.
+    fn enclosing_test(&self, _: &Location, _: &mut AppContext) -> Option<String> {
+        None
+    }
+
+    /// Returns a resolver of the version of the project the location belongs to, as declared in the language's manifest file.
+    /// The resolver is only invoked for the tasks referencing `$ZED_PROJECT_VERSION`, so the manifest is not read for every context.
+    fn project_version(&self, _: &Location, _: &mut AppContext) -> Option<VariableResolver> {
+        None
+    }
+
+    /// A task to run a single test, named by `$ZED_NEAREST_TEST`.
+    fn nearest_test_task(&self) -> Option<Definition> {
+        None
+    }
 }
 
 /// A context provider that finds out what symbol is currently focused in the buffer.
@@ -10,10 +10,16 @@ use project::project_settings::ProjectSettings;
 use regex::Regex;
 use settings::Settings;
 use smol::fs::{self, File};
-use std::{any::Any, borrow::Cow, env::consts, path::PathBuf, sync::Arc};
+use std::{
+    any::Any,
+    borrow::Cow,
+    env::consts,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 use task::{
     static_source::{Definition, TaskDefinitions},
-    TaskVariables, VariableName,
+    TaskVariables, VariableName, VariableResolver,
 };
 use util::{
     fs::remove_matching,
@@ -357,6 +363,41 @@ impl ContextProvider for RustContextProvider {
         Ok(context)
     }
 
+    fn suggested_cwd(&self, location: &Location, cx: &mut gpui::AppContext) -> Option<PathBuf> {
+        let file_path = location.buffer.read(cx).file()?.as_local()?.abs_path(cx);
+        closest_cargo_manifest_dir(&file_path)
+    }
+
+    fn enclosing_test(&self, location: &Location, cx: &mut gpui::AppContext) -> Option<String> {
+        let snapshot = location.buffer.read(cx).snapshot();
+        enclosing_test_name(&snapshot, location.range.start)
+    }
+
+    fn project_version(
+        &self,
+        location: &Location,
+        cx: &mut gpui::AppContext,
+    ) -> Option<VariableResolver> {
+        let file_path = location.buffer.read(cx).file()?.as_local()?.abs_path(cx);
+        Some(Arc::new(move || cargo_package_version(&file_path)))
+    }
+
+    fn nearest_test_task(&self) -> Option<Definition> {
+        Some(Definition {
+            label: "Rust: Test nearest test".to_owned(),
+            command: "cargo".into(),
+            args: vec![
+                "test".into(),
+                "-p".into(),
+                RUST_PACKAGE_TASK_VARIABLE.template_value(),
+                VariableName::NearestTest.template_value(),
+                "--".into(),
+                "--nocapture".into(),
+            ],
+            ..Definition::default()
+        })
+    }
+
     fn associated_tasks(&self) -> Option<TaskDefinitions> {
         Some(TaskDefinitions(vec![
             Definition {
@@ -408,6 +449,82 @@ impl ContextProvider for RustContextProvider {
     }
 }
 
+/// Finds the innermost function around the position, that is marked as a test (`#[test]`, `#[tokio::test]` and alike).
+fn enclosing_test_name(snapshot: &BufferSnapshot, position: Anchor) -> Option<String> {
+    let symbols = snapshot.symbols_containing(position, None)?;
+    symbols.iter().rev().find_map(|symbol| {
+        if !symbol.text.split_whitespace().any(|word| word == "fn") {
+            return None;
+        }
+        let item_row = symbol.range.start.to_point(snapshot).row;
+        if !has_test_attribute(snapshot, item_row) {
+            return None;
+        }
+        let name_range = symbol.name_ranges.last()?.clone();
+        Some(symbol.text[name_range].to_string())
+    })
+}
+
+/// Checks the attributes above the item on the given row, skipping other attributes and comments in between.
+fn has_test_attribute(snapshot: &BufferSnapshot, item_row: u32) -> bool {
+    for row in (0..item_row).rev() {
+        let line = snapshot
+            .text_for_range(Point::new(row, 0)..Point::new(row, snapshot.line_len(row)))
+            .collect::<String>();
+        let line = line.trim();
+        if let Some(attribute) = line.strip_prefix("#[") {
+            let attribute_path = attribute
+                .split(|c| c == ']' || c == '(')
+                .next()
+                .unwrap_or_default()
+                .trim();
+            if attribute_path == "test" || attribute_path.ends_with("::test") {
+                return true;
+            }
+        } else if !line.starts_with("//") {
+            return false;
+        }
+    }
+    false
+}
+
+/// Finds the directory of the crate the file belongs to, by looking up the closest `Cargo.toml` in the file's ancestors.
+fn closest_cargo_manifest_dir(file_path: &Path) -> Option<PathBuf> {
+    file_path
+        .ancestors()
+        .skip(1)
+        .find(|dir| dir.join("Cargo.toml").is_file())
+        .map(Path::to_path_buf)
+}
+
+/// Reads the version of the crate the file belongs to from its `Cargo.toml`,
+/// following `version.workspace = true` to the `[workspace.package]` of the manifests above.
+fn cargo_package_version(file_path: &Path) -> Option<String> {
+    let manifest_dir = closest_cargo_manifest_dir(file_path)?;
+    let manifest = read_cargo_manifest(&manifest_dir)?;
+    let version = manifest.get("package")?.get("version")?;
+    if let Some(version) = version.as_str() {
+        return Some(version.to_owned());
+    }
+    if version.get("workspace")?.as_bool()? {
+        manifest_dir.ancestors().find_map(|dir| {
+            read_cargo_manifest(dir)?
+                .get("workspace")?
+                .get("package")?
+                .get("version")?
+                .as_str()
+                .map(str::to_owned)
+        })
+    } else {
+        None
+    }
+}
+
+fn read_cargo_manifest(dir: &Path) -> Option<toml::Value> {
+    let contents = std::fs::read_to_string(dir.join("Cargo.toml")).ok()?;
+    toml::from_str(&contents).log_err()
+}
+
 async fn get_cached_server_binary(container_dir: PathBuf) -> Option<LanguageServerBinary> {
     maybe!(async {
         let mut last = None;
@@ -437,6 +554,141 @@ mod tests {
     use settings::SettingsStore;
     use text::BufferId;
     use theme::SyntaxTheme;
+    use unindent::Unindent;
+
+    #[test]
+    fn test_closest_cargo_manifest_dir() {
+        let dir = util::test::temp_tree(serde_json::json!({
+            "Cargo.toml": "[workspace]",
+            "crates": {
+                "server": {
+                    "Cargo.toml": "[package]",
+                    "src": {
+                        "main.rs": "fn main() {}",
+                        "handlers": {
+                            "mod.rs": "",
+                        },
+                    },
+                },
+            },
+            "scripts": {
+                "build.rs": "",
+            },
+        }));
+        let root = dir.path();
+
+        assert_eq!(
+            closest_cargo_manifest_dir(&root.join("crates/server/src/handlers/mod.rs")),
+            Some(root.join("crates/server")),
+            "Nested crate files should run tasks in their crate directory"
+        );
+        assert_eq!(
+            closest_cargo_manifest_dir(&root.join("crates/server/Cargo.toml")),
+            Some(root.join("crates/server")),
+        );
+        assert_eq!(
+            closest_cargo_manifest_dir(&root.join("scripts/build.rs")),
+            Some(root.to_path_buf()),
+            "Files outside of any crate should fall back to the workspace manifest"
+        );
+    }
+
+    #[test]
+    fn test_cargo_package_version() {
+        let dir = util::test::temp_tree(serde_json::json!({
+            "Cargo.toml": "[workspace]\n[workspace.package]\nversion = \"0.3.0\"",
+            "crates": {
+                "server": {
+                    "Cargo.toml": "[package]\nname = \"server\"\nversion = \"1.2.3\"",
+                    "src": {
+                        "main.rs": "fn main() {}",
+                    },
+                },
+                "client": {
+                    "Cargo.toml": "[package]\nname = \"client\"\nversion.workspace = true",
+                    "src": {
+                        "lib.rs": "",
+                    },
+                },
+                "scratch": {
+                    "Cargo.toml": "[package]\nname = \"scratch\"",
+                    "src": {
+                        "lib.rs": "",
+                    },
+                },
+            },
+        }));
+        let root = dir.path();
+
+        assert_eq!(
+            cargo_package_version(&root.join("crates/server/src/main.rs")),
+            Some("1.2.3".to_owned())
+        );
+        assert_eq!(
+            cargo_package_version(&root.join("crates/client/src/lib.rs")),
+            Some("0.3.0".to_owned()),
+            "Version inherited from the workspace should be resolved from the workspace manifest"
+        );
+        assert_eq!(
+            cargo_package_version(&root.join("crates/scratch/src/lib.rs")),
+            None,
+            "Crates without a version should not have it resolved"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_enclosing_test_name(cx: &mut TestAppContext) {
+        let language = crate::language("rust", tree_sitter_rust::language());
+        let text = r#"
+            fn helper() {
+                let a = 1;
+            }
+
+            #[cfg(test)]
+            mod tests {
+                #[test]
+                // A comment between the attribute and the test.
+                fn test_a() {
+                    let b = 2;
+                    assert_eq!(b, 2);
+                }
+
+                #[gpui::test(iterations = 10)]
+                async fn test_b() {
+                    let c = 3;
+                }
+            }
+        "#
+        .unindent();
+        let buffer = cx.new_model(|cx| {
+            Buffer::new(
+                0,
+                BufferId::new(cx.entity_id().as_u64()).unwrap(),
+                text.clone(),
+            )
+            .with_language(language, cx)
+        });
+        cx.executor().run_until_parked();
+
+        let enclosing_test_at = |needle: &str, cx: &mut TestAppContext| {
+            buffer.update(cx, |buffer, _| {
+                let snapshot = buffer.snapshot();
+                let position = snapshot.anchor_before(text.find(needle).unwrap());
+                enclosing_test_name(&snapshot, position)
+            })
+        };
+        assert_eq!(
+            enclosing_test_at("assert_eq", cx),
+            Some("test_a".to_string()),
+            "Test should be found when the cursor is inside its body"
+        );
+        assert_eq!(enclosing_test_at("let c", cx), Some("test_b".to_string()));
+        assert_eq!(
+            enclosing_test_at("let a", cx),
+            None,
+            "Functions without test attributes should not be treated as tests"
+        );
+    }
 
     #[gpui::test]
     async fn test_process_rust_diagnostics() {
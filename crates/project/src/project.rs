@@ -94,7 +94,12 @@ use std::{
     },
     time::{Duration, Instant},
 };
-use task::static_source::{StaticSource, TrackedFile};
+use task::{
+    cargo_source::CargoSource,
+    makefile_source::{MakefileSource, MAKEFILE_NAMES},
+    package_json_source::{PackageJsonSource, PackageManager},
+    static_source::{StaticSource, TrackedFile},
+};
 use terminals::Terminals;
 use text::{Anchor, BufferId, RopeFingerprint};
 use util::{
@@ -114,7 +119,9 @@ pub use language::Location;
 pub use prettier::FORMAT_SUFFIX as TEST_PRETTIER_FORMAT_SUFFIX;
 #[cfg(feature = "test-support")]
 pub use task_inventory::test_inventory::*;
-pub use task_inventory::{Inventory, TaskSourceKind};
+pub use task_inventory::{
+    CompletedTaskRun, Inventory, InventoryEvent, ScheduledTask, TaskSourceKind,
+};
 pub use worktree::{
     DiagnosticSummary, Entry, EntryKind, File, LocalWorktree, PathChange, ProjectEntryId,
     RepositoryEntry, UpdatedEntriesSet, UpdatedGitRepositoriesSet, Worktree, WorktreeId,
@@ -715,6 +722,7 @@ impl Project {
                 nonce: StdRng::from_entropy().gen(),
                 terminals: Terminals {
                     local_handles: Vec::new(),
+                    task_diagnostics: HashMap::default(),
                 },
                 copilot_lsp_subscription,
                 copilot_log_subscription: None,
@@ -851,6 +859,7 @@ impl Project {
                 nonce: StdRng::from_entropy().gen(),
                 terminals: Terminals {
                     local_handles: Vec::new(),
+                    task_diagnostics: HashMap::default(),
                 },
                 copilot_lsp_subscription,
                 copilot_log_subscription: None,
@@ -7067,6 +7076,13 @@ impl Project {
             let worktree = worktree.read(cx);
             self.is_shared() || worktree.is_visible() || worktree.is_remote()
         };
+        if worktree.read(cx).is_visible() {
+            let worktree_id = worktree.read(cx).id();
+            let root_name = Arc::from(worktree.read(cx).root_name());
+            self.task_inventory().update(cx, |inventory, _| {
+                inventory.set_worktree_name(worktree_id, root_name);
+            });
+        }
         if push_strong_handle {
             self.worktrees
                 .push(WorktreeHandle::Strong(worktree.clone()));
@@ -7407,6 +7423,139 @@ impl Project {
         .detach();
     }
 
+    /// Adds the tasks for the scripts of a `package.json` file of the worktree, run with the package manager of its lockfile.
+    fn update_package_json_tasks(
+        &mut self,
+        worktree: &Model<Worktree>,
+        path: &Path,
+        abs_path: PathBuf,
+        removed: bool,
+        cx: &mut ModelContext<Self>,
+    ) {
+        if path
+            .components()
+            .any(|component| component.as_os_str() == "node_modules")
+        {
+            return;
+        }
+        let worktree = worktree.read(cx);
+        let worktree_id = worktree.id();
+        let package_manager = PackageManager::detect(|lockfile| {
+            worktree
+                .entry_for_path(path.with_file_name(lockfile))
+                .is_some()
+        });
+        let id_base = format!(
+            "package_json_tasks_for_workspace_{worktree_id}_{}",
+            path.display()
+        );
+        let fs = self.fs.clone();
+        self.task_inventory().update(cx, |task_inventory, cx| {
+            if removed {
+                task_inventory.remove_local_static_source(&abs_path);
+                return;
+            }
+            let cwd = abs_path
+                .parent()
+                .map(|dir| dir.to_string_lossy().into_owned());
+            let package_json_abs_path = abs_path.clone();
+            task_inventory.add_source(
+                TaskSourceKind::Worktree {
+                    id: worktree_id,
+                    abs_path,
+                },
+                |cx| {
+                    let package_json_rx =
+                        watch_config_file(&cx.background_executor(), fs, package_json_abs_path);
+                    PackageJsonSource::new(id_base, package_json_rx, package_manager, cwd, cx)
+                },
+                cx,
+            );
+        });
+    }
+
+    /// Adds the tasks for the targets of a `Makefile` of the worktree, run in the directory of the file.
+    fn update_makefile_tasks(
+        &mut self,
+        worktree: &Model<Worktree>,
+        path: &Path,
+        abs_path: PathBuf,
+        removed: bool,
+        cx: &mut ModelContext<Self>,
+    ) {
+        let worktree_id = worktree.read(cx).id();
+        let id_base = format!(
+            "makefile_tasks_for_workspace_{worktree_id}_{}",
+            path.display()
+        );
+        let fs = self.fs.clone();
+        self.task_inventory().update(cx, |task_inventory, cx| {
+            if removed {
+                task_inventory.remove_local_static_source(&abs_path);
+                return;
+            }
+            let cwd = abs_path
+                .parent()
+                .map(|dir| dir.to_string_lossy().into_owned());
+            let makefile_abs_path = abs_path.clone();
+            task_inventory.add_source(
+                TaskSourceKind::Worktree {
+                    id: worktree_id,
+                    abs_path,
+                },
+                |cx| {
+                    let makefile_rx =
+                        watch_config_file(&cx.background_executor(), fs, makefile_abs_path);
+                    MakefileSource::new(id_base, makefile_rx, cwd, cx)
+                },
+                cx,
+            );
+        });
+    }
+
+    /// Adds the tasks for the package and workspace of a `Cargo.toml` manifest of the worktree, run in the directory of the manifest.
+    fn update_cargo_tasks(
+        &mut self,
+        worktree: &Model<Worktree>,
+        path: &Path,
+        abs_path: PathBuf,
+        removed: bool,
+        cx: &mut ModelContext<Self>,
+    ) {
+        // Manifests of the packaged crates and the vendored dependencies are not the project's own.
+        if path
+            .components()
+            .any(|component| component.as_os_str() == "target" || component.as_os_str() == "vendor")
+        {
+            return;
+        }
+        let worktree_id = worktree.read(cx).id();
+        let id_base = format!("cargo_tasks_for_workspace_{worktree_id}_{}", path.display());
+        let fs = self.fs.clone();
+        self.task_inventory().update(cx, |task_inventory, cx| {
+            if removed {
+                task_inventory.remove_local_static_source(&abs_path);
+                return;
+            }
+            let cwd = abs_path
+                .parent()
+                .map(|dir| dir.to_string_lossy().into_owned());
+            let manifest_abs_path = abs_path.clone();
+            task_inventory.add_source(
+                TaskSourceKind::Worktree {
+                    id: worktree_id,
+                    abs_path,
+                },
+                |cx| {
+                    let manifest_rx =
+                        watch_config_file(&cx.background_executor(), fs, manifest_abs_path);
+                    CargoSource::new(id_base, manifest_rx, cwd, cx)
+                },
+                cx,
+            );
+        });
+    }
+
     fn update_local_worktree_settings(
         &mut self,
         worktree: &Model<Worktree>,
@@ -7421,6 +7570,9 @@ impl Project {
         let remote_worktree_id = worktree.read(cx).id();
 
         let mut settings_contents = Vec::new();
+        let mut package_json_changes = Vec::new();
+        let mut makefile_changes = Vec::new();
+        let mut cargo_manifest_changes = Vec::new();
         for (path, _, change) in changes.iter() {
             let removed = change == &PathChange::Removed;
             let abs_path = match worktree.read(cx).absolutize(path) {
@@ -7503,6 +7655,39 @@ impl Project {
                         );
                     }
                 })
+            } else if path.ends_with("Cargo.toml") {
+                cargo_manifest_changes.push((path.clone(), abs_path, removed));
+            } else if MAKEFILE_NAMES.iter().any(|name| path.ends_with(name)) {
+                makefile_changes.push((path.clone(), abs_path, removed));
+            } else if path.ends_with("package.json") {
+                package_json_changes.push((path.clone(), abs_path, removed));
+            } else if let Some(lockfile) = path.file_name() {
+                // Lockfiles don't change the tasks, but the package manager to run them with.
+                let is_lockfile = PackageManager::LOCKFILES
+                    .iter()
+                    .any(|(name, _)| lockfile == *name);
+                if is_lockfile && change != &PathChange::Updated {
+                    let package_json = path.with_file_name("package.json");
+                    if worktree.read(cx).entry_for_path(&package_json).is_some() {
+                        let abs_path = abs_path.with_file_name("package.json");
+                        self.task_inventory().update(cx, |task_inventory, _| {
+                            task_inventory.remove_local_static_source(&abs_path);
+                        });
+                        package_json_changes.push((Arc::from(package_json), abs_path, false));
+                    }
+                }
+            }
+        }
+
+        if worktree.read(cx).is_visible() {
+            for (path, abs_path, removed) in package_json_changes {
+                self.update_package_json_tasks(worktree, &path, abs_path, removed, cx);
+            }
+            for (path, abs_path, removed) in makefile_changes {
+                self.update_makefile_tasks(worktree, &path, abs_path, removed, cx);
+            }
+            for (path, abs_path, removed) in cargo_manifest_changes {
+                self.update_cargo_tasks(worktree, &path, abs_path, removed, cx);
             }
         }
 
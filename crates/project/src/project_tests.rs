@@ -14,6 +14,7 @@ use serde_json::json;
 #[cfg(not(windows))]
 use std::os;
 use std::task::Poll;
+use task::in_memory_source::{InMemorySource, TaskBuilder};
 use unindent::Unindent as _;
 use util::{assert_set_eq, paths::PathMatcher, test::temp_tree};
 use worktree::WorktreeModelHandle as _;
@@ -188,6 +189,150 @@ async fn test_managing_project_specific_settings(cx: &mut gpui::TestAppContext)
     });
 }
 
+#[gpui::test]
+async fn test_package_json_tasks(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        "/the-root",
+        json!({
+            "package.json": r#"{ "scripts": { "build": "tsc" } }"#,
+            "web": {
+                "package.json": r#"{ "scripts": { "dev": "vite" } }"#,
+                "pnpm-lock.yaml": "",
+            },
+            "node_modules": {
+                "dep": {
+                    "package.json": r#"{ "scripts": { "prepare": "husky" } }"#,
+                },
+            },
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs.clone(), ["/the-root".as_ref()], cx).await;
+    let worktree_id = project.update(cx, |project, cx| {
+        project.worktrees().next().unwrap().read(cx).id()
+    });
+    let list_tasks = |cx: &mut gpui::TestAppContext| {
+        cx.executor().run_until_parked();
+        project.update(cx, |project, cx| {
+            project.task_inventory().update(cx, |inventory, cx| {
+                inventory
+                    .list_tasks(None, None, false, cx)
+                    .into_iter()
+                    .map(|(source_kind, task)| (source_kind, task.name().to_string()))
+                    .collect::<Vec<_>>()
+            })
+        })
+    };
+    let root_source = TaskSourceKind::Worktree {
+        id: worktree_id,
+        abs_path: PathBuf::from("/the-root/package.json"),
+    };
+    let web_source = TaskSourceKind::Worktree {
+        id: worktree_id,
+        abs_path: PathBuf::from("/the-root/web/package.json"),
+    };
+
+    assert_eq!(
+        list_tasks(cx),
+        vec![
+            (root_source.clone(), "npm run build".to_string()),
+            (web_source.clone(), "pnpm run dev".to_string()),
+        ],
+        "Scripts should be run with the package manager of the lockfile, dependencies should be skipped"
+    );
+
+    fs.save(
+        "/the-root/package.json".as_ref(),
+        &r#"{ "scripts": { "build": "tsc", "test": "vitest" } }"#.into(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+    fs.create_file("/the-root/yarn.lock".as_ref(), Default::default())
+        .await
+        .unwrap();
+    assert_eq!(
+        list_tasks(cx),
+        vec![
+            (root_source.clone(), "yarn run build".to_string()),
+            (root_source, "yarn run test".to_string()),
+            (web_source, "pnpm run dev".to_string()),
+        ],
+        "Changed scripts and lockfiles should be picked up without reopening the project"
+    );
+}
+
+#[gpui::test]
+async fn test_worktree_restricted_tasks(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree("/dir", json!({ "frontend": {}, "backend": {} }))
+        .await;
+    let project = Project::test(
+        fs.clone(),
+        ["/dir/frontend".as_ref(), "/dir/backend".as_ref()],
+        cx,
+    )
+    .await;
+    let (frontend_id, backend_id) = project.update(cx, |project, cx| {
+        let mut worktrees = project.worktrees().map(|worktree| worktree.read(cx).id());
+        (worktrees.next().unwrap(), worktrees.next().unwrap())
+    });
+    project.update(cx, |project, cx| {
+        project.task_inventory().update(cx, |inventory, cx| {
+            inventory.add_source(
+                TaskSourceKind::InMemory {
+                    name: Arc::from("test"),
+                },
+                |cx| {
+                    InMemorySource::new(
+                        vec![
+                            TaskBuilder::new("npm start")
+                                .command("npm")
+                                .worktrees(["frontend"])
+                                .build("test"),
+                            TaskBuilder::new("make").command("make").build("test"),
+                        ],
+                        cx,
+                    )
+                },
+                cx,
+            )
+        })
+    });
+    let task_names = |worktree: Option<WorktreeId>, cx: &mut gpui::TestAppContext| {
+        project.update(cx, |project, cx| {
+            project.task_inventory().update(cx, |inventory, cx| {
+                inventory
+                    .list_tasks(None, worktree, false, cx)
+                    .into_iter()
+                    .map(|(_, task)| task.name().to_string())
+                    .collect::<Vec<_>>()
+            })
+        })
+    };
+
+    assert_eq!(task_names(Some(frontend_id), cx), vec!["make", "npm start"]);
+    assert_eq!(
+        task_names(Some(backend_id), cx),
+        vec!["make"],
+        "Worktree-restricted task should not be listed for the other worktrees"
+    );
+    assert_eq!(
+        task_names(None, cx),
+        vec!["make", "npm start"],
+        "Without the active worktree, the task should be listed while its worktree is present"
+    );
+
+    project.update(cx, |project, cx| project.remove_worktree(frontend_id, cx));
+    assert_eq!(task_names(None, cx), vec!["make"]);
+}
+
 #[gpui::test]
 async fn test_managing_language_servers(cx: &mut gpui::TestAppContext) {
     init_test(cx);
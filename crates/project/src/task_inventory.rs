@@ -6,20 +6,73 @@ use std::{
     sync::Arc,
 };
 
-use collections::{HashMap, VecDeque};
-use gpui::{AppContext, Context, Model, ModelContext, Subscription};
+use collections::{HashMap, HashSet, VecDeque};
+use gpui::{AppContext, Context, EventEmitter, Model, ModelContext, Subscription, WeakModel};
 use itertools::Itertools;
 use language::Language;
-use task::{static_source::tasks_for, Task, TaskContext, TaskSource};
+use task::{static_source::tasks_for, Task, TaskContext, TaskId, TaskSource};
+use terminal::{TaskStatus, Terminal};
 use util::{post_inc, NumericPrefixWithSuffix};
 use worktree::WorktreeId;
 
 /// Inventory tracks available tasks for a given project.
 pub struct Inventory {
     sources: Vec<SourceInInventory>,
-    last_scheduled_tasks: VecDeque<(Arc<dyn Task>, TaskContext)>,
+    last_scheduled_tasks: VecDeque<ScheduledTask>,
+    /// The exit code of the last run of the scheduled task, for the tasks that have finished since they were scheduled.
+    completed_tasks: HashMap<TaskId, i32>,
+    last_completed_run: Option<CompletedTaskRun>,
+    watched_task: Option<TaskId>,
+    tasks_by_id: HashMap<TaskId, (TaskSourceKind, Arc<dyn Task>)>,
+    /// Tasks listed before all others in the LRU sorting, regardless of their usage.
+    pinned_tasks: HashSet<TaskId>,
+    /// Terminals spawned for the runs of the tasks, the latest last, to cancel the runs still going on in them.
+    task_terminals: Vec<(TaskId, WeakModel<Terminal>)>,
+    /// Root names of the visible worktrees of the project, to list the tasks restricted to some of them.
+    worktree_names: HashMap<WorktreeId, Arc<str>>,
 }
 
+/// Events of the [`Inventory`], emitted on top of its notifications about any change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InventoryEvent {
+    /// A source of the inventory changed its tasks, e.g. after its definitions file got edited.
+    SourceChanged,
+}
+
+impl EventEmitter<InventoryEvent> for Inventory {}
+
+/// A finished run of the task, with its exit code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletedTaskRun {
+    pub id: TaskId,
+    pub exit_code: i32,
+    /// A number of the runs of all tasks completed before this one, to tell apart the consecutive runs with the same outcome.
+    pub ix: usize,
+}
+
+/// A task scheduled from the inventory, with the context it was scheduled in.
+#[derive(Clone)]
+pub struct ScheduledTask {
+    pub task: Arc<dyn Task>,
+    pub task_context: TaskContext,
+    /// The label of the task with the variables of the context substituted, that tells apart the runs of the same task in different contexts.
+    pub resolved_label: String,
+}
+
+impl ScheduledTask {
+    fn new(task: Arc<dyn Task>, task_context: TaskContext) -> Self {
+        let resolved_label = task.resolved_label(&task_context);
+        Self {
+            task,
+            task_context,
+            resolved_label,
+        }
+    }
+}
+
+/// How many scheduled tasks the inventory remembers, for the LRU sorting and the recents.
+const MAX_SCHEDULED_TASKS: usize = 5_000;
+
 struct SourceInInventory {
     source: Model<Box<dyn TaskSource>>,
     _subscription: Subscription,
@@ -34,17 +87,19 @@ pub enum TaskSourceKind {
     UserInput,
     /// ~/.config/zed/task.json - like global files with task definitions, applicable to any path
     AbsPath(PathBuf),
-    /// Tasks from the worktree's .zed/task.json
+    /// Tasks from the worktree's .zed/task.json, or synthesized from its package.json scripts, Makefile targets and Cargo manifests
     Worktree { id: WorktreeId, abs_path: PathBuf },
     /// Languages-specific tasks coming from extensions.
     Language { name: Arc<str> },
+    /// Tasks built from code by extensions or embedders, with the name of whoever registered them.
+    InMemory { name: Arc<str> },
 }
 
 impl TaskSourceKind {
     fn abs_path(&self) -> Option<&Path> {
         match self {
             Self::AbsPath(abs_path) | Self::Worktree { abs_path, .. } => Some(abs_path),
-            Self::UserInput | Self::Language { .. } => None,
+            Self::UserInput | Self::Language { .. } | Self::InMemory { .. } => None,
         }
     }
 
@@ -61,9 +116,38 @@ impl Inventory {
         cx.new_model(|_| Self {
             sources: Vec::new(),
             last_scheduled_tasks: VecDeque::new(),
+            completed_tasks: HashMap::default(),
+            last_completed_run: None,
+            watched_task: None,
+            tasks_by_id: HashMap::default(),
+            pinned_tasks: HashSet::default(),
+            task_terminals: Vec::new(),
+            worktree_names: HashMap::default(),
         })
     }
 
+    /// Registers the root name of a visible worktree, for the tasks restricted to certain worktrees.
+    pub fn set_worktree_name(&mut self, worktree: WorktreeId, name: Arc<str>) {
+        self.worktree_names.insert(worktree, name);
+    }
+
+    /// Whether the task is available in the worktree, or in any worktree of the project, if none is given.
+    fn available_in_worktree(&self, task: &dyn Task, worktree: Option<WorktreeId>) -> bool {
+        let allowed_worktrees = task.worktrees();
+        if allowed_worktrees.is_empty() {
+            return true;
+        }
+        let is_allowed = |name: &Arc<str>| {
+            allowed_worktrees
+                .iter()
+                .any(|allowed| allowed.as_str() == name.as_ref())
+        };
+        match worktree {
+            Some(worktree) => self.worktree_names.get(&worktree).map_or(false, is_allowed),
+            None => self.worktree_names.values().any(is_allowed),
+        }
+    }
+
     /// If the task with the same path was not added yet,
     /// registers a new tasks source to fetch for available tasks later.
     /// Unless a source is removed, ignores future additions for the same path.
@@ -84,7 +168,9 @@ impl Inventory {
         let source = create_source(cx);
         let type_id = source.read(cx).type_id();
         let source = SourceInInventory {
-            _subscription: cx.observe(&source, |_, _, cx| {
+            _subscription: cx.observe(&source, |inventory, _, cx| {
+                inventory.reindex_tasks(cx);
+                cx.emit(InventoryEvent::SourceChanged);
                 cx.notify();
             }),
             source,
@@ -92,15 +178,33 @@ impl Inventory {
             kind,
         };
         self.sources.push(source);
+        self.reindex_tasks(cx);
         cx.notify();
     }
 
+    fn reindex_tasks(&mut self, cx: &mut ModelContext<Self>) {
+        let mut tasks_by_id = HashMap::default();
+        for source in &self.sources {
+            let tasks = source
+                .source
+                .update(cx, |source, cx| source.tasks_to_schedule(cx));
+            for task in tasks {
+                tasks_by_id
+                    .entry(task.id().clone())
+                    .or_insert_with(|| (source.kind.clone(), task));
+            }
+        }
+        self.tasks_by_id = tasks_by_id;
+    }
+
     /// If present, removes the local static source entry that has the given path,
     /// making corresponding task definitions unavailable in the fetch results.
     ///
     /// Now, entry for this path can be re-added again.
     pub fn remove_local_static_source(&mut self, abs_path: &Path) {
         self.sources.retain(|s| s.kind.abs_path() != Some(abs_path));
+        self.tasks_by_id
+            .retain(|_, (kind, _)| kind.abs_path() != Some(abs_path));
     }
 
     /// If present, removes the worktree source entry that has the given worktree id,
@@ -108,7 +212,10 @@ impl Inventory {
     ///
     /// Now, entry for this path can be re-added again.
     pub fn remove_worktree_sources(&mut self, worktree: WorktreeId) {
+        self.worktree_names.remove(&worktree);
         self.sources.retain(|s| s.kind.worktree() != Some(worktree));
+        self.tasks_by_id
+            .retain(|_, (kind, _)| kind.worktree() != Some(worktree));
     }
 
     pub fn source<T: TaskSource>(&self) -> Option<Model<Box<dyn TaskSource>>> {
@@ -127,6 +234,7 @@ impl Inventory {
     }
 
     /// Pulls its sources to list runnables for the editor given, or all runnables for no editor.
+    /// With `lru` sorting, the pinned tasks go first, then the recently scheduled ones.
     pub fn list_tasks(
         &self,
         language: Option<Arc<Language>>,
@@ -155,10 +263,10 @@ impl Inventory {
         let tasks_by_usage = if lru {
             self.last_scheduled_tasks.iter().rev().fold(
                 HashMap::default(),
-                |mut tasks, (task, context)| {
-                    tasks
-                        .entry(task.id().clone())
-                        .or_insert_with(|| (post_inc(&mut lru_score), Some(context)));
+                |mut tasks, scheduled| {
+                    tasks.entry(scheduled.task.id().clone()).or_insert_with(|| {
+                        (post_inc(&mut lru_score), Some(&scheduled.task_context))
+                    });
                     tasks
                 },
             )
@@ -181,6 +289,7 @@ impl Inventory {
                     .map(|task| (&source.kind, task))
             })
             .chain(language_tasks)
+            .filter(|(_, task)| self.available_in_worktree(task.as_ref(), worktree))
             .map(|task| {
                 let usages = if lru {
                     tasks_by_usage
@@ -190,13 +299,15 @@ impl Inventory {
                 } else {
                     not_used_score
                 };
-                (task, usages)
+                let pinned = lru && self.pinned_tasks.contains(task.1.id());
+                (task, (!pinned, usages))
             })
             .sorted_unstable_by(
-                |((kind_a, task_a), usages_a), ((kind_b, task_b), usages_b)| {
-                    usages_a
-                        .0
-                        .cmp(&usages_b.0)
+                |((kind_a, task_a), (not_pinned_a, usages_a)),
+                 ((kind_b, task_b), (not_pinned_b, usages_b))| {
+                    not_pinned_a
+                        .cmp(not_pinned_b)
+                        .then(usages_a.0.cmp(&usages_b.0))
                         .then(
                             kind_a
                                 .worktree()
@@ -224,18 +335,153 @@ impl Inventory {
             .collect()
     }
 
+    /// Looks up a task with the given id among the tasks of all sources, without listing them.
+    /// Language-specific tasks are not looked up, as they depend on the buffer the tasks are listed for.
+    pub fn task_by_id(&self, id: &TaskId) -> Option<(TaskSourceKind, Arc<dyn Task>)> {
+        self.tasks_by_id.get(id).cloned()
+    }
+
     /// Returns the last scheduled task, if any of the sources contains one with the matching id.
     pub fn last_scheduled_task(&self) -> Option<(Arc<dyn Task>, TaskContext)> {
-        self.last_scheduled_tasks.back().cloned()
+        self.last_scheduled_tasks
+            .back()
+            .map(|scheduled| (scheduled.task.clone(), scheduled.task_context.clone()))
+    }
+
+    /// Distinct tasks scheduled the most recently, the latest first, each with the context it was scheduled in the last time.
+    /// Runs of the same task are distinct, if their labels were resolved differently.
+    pub fn recently_scheduled_tasks(&self, limit: usize) -> Vec<ScheduledTask> {
+        let mut seen = HashSet::default();
+        self.last_scheduled_tasks
+            .iter()
+            .rev()
+            .filter(|scheduled| {
+                seen.insert((
+                    scheduled.task.id().clone(),
+                    scheduled.resolved_label.clone(),
+                ))
+            })
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// The context the task with the given id was scheduled in the last time, if it was scheduled at all.
+    pub fn last_scheduled_context(&self, id: &TaskId) -> Option<TaskContext> {
+        self.last_scheduled_tasks
+            .iter()
+            .rev()
+            .find(|scheduled| scheduled.task.id() == id)
+            .map(|scheduled| scheduled.task_context.clone())
+    }
+
+    /// The most recently scheduled task, whose last run has failed, with the context it was scheduled in.
+    /// Tasks scheduled after it are skipped, if they have succeeded or are still running.
+    pub fn last_failed_task(&self) -> Option<(Arc<dyn Task>, TaskContext)> {
+        self.last_scheduled_tasks
+            .iter()
+            .rev()
+            .find(|scheduled| {
+                self.completed_tasks
+                    .get(scheduled.task.id())
+                    .is_some_and(|&exit_code| exit_code != 0)
+            })
+            .map(|scheduled| (scheduled.task.clone(), scheduled.task_context.clone()))
+    }
+
+    /// Records the exit code of the last run of the task, once it finishes.
+    pub fn task_completed(&mut self, id: TaskId, exit_code: i32, cx: &mut ModelContext<Self>) {
+        self.completed_tasks.insert(id.clone(), exit_code);
+        let ix = self
+            .last_completed_run
+            .as_ref()
+            .map_or(0, |last_run| last_run.ix + 1);
+        self.last_completed_run = Some(CompletedTaskRun { id, exit_code, ix });
+        cx.notify();
+    }
+
+    /// The run of any task that has finished the most recently.
+    pub fn last_completed_run(&self) -> Option<&CompletedTaskRun> {
+        self.last_completed_run.as_ref()
+    }
+
+    /// Tracks the terminal spawned for a run of the task, to be able to cancel the run later.
+    pub fn task_terminal_spawned(&mut self, id: TaskId, terminal: WeakModel<Terminal>) {
+        self.task_terminals
+            .retain(|(_, terminal)| terminal.upgrade().is_some());
+        self.task_terminals.push((id, terminal));
+    }
+
+    /// Terminals of the task runs that are still going on, the most recently spawned last.
+    pub fn running_task_terminals(&self, cx: &AppContext) -> Vec<(TaskId, Model<Terminal>)> {
+        self.task_terminals
+            .iter()
+            .filter_map(|(id, terminal)| {
+                let terminal = terminal.upgrade()?;
+                let running = terminal.read(cx).task()?.status == TaskStatus::Running;
+                running.then(|| (id.clone(), terminal))
+            })
+            .collect()
+    }
+
+    /// A task that should be rerun every time the task sources change, if any.
+    pub fn watched_task(&self) -> Option<&TaskId> {
+        self.watched_task.as_ref()
+    }
+
+    /// Sets the task to rerun every time the task sources change, or stops watching if `None` is passed.
+    pub fn set_watched_task(&mut self, task_id: Option<TaskId>) {
+        self.watched_task = task_id;
+    }
+
+    /// Whether the task with the given id is pinned to the top of the task lists.
+    pub fn is_pinned(&self, id: &TaskId) -> bool {
+        self.pinned_tasks.contains(id)
+    }
+
+    /// Pins the task with the given id to the top of the task lists, or unpins it, if it was pinned.
+    pub fn toggle_pinned(&mut self, id: &TaskId) {
+        if !self.pinned_tasks.remove(id) {
+            self.pinned_tasks.insert(id.clone());
+        }
+    }
+
+    /// Ids of all pinned tasks, including the ones not present in the sources currently.
+    pub fn pinned_tasks(&self) -> impl Iterator<Item = &TaskId> {
+        self.pinned_tasks.iter()
+    }
+
+    /// Pins the tasks pinned before the app restart, in addition to the ones pinned since then.
+    pub fn restore_pinned_tasks(&mut self, ids: impl IntoIterator<Item = TaskId>) {
+        self.pinned_tasks.extend(ids);
     }
 
     /// Registers task "usage" as being scheduled – to be used for LRU sorting when listing all tasks.
     pub fn task_scheduled(&mut self, task: Arc<dyn Task>, task_context: TaskContext) {
-        self.last_scheduled_tasks.push_back((task, task_context));
-        if self.last_scheduled_tasks.len() > 5_000 {
+        self.completed_tasks.remove(task.id());
+        self.last_scheduled_tasks
+            .push_back(ScheduledTask::new(task, task_context));
+        if self.last_scheduled_tasks.len() > MAX_SCHEDULED_TASKS {
             self.last_scheduled_tasks.pop_front();
         }
     }
+
+    /// All scheduled tasks, the oldest first, including the repeated runs of the same task.
+    pub fn scheduled_tasks_history(&self) -> impl DoubleEndedIterator<Item = &ScheduledTask> {
+        self.last_scheduled_tasks.iter()
+    }
+
+    /// Adds the tasks scheduled before the app restart, the oldest first, to the history:
+    /// the tasks scheduled since then are kept as the most recent ones.
+    pub fn restore_scheduled_tasks(&mut self, tasks: Vec<(Arc<dyn Task>, TaskContext)>) {
+        for (task, task_context) in tasks.into_iter().rev() {
+            if self.last_scheduled_tasks.len() >= MAX_SCHEDULED_TASKS {
+                break;
+            }
+            self.last_scheduled_tasks
+                .push_front(ScheduledTask::new(task, task_context));
+        }
+    }
 }
 
 #[cfg(any(test, feature = "test-support"))]
@@ -364,6 +610,10 @@ pub mod test_inventory {
 #[cfg(test)]
 mod tests {
     use gpui::TestAppContext;
+    use task::{
+        static_source::{Definition, TaskDefinitions},
+        TaskVariables, VariableName,
+    };
 
     use super::test_inventory::*;
     use super::*;
@@ -511,6 +761,193 @@ mod tests {
         );
     }
 
+    #[gpui::test]
+    fn test_pinned_tasks_sorting(cx: &mut TestAppContext) {
+        let inventory = cx.update(Inventory::new);
+        inventory.update(cx, |inventory, cx| {
+            inventory.add_source(
+                TaskSourceKind::UserInput,
+                |cx| {
+                    StaticTestSource::new(
+                        vec!["build".to_string(), "lint".to_string(), "test".to_string()],
+                        cx,
+                    )
+                },
+                cx,
+            );
+        });
+        let toggle_pinned = |task_name: &str, cx: &mut TestAppContext| {
+            inventory.update(cx, |inventory, cx| {
+                let (_, task) = inventory
+                    .list_tasks(None, None, false, cx)
+                    .into_iter()
+                    .find(|(_, task)| task.name() == task_name)
+                    .unwrap();
+                inventory.toggle_pinned(task.id());
+            })
+        };
+
+        register_task_used(&inventory, "lint", cx);
+        toggle_pinned("test", cx);
+        assert_eq!(
+            list_task_names(&inventory, None, true, cx),
+            vec!["test".to_string(), "lint".to_string(), "build".to_string()],
+            "Pinned tasks should go before the recently used ones"
+        );
+        assert_eq!(
+            list_task_names(&inventory, None, false, cx),
+            vec!["build".to_string(), "lint".to_string(), "test".to_string()],
+            "Task list without lru sorting should ignore the pins"
+        );
+
+        toggle_pinned("test", cx);
+        assert_eq!(
+            list_task_names(&inventory, None, true, cx),
+            vec!["lint".to_string(), "build".to_string(), "test".to_string()],
+            "Unpinned task should be sorted as usual"
+        );
+    }
+
+    #[gpui::test]
+    fn test_last_failed_task(cx: &mut TestAppContext) {
+        let inventory = cx.update(Inventory::new);
+        inventory.update(cx, |inventory, cx| {
+            inventory.add_source(
+                TaskSourceKind::UserInput,
+                |cx| {
+                    StaticTestSource::new(
+                        vec!["build".to_string(), "test".to_string(), "lint".to_string()],
+                        cx,
+                    )
+                },
+                cx,
+            );
+        });
+        let last_failed_task = |cx: &mut TestAppContext| {
+            inventory.update(cx, |inventory, _| {
+                inventory
+                    .last_failed_task()
+                    .map(|(task, _)| task.name().to_string())
+            })
+        };
+        let complete_task = |task_name: &str, exit_code: i32, cx: &mut TestAppContext| {
+            inventory.update(cx, |inventory, cx| {
+                let (_, task) = inventory
+                    .list_tasks(None, None, false, cx)
+                    .into_iter()
+                    .find(|(_, task)| task.name() == task_name)
+                    .unwrap();
+                inventory.task_completed(task.id().clone(), exit_code, cx);
+            })
+        };
+
+        register_task_used(&inventory, "build", cx);
+        register_task_used(&inventory, "test", cx);
+        assert_eq!(last_failed_task(cx), None, "Running tasks have not failed");
+        complete_task("build", 1, cx);
+        complete_task("test", 0, cx);
+        register_task_used(&inventory, "lint", cx);
+        assert_eq!(
+            last_failed_task(cx),
+            Some("build".to_string()),
+            "Succeeded and running tasks scheduled after the failed one should be skipped"
+        );
+
+        complete_task("lint", 1, cx);
+        assert_eq!(last_failed_task(cx), Some("lint".to_string()));
+        register_task_used(&inventory, "lint", cx);
+        complete_task("lint", 0, cx);
+        assert_eq!(
+            last_failed_task(cx),
+            Some("build".to_string()),
+            "Task should not count as failed after its rerun succeeds"
+        );
+    }
+
+    #[gpui::test]
+    fn test_recent_tasks_with_resolved_labels(cx: &mut TestAppContext) {
+        let inventory = cx.update(Inventory::new);
+        let task = tasks_for(
+            TaskDefinitions(vec![Definition {
+                label: "Test $ZED_SYMBOL".to_string(),
+                command: "cargo".to_string(),
+                args: vec!["test".to_string(), "$ZED_SYMBOL".to_string()],
+                ..Definition::default()
+            }]),
+            "test",
+        )
+        .remove(0);
+        let context_with_symbol = |symbol: &str| TaskContext {
+            task_variables: TaskVariables::from_iter([(VariableName::Symbol, symbol.to_string())]),
+            ..TaskContext::default()
+        };
+        inventory.update(cx, |inventory, _| {
+            for symbol in ["foo", "bar", "foo"] {
+                inventory.task_scheduled(task.clone(), context_with_symbol(symbol));
+            }
+            let recent_labels = inventory
+                .recently_scheduled_tasks(5)
+                .into_iter()
+                .map(|scheduled| scheduled.resolved_label)
+                .collect::<Vec<_>>();
+            assert_eq!(
+                recent_labels,
+                vec!["Test foo".to_string(), "Test bar".to_string()],
+                "Runs of the same task with different resolved labels should be distinct recents"
+            );
+        });
+    }
+
+    #[gpui::test]
+    fn test_task_by_id(cx: &mut TestAppContext) {
+        let inventory = cx.update(Inventory::new);
+        let path = Path::new("path");
+        inventory.update(cx, |inventory, cx| {
+            inventory.add_source(
+                TaskSourceKind::UserInput,
+                |cx| StaticTestSource::new(vec!["user_input".to_string()], cx),
+                cx,
+            );
+            inventory.add_source(
+                TaskSourceKind::AbsPath(path.to_path_buf()),
+                |cx| {
+                    StaticTestSource::new(vec!["static_1".to_string(), "static_2".to_string()], cx)
+                },
+                cx,
+            );
+        });
+        let task_by_id = |id: &str, cx: &mut TestAppContext| {
+            inventory.update(cx, |inventory, _| {
+                inventory
+                    .task_by_id(&TaskId(id.to_string()))
+                    .map(|(kind, task)| (kind, task.name().to_string()))
+            })
+        };
+
+        assert_eq!(
+            task_by_id("task_0_user_input", cx),
+            Some((TaskSourceKind::UserInput, "user_input".to_string()))
+        );
+        assert_eq!(
+            task_by_id("task_1_static_2", cx),
+            Some((
+                TaskSourceKind::AbsPath(path.to_path_buf()),
+                "static_2".to_string()
+            ))
+        );
+        assert_eq!(task_by_id("task_2_static_2", cx), None);
+        assert_eq!(task_by_id("missing", cx), None);
+
+        inventory.update(cx, |inventory, _| {
+            inventory.remove_local_static_source(path)
+        });
+        assert_eq!(
+            task_by_id("task_1_static_2", cx),
+            None,
+            "Tasks of the removed source should not be found"
+        );
+    }
+
     #[gpui::test]
     fn test_inventory_static_task_filters(cx: &mut TestAppContext) {
         let inventory_with_statics = cx.update(Inventory::new);
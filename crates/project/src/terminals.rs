@@ -1,20 +1,37 @@
 use crate::Project;
-use collections::HashMap;
+use collections::{HashMap, HashSet};
 use gpui::{AnyWindowHandle, Context, Entity, Model, ModelContext, WeakModel};
+use language::{Diagnostic, DiagnosticEntry, PointUtf16, Unclipped};
+use lsp::{DiagnosticSeverity, LanguageServerId};
 use settings::Settings;
 use smol::channel::bounded;
-use std::path::{Path, PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    time::Instant,
+};
+use task::{
+    problem_matcher::{Problem, ProblemSeverity},
+    TaskId,
+};
 use terminal::{
     terminal_settings::{self, Shell, TerminalSettings, VenvSettingsContent},
     SpawnTask, TaskState, TaskStatus, Terminal, TerminalBuilder,
 };
-use util::ResultExt;
+use util::{post_inc, ResultExt};
 
 // #[cfg(target_os = "macos")]
 // use std::os::unix::ffi::OsStrExt;
 
 pub struct Terminals {
     pub(crate) local_handles: Vec<WeakModel<terminal::Terminal>>,
+    /// Diagnostics of the problems that the tasks found in their output, from the last run of every task.
+    pub(crate) task_diagnostics: HashMap<TaskId, TaskDiagnostics>,
+}
+
+/// Diagnostics of a task's problems, by the absolute paths of the files, reported under a language server id of the task's own.
+pub(crate) struct TaskDiagnostics {
+    server_id: LanguageServerId,
+    entries: HashMap<PathBuf, Vec<DiagnosticEntry<Unclipped<PointUtf16>>>>,
 }
 
 impl Project {
@@ -31,6 +48,10 @@ impl Project {
         );
 
         let is_terminal = spawn_task.is_none();
+        let task_id = spawn_task.as_ref().map(|spawn_task| spawn_task.id.clone());
+        let matches_problems = spawn_task
+            .as_ref()
+            .map_or(false, |spawn_task| spawn_task.problem_parser.is_some());
         let settings = TerminalSettings::get_global(cx);
         let python_settings = settings.detect_venv.clone();
         let (completion_tx, completion_rx) = bounded(1);
@@ -53,7 +74,16 @@ impl Project {
                 Some(TaskState {
                     id: spawn_task.id,
                     label: spawn_task.label,
+                    group: spawn_task.group,
+                    singleton_group: spawn_task.singleton_group,
+                    reveal: spawn_task.reveal,
+                    cwd: working_directory.clone(),
                     status: TaskStatus::Running,
+                    show_summary: spawn_task.show_summary,
+                    started_at: Instant::now(),
+                    finished_at: None,
+                    problem_parser: spawn_task.problem_parser,
+                    scanned_lines: 0,
                     completion_rx,
                 }),
                 Shell::WithArguments {
@@ -97,6 +127,36 @@ impl Project {
             })
             .detach();
 
+            if let Some(task_id) = task_id {
+                if matches_problems {
+                    self.clear_task_diagnostics(&task_id, cx);
+                }
+                self.task_inventory().update(cx, |inventory, cx| {
+                    inventory.task_terminal_spawned(task_id.clone(), terminal_handle.downgrade());
+                    cx.notify();
+                });
+                cx.subscribe(
+                    &terminal_handle,
+                    move |project, terminal, event, cx| match event {
+                        terminal::Event::TaskCompleted { exit_code } => {
+                            project.task_inventory().update(cx, |inventory, cx| {
+                                inventory.task_completed(task_id.clone(), *exit_code, cx)
+                            });
+                        }
+                        terminal::Event::TaskProblems(problems) => {
+                            let label = terminal
+                                .read(cx)
+                                .task()
+                                .map(|task| task.label.clone())
+                                .unwrap_or_default();
+                            project.report_task_problems(&task_id, &label, problems, cx);
+                        }
+                        _ => {}
+                    },
+                )
+                .detach();
+            }
+
             // if the terminal is not a task, activate full Python virtual environment
             if is_terminal {
                 if let Some(python_settings) = &python_settings.as_option() {
@@ -208,6 +268,90 @@ impl Project {
     pub fn local_terminal_handles(&self) -> &Vec<WeakModel<terminal::Terminal>> {
         &self.terminals.local_handles
     }
+
+    /// Clears the diagnostics of the problems found by the previous run of the task, before it's rerun.
+    fn clear_task_diagnostics(&mut self, task_id: &TaskId, cx: &mut ModelContext<Project>) {
+        let Some(diagnostics) = self.terminals.task_diagnostics.get_mut(task_id) else {
+            return;
+        };
+        let server_id = diagnostics.server_id;
+        let paths = diagnostics
+            .entries
+            .drain()
+            .map(|(path, _)| path)
+            .collect::<Vec<_>>();
+        for path in paths {
+            self.update_diagnostic_entries(server_id, path, None, Vec::new(), cx)
+                .log_err();
+        }
+    }
+
+    /// Adds the problems found in the task output to the diagnostics of the task, skipping the files outside of the local worktrees.
+    fn report_task_problems(
+        &mut self,
+        task_id: &TaskId,
+        task_label: &str,
+        problems: &[Problem],
+        cx: &mut ModelContext<Project>,
+    ) {
+        let problems = problems
+            .iter()
+            .filter(|problem| self.find_local_worktree(&problem.path, cx).is_some())
+            .collect::<Vec<_>>();
+        if problems.is_empty() {
+            return;
+        }
+        let languages = self.languages.clone();
+        let diagnostics = self
+            .terminals
+            .task_diagnostics
+            .entry(task_id.clone())
+            .or_insert_with(|| TaskDiagnostics {
+                server_id: languages.next_language_server_id(),
+                entries: HashMap::default(),
+            });
+        let mut updated_paths = HashSet::default();
+        for problem in problems {
+            let point = Unclipped(PointUtf16::new(
+                problem.row.saturating_sub(1),
+                problem.column.saturating_sub(1),
+            ));
+            diagnostics
+                .entries
+                .entry(problem.path.clone())
+                .or_default()
+                .push(DiagnosticEntry {
+                    range: point..point,
+                    diagnostic: Diagnostic {
+                        source: Some(task_label.to_owned()),
+                        code: problem.code.clone(),
+                        severity: match problem.severity {
+                            ProblemSeverity::Error => DiagnosticSeverity::ERROR,
+                            ProblemSeverity::Warning => DiagnosticSeverity::WARNING,
+                            ProblemSeverity::Info => DiagnosticSeverity::INFORMATION,
+                        },
+                        message: problem.message.clone(),
+                        group_id: post_inc(&mut self.next_diagnostic_group_id),
+                        is_primary: true,
+                        is_disk_based: true,
+                        ..Diagnostic::default()
+                    },
+                });
+            updated_paths.insert(problem.path.clone());
+        }
+        let server_id = diagnostics.server_id;
+        let updates = updated_paths
+            .into_iter()
+            .map(|path| {
+                let entries = diagnostics.entries[&path].clone();
+                (path, entries)
+            })
+            .collect::<Vec<_>>();
+        for (path, entries) in updates {
+            self.update_diagnostic_entries(server_id, path, None, entries, cx)
+                .log_err();
+        }
+    }
 }
 
 // TODO: Add a few tests for adding and removing terminal tabs
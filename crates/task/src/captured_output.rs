@@ -0,0 +1,106 @@
+//! Storage for the task output that is captured outside of the terminal, protected against the runaway tasks.
+
+/// Default limit of the output captured for a task, if nothing else is configured.
+pub const DEFAULT_MAX_OUTPUT_BYTES: usize = 1024 * 1024;
+
+/// A marker, appended to the captured output after it gets truncated.
+pub const TRUNCATION_MARKER: &str = "\n[output truncated]\n";
+
+/// Output of a task, captured up to a certain size: everything after the limit is dropped
+/// and [`TRUNCATION_MARKER`] is appended instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedOutput {
+    text: String,
+    max_bytes: usize,
+    truncated: bool,
+}
+
+impl CapturedOutput {
+    /// Creates an empty output, that will store at most `max_bytes` of the task's output.
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            text: String::new(),
+            max_bytes,
+            truncated: false,
+        }
+    }
+
+    /// Appends another chunk of the output.
+    /// Returns `false` if the chunk did not fit into the limit, hence the output got truncated,
+    /// and the process may be killed as nothing else will be captured.
+    pub fn push_str(&mut self, chunk: &str) -> bool {
+        if self.truncated {
+            return false;
+        }
+        let remaining = self.max_bytes.saturating_sub(self.text.len());
+        if chunk.len() <= remaining {
+            self.text.push_str(chunk);
+            return true;
+        }
+
+        let mut end = remaining;
+        while !chunk.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.text.push_str(&chunk[..end]);
+        self.text.push_str(TRUNCATION_MARKER);
+        self.truncated = true;
+        false
+    }
+
+    /// The output captured so far, with the [`TRUNCATION_MARKER`] at the end if it got truncated.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Whether the output exceeded the limit and got truncated.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+}
+
+impl Default for CapturedOutput {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_OUTPUT_BYTES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_within_limit() {
+        let mut output = CapturedOutput::new(10);
+        assert!(output.push_str("hello"));
+        assert!(output.push_str("world"));
+        assert_eq!(output.text(), "helloworld");
+        assert!(!output.is_truncated());
+    }
+
+    #[test]
+    fn test_output_beyond_limit_is_truncated() {
+        let mut output = CapturedOutput::new(8);
+        assert!(output.push_str("hello"));
+        assert!(!output.push_str(" world"));
+        assert_eq!(output.text(), format!("hello wo{TRUNCATION_MARKER}"));
+        assert!(output.is_truncated());
+
+        assert!(
+            !output.push_str("more"),
+            "Nothing should be captured after truncation"
+        );
+        assert_eq!(output.text(), format!("hello wo{TRUNCATION_MARKER}"));
+    }
+
+    #[test]
+    fn test_truncation_respects_char_boundaries() {
+        let mut output = CapturedOutput::new(3);
+        assert!(!output.push_str("aßc"));
+        assert_eq!(output.text(), format!("aß{TRUNCATION_MARKER}"));
+
+        let mut output = CapturedOutput::new(2);
+        assert!(!output.push_str("aßc"));
+        assert_eq!(output.text(), format!("a{TRUNCATION_MARKER}"));
+    }
+}
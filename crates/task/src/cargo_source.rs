@@ -0,0 +1,208 @@
+//! A source of tasks, synthesized from the packages and targets of a `Cargo.toml` manifest.
+
+use std::sync::Arc;
+
+use futures::{channel::mpsc::UnboundedReceiver, StreamExt};
+use gpui::{AppContext, Context, Model, ModelContext};
+use serde::Deserialize;
+use util::ResultExt;
+
+use crate::{
+    static_source::{tasks_for, Definition, TaskDefinitions},
+    Task, TaskSource, VariableName,
+};
+
+#[derive(Debug, Default, Deserialize)]
+struct CargoManifest {
+    package: Option<CargoPackage>,
+    workspace: Option<CargoWorkspace>,
+    #[serde(default)]
+    bin: Vec<CargoTarget>,
+    #[serde(default)]
+    test: Vec<CargoTarget>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CargoPackage {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CargoWorkspace {}
+
+#[derive(Debug, Deserialize)]
+struct CargoTarget {
+    name: Option<String>,
+}
+
+/// Task definitions for the `Cargo.toml` contents, spawned in `cwd`, the directory of the manifest:
+/// `cargo build`, `cargo test`, `cargo clippy` and `cargo run` for its package, with a task per its `[[bin]]` and `[[test]]` targets,
+/// and a task to test the function under the cursor; and the same tasks for the whole workspace, if the manifest declares one.
+/// Targets discovered by cargo from the file layout (`src/bin/*.rs`, `tests/*.rs`) are not listed, only the declared ones.
+pub fn cargo_tasks(contents: &str, cwd: Option<String>) -> anyhow::Result<TaskDefinitions> {
+    let manifest = if contents.trim().is_empty() {
+        CargoManifest::default()
+    } else {
+        toml::from_str::<CargoManifest>(contents)?
+    };
+    let cargo = |args: &[&str]| Definition {
+        label: format!("cargo {}", args.join(" ")),
+        command: "cargo".to_owned(),
+        args: args.iter().map(|arg| (*arg).to_owned()).collect(),
+        cwd: cwd.clone(),
+        ..Definition::default()
+    };
+
+    let mut definitions = Vec::new();
+    if manifest.workspace.is_some() {
+        for command in ["build", "test", "clippy"] {
+            definitions.push(cargo(&[command, "--workspace"]));
+        }
+    }
+    if let Some(package) = &manifest.package {
+        let package = package.name.as_str();
+        for command in ["build", "test", "clippy", "run"] {
+            definitions.push(cargo(&[command, "-p", package]));
+        }
+        let symbol = VariableName::Symbol.template_value();
+        let mut test_function = cargo(&["test", "-p", package, &symbol, "--", "--nocapture"]);
+        test_function.label = format!("cargo test -p {package} {symbol}");
+        definitions.push(test_function);
+        for bin in manifest.bin.iter().filter_map(|bin| bin.name.as_deref()) {
+            definitions.push(cargo(&["run", "-p", package, "--bin", bin]));
+        }
+        for test in manifest.test.iter().filter_map(|test| test.name.as_deref()) {
+            definitions.push(cargo(&["test", "-p", package, "--test", test]));
+        }
+    }
+    Ok(TaskDefinitions(definitions))
+}
+
+/// A source of the tasks for a `Cargo.toml` manifest, reloaded when the file changes.
+pub struct CargoSource {
+    tasks: Vec<Arc<dyn Task>>,
+}
+
+impl CargoSource {
+    /// Initializes the source, that synthesizes the tasks from every new contents of the manifest sent by the `tracker`.
+    pub fn new(
+        id_base: String,
+        mut tracker: UnboundedReceiver<String>,
+        cwd: Option<String>,
+        cx: &mut AppContext,
+    ) -> Model<Box<dyn TaskSource>> {
+        cx.new_model(|cx: &mut ModelContext<Box<dyn TaskSource>>| {
+            cx.spawn(|source, mut cx| async move {
+                while let Some(contents) = tracker.next().await {
+                    let Some(definitions) = cargo_tasks(&contents, cwd.clone()).log_err() else {
+                        continue;
+                    };
+                    let updated = source.update(&mut cx, |source, cx| {
+                        if let Some(source) = source.as_any().downcast_mut::<Self>() {
+                            source.tasks = tasks_for(definitions, &id_base);
+                            cx.notify();
+                        }
+                    });
+                    if updated.is_err() {
+                        break;
+                    }
+                }
+            })
+            .detach();
+            Box::new(Self { tasks: Vec::new() }) as Box<dyn TaskSource>
+        })
+    }
+}
+
+impl TaskSource for CargoSource {
+    fn as_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn tasks_to_schedule(
+        &mut self,
+        _: &mut ModelContext<Box<dyn TaskSource>>,
+    ) -> Vec<Arc<dyn Task>> {
+        self.tasks.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(contents: &str) -> Vec<String> {
+        cargo_tasks(contents, Some("/project".to_owned()))
+            .unwrap()
+            .0
+            .into_iter()
+            .map(|definition| {
+                assert_eq!(definition.cwd.as_deref(), Some("/project"));
+                definition.label
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_cargo_tasks() {
+        let manifest = r#"
+[package]
+name = "app"
+version = "0.1.0"
+
+[[bin]]
+name = "app-cli"
+path = "src/cli.rs"
+
+[[bin]]
+path = "src/unnamed.rs"
+
+[[test]]
+name = "integration"
+path = "tests/integration.rs"
+
+[dependencies]
+serde = "1"
+"#;
+        assert_eq!(
+            labels(manifest),
+            vec![
+                "cargo build -p app",
+                "cargo test -p app",
+                "cargo clippy -p app",
+                "cargo run -p app",
+                "cargo test -p app $ZED_SYMBOL",
+                "cargo run -p app --bin app-cli",
+                "cargo test -p app --test integration",
+            ],
+            "Targets without a name should be skipped"
+        );
+
+        let test_function = cargo_tasks(manifest, None).unwrap().0.remove(4);
+        assert_eq!(
+            test_function.args,
+            vec!["test", "-p", "app", "$ZED_SYMBOL", "--", "--nocapture"],
+        );
+    }
+
+    #[test]
+    fn test_cargo_workspace_tasks() {
+        assert_eq!(
+            labels(
+                r#"
+[workspace]
+members = ["crates/*"]
+resolver = "2"
+"#
+            ),
+            vec![
+                "cargo build --workspace",
+                "cargo test --workspace",
+                "cargo clippy --workspace",
+            ],
+            "Virtual manifests should only have the workspace tasks, the members get theirs from their own manifests"
+        );
+        assert!(labels("").is_empty());
+        assert!(cargo_tasks("[package", None).is_err());
+    }
+}
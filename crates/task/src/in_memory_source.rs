@@ -0,0 +1,182 @@
+//! A source of tasks, defined from code instead of a tasks config file: by extensions or the embedders of Zed.
+
+use std::sync::Arc;
+
+use gpui::{AppContext, Context, Model, ModelContext};
+
+use crate::{
+    static_source::{tasks_for, Definition, RevealStrategy, TaskDefinitions},
+    Task, TaskId, TaskSource,
+};
+
+/// A builder of a task, spawned the same way as the tasks from the tasks config file with the same definition.
+#[derive(Clone, Debug)]
+pub struct TaskBuilder {
+    definition: Definition,
+}
+
+impl TaskBuilder {
+    /// Starts a task with the given label, that's used to display the task and to identify it within its source.
+    pub fn new(label: impl Into<String>) -> Self {
+        Self {
+            definition: Definition {
+                label: label.into(),
+                ..Definition::default()
+            },
+        }
+    }
+
+    /// Sets the command to spawn, variables are expanded in it.
+    pub fn command(mut self, command: impl Into<String>) -> Self {
+        self.definition.command = command.into();
+        self
+    }
+
+    /// Sets the arguments of the command, variables are expanded in them.
+    pub fn args(mut self, args: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.definition.args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Adds an env variable to set for the command.
+    pub fn env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.definition.env.insert(name.into(), value.into());
+        self
+    }
+
+    /// Sets the directory to spawn the command in, relative paths are resolved against the project root.
+    pub fn cwd(mut self, cwd: impl Into<String>) -> Self {
+        self.definition.cwd = Some(cwd.into());
+        self
+    }
+
+    /// Sets what to do with the terminal pane and tab, after the command was started.
+    pub fn reveal(mut self, reveal: RevealStrategy) -> Self {
+        self.definition.reveal = Some(reveal);
+        self
+    }
+
+    /// Makes the task spawn in a new terminal tab every time, instead of reusing the existing one.
+    pub fn use_new_terminal(mut self, use_new_terminal: bool) -> Self {
+        self.definition.use_new_terminal = use_new_terminal;
+        self
+    }
+
+    /// Allows multiple instances of the task to run at the same time.
+    pub fn allow_concurrent_runs(mut self, allow_concurrent_runs: bool) -> Self {
+        self.definition.allow_concurrent_runs = allow_concurrent_runs;
+        self
+    }
+
+    /// Restricts the task to the worktrees with the given root names.
+    pub fn worktrees(mut self, worktrees: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.definition.worktrees = worktrees.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Finishes the task, to be added into an [`InMemorySource`] registered with the `source_name`:
+    /// the task's id is derived from both, so that the same labels of different sources do not clash.
+    pub fn build(self, source_name: &str) -> Arc<dyn Task> {
+        tasks_for(
+            TaskDefinitions(vec![self.definition]),
+            &format!("in_memory_{source_name}"),
+        )
+        .pop()
+        .expect("a task for a single definition")
+    }
+}
+
+/// A source of the tasks built from code, kept in memory only.
+pub struct InMemorySource {
+    tasks: Vec<Arc<dyn Task>>,
+}
+
+impl InMemorySource {
+    /// Initializes the source with the given tasks.
+    pub fn new(tasks: Vec<Arc<dyn Task>>, cx: &mut AppContext) -> Model<Box<dyn TaskSource>> {
+        cx.new_model(|_| Box::new(Self { tasks }) as Box<dyn TaskSource>)
+    }
+
+    /// Adds a task to the source, replacing the one with the same id, if any.
+    /// The source's model has to be notified afterwards, for the inventory to pick up the change.
+    pub fn add(&mut self, task: Arc<dyn Task>) {
+        self.remove(task.id());
+        self.tasks.push(task);
+    }
+
+    /// Removes a task with the given id from the source.
+    pub fn remove(&mut self, id: &TaskId) {
+        self.tasks.retain(|task| task.id() != id);
+    }
+}
+
+impl TaskSource for InMemorySource {
+    fn as_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn tasks_to_schedule(
+        &mut self,
+        _: &mut ModelContext<Box<dyn TaskSource>>,
+    ) -> Vec<Arc<dyn Task>> {
+        self.tasks.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{TaskContext, TaskVariables, VariableName};
+
+    use super::*;
+
+    #[test]
+    fn test_task_builder() {
+        let task = TaskBuilder::new("test $ZED_SYMBOL")
+            .command("cargo")
+            .args(["test", "$ZED_SYMBOL"])
+            .env("RUST_BACKTRACE", "1")
+            .cwd("crates/task")
+            .build("test extension");
+        assert_eq!(task.name(), "test $ZED_SYMBOL");
+        assert_ne!(
+            task.id(),
+            TaskBuilder::new("test $ZED_SYMBOL")
+                .command("cargo")
+                .build("other extension")
+                .id(),
+            "Tasks with the same label from different sources should have different ids"
+        );
+
+        let spawn_in_terminal = task
+            .prepare_exec(TaskContext {
+                cwd: Some(PathBuf::from("/project")),
+                task_variables: TaskVariables::from_iter([(
+                    VariableName::Symbol,
+                    "parse".to_owned(),
+                )]),
+                ..TaskContext::default()
+            })
+            .unwrap();
+        assert_eq!(spawn_in_terminal.label, "test parse");
+        assert_eq!(spawn_in_terminal.command, "cargo");
+        assert_eq!(spawn_in_terminal.args, vec!["test", "$ZED_SYMBOL"]);
+        assert_eq!(
+            spawn_in_terminal.cwd,
+            Some(PathBuf::from("/project/crates/task"))
+        );
+        assert_eq!(
+            spawn_in_terminal
+                .env
+                .get("RUST_BACKTRACE")
+                .map(String::as_str),
+            Some("1")
+        );
+        assert_eq!(
+            spawn_in_terminal.env.get("ZED_SYMBOL").map(String::as_str),
+            Some("parse"),
+            "Variables should be passed to the command in its env"
+        );
+    }
+}
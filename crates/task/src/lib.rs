@@ -1,26 +1,45 @@
 //! Baseline interface of Tasks in Zed: all tasks in Zed are intended to use those for implementing their own logic.
 #![deny(missing_docs)]
 
+pub mod captured_output;
+pub mod cargo_source;
+pub mod in_memory_source;
+pub mod makefile_source;
 pub mod oneshot_source;
+pub mod package_json_source;
+pub mod problem_matcher;
+pub mod shell_history_source;
 pub mod static_source;
+pub mod test_results;
 mod vscode_format;
 
 use collections::HashMap;
 use gpui::ModelContext;
-use static_source::RevealStrategy;
+use problem_matcher::ProblemMatcher;
+use serde::{Deserialize, Serialize};
+use static_source::{
+    ArgsOverride, GitDiff, RevealStrategy, ShowStrategy, TaskContainer, TaskDockPosition, TaskRetry,
+};
 use std::any::Any;
 use std::borrow::Cow;
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::ops::{Range, RangeInclusive};
+use std::path::{Path, PathBuf};
+use std::sync::{
+    atomic::{self, AtomicU64},
+    Arc, OnceLock,
+};
+use test_results::TestResultsFormat;
 pub use vscode_format::VsCodeTaskFile;
 
 /// Task identifier, unique within the application.
 /// Based on it, task reruns and terminal tabs are managed.
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub struct TaskId(pub String);
 
 /// Contains all information needed by Zed to spawn a new terminal tab for the given task.
-#[derive(Debug, Clone)]
+#[derive(Debug, Default, Clone)]
 pub struct SpawnInTerminal {
     /// Id of the task to use when determining task tab affinity.
     pub id: TaskId,
@@ -38,15 +57,733 @@ pub struct SpawnInTerminal {
     pub use_new_terminal: bool,
     /// Whether to allow multiple instances of the same task to be run, or rather wait for the existing ones to finish.
     pub allow_concurrent_runs: bool,
+    /// Whether to kill the running instances of the same task before spawning this one.
+    pub cancel_previous: bool,
     /// What to do with the terminal pane and tab, after the command was started.
-    pub reveal: RevealStrategy,
+    /// `None` if the task does not specify it, so the default from the settings should be used.
+    pub reveal: Option<RevealStrategy>,
+    /// A dock to place the terminal with the task in.
+    /// `None` if the task does not specify it, so the terminal stays in the dock from the settings.
+    pub dock: Option<TaskDockPosition>,
+    /// A container to run the command in, if any.
+    pub container: Option<TaskContainer>,
+    /// Whether to show stderr of the command interleaved with its stdout, or capture it separately into [`SpawnInTerminal::stderr_file`].
+    pub combine_output: bool,
+    /// How many bytes of the output to keep, when it's captured outside of the terminal.
+    pub max_output_bytes: usize,
+    /// Whether to kill the process, once its captured output exceeds [`SpawnInTerminal::max_output_bytes`].
+    pub kill_on_output_limit: bool,
+    /// Whether to show all task variables resolved and ask for a confirmation before spawning the task.
+    pub confirm: bool,
+    /// Where to show the output of the command.
+    pub show: ShowStrategy,
+    /// Niceness to lower the OS priority of the command with, if any.
+    pub nice: Option<i32>,
+    /// Whether to run the command with the elevated privileges, asking for the credentials every time.
+    pub elevate: bool,
+    /// Executables that have to be present on `PATH` for the task to run.
+    pub requires: Vec<String>,
+    /// Whether to rerun the command when it fails, and how long to wait between the attempts.
+    pub retry: Option<TaskRetry>,
+    /// Env variables to set from the secret store, by the keys of their secrets.
+    /// Read into [`SpawnInTerminal::env`] right before the spawn, so that the values never get into the task previews;
+    /// the names stay here, for the command rewrites to keep the values off the command line.
+    pub secrets: HashMap<String, String>,
+    /// A file to open and follow while the task runs.
+    pub tail_file: Option<PathBuf>,
+    /// Which changes of the git repository to supply to the command, written into [`SpawnInTerminal::git_diff_file`].
+    pub git_diff: Option<GitDiff>,
+    /// A group of tasks to show this one in, when it's running.
+    pub group: Option<String>,
+    /// Whether to replace the `$(command)` substitutions in the args with the outputs of their commands, before spawning the task.
+    pub command_substitution: bool,
+    /// A command line to run in the same shell before the command, the command is not run if it fails.
+    pub pre: Option<String>,
+    /// A command line to run in the same shell after the command, whether the command succeeds or fails.
+    pub post: Option<String>,
+    /// A format of the test results the command reports, to summarize them once the command finishes.
+    pub results_format: Option<TestResultsFormat>,
+    /// A file the command writes its test results into; the stdout of the command has the results otherwise.
+    pub results_file: Option<PathBuf>,
+    /// Whether to reveal and select the cwd of the task in the project panel, when the task is spawned.
+    pub reveal_cwd_in_project_panel: bool,
+    /// A split of the active pane to open the task's terminal in, instead of the terminal panel.
+    pub split: Option<TaskSplitDirection>,
+    /// A message to commit all changes of the git repository with, once the command succeeds.
+    pub git_commit_message: Option<String>,
+    /// Labels of the tasks to run before the command, in order; the command is only run after all of them succeed.
+    pub depends_on: Vec<String>,
+    /// Whether to spawn the tasks of [`SpawnInTerminal::depends_on`] at once, each in its own terminal, instead of the command.
+    pub parallel: bool,
+    /// A label of the `parallel` task that spawned this one: the terminal of this task is shown as a sub-tab of the terminal tab of that task.
+    pub terminal_group: Option<String>,
+    /// A group of the tasks to cancel the running tasks of, before spawning this one.
+    pub singleton_group: Option<String>,
+    /// Whether to print the duration and the exit status of the task into its terminal, after it finishes.
+    pub show_summary: bool,
+    /// A matcher of the problems in the output of the command, to report them as the project diagnostics.
+    pub problem_matcher: Option<ProblemMatcher>,
+    /// A number unique to every preparation of a task, see [`SpawnInTerminal::next_spawn_nonce`]:
+    /// tells the temporary files of the concurrent runs of the same task apart.
+    pub spawn_nonce: u64,
+}
+
+/// A direction to split the active pane in, for the terminal of a task.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskSplitDirection {
+    /// A new pane to the right of the active one.
+    Right,
+    /// A new pane below the active one.
+    Down,
+}
+
+/// Quotes the string for the POSIX shells, to pass it as a single word.
+pub fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// The shell the command rewrites are written for: the tasks are spawned in the user's shell, that may be fish, nushell or PowerShell,
+/// so the rewritten commands run their POSIX shell syntax with `sh` explicitly.
+const POSIX_SHELL: &str = "sh";
+
+/// A command and its args that run the POSIX shell `script` with `sh`, passing the script quoted as a single word,
+/// whichever shell the task gets spawned in.
+pub fn posix_script_command(script: &str) -> (String, Vec<String>) {
+    let script = shlex::try_quote(script).map_or_else(|_| shell_quote(script), Cow::into_owned);
+    (POSIX_SHELL.to_owned(), vec!["-c".to_owned(), script])
+}
+
+/// Niceness values accepted for the tasks: from the default priority to the lowest one.
+pub const NICENESS_RANGE: RangeInclusive<i32> = 0..=19;
+
+impl SpawnInTerminal {
+    /// A flag to increase the verbosity of the command with, if it is run by a known runner: `cargo`, `npm` or `pytest`.
+    pub fn verbosity_flag(&self) -> Option<&'static str> {
+        match Path::new(&self.command).file_stem()?.to_str()? {
+            "cargo" | "pytest" => Some("-v"),
+            "npm" => Some("--verbose"),
+            "python" | "python3"
+                if self
+                    .args
+                    .starts_with(&["-m".to_owned(), "pytest".to_owned()]) =>
+            {
+                Some("-v")
+            }
+            _ => None,
+        }
+    }
+
+    /// Adds the [`SpawnInTerminal::verbosity_flag`] to the arguments, before the `--` that separates the runner's own arguments, if any.
+    /// Returns `false` and leaves the command as is, if the runner is not known.
+    pub fn add_verbosity_flag(&mut self) -> bool {
+        let Some(flag) = self.verbosity_flag() else {
+            return false;
+        };
+        let flag_ix = self
+            .args
+            .iter()
+            .position(|arg| arg == "--")
+            .unwrap_or(self.args.len());
+        self.args.insert(flag_ix, flag.to_owned());
+        true
+    }
+
+    /// Rewrites the command so that it gets spawned with the lowered OS priority, if [`SpawnInTerminal::nice`] is set:
+    /// with `nice` on Unix, and with the below normal priority class on Windows.
+    /// Values outside of [`NICENESS_RANGE`] are ignored with a warning, leaving the default priority.
+    pub fn lower_priority(&mut self) {
+        let Some(nice) = self.nice.take() else {
+            return;
+        };
+        if !NICENESS_RANGE.contains(&nice) {
+            log::warn!(
+                "Ignoring niceness {nice} of task {:?}, expected a value in {NICENESS_RANGE:?}",
+                self.label
+            );
+            return;
+        }
+        if nice == 0 {
+            return;
+        }
+
+        let (runner, mut runner_args) = if cfg!(windows) {
+            (
+                "start",
+                vec![
+                    "/b".to_owned(),
+                    "/wait".to_owned(),
+                    "/belownormal".to_owned(),
+                ],
+            )
+        } else {
+            ("nice", vec!["-n".to_owned(), nice.to_string()])
+        };
+        runner_args.push(std::mem::replace(&mut self.command, runner.to_owned()));
+        runner_args.append(&mut self.args);
+        self.args = runner_args;
+    }
+
+    /// Rewrites the command so that it gets spawned with the elevated privileges, if [`SpawnInTerminal::elevate`] is set:
+    /// with `sudo` on Unix, keeping the env variables of the task and ignoring the cached credentials, so that the password is always asked for,
+    /// and with PowerShell's `Start-Process -Verb RunAs` on Windows, that asks for the consent and runs the command in a new console window,
+    /// waiting for it to exit with its exit code.
+    pub fn elevate_privileges(&mut self) {
+        if !std::mem::take(&mut self.elevate) {
+            return;
+        }
+
+        let command = std::mem::take(&mut self.command);
+        if cfg!(windows) {
+            let powershell_string = |value: &str| format!("'{}'", value.replace('\'', "''"));
+            let mut start_process =
+                format!("Start-Process -FilePath {}", powershell_string(&command));
+            if !self.args.is_empty() {
+                let args = self
+                    .args
+                    .drain(..)
+                    // `Start-Process` joins the arguments with spaces, as they are.
+                    .map(|arg| {
+                        if arg.is_empty() || arg.contains(char::is_whitespace) {
+                            powershell_string(&format!("\"{arg}\""))
+                        } else {
+                            powershell_string(&arg)
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join(",");
+                start_process.push_str(&format!(" -ArgumentList {args}"));
+            }
+            // The script is a single argument of PowerShell, that the shell the task is spawned in should not split or expand.
+            let script = format!(
+                "$process = {start_process} -Verb RunAs -Wait -PassThru; exit $process.ExitCode"
+            );
+            self.command = "powershell".to_owned();
+            self.args = vec![
+                "-NoProfile".to_owned(),
+                "-Command".to_owned(),
+                shell_quote(&script),
+            ];
+            return;
+        }
+
+        let mut runner_args = vec!["--reset-timestamp".to_owned()];
+        let env_names = self.env_names();
+        if !env_names.is_empty() {
+            runner_args.push(format!("--preserve-env={}", env_names.join(",")));
+        }
+        runner_args.push("--".to_owned());
+        runner_args.push(command);
+        runner_args.append(&mut self.args);
+        self.command = "sudo".to_owned();
+        self.args = runner_args;
+    }
+
+    /// Rewrites the command so that it gets spawned in the [`TaskContainer`] configured, if any.
+    /// The cwd is mapped into the container, and all env variables of the task are forwarded into it:
+    /// by name for `docker`, that reads them from its own env, and with their values for `devcontainer`, which needs them on the command line;
+    /// values of the secrets are only read at the spawn, so `sh` expands them from the env of the task then, keeping them off the command line.
+    pub fn wrap_in_container(&mut self) {
+        let Some(container) = self.container.take() else {
+            return;
+        };
+        let cwd = self
+            .cwd
+            .as_ref()
+            .map(|cwd| cwd.to_string_lossy().into_owned());
+        let env_names = self.env_names();
+
+        let mut runner_args = Vec::new();
+        let runner = match container {
+            TaskContainer::Image(image) => {
+                runner_args.extend(["run".to_owned(), "--rm".to_owned(), "-i".to_owned()]);
+                if let Some(cwd) = &cwd {
+                    runner_args.extend([
+                        "-v".to_owned(),
+                        shell_quote(&format!("{cwd}:{cwd}")),
+                        "-w".to_owned(),
+                        shell_quote(cwd),
+                    ]);
+                }
+                for name in env_names {
+                    runner_args.extend(["-e".to_owned(), name]);
+                }
+                runner_args.push(shell_quote(&image));
+                "docker"
+            }
+            TaskContainer::Exec(container) => {
+                runner_args.extend(["exec".to_owned(), "-i".to_owned()]);
+                if let Some(cwd) = &cwd {
+                    runner_args.extend(["-w".to_owned(), shell_quote(cwd)]);
+                }
+                for name in env_names {
+                    runner_args.extend(["-e".to_owned(), name]);
+                }
+                runner_args.push(shell_quote(&container));
+                "docker"
+            }
+            TaskContainer::Devcontainer => {
+                let mut devcontainer = vec!["devcontainer".to_owned(), "exec".to_owned()];
+                if let Some(cwd) = &cwd {
+                    devcontainer.extend(["--workspace-folder".to_owned(), shell_quote(cwd)]);
+                }
+                for name in env_names {
+                    let remote_env = match self
+                        .env
+                        .get(&name)
+                        .filter(|_| !self.secrets.contains_key(&name))
+                    {
+                        Some(value) => shell_quote(&format!("{name}={value}")),
+                        None => format!("{name}=\"${name}\""),
+                    };
+                    devcontainer.extend(["--remote-env".to_owned(), remote_env]);
+                }
+                devcontainer.push(self.take_command_line());
+                self.run_posix_script(devcontainer.join(" "));
+                return;
+            }
+        };
+        runner_args.push(std::mem::replace(&mut self.command, runner.to_owned()));
+        runner_args.append(&mut self.args);
+        self.args = runner_args;
+    }
+
+    /// Sorted names of all env variables of the task, the secrets included, whether they are read already or not.
+    fn env_names(&self) -> Vec<String> {
+        let mut names = self
+            .env
+            .keys()
+            .chain(self.secrets.keys())
+            .cloned()
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Takes the command and its args out, joined into the command line the shell runs.
+    fn take_command_line(&mut self) -> String {
+        std::iter::once(std::mem::take(&mut self.command))
+            .chain(self.args.drain(..))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Rewrites the command to run the POSIX shell `script`, see [`posix_script_command`].
+    fn run_posix_script(&mut self, script: String) {
+        (self.command, self.args) = posix_script_command(&script);
+    }
+
+    /// Rewrites the command so that the shell reruns it on failure, as configured by [`SpawnInTerminal::retry`],
+    /// sleeping for the [`TaskRetry::delay`] before every rerun. The exit status of the last attempt is reported.
+    pub fn retry_on_failure(&mut self) {
+        let Some(retry) = self.retry.take() else {
+            return;
+        };
+        if retry.attempts == 0 {
+            return;
+        }
+        let command_line = self.take_command_line();
+        let mut retrying_command = command_line.clone();
+        for attempt in 1..=retry.attempts {
+            let delay = retry.delay(attempt);
+            retrying_command.push_str(&format!(
+                " || {{ sleep {}.{:03}; {command_line}; }}",
+                delay.as_secs(),
+                delay.subsec_millis()
+            ));
+        }
+        self.run_posix_script(retrying_command);
+    }
+
+    /// Rewrites the command so that the shell runs the [`SpawnInTerminal::pre`] and [`SpawnInTerminal::post`] hooks around it, if any.
+    /// A failed `pre` hook aborts the task, the `post` hook runs after the command no matter its exit status, which is reported then.
+    pub fn add_hooks(&mut self) {
+        let (pre, post) = (self.pre.take(), self.post.take());
+        if pre.is_none() && post.is_none() {
+            return;
+        }
+        let mut command_line = self.take_command_line();
+        if let Some(post) = post {
+            command_line =
+                format!("{{ {command_line}; zed_task_status=$?; {post}; exit $zed_task_status; }}");
+        }
+        if let Some(pre) = pre {
+            command_line = format!("{{ {pre}; }} && {command_line}");
+        }
+        self.run_posix_script(command_line);
+    }
+
+    /// Rewrites the command so that, once it succeeds, the shell stages and commits all changes of the git repository
+    /// with the [`SpawnInTerminal::git_commit_message`], if there are any. Nothing is committed after a failed command.
+    pub fn commit_on_success(&mut self) {
+        let Some(message) = self.git_commit_message.take() else {
+            return;
+        };
+        let command_line = self.take_command_line();
+        let message = shell_quote(&message);
+        self.run_posix_script(format!(
+            "{{ {command_line}; }} && {{ [ -z \"$(git status --porcelain)\" ] || {{ git add -A && git commit -q -m {message}; }}; }}"
+        ));
+    }
+
+    /// Applies all rewrites of the command the task asks for, in the order they nest in, to have it ready for the spawn:
+    /// the dependencies given get their own commands rewritten the same way and run before the command, in the same shell.
+    /// The stderr of the whole gets separated last, to be captured from the dependencies too.
+    pub fn finalize(&mut self, dependencies: Vec<SpawnInTerminal>) {
+        let dependencies = dependencies
+            .into_iter()
+            .map(|mut dependency| {
+                dependency.rewrite_command();
+                dependency.capture_test_results();
+                dependency
+            })
+            .collect();
+        self.rewrite_command();
+        self.run_after(dependencies);
+        self.capture_test_results();
+        self.separate_stderr();
+    }
+
+    /// The rewrites of [`SpawnInTerminal::finalize`] every step of the task gets on its own.
+    fn rewrite_command(&mut self) {
+        self.lower_priority();
+        self.wrap_in_container();
+        self.elevate_privileges();
+        self.retry_on_failure();
+        self.add_hooks();
+        self.commit_on_success();
+    }
+
+    /// Rewrites the command so that the shell runs the commands of the dependencies first, in order, and the command
+    /// only after all of them succeed. Every dependency runs in its own subshell, in its cwd and with its env variables
+    /// that differ from the ones of the command. Values of the secrets never get onto the command line:
+    /// they are passed in the env of the command, under the names the subshell of the dependency exports them from.
+    pub fn run_after(&mut self, dependencies: Vec<SpawnInTerminal>) {
+        if dependencies.is_empty() {
+            return;
+        }
+        let command_line = self.take_command_line();
+        let mut secrets_env = Vec::new();
+        let mut command_lines = dependencies
+            .into_iter()
+            .enumerate()
+            .map(|(ix, dependency)| {
+                let mut prelude = Vec::new();
+                if let Some(cwd) = dependency
+                    .cwd
+                    .as_ref()
+                    .filter(|cwd| Some(*cwd) != self.cwd.as_ref())
+                {
+                    prelude.push(format!("cd {}", shell_quote(&cwd.to_string_lossy())));
+                }
+                let mut exports = dependency
+                    .env
+                    .iter()
+                    .filter(|(name, value)| self.env.get(*name) != Some(*value))
+                    .map(|(name, value)| {
+                        if dependency.secrets.contains_key(name) {
+                            let secret_name = format!("ZED_DEPENDENCY_{ix}_{name}");
+                            let export = format!("export {name}=\"${secret_name}\"");
+                            secrets_env.push((secret_name, value.clone()));
+                            export
+                        } else {
+                            format!("export {name}={}", shell_quote(value))
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                exports.sort();
+                prelude.extend(exports);
+                let dependency_command_line = std::iter::once(dependency.command)
+                    .chain(dependency.args)
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                prelude.push(dependency_command_line);
+                format!("( {} )", prelude.join(" && "))
+            })
+            .collect::<Vec<_>>();
+        self.env.extend(secrets_env);
+        command_lines.push(format!("( {command_line} )"));
+        self.run_posix_script(command_lines.join(" && "));
+    }
+
+    /// A directory to resolve the relative paths of the problems in the output against: `$ZED_WORKTREE_ROOT`, or the cwd if the task has no worktree.
+    pub fn problem_root(&self) -> Option<PathBuf> {
+        self.env
+            .get(&VariableName::WorktreeRoot.to_string())
+            .map(PathBuf::from)
+            .or_else(|| self.cwd.clone())
+    }
+
+    /// A new [`SpawnInTerminal::spawn_nonce`], different from all the previous ones of the process.
+    pub fn next_spawn_nonce() -> u64 {
+        static NEXT_SPAWN_NONCE: AtomicU64 = AtomicU64::new(0);
+        NEXT_SPAWN_NONCE.fetch_add(1, atomic::Ordering::Relaxed)
+    }
+
+    /// A temporary file of this run of the task, with the given extension.
+    /// Named after the task id, the process and the [`SpawnInTerminal::spawn_nonce`], so that the concurrent runs do not share it.
+    fn temp_file(&self, extension: &str) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        self.id.hash(&mut hasher);
+        std::env::temp_dir().join(format!(
+            "zed-task-{:x}-{}-{}.{extension}",
+            hasher.finish(),
+            std::process::id(),
+            self.spawn_nonce
+        ))
+    }
+
+    /// A file that the exit status of the command is written into once it finishes, when the task reports test results.
+    pub fn results_status_file(&self) -> Option<PathBuf> {
+        self.results_format?;
+        Some(self.temp_file("status"))
+    }
+
+    /// A file to read the test results of the command from: [`SpawnInTerminal::results_file`] or the captured stdout of the command.
+    pub fn test_results_file(&self) -> Option<PathBuf> {
+        self.results_format?;
+        self.results_file
+            .clone()
+            .or_else(|| Some(self.temp_file("results")))
+    }
+
+    /// Rewrites the command so that, when the task reports test results, its stdout gets captured into [`SpawnInTerminal::test_results_file`]
+    /// (unless the command writes a results file itself), and its exit status is written into [`SpawnInTerminal::results_status_file`] after that.
+    pub fn capture_test_results(&mut self) {
+        let (Some(status_file), Some(results_file)) =
+            (self.results_status_file(), self.test_results_file())
+        else {
+            return;
+        };
+        let status_file_tmp = shell_quote(&format!("{}.tmp", status_file.to_string_lossy()));
+        let status_file = shell_quote(&status_file.to_string_lossy());
+        let command_line = self.take_command_line();
+        let script = if self.results_file.is_some() {
+            format!("{command_line}; zed_task_status=$?; echo $zed_task_status > {status_file}; exit $zed_task_status")
+        } else {
+            let results_file = shell_quote(&results_file.to_string_lossy());
+            format!(
+                "{{ {command_line}; echo $? > {status_file_tmp}; }} | tee {results_file}; zed_task_status=$(cat {status_file_tmp}); echo $zed_task_status > {status_file}; exit $zed_task_status"
+            )
+        };
+        self.run_posix_script(script);
+    }
+
+    /// A file the command writes its output into, if the task names one: its test results file, or the file followed while it runs.
+    pub fn output_file(&self) -> Option<&PathBuf> {
+        self.results_file.as_ref().or(self.tail_file.as_ref())
+    }
+
+    /// A file that the stderr of the command is captured into, when the output is not combined.
+    pub fn stderr_file(&self) -> Option<PathBuf> {
+        if self.combine_output {
+            return None;
+        }
+        Some(self.temp_file("stderr"))
+    }
+
+    /// A file that the git diff is written into for the command, when the task asks for it.
+    pub fn git_diff_file(&self) -> Option<PathBuf> {
+        self.git_diff?;
+        Some(self.temp_file("diff"))
+    }
+
+    /// Supplies [`SpawnInTerminal::git_diff_file`] to the command: as its stdin, redirected by `sh`, and with the path in `$ZED_GIT_DIFF_FILE`.
+    pub fn supply_git_diff(&mut self) {
+        if let Some(diff_file) = self.git_diff_file() {
+            let diff_file = diff_file.to_string_lossy().into_owned();
+            let command_line = self.take_command_line();
+            self.run_posix_script(format!(
+                "{{ {command_line}; }} < {}",
+                shell_quote(&diff_file)
+            ));
+            self.env
+                .insert(VariableName::GitDiffFile.to_string(), diff_file);
+        }
+    }
+
+    /// Redirects stderr of the command into [`SpawnInTerminal::stderr_file`] with `sh`, if the output should not be combined.
+    pub fn separate_stderr(&mut self) {
+        if let Some(stderr_file) = self.stderr_file() {
+            let command_line = self.take_command_line();
+            self.run_posix_script(format!(
+                "{{ {command_line}; }} 2> {}",
+                shell_quote(&stderr_file.to_string_lossy())
+            ));
+        }
+    }
+
+    /// Inner commands of the `$(command)` substitutions in the args, in the order they appear in, if the task opted into them.
+    /// Arithmetic `$((...))`, escaped `\$(` and unclosed substitutions are left to the shell.
+    pub fn command_substitutions(&self) -> Vec<String> {
+        if !self.command_substitution {
+            return Vec::new();
+        }
+        let mut commands = Vec::new();
+        for arg in &self.args {
+            for range in command_substitution_ranges(arg) {
+                let command = arg[range.start + 2..range.end - 1].to_owned();
+                if !commands.contains(&command) {
+                    commands.push(command);
+                }
+            }
+        }
+        commands
+    }
+
+    /// Replaces the [`SpawnInTerminal::command_substitutions`] in the args with the outputs of their commands.
+    pub fn substitute_commands(&mut self, outputs: &HashMap<String, String>) {
+        if !self.command_substitution {
+            return;
+        }
+        for arg in &mut self.args {
+            for range in command_substitution_ranges(arg).into_iter().rev() {
+                if let Some(output) = outputs.get(&arg[range.start + 2..range.end - 1]) {
+                    arg.replace_range(range, output);
+                }
+            }
+        }
+    }
+
+    /// The command line with all Zed task variables (`$ZED_*` and `${ZED_*}`) substituted from [`SpawnInTerminal::env`],
+    /// the way the shell expands them when the task is spawned. Other variables and the escaped `\$` are left as is.
+    /// Returns the names of the Zed task variables referenced, but not available in the current context, if there are any.
+    pub fn resolve_command(&self) -> Result<String, Vec<String>> {
+        let (resolved, missing_variables) = self.resolve_available_variables();
+        if missing_variables.is_empty() {
+            Ok(resolved)
+        } else {
+            Err(missing_variables)
+        }
+    }
+
+    /// Same as [`SpawnInTerminal::resolve_command`], but leaves the references to the missing Zed task variables as is,
+    /// returning the command line along with the names of those variables.
+    pub fn resolve_available_variables(&self) -> (String, Vec<String>) {
+        let command_line = std::iter::once(&self.command)
+            .chain(&self.args)
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut resolved = String::with_capacity(command_line.len());
+        let mut missing_variables = Vec::new();
+        let mut rest = command_line.as_str();
+        while let Some(dollar_ix) = rest.find('$') {
+            let (before, after) = rest.split_at(dollar_ix);
+            resolved.push_str(before);
+            let after = &after[1..];
+            let (braced, name_start) = match after.strip_prefix('{') {
+                Some(name_start) => (true, name_start),
+                None => (false, after),
+            };
+            let name_len = name_start
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(name_start.len());
+            let name = &name_start[..name_len];
+            let unclosed = braced && !name_start[name_len..].starts_with('}');
+            if before.ends_with('\\') || !name.starts_with("ZED_") || unclosed {
+                resolved.push('$');
+                rest = after;
+                continue;
+            }
+            match self.env.get(name) {
+                Some(value) => resolved.push_str(value),
+                None => {
+                    let reference_len = 1 + name_len + 2 * usize::from(braced);
+                    resolved.push_str(&rest[dollar_ix..dollar_ix + reference_len]);
+                    if !missing_variables.iter().any(|missing| missing == name) {
+                        missing_variables.push(name.to_owned());
+                    }
+                }
+            }
+            rest = &name_start[name_len + usize::from(braced)..];
+        }
+        resolved.push_str(rest);
+        (resolved, missing_variables)
+    }
+}
+
+/// Ranges of the whole `$(command)` substitutions in the arg, with nested parentheses balanced.
+fn command_substitution_ranges(arg: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut search_start = 0;
+    while let Some(offset) = arg[search_start..].find("$(") {
+        let start = search_start + offset;
+        search_start = start + 2;
+        if arg[..start].ends_with('\\') || arg[search_start..].starts_with('(') {
+            continue;
+        }
+        let mut depth = 1;
+        let end = arg[search_start..].char_indices().find_map(|(ix, c)| {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            (depth == 0).then_some(search_start + ix + 1)
+        });
+        let Some(end) = end else {
+            break;
+        };
+        ranges.push(start..end);
+        search_start = end;
+    }
+    ranges
+}
+
+/// Prepares a sequence of tasks to be spawned one after another, in the same context.
+/// Later steps may refer to the resolved metadata of the earlier ones in their args: `${steps.<label>.cwd}` to the cwd,
+/// and `${steps.<label>.output_file}` to [`SpawnInTerminal::output_file`].
+/// Returns `None` if any of the steps cannot be prepared.
+pub fn prepare_steps(steps: &[Arc<dyn Task>], cx: &TaskContext) -> Option<Vec<SpawnInTerminal>> {
+    let mut step_variables = HashMap::<String, String>::default();
+    steps
+        .iter()
+        .map(|step| {
+            let mut spawn_in_terminal = step.prepare_exec(cx.clone())?;
+            for arg in &mut spawn_in_terminal.args {
+                for (template, value) in &step_variables {
+                    *arg = arg.replace(template, value);
+                }
+            }
+            let label = &spawn_in_terminal.label;
+            if let Some(cwd) = &spawn_in_terminal.cwd {
+                step_variables.insert(
+                    format!("${{steps.{label}.cwd}}"),
+                    cwd.to_string_lossy().into_owned(),
+                );
+            }
+            if let Some(output_file) = spawn_in_terminal.output_file() {
+                step_variables.insert(
+                    format!("${{steps.{label}.output_file}}"),
+                    output_file.to_string_lossy().into_owned(),
+                );
+            }
+            Some(spawn_in_terminal)
+        })
+        .collect()
 }
 
 /// Variables, available for use in [`TaskContext`] when a Zed's task gets turned into real command.
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum VariableName {
     /// An absolute path of the currently opened file.
     File,
+    /// A path of the currently opened file, relative to the root of its worktree.
+    RelativeFile,
+    /// An absolute path of the directory, that contains the currently opened file.
+    FileDirname,
+    /// A name of the currently opened file, without its extension.
+    Stem,
+    /// An extension of the currently opened file, without the dot: only the last one for the names with multiple dots.
+    Extension,
+    /// A name of the branch checked out in the git repository of the currently opened file.
+    GitBranch,
+    /// A number of the error diagnostics in the currently opened file, reported by all its language servers.
+    ErrorCount,
+    /// A number of the warning diagnostics in the currently opened file, reported by all its language servers.
+    WarningCount,
     /// An absolute path of the currently opened worktree, that contains the file.
     WorktreeRoot,
     /// A symbol text, that contains latest cursor/selection position.
@@ -55,8 +792,47 @@ pub enum VariableName {
     Row,
     /// A column with the latest cursor/selection position.
     Column,
+    /// A row with the end of the latest selection, same as [`VariableName::Row`] when nothing is selected.
+    RowEnd,
+    /// A column with the end of the latest selection, same as [`VariableName::Column`] when nothing is selected.
+    ColumnEnd,
     /// Text from the latest selection.
     SelectedText,
+    /// Text from all non-empty selections, in the order of the selections, separated by newlines.
+    AllSelectedText,
+    /// Whitespace the line with the start of the latest selection is indented with.
+    SelectionIndent,
+    /// A name of the language at the latest cursor/selection position.
+    Language,
+    /// Absolute paths of all files opened in the workspace, separated by newlines,
+    /// so that an unquoted `$ZED_OPEN_FILES` expands into an argument per file.
+    OpenFiles,
+    /// A name of the test that encloses the latest cursor/selection position, as found by the language.
+    NearestTest,
+    /// A tab size of the current buffer, as configured in its language settings.
+    TabSize,
+    /// An indentation style of the current buffer: `tab` or `space`.
+    IndentStyle,
+    /// A name of the focused surface of the workspace, e.g. `editor`, `terminal_panel` or `project_panel`.
+    FocusedView,
+    /// A version of the project the current file belongs to, as declared in its manifest (e.g. `Cargo.toml`).
+    ProjectVersion,
+    /// A path to the temporary file with the git diff, supplied to the tasks that ask for it.
+    GitDiffFile,
+    /// Absolute paths of the files staged in the git repository of the task's worktree, separated by newlines,
+    /// so that an unquoted `$ZED_STAGED_FILES` expands into an argument per file.
+    StagedFiles,
+    /// An author of the line with the latest cursor/selection position, as reported by git blame for the current file.
+    LineAuthor,
+    /// An encoding of the current buffer, e.g. `utf-8`.
+    FileEncoding,
+    /// Line endings of the current buffer: `lf` or `crlf`.
+    LineEnding,
+    /// A git commit or stash chosen with `task::SpawnWithGitRef`, e.g. `1a2b3c4` or `stash@{0}`.
+    /// Nothing is checked out: the task is spawned in its usual cwd, and decides what to do with the ref itself.
+    GitRef,
+    /// A title of the workspace, as shown in its window title: the names of its worktrees, separated by `, `.
+    WorkspaceName,
     /// Custom variable, provided by the plugin or other external source.
     /// Will be printed with `ZED_` prefix to avoid potential conflicts with other variables.
     Custom(Cow<'static, str>),
@@ -74,47 +850,240 @@ impl VariableName {
     }
 }
 
+impl From<&str> for VariableName {
+    /// Parses the env name of a variable, e.g. `ZED_FILE`, treating the unknown names as the custom variables:
+    /// `VAR` and `ZED_VAR` are both the custom `VAR` variable, printed as `ZED_VAR`.
+    fn from(name: &str) -> Self {
+        [
+            Self::File,
+            Self::RelativeFile,
+            Self::FileDirname,
+            Self::Stem,
+            Self::Extension,
+            Self::GitBranch,
+            Self::ErrorCount,
+            Self::WarningCount,
+            Self::WorktreeRoot,
+            Self::Symbol,
+            Self::Row,
+            Self::Column,
+            Self::RowEnd,
+            Self::ColumnEnd,
+            Self::SelectedText,
+            Self::AllSelectedText,
+            Self::SelectionIndent,
+            Self::Language,
+            Self::OpenFiles,
+            Self::NearestTest,
+            Self::TabSize,
+            Self::IndentStyle,
+            Self::FocusedView,
+            Self::ProjectVersion,
+            Self::GitDiffFile,
+            Self::StagedFiles,
+            Self::LineAuthor,
+            Self::FileEncoding,
+            Self::LineEnding,
+            Self::GitRef,
+            Self::WorkspaceName,
+        ]
+        .into_iter()
+        .find(|variable| variable.to_string() == name)
+        .unwrap_or_else(|| {
+            Self::Custom(Cow::Owned(
+                name.strip_prefix("ZED_").unwrap_or(name).to_owned(),
+            ))
+        })
+    }
+}
+
 impl std::fmt::Display for VariableName {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Self::File => write!(f, "ZED_FILE"),
+            Self::RelativeFile => write!(f, "ZED_RELATIVE_FILE"),
+            Self::FileDirname => write!(f, "ZED_FILE_DIRNAME"),
+            Self::Stem => write!(f, "ZED_FILENAME_NO_EXT"),
+            Self::Extension => write!(f, "ZED_FILE_EXTENSION"),
+            Self::GitBranch => write!(f, "ZED_GIT_BRANCH"),
+            Self::ErrorCount => write!(f, "ZED_ERROR_COUNT"),
+            Self::WarningCount => write!(f, "ZED_WARNING_COUNT"),
             Self::WorktreeRoot => write!(f, "ZED_WORKTREE_ROOT"),
             Self::Symbol => write!(f, "ZED_SYMBOL"),
             Self::Row => write!(f, "ZED_ROW"),
             Self::Column => write!(f, "ZED_COLUMN"),
+            Self::RowEnd => write!(f, "ZED_ROW_END"),
+            Self::ColumnEnd => write!(f, "ZED_COLUMN_END"),
             Self::SelectedText => write!(f, "ZED_SELECTED_TEXT"),
+            Self::AllSelectedText => write!(f, "ZED_ALL_SELECTED_TEXT"),
+            Self::SelectionIndent => write!(f, "ZED_SELECTION_INDENT"),
+            Self::Language => write!(f, "ZED_LANGUAGE"),
+            Self::OpenFiles => write!(f, "ZED_OPEN_FILES"),
+            Self::NearestTest => write!(f, "ZED_NEAREST_TEST"),
+            Self::TabSize => write!(f, "ZED_TAB_SIZE"),
+            Self::IndentStyle => write!(f, "ZED_INDENT_STYLE"),
+            Self::FocusedView => write!(f, "ZED_FOCUSED_VIEW"),
+            Self::ProjectVersion => write!(f, "ZED_PROJECT_VERSION"),
+            Self::GitDiffFile => write!(f, "ZED_GIT_DIFF_FILE"),
+            Self::StagedFiles => write!(f, "ZED_STAGED_FILES"),
+            Self::LineAuthor => write!(f, "ZED_LINE_AUTHOR"),
+            Self::FileEncoding => write!(f, "ZED_FILE_ENCODING"),
+            Self::LineEnding => write!(f, "ZED_LINE_ENDING"),
+            Self::GitRef => write!(f, "ZED_GIT_REF"),
+            Self::WorkspaceName => write!(f, "ZED_WORKSPACE_NAME"),
             Self::Custom(s) => write!(f, "ZED_{s}"),
         }
     }
 }
 
+/// Computes the value of a variable that is too expensive to compute for every context, see [`TaskVariables::insert_lazy`].
+pub type VariableResolver = Arc<dyn Fn() -> Option<String> + Send + Sync>;
+
+/// A variable, computed on the first reference and cached for all clones of the context it was inserted into.
+#[derive(Clone)]
+pub struct LazyVariable {
+    resolver: VariableResolver,
+    value: Arc<OnceLock<Option<String>>>,
+}
+
+impl LazyVariable {
+    /// A variable to compute with the `resolver`, once it's needed.
+    pub fn new(resolver: VariableResolver) -> Self {
+        Self {
+            resolver,
+            value: Arc::default(),
+        }
+    }
+
+    /// Computes the value of the variable, or returns the already computed one.
+    pub fn resolve(&self) -> Option<String> {
+        self.value.get_or_init(|| (self.resolver)()).clone()
+    }
+}
+
+impl std::fmt::Debug for LazyVariable {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_tuple("LazyVariable")
+            .field(&self.value.get())
+            .finish()
+    }
+}
+
+impl PartialEq for LazyVariable {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.value, &other.value)
+    }
+}
+
+impl Eq for LazyVariable {}
+
 /// Container for predefined environment variables that describe state of Zed at the time the task was spawned.
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
-pub struct TaskVariables(HashMap<VariableName, String>);
+pub struct TaskVariables {
+    values: HashMap<VariableName, String>,
+    lazy: HashMap<VariableName, LazyVariable>,
+}
 
 impl TaskVariables {
     /// Converts the container into a map of environment variables and their values.
+    /// Lazy variables are not included, unless [`TaskVariables::resolve_referenced`] resolved them before.
     fn into_env_variables(self) -> HashMap<String, String> {
-        self.0
+        self.values
             .into_iter()
             .map(|(name, value)| (name.to_string(), value))
             .collect()
     }
 
+    /// Resolves the lazy variables that are referenced in any of the templates (as `$ZED_VARIABLE` or `${ZED_VARIABLE}`),
+    /// turning them into the regular ones. Lazy variables not referenced are dropped without being computed.
+    fn resolve_referenced<'a>(&mut self, templates: impl IntoIterator<Item = &'a str>) {
+        let templates = templates.into_iter().collect::<Vec<_>>();
+        for (variable, lazy_variable) in self.lazy.drain() {
+            let name = variable.to_string();
+            if !templates
+                .iter()
+                .any(|template| references_variable(template, &name))
+            {
+                continue;
+            }
+            if let Some(value) = lazy_variable.resolve() {
+                self.values.insert(variable, value);
+            }
+        }
+    }
+
+    /// The lazy variables that are referenced in any of the templates, to compute them ahead of [`Task::prepare_exec`], e.g. in the background:
+    /// they share the cached value with the ones of the container, so the task gets them prepared without computing.
+    pub fn lazy_referenced(&self, templates: &[String]) -> Vec<LazyVariable> {
+        self.lazy
+            .iter()
+            .filter(|(variable, _)| {
+                let name = variable.to_string();
+                templates
+                    .iter()
+                    .any(|template| references_variable(template, &name))
+            })
+            .map(|(_, lazy_variable)| lazy_variable.clone())
+            .collect()
+    }
+
     /// Inserts another variable into the container, overwriting the existing one if it already exists — in this case, the old value is returned.
     pub fn insert(&mut self, variable: VariableName, value: String) -> Option<String> {
-        self.0.insert(variable, value)
+        self.lazy.remove(&variable);
+        self.values.insert(variable, value)
+    }
+
+    /// Inserts a variable that gets computed only when a task references it, overwriting the existing one if it already exists.
+    /// The value is computed at most once per context, even if it gets cloned, and the variable is omitted if the resolver returns `None`.
+    pub fn insert_lazy(&mut self, variable: VariableName, resolver: VariableResolver) {
+        self.values.remove(&variable);
+        self.lazy.insert(variable, LazyVariable::new(resolver));
+    }
+
+    /// Iterates over all variables in the container, in no particular order.
+    /// Lazy variables are not computed, hence not included.
+    pub fn iter(&self) -> impl Iterator<Item = (&VariableName, &str)> {
+        self.values
+            .iter()
+            .map(|(name, value)| (name, value.as_str()))
+    }
+
+    /// Returns the value of the variable, if it's present in the container.
+    /// Lazy variables are not computed, hence not returned.
+    pub fn get(&self, variable: &VariableName) -> Option<&str> {
+        self.values.get(variable).map(String::as_str)
     }
 
     /// Extends the container with another one, overwriting the existing variables on collision.
     pub fn extend(&mut self, other: Self) {
-        self.0.extend(other.0);
+        for variable in other.values.keys() {
+            self.lazy.remove(variable);
+        }
+        for variable in other.lazy.keys() {
+            self.values.remove(variable);
+        }
+        self.values.extend(other.values);
+        self.lazy.extend(other.lazy);
     }
 }
 
+/// Whether the template references the variable as `$NAME` or `${NAME}`, and not as a prefix of another variable.
+fn references_variable(template: &str, name: &str) -> bool {
+    template.match_indices('$').any(|(ix, _)| {
+        let rest = &template[ix + 1..];
+        let rest = rest.strip_prefix('{').unwrap_or(rest);
+        rest.strip_prefix(name).map_or(false, |after| {
+            !after.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_')
+        })
+    })
+}
+
 impl FromIterator<(VariableName, String)> for TaskVariables {
     fn from_iter<T: IntoIterator<Item = (VariableName, String)>>(iter: T) -> Self {
-        Self(HashMap::from_iter(iter))
+        Self {
+            values: HashMap::from_iter(iter),
+            lazy: HashMap::default(),
+        }
     }
 }
 
@@ -125,6 +1094,20 @@ pub struct TaskContext {
     pub cwd: Option<PathBuf>,
     /// Additional environment variables associated with a given task.
     pub task_variables: TaskVariables,
+    /// Texts to use as [`VariableName::SelectedText`] instead, when nothing is selected.
+    pub selection_fallbacks: SelectionFallbacks,
+    /// Inline overrides of the task args, the topmost layer of [`static_source::resolve_args`], e.g. typed into the tasks modal after `--`.
+    pub args_override: ArgsOverride,
+}
+
+/// Texts around the cursor, for the tasks that want to operate on something when the selection is empty.
+/// Only captured when the selection is empty.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SelectionFallbacks {
+    /// Full contents of the buffer, copied only for the tasks that fall back to it.
+    pub whole_file: Option<LazyVariable>,
+    /// Contents of the line with the cursor, without the trailing newline.
+    pub current_line: Option<String>,
 }
 
 /// Represents a short lived recipe of a task, whose main purpose
@@ -139,6 +1122,32 @@ pub trait Task {
     /// Sets up everything needed to spawn the task in the given directory (`cwd`).
     /// If a task is intended to be spawned in the terminal, it should return the corresponding struct filled with the data necessary.
     fn prepare_exec(&self, cx: TaskContext) -> Option<SpawnInTerminal>;
+    /// Templates of the task that may reference the variables of the context, see [`TaskVariables::lazy_referenced`].
+    fn variable_templates(&self, _: &TaskContext) -> Vec<String> {
+        Vec::new()
+    }
+    /// The label of the task to display in the context, with only the variables already computed in it substituted:
+    /// unlike [`Task::prepare_exec`], it never computes the lazy variables or the selection fallbacks.
+    fn resolved_label(&self, _: &TaskContext) -> String {
+        self.name().to_owned()
+    }
+    /// The `command args` the task runs in the context, resolved as cheaply as [`Task::resolved_label`], to preview the task.
+    /// Empty if the task cannot tell it without being prepared.
+    fn command_line_preview(&self, _: &TaskContext) -> String {
+        String::new()
+    }
+    /// Whether the task should be run automatically, once the project it belongs to is opened.
+    fn run_on_project_open(&self) -> bool {
+        false
+    }
+    /// Whether to offer restarting the task when the project is reopened, if the task was running when the app quit.
+    fn restore_on_reopen(&self) -> bool {
+        false
+    }
+    /// Root names of the worktrees the task is restricted to, available in all worktrees, if empty.
+    fn worktrees(&self) -> &[String] {
+        &[]
+    }
 }
 
 /// [`Source`] produces tasks that can be scheduled.
@@ -154,3 +1163,614 @@ pub trait TaskSource: Any {
         cx: &mut ModelContext<Box<dyn TaskSource>>,
     ) -> Vec<Arc<dyn Task>>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use static_source::{tasks_for, Definition, TaskDefinitions};
+
+    fn spawn_in_terminal(container: Option<TaskContainer>) -> SpawnInTerminal {
+        SpawnInTerminal {
+            id: TaskId("test_id".to_owned()),
+            label: "test".to_owned(),
+            command: "cargo".to_owned(),
+            args: vec!["test".to_owned()],
+            cwd: Some(PathBuf::from("/project")),
+            env: HashMap::from_iter([("ZED_FILE".to_owned(), "/project/src/lib.rs".to_owned())]),
+            use_new_terminal: false,
+            allow_concurrent_runs: false,
+            cancel_previous: false,
+            reveal: None,
+            dock: None,
+            container,
+            combine_output: true,
+            max_output_bytes: captured_output::DEFAULT_MAX_OUTPUT_BYTES,
+            kill_on_output_limit: false,
+            confirm: false,
+            show: ShowStrategy::Terminal,
+            nice: None,
+            elevate: false,
+            requires: Vec::new(),
+            retry: None,
+            secrets: HashMap::default(),
+            tail_file: None,
+            git_diff: None,
+            group: None,
+            terminal_group: None,
+            command_substitution: false,
+            pre: None,
+            post: None,
+            results_format: None,
+            results_file: None,
+            reveal_cwd_in_project_panel: false,
+            split: None,
+            git_commit_message: None,
+            depends_on: Vec::new(),
+            parallel: false,
+            singleton_group: None,
+            show_summary: false,
+            problem_matcher: None,
+            spawn_nonce: 0,
+        }
+    }
+
+    #[test]
+    fn test_retry_on_failure() {
+        let mut spawn = spawn_in_terminal(None);
+        spawn.retry_on_failure();
+        assert_eq!(spawn.command, "cargo", "Nothing to do without a retry");
+
+        spawn.retry = Some(TaskRetry {
+            attempts: 2,
+            delay_ms: 500,
+            backoff: static_source::RetryBackoff::Linear,
+            max_delay_ms: 60_000,
+        });
+        spawn.retry_on_failure();
+        assert_eq!(
+            posix_script(&spawn),
+            "cargo test || { sleep 0.500; cargo test; } || { sleep 1.000; cargo test; }"
+        );
+    }
+
+    #[test]
+    fn test_add_hooks() {
+        let mut spawn = spawn_in_terminal(None);
+        spawn.add_hooks();
+        assert_eq!(spawn.command, "cargo", "Nothing to do without the hooks");
+
+        spawn.pre = Some("./setup.sh".to_owned());
+        spawn.post = Some("./cleanup.sh $ZED_FILE".to_owned());
+        spawn.add_hooks();
+        assert_eq!(
+            posix_script(&spawn),
+            "{ ./setup.sh; } && { cargo test; zed_task_status=$?; ./cleanup.sh $ZED_FILE; exit $zed_task_status; }"
+        );
+    }
+
+    /// The script of the command rewritten to run with [`POSIX_SHELL`], unquoted.
+    fn posix_script(spawn: &SpawnInTerminal) -> String {
+        assert_eq!(spawn.command, POSIX_SHELL);
+        assert_eq!(spawn.args.len(), 2);
+        assert_eq!(spawn.args[0], "-c");
+        let mut words = shlex::split(&spawn.args[1]).expect("script should be quoted");
+        assert_eq!(words.len(), 1, "script should be quoted as a single word");
+        words.pop().unwrap()
+    }
+
+    /// A process running the command line of the task the way the terminal does, with a shell and the env of the task.
+    fn shell_process(spawn: &SpawnInTerminal) -> std::process::Command {
+        let command_line = std::iter::once(spawn.command.as_str())
+            .chain(spawn.args.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut process = std::process::Command::new("sh");
+        process.arg("-c").arg(command_line).envs(&spawn.env);
+        process
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_hooks_run_order() {
+        let run = |pre: &str, command: &str| {
+            let mut spawn = spawn_in_terminal(None);
+            spawn.command = command.to_owned();
+            spawn.args = Vec::new();
+            spawn.pre = Some(pre.to_owned());
+            spawn.post = Some("echo post-$ZED_FILE".to_owned());
+            spawn.add_hooks();
+            let output = shell_process(&spawn).output().unwrap();
+            (
+                String::from_utf8_lossy(&output.stdout).into_owned(),
+                output.status.code(),
+            )
+        };
+
+        assert_eq!(
+            run("echo pre", "echo main"),
+            ("pre\nmain\npost-/project/src/lib.rs\n".to_owned(), Some(0)),
+            "Hooks should run around the command, with the variables expanded"
+        );
+        assert_eq!(
+            run("echo pre", "echo main && false"),
+            ("pre\nmain\npost-/project/src/lib.rs\n".to_owned(), Some(1)),
+            "Post hook should run after the failed command, keeping its exit status"
+        );
+        assert_eq!(
+            run("echo pre && false", "echo main"),
+            ("pre\n".to_owned(), Some(1)),
+            "Failed pre hook should abort the task"
+        );
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_run_after_dependencies() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path().to_path_buf();
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        let dependency = |command: &str, cwd: PathBuf| {
+            let mut dependency = spawn_in_terminal(None);
+            dependency.command = command.to_owned();
+            dependency.args = Vec::new();
+            dependency.cwd = Some(cwd);
+            dependency
+                .env
+                .insert("GREETING".to_owned(), "it's me".to_owned());
+            dependency
+        };
+        let run = |dependencies: Vec<SpawnInTerminal>| {
+            let mut spawn = spawn_in_terminal(None);
+            spawn.command = "echo main; echo \"[$GREETING]\"".to_owned();
+            spawn.args = Vec::new();
+            spawn.cwd = Some(dir.clone());
+            spawn.run_after(dependencies);
+            let output = shell_process(&spawn).current_dir(&dir).output().unwrap();
+            (
+                output.status.code(),
+                String::from_utf8_lossy(&output.stdout).into_owned(),
+            )
+        };
+
+        assert_eq!(
+            run(vec![
+                dependency("echo \"$GREETING\"", dir.clone()),
+                dependency("basename \"$PWD\"", dir.join("sub")),
+            ]),
+            (Some(0), "it's me\nsub\nmain\n[]\n".to_owned()),
+            "Dependencies should run in order, in their cwd and env, not leaking into the command"
+        );
+        assert_eq!(
+            run(vec![
+                dependency("exit 3", dir.clone()),
+                dependency("echo second", dir.clone()),
+            ]),
+            (Some(3), String::new()),
+            "Nothing should run after a failed dependency"
+        );
+
+        let mut with_secret = dependency("echo \"[$TOKEN]\"", dir.clone());
+        with_secret
+            .env
+            .insert("TOKEN".to_owned(), "s3cr3t".to_owned());
+        with_secret
+            .secrets
+            .insert("TOKEN".to_owned(), "deploy-token".to_owned());
+        let mut spawn = spawn_in_terminal(None);
+        spawn.command = "true".to_owned();
+        spawn.args = Vec::new();
+        spawn.cwd = Some(dir.clone());
+        spawn.run_after(vec![with_secret]);
+        assert!(
+            !posix_script(&spawn).contains("s3cr3t"),
+            "Secret of a dependency should never get onto the command line"
+        );
+        let output = shell_process(&spawn).current_dir(&dir).output().unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "[s3cr3t]\n",
+            "Secret should still reach the dependency through the env"
+        );
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_commit_on_success() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let repo = temp_dir.path();
+        let identity = [
+            ("GIT_AUTHOR_NAME", "Zed"),
+            ("GIT_AUTHOR_EMAIL", "zed@example.com"),
+            ("GIT_COMMITTER_NAME", "Zed"),
+            ("GIT_COMMITTER_EMAIL", "zed@example.com"),
+        ];
+        let git = |args: &[&str]| {
+            let output = std::process::Command::new("git")
+                .args(args)
+                .current_dir(repo)
+                .envs(identity)
+                .output()
+                .unwrap();
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        };
+        git(&["init", "-q"]);
+        let run = |command: &str| {
+            let mut spawn = spawn_in_terminal(None);
+            spawn.command = command.to_owned();
+            spawn.args = Vec::new();
+            spawn.git_commit_message = Some("format 'src'".to_owned());
+            spawn.commit_on_success();
+            shell_process(&spawn)
+                .current_dir(repo)
+                .envs(identity)
+                .status()
+                .unwrap()
+                .code()
+        };
+
+        assert_eq!(run("echo formatted > a.txt"), Some(0));
+        assert_eq!(
+            git(&["log", "--format=%s"]),
+            "format 'src'\n",
+            "Changes of a successful run should be committed with the message"
+        );
+        assert_eq!(git(&["status", "--porcelain"]), "");
+
+        assert_eq!(
+            run("echo broken > a.txt && false"),
+            Some(1),
+            "Exit status of the failed command should be kept"
+        );
+        assert_eq!(
+            git(&["log", "--format=%s"]),
+            "format 'src'\n",
+            "Nothing should be committed after a failed run"
+        );
+        git(&["checkout", "-q", "--", "a.txt"]);
+
+        assert_eq!(run("true"), Some(0));
+        assert_eq!(
+            git(&["log", "--format=%s"]),
+            "format 'src'\n",
+            "Nothing should be committed without changes"
+        );
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_capture_test_results() {
+        let mut spawn = spawn_in_terminal(None);
+        spawn.id = TaskId("test_capture_test_results".to_owned());
+        spawn.command = "echo 'not ok 1' && false".to_owned();
+        spawn.args = Vec::new();
+        spawn.results_format = Some(TestResultsFormat::Tap);
+        let status_file = spawn.results_status_file().unwrap();
+        let results_file = spawn.test_results_file().unwrap();
+        spawn.capture_test_results();
+        let output = shell_process(&spawn).output().unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "not ok 1\n");
+        assert_eq!(
+            output.status.code(),
+            Some(1),
+            "Exit status of the command should be kept"
+        );
+        assert_eq!(
+            std::fs::read_to_string(&results_file).unwrap(),
+            "not ok 1\n",
+            "Stdout should be captured as the test results"
+        );
+        assert_eq!(std::fs::read_to_string(&status_file).unwrap(), "1\n");
+        std::fs::remove_file(results_file).ok();
+        std::fs::remove_file(status_file).ok();
+    }
+
+    #[test]
+    fn test_add_verbosity_flag() {
+        let mut cargo_test = spawn_in_terminal(None);
+        assert!(cargo_test.add_verbosity_flag());
+        assert_eq!(cargo_test.command, "cargo");
+        assert_eq!(cargo_test.args, vec!["test", "-v"]);
+
+        let mut cargo_test_with_test_args = spawn_in_terminal(None);
+        cargo_test_with_test_args
+            .args
+            .extend(["--".to_owned(), "--nocapture".to_owned()]);
+        assert!(cargo_test_with_test_args.add_verbosity_flag());
+        assert_eq!(
+            cargo_test_with_test_args.args,
+            vec!["test", "-v", "--", "--nocapture"],
+            "Verbosity flag should be passed to cargo, not to the test binary"
+        );
+
+        let mut unknown_runner = spawn_in_terminal(None);
+        unknown_runner.command = "make".to_owned();
+        assert!(!unknown_runner.add_verbosity_flag());
+        assert_eq!(unknown_runner.args, vec!["test"]);
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_lower_priority() {
+        let mut spawn = spawn_in_terminal(None);
+        spawn.nice = Some(10);
+        spawn.lower_priority();
+        assert_eq!(spawn.command, "nice");
+        assert_eq!(spawn.args, vec!["-n", "10", "cargo", "test"]);
+        assert_eq!(spawn.nice, None);
+
+        for ignored_nice in [None, Some(0), Some(-5), Some(42)] {
+            let mut spawn = spawn_in_terminal(None);
+            spawn.nice = ignored_nice;
+            spawn.lower_priority();
+            assert_eq!(
+                (spawn.command.as_str(), spawn.args),
+                ("cargo", vec!["test".to_owned()]),
+                "Niceness {ignored_nice:?} should leave the default priority"
+            );
+        }
+    }
+
+    #[test]
+    fn test_elevate_privileges() {
+        let mut not_elevated = spawn_in_terminal(None);
+        not_elevated.elevate_privileges();
+        assert_eq!(
+            (not_elevated.command.as_str(), not_elevated.args),
+            ("cargo", vec!["test".to_owned()])
+        );
+
+        let mut elevated = spawn_in_terminal(None);
+        elevated.elevate = true;
+        elevated
+            .env
+            .insert("RUST_LOG".to_owned(), "info".to_owned());
+        elevated
+            .secrets
+            .insert("TOKEN".to_owned(), "deploy-token".to_owned());
+        elevated
+            .env
+            .insert("TOKEN".to_owned(), "secret value".to_owned());
+        elevated.elevate_privileges();
+        assert!(!elevated.elevate);
+        if cfg!(windows) {
+            assert_eq!(elevated.command, "powershell");
+            assert_eq!(
+                elevated.args,
+                vec![
+                    "-NoProfile".to_owned(),
+                    "-Command".to_owned(),
+                    shell_quote("$process = Start-Process -FilePath 'cargo' -ArgumentList 'test' -Verb RunAs -Wait -PassThru; exit $process.ExitCode")
+                ],
+                "PowerShell script should be passed as a single quoted argument"
+            );
+        } else {
+            assert_eq!(elevated.command, "sudo");
+            assert_eq!(
+                elevated.args,
+                vec![
+                    "--reset-timestamp",
+                    "--preserve-env=RUST_LOG,TOKEN",
+                    "--",
+                    "cargo",
+                    "test"
+                ],
+                "Elevation should never reuse the cached credentials and keep the task env"
+            );
+        }
+    }
+
+    #[test]
+    fn test_wrap_in_container() {
+        let mut on_host = spawn_in_terminal(None);
+        on_host.wrap_in_container();
+        assert_eq!(on_host.command, "cargo");
+        assert_eq!(on_host.args, vec!["test"]);
+
+        let mut in_image = spawn_in_terminal(Some(TaskContainer::Image("rust:latest".to_owned())));
+        in_image.wrap_in_container();
+        assert_eq!(in_image.command, "docker");
+        assert_eq!(
+            in_image.args,
+            vec![
+                "run",
+                "--rm",
+                "-i",
+                "-v",
+                "'/project:/project'",
+                "-w",
+                "'/project'",
+                "-e",
+                "ZED_FILE",
+                "'rust:latest'",
+                "cargo",
+                "test"
+            ]
+        );
+        assert_eq!(in_image.container, None);
+
+        let mut in_running_container =
+            spawn_in_terminal(Some(TaskContainer::Exec("my dev server".to_owned())));
+        in_running_container.cwd = Some(PathBuf::from("/my project"));
+        in_running_container.wrap_in_container();
+        assert_eq!(in_running_container.command, "docker");
+        assert_eq!(
+            in_running_container.args,
+            vec![
+                "exec",
+                "-i",
+                "-w",
+                "'/my project'",
+                "-e",
+                "ZED_FILE",
+                "'my dev server'",
+                "cargo",
+                "test"
+            ],
+            "Paths and names with spaces should be quoted"
+        );
+
+        let mut in_devcontainer = spawn_in_terminal(Some(TaskContainer::Devcontainer));
+        in_devcontainer
+            .secrets
+            .insert("TOKEN".to_owned(), "deploy-token".to_owned());
+        in_devcontainer
+            .env
+            .insert("TOKEN".to_owned(), "secret value".to_owned());
+        in_devcontainer.wrap_in_container();
+        assert_eq!(
+            posix_script(&in_devcontainer),
+            "devcontainer exec --workspace-folder '/project' --remote-env TOKEN=\"$TOKEN\" --remote-env 'ZED_FILE=/project/src/lib.rs' cargo test",
+            "Env values should be passed quoted, secrets should be expanded by sh at the spawn, even when read already"
+        );
+    }
+
+    #[test]
+    fn test_separate_stderr() {
+        let mut combined = spawn_in_terminal(None);
+        combined.separate_stderr();
+        assert_eq!(combined.stderr_file(), None);
+        assert_eq!(combined.args, vec!["test"]);
+
+        let mut separate = SpawnInTerminal {
+            combine_output: false,
+            ..spawn_in_terminal(None)
+        };
+        let stderr_file = separate
+            .stderr_file()
+            .expect("stderr should be captured separately");
+        assert!(stderr_file.starts_with(std::env::temp_dir()));
+        separate.separate_stderr();
+        assert_eq!(
+            posix_script(&separate),
+            format!(
+                "{{ cargo test; }} 2> {}",
+                shell_quote(&stderr_file.to_string_lossy())
+            )
+        );
+
+        let concurrent = SpawnInTerminal {
+            combine_output: false,
+            spawn_nonce: SpawnInTerminal::next_spawn_nonce(),
+            ..spawn_in_terminal(None)
+        };
+        assert_ne!(
+            concurrent.stderr_file(),
+            SpawnInTerminal {
+                spawn_nonce: SpawnInTerminal::next_spawn_nonce(),
+                ..concurrent.clone()
+            }
+            .stderr_file(),
+            "Concurrent runs of the same task should capture stderr into different files"
+        );
+    }
+
+    #[test]
+    fn test_later_step_refers_to_earlier_step_cwd() {
+        let steps = tasks_for(
+            TaskDefinitions(vec![
+                Definition {
+                    label: "build".to_owned(),
+                    command: "cargo".to_owned(),
+                    args: vec!["build".to_owned()],
+                    cwd: Some("/project/crates/server".to_owned()),
+                    ..Definition::default()
+                },
+                Definition {
+                    label: "test".to_owned(),
+                    command: "cargo".to_owned(),
+                    args: vec!["nextest".to_owned(), "run".to_owned()],
+                    results_format: Some(TestResultsFormat::Junit),
+                    results_file: Some("target/junit.xml".to_owned()),
+                    ..Definition::default()
+                },
+                Definition {
+                    label: "deploy".to_owned(),
+                    command: "deploy".to_owned(),
+                    args: vec![
+                        "--from".to_owned(),
+                        "${steps.build.cwd}/target".to_owned(),
+                        "--report".to_owned(),
+                        "${steps.test.output_file}".to_owned(),
+                    ],
+                    ..Definition::default()
+                },
+            ]),
+            "test",
+        );
+        let prepared = prepare_steps(
+            &steps,
+            &TaskContext {
+                cwd: Some(PathBuf::from("/project")),
+                task_variables: TaskVariables::default(),
+                selection_fallbacks: SelectionFallbacks::default(),
+                args_override: Default::default(),
+            },
+        )
+        .unwrap();
+        assert_eq!(prepared.len(), 3);
+        assert_eq!(prepared[2].cwd, Some(PathBuf::from("/project")));
+        assert_eq!(
+            prepared[2].args,
+            vec![
+                "--from",
+                "/project/crates/server/target",
+                "--report",
+                "/project/target/junit.xml"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_variable_name_from_env_name() {
+        assert_eq!(VariableName::from("ZED_FILE"), VariableName::File);
+        assert_eq!(
+            VariableName::from("ZED_STAGED_FILES"),
+            VariableName::StagedFiles
+        );
+        assert_eq!(
+            VariableName::from("VAR"),
+            VariableName::Custom(Cow::Borrowed("VAR"))
+        );
+        assert_eq!(
+            VariableName::from("ZED_VAR").to_string(),
+            "ZED_VAR",
+            "Custom variables should not get the prefix twice"
+        );
+    }
+
+    #[test]
+    fn test_resolve_command() {
+        let mut spawn = spawn_in_terminal(None);
+        spawn.command = "echo".to_owned();
+        spawn.args = vec![
+            "$ZED_FILE".to_owned(),
+            "${ZED_FILE}:1".to_owned(),
+            "$HOME".to_owned(),
+            "\\$ZED_FILE".to_owned(),
+        ];
+        assert_eq!(
+            spawn.resolve_command(),
+            Ok("echo /project/src/lib.rs /project/src/lib.rs:1 $HOME \\$ZED_FILE".to_owned())
+        );
+
+        spawn.args = vec![
+            "$ZED_SYMBOL".to_owned(),
+            "${ZED_ROW}".to_owned(),
+            "$ZED_SYMBOL".to_owned(),
+        ];
+        assert_eq!(
+            spawn.resolve_command(),
+            Err(vec!["ZED_SYMBOL".to_owned(), "ZED_ROW".to_owned()]),
+            "Each missing variable should be reported once"
+        );
+        spawn.args.push("$ZED_FILE".to_owned());
+        assert_eq!(
+            spawn.resolve_available_variables(),
+            (
+                "echo $ZED_SYMBOL ${ZED_ROW} $ZED_SYMBOL /project/src/lib.rs".to_owned(),
+                vec!["ZED_SYMBOL".to_owned(), "ZED_ROW".to_owned()]
+            ),
+            "Missing variables should be left as their references"
+        );
+    }
+}
@@ -0,0 +1,174 @@
+//! A source of tasks, synthesized from the targets of a `Makefile`.
+
+use std::sync::Arc;
+
+use collections::HashSet;
+use futures::{channel::mpsc::UnboundedReceiver, StreamExt};
+use gpui::{AppContext, Context, Model, ModelContext};
+
+use crate::{
+    static_source::{tasks_for, Definition, TaskDefinitions},
+    Task, TaskSource,
+};
+
+/// Names of the files `make` reads the rules from, when it's run without `-f`.
+pub const MAKEFILE_NAMES: &[&str] = &["GNUmakefile", "makefile", "Makefile"];
+
+/// Targets of the `Makefile` contents that can be run with `make <target>`, in the order they are declared.
+/// Understands the `.PHONY` declarations, skips the pattern rules (`%.o: %.c`), the special targets (`.SUFFIXES`),
+/// the targets computed from variables and the variable assignments.
+pub fn makefile_targets(contents: &str) -> Vec<String> {
+    let mut targets = Vec::new();
+    let mut seen = HashSet::default();
+    let mut logical_line = String::new();
+    for line in contents.lines() {
+        if logical_line.is_empty() && line.starts_with('\t') {
+            // A recipe line.
+            continue;
+        }
+        if let Some(continued) = line.strip_suffix('\\') {
+            logical_line.push_str(continued);
+            logical_line.push(' ');
+            continue;
+        }
+        logical_line.push_str(line);
+        let line = std::mem::take(&mut logical_line);
+        let line = line.split('#').next().unwrap_or_default();
+        let Some((rule_targets, prerequisites)) = line.split_once(':') else {
+            continue;
+        };
+        if rule_targets.contains('=') || prerequisites.trim_start_matches(':').starts_with('=') {
+            continue;
+        }
+        let rule_targets = rule_targets.split_whitespace().collect::<Vec<_>>();
+        let new_targets = if rule_targets == [".PHONY"] {
+            prerequisites.split_whitespace().collect()
+        } else {
+            rule_targets
+        };
+        for target in new_targets {
+            let runnable = !target.starts_with('.') && !target.contains(['%', '$']);
+            if runnable && seen.insert(target.to_owned()) {
+                targets.push(target.to_owned());
+            }
+        }
+    }
+    targets
+}
+
+/// Task definitions to run every target of the `Makefile` contents, e.g. `make build`, spawned in `cwd`.
+pub fn makefile_tasks(contents: &str, cwd: Option<String>) -> TaskDefinitions {
+    TaskDefinitions(
+        makefile_targets(contents)
+            .into_iter()
+            .map(|target| Definition {
+                label: format!("make {target}"),
+                command: "make".to_owned(),
+                args: vec![target],
+                cwd: cwd.clone(),
+                ..Definition::default()
+            })
+            .collect(),
+    )
+}
+
+/// A source of the tasks for the targets of a `Makefile`, reloaded when the file changes.
+pub struct MakefileSource {
+    tasks: Vec<Arc<dyn Task>>,
+}
+
+impl MakefileSource {
+    /// Initializes the source, that synthesizes the tasks from every new contents of the file sent by the `tracker`.
+    pub fn new(
+        id_base: String,
+        mut tracker: UnboundedReceiver<String>,
+        cwd: Option<String>,
+        cx: &mut AppContext,
+    ) -> Model<Box<dyn TaskSource>> {
+        cx.new_model(|cx: &mut ModelContext<Box<dyn TaskSource>>| {
+            cx.spawn(|source, mut cx| async move {
+                while let Some(contents) = tracker.next().await {
+                    let definitions = makefile_tasks(&contents, cwd.clone());
+                    let updated = source.update(&mut cx, |source, cx| {
+                        if let Some(source) = source.as_any().downcast_mut::<Self>() {
+                            source.tasks = tasks_for(definitions, &id_base);
+                            cx.notify();
+                        }
+                    });
+                    if updated.is_err() {
+                        break;
+                    }
+                }
+            })
+            .detach();
+            Box::new(Self { tasks: Vec::new() }) as Box<dyn TaskSource>
+        })
+    }
+}
+
+impl TaskSource for MakefileSource {
+    fn as_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn tasks_to_schedule(
+        &mut self,
+        _: &mut ModelContext<Box<dyn TaskSource>>,
+    ) -> Vec<Arc<dyn Task>> {
+        self.tasks.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_makefile_targets() {
+        let makefile = "\
+CC := gcc
+CFLAGS = -O2 -Wall
+BIN_DIR ?= bin
+export RUSTFLAGS := -Dwarnings
+
+.PHONY: all clean \\
+\tinstall docs
+
+all: $(BIN_DIR)/app # the default target
+\t$(CC) $(CFLAGS) -o app main.c
+
+%.o: %.c
+\t$(CC) -c $< -o $@
+
+$(BIN_DIR)/app: main.o
+\t$(CC) -o $@ $^
+
+.SUFFIXES:
+
+main.o util.o: common.h
+
+clean:
+\trm -rf $(BIN_DIR) *.o
+\techo \"all: fake\"
+
+test:: all
+\t./app --test
+";
+        assert_eq!(
+            makefile_targets(makefile),
+            vec!["all", "clean", "install", "docs", "main.o", "util.o", "test"],
+            "Targets should be listed as declared, including the phony ones, with the pattern and special rules, variables and recipes skipped"
+        );
+    }
+
+    #[test]
+    fn test_makefile_tasks() {
+        let definitions = makefile_tasks("build:\n\tcargo build\n", Some("/dir/sub".to_owned()));
+        assert_eq!(definitions.0.len(), 1);
+        let definition = &definitions.0[0];
+        assert_eq!(definition.label, "make build");
+        assert_eq!(definition.command, "make");
+        assert_eq!(definition.args, vec!["build".to_owned()]);
+        assert_eq!(definition.cwd.as_deref(), Some("/dir/sub"));
+    }
+}
@@ -3,7 +3,8 @@
 use std::sync::Arc;
 
 use crate::{
-    static_source::RevealStrategy, SpawnInTerminal, Task, TaskContext, TaskId, TaskSource,
+    captured_output::DEFAULT_MAX_OUTPUT_BYTES, static_source::ShowStrategy, SpawnInTerminal, Task,
+    TaskContext, TaskId, TaskSource,
 };
 use gpui::{AppContext, Context, Model};
 
@@ -13,12 +14,12 @@ pub struct OneshotSource {
 }
 
 #[derive(Clone)]
-struct OneshotTask {
+pub(crate) struct OneshotTask {
     id: TaskId,
 }
 
 impl OneshotTask {
-    fn new(prompt: String) -> Self {
+    pub(crate) fn new(prompt: String) -> Self {
         Self { id: TaskId(prompt) }
     }
 }
@@ -36,14 +37,24 @@ impl Task for OneshotTask {
         None
     }
 
+    fn variable_templates(&self, _: &TaskContext) -> Vec<String> {
+        vec![self.id().0.clone()]
+    }
+
+    fn command_line_preview(&self, _: &TaskContext) -> String {
+        self.id().0.clone()
+    }
+
     fn prepare_exec(&self, cx: TaskContext) -> Option<SpawnInTerminal> {
         if self.id().0.is_empty() {
             return None;
         }
         let TaskContext {
             cwd,
-            task_variables,
+            mut task_variables,
+            ..
         } = cx;
+        task_variables.resolve_referenced([self.id().0.as_str()]);
         Some(SpawnInTerminal {
             id: self.id().clone(),
             label: self.name().to_owned(),
@@ -53,7 +64,38 @@ impl Task for OneshotTask {
             env: task_variables.into_env_variables(),
             use_new_terminal: Default::default(),
             allow_concurrent_runs: Default::default(),
-            reveal: RevealStrategy::default(),
+            cancel_previous: Default::default(),
+            reveal: None,
+            dock: None,
+            container: None,
+            combine_output: true,
+            max_output_bytes: DEFAULT_MAX_OUTPUT_BYTES,
+            kill_on_output_limit: false,
+            confirm: false,
+            show: ShowStrategy::default(),
+            nice: None,
+            elevate: false,
+            requires: Vec::new(),
+            retry: None,
+            secrets: Default::default(),
+            tail_file: None,
+            git_diff: None,
+            group: None,
+            terminal_group: None,
+            command_substitution: false,
+            pre: None,
+            post: None,
+            results_format: None,
+            results_file: None,
+            reveal_cwd_in_project_panel: false,
+            split: None,
+            git_commit_message: None,
+            depends_on: Vec::new(),
+            parallel: false,
+            singleton_group: None,
+            show_summary: false,
+            problem_matcher: None,
+            spawn_nonce: SpawnInTerminal::next_spawn_nonce(),
         })
     }
 }
@@ -0,0 +1,213 @@
+//! A source of tasks, synthesized from the `scripts` of a `package.json` file.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use futures::{channel::mpsc::UnboundedReceiver, StreamExt};
+use gpui::{AppContext, Context, Model, ModelContext};
+use serde::Deserialize;
+use util::ResultExt;
+
+use crate::{
+    static_source::{tasks_for, Definition, TaskDefinitions},
+    Task, TaskSource,
+};
+
+/// A package manager to run the scripts with, detected by the lockfile next to `package.json`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PackageManager {
+    /// `npm`, used when there's no lockfile of the other package managers.
+    #[default]
+    Npm,
+    /// `yarn`, detected by `yarn.lock`.
+    Yarn,
+    /// `pnpm`, detected by `pnpm-lock.yaml`.
+    Pnpm,
+}
+
+impl PackageManager {
+    /// Lockfiles of the package managers, in the order they are checked in.
+    pub const LOCKFILES: &'static [(&'static str, Self)] = &[
+        ("pnpm-lock.yaml", Self::Pnpm),
+        ("yarn.lock", Self::Yarn),
+        ("package-lock.json", Self::Npm),
+    ];
+
+    /// Detects the package manager by the lockfile that exists in the directory of `package.json`, npm if there's none.
+    pub fn detect(lockfile_exists: impl Fn(&str) -> bool) -> Self {
+        Self::LOCKFILES
+            .iter()
+            .find(|(lockfile, _)| lockfile_exists(lockfile))
+            .map_or(Self::Npm, |(_, package_manager)| *package_manager)
+    }
+
+    /// A command of the package manager.
+    pub fn command(&self) -> &'static str {
+        match self {
+            Self::Npm => "npm",
+            Self::Yarn => "yarn",
+            Self::Pnpm => "pnpm",
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PackageJson {
+    #[serde(default)]
+    scripts: BTreeMap<String, serde_json_lenient::Value>,
+}
+
+/// Task definitions for every script of the `package.json` contents, e.g. `npm run build`, spawned in `cwd`.
+/// Scripts are sorted by their names.
+pub fn package_json_tasks(
+    contents: &str,
+    package_manager: PackageManager,
+    cwd: Option<String>,
+) -> anyhow::Result<TaskDefinitions> {
+    let package_json = if contents.trim().is_empty() {
+        PackageJson::default()
+    } else {
+        serde_json_lenient::from_str::<PackageJson>(contents)?
+    };
+    let command = package_manager.command();
+    Ok(TaskDefinitions(
+        package_json
+            .scripts
+            .into_iter()
+            .filter(|(_, script)| script.is_string())
+            .map(|(name, _)| Definition {
+                label: format!("{command} run {name}"),
+                command: command.to_owned(),
+                args: vec!["run".to_owned(), name],
+                cwd: cwd.clone(),
+                ..Definition::default()
+            })
+            .collect(),
+    ))
+}
+
+/// A source of the tasks for the scripts of a `package.json` file, reloaded when the file changes.
+pub struct PackageJsonSource {
+    tasks: Vec<Arc<dyn Task>>,
+}
+
+impl PackageJsonSource {
+    /// Initializes the source, that synthesizes the tasks from every new contents of the file sent by the `tracker`.
+    pub fn new(
+        id_base: String,
+        mut tracker: UnboundedReceiver<String>,
+        package_manager: PackageManager,
+        cwd: Option<String>,
+        cx: &mut AppContext,
+    ) -> Model<Box<dyn TaskSource>> {
+        cx.new_model(|cx: &mut ModelContext<Box<dyn TaskSource>>| {
+            cx.spawn(|source, mut cx| async move {
+                while let Some(contents) = tracker.next().await {
+                    let Some(definitions) =
+                        package_json_tasks(&contents, package_manager, cwd.clone()).log_err()
+                    else {
+                        continue;
+                    };
+                    let updated = source.update(&mut cx, |source, cx| {
+                        if let Some(source) = source.as_any().downcast_mut::<Self>() {
+                            source.tasks = tasks_for(definitions, &id_base);
+                            cx.notify();
+                        }
+                    });
+                    if updated.is_err() {
+                        break;
+                    }
+                }
+            })
+            .detach();
+            Box::new(Self { tasks: Vec::new() }) as Box<dyn TaskSource>
+        })
+    }
+}
+
+impl TaskSource for PackageJsonSource {
+    fn as_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn tasks_to_schedule(
+        &mut self,
+        _: &mut ModelContext<Box<dyn TaskSource>>,
+    ) -> Vec<Arc<dyn Task>> {
+        self.tasks.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_package_json_tasks() {
+        let contents = r#"{
+            "name": "demo",
+            "scripts": {
+                "test": "vitest",
+                "build": "tsc -p .",
+                "weird": { "not": "a script" },
+            },
+        }"#;
+        let definitions =
+            package_json_tasks(contents, PackageManager::Pnpm, Some("/dir/web".to_owned()))
+                .unwrap();
+        assert_eq!(
+            definitions
+                .0
+                .iter()
+                .map(|definition| (
+                    definition.label.as_str(),
+                    definition.command.as_str(),
+                    definition.args.clone(),
+                    definition.cwd.as_deref(),
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "pnpm run build",
+                    "pnpm",
+                    vec!["run".to_owned(), "build".to_owned()],
+                    Some("/dir/web"),
+                ),
+                (
+                    "pnpm run test",
+                    "pnpm",
+                    vec!["run".to_owned(), "test".to_owned()],
+                    Some("/dir/web"),
+                ),
+            ],
+            "Scripts should be sorted by name, non-string scripts should be skipped"
+        );
+
+        assert!(
+            package_json_tasks(r#"{ "name": "no-scripts" }"#, PackageManager::Npm, None)
+                .unwrap()
+                .0
+                .is_empty()
+        );
+        assert!(package_json_tasks("", PackageManager::Npm, None)
+            .unwrap()
+            .0
+            .is_empty());
+        assert!(package_json_tasks("{ not json", PackageManager::Npm, None).is_err());
+    }
+
+    #[test]
+    fn test_detect_package_manager() {
+        assert_eq!(PackageManager::detect(|_| false), PackageManager::Npm);
+        assert_eq!(
+            PackageManager::detect(|lockfile| lockfile == "yarn.lock"),
+            PackageManager::Yarn
+        );
+        assert_eq!(
+            PackageManager::detect(
+                |lockfile| lockfile == "pnpm-lock.yaml" || lockfile == "yarn.lock"
+            ),
+            PackageManager::Pnpm,
+            "pnpm lockfile should win over the others"
+        );
+    }
+}
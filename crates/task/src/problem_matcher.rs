@@ -0,0 +1,350 @@
+//! Matchers of the problems that tasks print into their output, e.g. compiler errors, to report them as the project diagnostics.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+use regex::{Captures, Regex};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A matcher of the problems in the output of a task: one of the built-in matchers, or a custom pattern.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum ProblemMatcher {
+    /// A matcher of the output of a known tool.
+    Builtin(BuiltinProblemMatcher),
+    /// A regex that matches a problem in a single line of the output.
+    Custom(ProblemPattern),
+}
+
+/// A matcher of the output of a known tool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BuiltinProblemMatcher {
+    /// `error[E0308]: mismatched types` lines of `rustc` and `cargo`, followed by their ` --> src/main.rs:2:5` location lines.
+    Rustc,
+    /// `src/a.ts(3,5): error TS2322: ...` lines of `tsc`, and the `src/a.ts:3:5 - error TS2322: ...` lines of its `--pretty` output.
+    Tsc,
+}
+
+/// A regex that matches a problem in a single line of the output, with the numbers of its capture groups for the parts of the problem.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ProblemPattern {
+    /// A regex to match every line of the output against.
+    pub regexp: String,
+    /// A group with the path of the file.
+    #[serde(default = "default_file_group")]
+    pub file: usize,
+    /// A group with the line in the file, starting from 1.
+    #[serde(default = "default_line_group")]
+    pub line: usize,
+    /// A group with the column in the line, starting from 1; the problem is at the first column, if the group does not match.
+    #[serde(default = "default_column_group")]
+    pub column: usize,
+    /// A group with the message; the whole line is the message, if the group does not match.
+    #[serde(default = "default_message_group")]
+    pub message: usize,
+    /// A group with the severity: `error`, `warning` or `info`; the problem is an error, if the group is not set or does not match.
+    #[serde(default)]
+    pub severity: Option<usize>,
+}
+
+fn default_file_group() -> usize {
+    1
+}
+
+fn default_line_group() -> usize {
+    2
+}
+
+fn default_column_group() -> usize {
+    3
+}
+
+fn default_message_group() -> usize {
+    4
+}
+
+/// How severe a problem is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProblemSeverity {
+    /// An error, e.g. one that fails the build.
+    Error,
+    /// A warning.
+    Warning,
+    /// A note or a hint.
+    Info,
+}
+
+impl ProblemSeverity {
+    fn parse(severity: &str) -> Self {
+        match severity.to_ascii_lowercase().as_str() {
+            "warning" | "warn" => Self::Warning,
+            "info" | "note" | "hint" | "help" => Self::Info,
+            _ => Self::Error,
+        }
+    }
+}
+
+/// A problem printed into the output of a task.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Problem {
+    /// An absolute path of the file with the problem, if the root of the relative paths is known.
+    pub path: PathBuf,
+    /// A line in the file, starting from 1.
+    pub row: u32,
+    /// A column in the line, starting from 1.
+    pub column: u32,
+    /// How severe the problem is.
+    pub severity: ProblemSeverity,
+    /// A code of the problem, e.g. `E0308` or `TS2322`, if the tool reports one.
+    pub code: Option<String>,
+    /// A description of the problem.
+    pub message: String,
+}
+
+#[derive(Debug)]
+enum LineMatcher {
+    Rustc {
+        header: Regex,
+        location: Regex,
+    },
+    Tsc {
+        regexes: [Regex; 2],
+    },
+    Custom {
+        regex: Regex,
+        groups: ProblemPattern,
+    },
+}
+
+/// Parses the output of a task line by line, as it's printed, into the problems it reports.
+#[derive(Debug)]
+pub struct ProblemParser {
+    matcher: LineMatcher,
+    root: Option<PathBuf>,
+    /// A `rustc` problem reported without its location yet, which is printed on one of the next lines.
+    pending_problem: Option<(ProblemSeverity, Option<String>, String)>,
+}
+
+impl ProblemParser {
+    /// Creates a parser of the matcher, that resolves the relative paths of the problems against the root.
+    /// Fails if the custom pattern is not a valid regex.
+    pub fn new(matcher: &ProblemMatcher, root: Option<PathBuf>) -> anyhow::Result<Self> {
+        let matcher = match matcher {
+            ProblemMatcher::Builtin(BuiltinProblemMatcher::Rustc) => LineMatcher::Rustc {
+                header: Regex::new(r"^(error|warning)(?:\[(\w+)\])?: (.+)$")?,
+                location: Regex::new(r"^\s*--> (.+?):(\d+):(\d+)$")?,
+            },
+            ProblemMatcher::Builtin(BuiltinProblemMatcher::Tsc) => LineMatcher::Tsc {
+                regexes: [
+                    Regex::new(r"^(.+?)\((\d+),(\d+)\): (error|warning) (TS\d+): (.+)$")?,
+                    Regex::new(r"^(.+?):(\d+):(\d+) - (error|warning) (TS\d+): (.+)$")?,
+                ],
+            },
+            ProblemMatcher::Custom(pattern) => LineMatcher::Custom {
+                regex: Regex::new(&pattern.regexp).with_context(|| {
+                    format!("parsing the problem matcher regexp {:?}", pattern.regexp)
+                })?,
+                groups: pattern.clone(),
+            },
+        };
+        Ok(Self {
+            matcher,
+            root,
+            pending_problem: None,
+        })
+    }
+
+    /// Parses the next line of the output, returning the problem that the line completes, if any.
+    pub fn parse_line(&mut self, line: &str) -> Option<Problem> {
+        let line = line.trim_end();
+        match &self.matcher {
+            LineMatcher::Rustc { header, location } => {
+                if let Some(captures) = header.captures(line) {
+                    self.pending_problem = Some((
+                        ProblemSeverity::parse(&captures[1]),
+                        captures.get(2).map(|code| code.as_str().to_owned()),
+                        captures[3].to_owned(),
+                    ));
+                    return None;
+                }
+                let captures = location.captures(line)?;
+                let (severity, code, message) = self.pending_problem.take()?;
+                Some(Problem {
+                    path: self.resolve_path(&captures[1]),
+                    row: captures[2].parse().ok()?,
+                    column: captures[3].parse().ok()?,
+                    severity,
+                    code,
+                    message,
+                })
+            }
+            LineMatcher::Tsc { regexes } => {
+                let captures = regexes.iter().find_map(|regex| regex.captures(line))?;
+                Some(Problem {
+                    path: self.resolve_path(&captures[1]),
+                    row: captures[2].parse().ok()?,
+                    column: captures[3].parse().ok()?,
+                    severity: ProblemSeverity::parse(&captures[4]),
+                    code: Some(captures[5].to_owned()),
+                    message: captures[6].to_owned(),
+                })
+            }
+            LineMatcher::Custom { regex, groups } => {
+                let captures = regex.captures(line)?;
+                let group = |captures: &Captures<'_>, ix: usize| {
+                    captures.get(ix).map(|group| group.as_str())
+                };
+                Some(Problem {
+                    path: self.resolve_path(group(&captures, groups.file)?),
+                    row: group(&captures, groups.line)?.parse().ok()?,
+                    column: group(&captures, groups.column)
+                        .and_then(|column| column.parse().ok())
+                        .unwrap_or(1),
+                    severity: groups
+                        .severity
+                        .and_then(|ix| group(&captures, ix))
+                        .map_or(ProblemSeverity::Error, ProblemSeverity::parse),
+                    code: None,
+                    message: group(&captures, groups.message).unwrap_or(line).to_owned(),
+                })
+            }
+        }
+    }
+
+    fn resolve_path(&self, path: &str) -> PathBuf {
+        let path = Path::new(path);
+        match &self.root {
+            Some(root) if path.is_relative() => root.join(path),
+            _ => path.to_path_buf(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(matcher: ProblemMatcher, output: &str) -> Vec<Problem> {
+        let mut parser = ProblemParser::new(&matcher, Some(PathBuf::from("/project"))).unwrap();
+        output
+            .lines()
+            .filter_map(|line| parser.parse_line(line))
+            .collect()
+    }
+
+    #[test]
+    fn test_rustc_problems() {
+        let output = r#"   Compiling demo v0.1.0 (/project)
+warning: unused variable: `x`
+ --> src/lib.rs:3:9
+  |
+3 |     let x = 1;
+  |         ^ help: if this is intentional, prefix it with an underscore: `_x`
+  |
+  = note: `#[warn(unused_variables)]` on by default
+
+error[E0308]: mismatched types
+  --> /elsewhere/src/main.rs:12:18
+   |
+12 |     let y: u32 = "y";
+   |                  ^^^ expected `u32`, found `&str`
+   |
+  ::: src/other.rs:1:1
+error: could not compile `demo` (bin "demo") due to 1 previous error
+"#;
+        assert_eq!(
+            parse(
+                ProblemMatcher::Builtin(BuiltinProblemMatcher::Rustc),
+                output
+            ),
+            vec![
+                Problem {
+                    path: PathBuf::from("/project/src/lib.rs"),
+                    row: 3,
+                    column: 9,
+                    severity: ProblemSeverity::Warning,
+                    code: None,
+                    message: "unused variable: `x`".to_owned(),
+                },
+                Problem {
+                    path: PathBuf::from("/elsewhere/src/main.rs"),
+                    row: 12,
+                    column: 18,
+                    severity: ProblemSeverity::Error,
+                    code: Some("E0308".to_owned()),
+                    message: "mismatched types".to_owned(),
+                },
+            ],
+            "Only the primary location of every problem should be reported, relative paths resolved against the root"
+        );
+    }
+
+    #[test]
+    fn test_tsc_problems() {
+        let output =
+            "src/a.ts(3,5): error TS2322: Type 'string' is not assignable to type 'number'.
+src/b.ts:7:1 - warning TS6133: 'unused' is declared but its value is never read.
+
+Found 2 errors in 2 files.
+";
+        assert_eq!(
+            parse(ProblemMatcher::Builtin(BuiltinProblemMatcher::Tsc), output),
+            vec![
+                Problem {
+                    path: PathBuf::from("/project/src/a.ts"),
+                    row: 3,
+                    column: 5,
+                    severity: ProblemSeverity::Error,
+                    code: Some("TS2322".to_owned()),
+                    message: "Type 'string' is not assignable to type 'number'.".to_owned(),
+                },
+                Problem {
+                    path: PathBuf::from("/project/src/b.ts"),
+                    row: 7,
+                    column: 1,
+                    severity: ProblemSeverity::Warning,
+                    code: Some("TS6133".to_owned()),
+                    message: "'unused' is declared but its value is never read.".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_custom_problems() {
+        let matcher: ProblemMatcher = serde_json_lenient::from_str(
+            r#"{ "regexp": "^(\\S+):(\\d+): (\\w+): (.*)$", "column": 5, "message": 4, "severity": 3 }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            parse(
+                matcher,
+                "lint.py:4: warning: line too long\nsomething else\nlint.py:x: error: bad line"
+            ),
+            vec![Problem {
+                path: PathBuf::from("/project/lint.py"),
+                row: 4,
+                column: 1,
+                severity: ProblemSeverity::Warning,
+                code: None,
+                message: "line too long".to_owned(),
+            }],
+            "Problems without the column should be at the first one, unparsable lines should be skipped"
+        );
+        assert!(ProblemParser::new(
+            &ProblemMatcher::Custom(ProblemPattern {
+                regexp: "(unclosed".to_owned(),
+                file: 1,
+                line: 2,
+                column: 3,
+                message: 4,
+                severity: None,
+            }),
+            None
+        )
+        .is_err());
+    }
+}
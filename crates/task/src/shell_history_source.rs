@@ -0,0 +1,163 @@
+//! A source of tasks, based on the recent commands from the user's shell history file.
+
+use std::sync::Arc;
+
+use collections::HashSet;
+use futures::{channel::mpsc::UnboundedReceiver, StreamExt};
+use gpui::{AppContext, Context, Model, ModelContext};
+
+use crate::{oneshot_source::OneshotTask, Task, TaskSource};
+
+/// How many of the most recent distinct commands from the history file are offered as tasks.
+pub const MAX_SHELL_HISTORY_ENTRIES: usize = 100;
+
+/// A source of oneshot tasks for the most recent commands from a shell history file, reloaded when the file changes.
+pub struct ShellHistorySource {
+    tasks: Vec<Arc<dyn Task>>,
+}
+
+impl ShellHistorySource {
+    /// Initializes the source, that parses every new contents of the history file sent by the `tracker`.
+    pub fn new(
+        mut tracker: UnboundedReceiver<String>,
+        cx: &mut AppContext,
+    ) -> Model<Box<dyn TaskSource>> {
+        cx.new_model(|cx: &mut ModelContext<Box<dyn TaskSource>>| {
+            cx.spawn(|source, mut cx| async move {
+                while let Some(history) = tracker.next().await {
+                    let commands = parse_shell_history(&history, MAX_SHELL_HISTORY_ENTRIES);
+                    let updated = source.update(&mut cx, |source, cx| {
+                        if let Some(source) = source.as_any().downcast_mut::<Self>() {
+                            source.tasks = commands
+                                .into_iter()
+                                .map(|command| -> Arc<dyn Task> {
+                                    Arc::new(OneshotTask::new(command))
+                                })
+                                .collect();
+                            cx.notify();
+                        }
+                    });
+                    if updated.is_err() {
+                        break;
+                    }
+                }
+            })
+            .detach();
+            Box::new(Self { tasks: Vec::new() }) as Box<dyn TaskSource>
+        })
+    }
+}
+
+impl TaskSource for ShellHistorySource {
+    fn as_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn tasks_to_schedule(
+        &mut self,
+        _: &mut ModelContext<Box<dyn TaskSource>>,
+    ) -> Vec<Arc<dyn Task>> {
+        self.tasks.clone()
+    }
+}
+
+/// Distinct commands of a bash or zsh history file, the most recent first, at most `max_entries` of them.
+/// Understands the bash `#<timestamp>` lines, the zsh extended `: <timestamp>:<duration>;<command>` format,
+/// and the commands continued on the next lines with a trailing `\`.
+pub fn parse_shell_history(history: &str, max_entries: usize) -> Vec<String> {
+    let mut commands = Vec::<String>::new();
+    let mut continued: Option<String> = None;
+    for line in history.lines() {
+        let line = match continued.take() {
+            Some(mut command) => {
+                command.push('\n');
+                command.push_str(line);
+                command
+            }
+            None => {
+                let is_timestamp = line.strip_prefix('#').map_or(false, |timestamp| {
+                    !timestamp.is_empty() && timestamp.chars().all(|c| c.is_ascii_digit())
+                });
+                if is_timestamp {
+                    continue;
+                }
+                strip_zsh_metadata(line).to_owned()
+            }
+        };
+        if line.ends_with('\\') {
+            continued = Some(line);
+            continue;
+        }
+        let command = line.trim();
+        if !command.is_empty() {
+            commands.push(command.to_owned());
+        }
+    }
+
+    let mut seen = HashSet::default();
+    commands
+        .into_iter()
+        .rev()
+        .filter(|command| seen.insert(command.clone()))
+        .take(max_entries)
+        .collect()
+}
+
+/// Strips the `: <timestamp>:<duration>;` prefix of a zsh extended history line, if it has one.
+fn strip_zsh_metadata(line: &str) -> &str {
+    let Some(metadata) = line.strip_prefix(": ") else {
+        return line;
+    };
+    match metadata.split_once(';') {
+        Some((timing, command)) if timing.chars().all(|c| c.is_ascii_digit() || c == ':') => {
+            command
+        }
+        _ => line,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_shell_history() {
+        let bash_history = "\
+#1700000000
+cargo build
+git status
+#1700000100
+cargo test -p task
+git status
+
+ls -la
+";
+        assert_eq!(
+            parse_shell_history(bash_history, 10),
+            vec!["ls -la", "git status", "cargo test -p task", "cargo build"],
+            "Commands should be deduplicated, the most recent first, with the timestamp lines skipped"
+        );
+        assert_eq!(
+            parse_shell_history(bash_history, 2),
+            vec!["ls -la", "git status"],
+            "Only the most recent commands should be kept"
+        );
+
+        let zsh_history = "\
+: 1700000000:0;npm install
+: 1700000005:12;docker build \\
+  -t app .
+: 1700000020:0;npm install
+echo ': not metadata;'
+";
+        assert_eq!(
+            parse_shell_history(zsh_history, 10),
+            vec![
+                "echo ': not metadata;'",
+                "npm install",
+                "docker build \\\n  -t app .",
+            ],
+            "Zsh metadata should be stripped and the continued commands joined"
+        );
+    }
+}
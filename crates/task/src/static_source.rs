@@ -1,6 +1,6 @@
 //! A source of tasks, based on a static configuration, deserialized from the tasks config file, and related infrastructure for tracking changes to the file.
 
-use std::{borrow::Cow, sync::Arc};
+use std::{borrow::Cow, path::PathBuf, sync::Arc, time::Duration};
 
 use collections::HashMap;
 use futures::StreamExt;
@@ -9,7 +9,11 @@ use schemars::{gen::SchemaSettings, JsonSchema};
 use serde::{Deserialize, Serialize};
 use util::ResultExt;
 
-use crate::{SpawnInTerminal, Task, TaskContext, TaskId, TaskSource};
+use crate::{
+    captured_output::DEFAULT_MAX_OUTPUT_BYTES, problem_matcher::ProblemMatcher,
+    test_results::TestResultsFormat, SpawnInTerminal, Task, TaskContext, TaskId, TaskSource,
+    TaskVariables, VariableName,
+};
 use futures::channel::mpsc::UnboundedReceiver;
 
 /// A single config file entry with the deserialized task definition.
@@ -29,6 +33,56 @@ impl StaticTask {
             definition,
         })
     }
+
+    fn git_commit_message_template(&self) -> Option<&str> {
+        let git_commit = self.definition.then.as_ref()?.git_commit.as_ref()?;
+        Some(git_commit.message.as_str())
+    }
+
+    /// The command and the args of the task, with the overrides of the platform, the language and the context applied.
+    fn command_and_args(
+        &self,
+        task_variables: &TaskVariables,
+        args_override: &ArgsOverride,
+    ) -> (String, Vec<String>) {
+        let platform_overrides = self.definition.by_platform.get(std::env::consts::OS);
+        let language_overrides = task_variables
+            .get(&VariableName::Language)
+            .and_then(|language| self.definition.by_language.get(language));
+        let command = language_overrides
+            .and_then(|overrides| overrides.command.clone())
+            .or_else(|| platform_overrides.and_then(|overrides| overrides.command.clone()))
+            .unwrap_or_else(|| self.definition.command.clone());
+        let args = resolve_args(
+            &self.definition.args,
+            platform_overrides
+                .into_iter()
+                .chain(language_overrides)
+                .map(|overrides| &overrides.args_override)
+                .chain(Some(args_override)),
+        )
+        .iter()
+        .map(|arg| escape_literal_dollars(arg).into_owned())
+        .collect::<Vec<_>>();
+        (command, args)
+    }
+
+    /// All templates of the task that get the variables substituted, with the command and the args resolved.
+    fn templates<'a>(
+        &'a self,
+        command: &'a str,
+        args: &'a [String],
+    ) -> impl Iterator<Item = &'a str> {
+        std::iter::once(command)
+            .chain(std::iter::once(self.definition.label.as_str()))
+            .chain(args.iter().map(String::as_str))
+            .chain(self.definition.cwd.as_deref())
+            .chain(self.definition.tail_file.as_deref())
+            .chain(self.definition.pre.as_deref())
+            .chain(self.definition.post.as_deref())
+            .chain(self.definition.results_file.as_deref())
+            .chain(self.git_commit_message_template())
+    }
 }
 
 /// TODO: doc
@@ -45,34 +99,144 @@ impl Task for StaticTask {
     fn prepare_exec(&self, cx: TaskContext) -> Option<SpawnInTerminal> {
         let TaskContext {
             cwd,
-            task_variables,
+            mut task_variables,
+            selection_fallbacks,
+            args_override,
         } = cx;
+        let nothing_selected = task_variables
+            .get(&VariableName::SelectedText)
+            .map_or(true, str::is_empty);
+        if nothing_selected {
+            let fallback = match self.definition.selection_fallback {
+                SelectionFallback::None => None,
+                SelectionFallback::WholeFile => selection_fallbacks
+                    .whole_file
+                    .and_then(|whole_file| whole_file.resolve()),
+                SelectionFallback::CurrentLine => selection_fallbacks.current_line,
+            };
+            if let Some(fallback) = fallback {
+                task_variables.insert(VariableName::SelectedText, fallback);
+            }
+        }
+        let (command, args) = self.command_and_args(&task_variables, &args_override);
+        task_variables.resolve_referenced(self.templates(&command, &args));
         let task_variables = task_variables.into_env_variables();
-        let cwd = self
+        // Labels are only displayed, so the template is kept as is, if any of its variables are missing.
+        let label = subst::substitute(&self.definition.label, &task_variables)
+            .unwrap_or_else(|_| self.definition.label.clone());
+        let cwd = match self.definition.cwd.as_deref() {
+            Some(INHERIT_CWD) => std::env::current_dir().log_err(),
+            Some(path) => {
+                let path = PathBuf::from(subst::substitute(path, &task_variables).log_err()?);
+                Some(match cwd {
+                    Some(cwd) => cwd.join(path),
+                    None => path,
+                })
+            }
+            None => cwd,
+        };
+        let tail_file = self
             .definition
-            .cwd
-            .clone()
-            .and_then(|path| {
-                subst::substitute(&path, &task_variables)
-                    .map(Into::into)
-                    .ok()
-            })
-            .or(cwd);
-        let mut definition_env = self.definition.env.clone();
+            .tail_file
+            .as_deref()
+            .and_then(|path| subst::substitute(path, &task_variables).log_err())
+            .map(|path| match &cwd {
+                Some(cwd) => cwd.join(path),
+                None => PathBuf::from(path),
+            });
+        let results_file = self
+            .definition
+            .results_file
+            .as_deref()
+            .and_then(|path| subst::substitute(path, &task_variables).log_err())
+            .map(|path| match &cwd {
+                Some(cwd) => cwd.join(path),
+                None => PathBuf::from(path),
+            });
+        // Same as labels, messages with the missing variables are kept as is, not to lose the commit of a successful run.
+        let git_commit_message = self.git_commit_message_template().map(|message| {
+            subst::substitute(message, &task_variables).unwrap_or_else(|_| message.to_owned())
+        });
+        let (secrets, mut definition_env): (HashMap<_, _>, HashMap<_, _>) = self
+            .definition
+            .env
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .partition(|(_, value)| value.starts_with(SECRET_ENV_PREFIX));
+        let secrets = secrets
+            .into_iter()
+            .map(|(name, value)| (name, value[SECRET_ENV_PREFIX.len()..].to_owned()))
+            .collect();
         definition_env.extend(task_variables);
         Some(SpawnInTerminal {
             id: self.id.clone(),
             cwd,
             use_new_terminal: self.definition.use_new_terminal,
             allow_concurrent_runs: self.definition.allow_concurrent_runs,
-            label: self.definition.label.clone(),
-            command: self.definition.command.clone(),
-            args: self.definition.args.clone(),
+            cancel_previous: self.definition.cancel_previous,
+            label,
+            command,
+            args,
             reveal: self.definition.reveal,
+            dock: self.definition.dock,
             env: definition_env,
+            container: self.definition.container.clone(),
+            combine_output: self.definition.combine_output,
+            max_output_bytes: self.definition.max_output_bytes,
+            kill_on_output_limit: self.definition.kill_on_output_limit,
+            confirm: self.definition.confirm,
+            show: self.definition.show,
+            nice: self.definition.nice,
+            elevate: self.definition.elevate,
+            requires: self.definition.requires.clone(),
+            retry: self.definition.retry.clone(),
+            secrets,
+            tail_file,
+            git_diff: self.definition.git_diff,
+            group: self.definition.group.clone(),
+            terminal_group: None,
+            command_substitution: self.definition.command_substitution,
+            pre: self.definition.pre.clone(),
+            post: self.definition.post.clone(),
+            results_format: self.definition.results_format,
+            results_file,
+            reveal_cwd_in_project_panel: self.definition.reveal_cwd_in_project_panel,
+            split: None,
+            git_commit_message,
+            depends_on: self.definition.depends_on.clone(),
+            parallel: self.definition.parallel,
+            singleton_group: self.definition.singleton_group.clone(),
+            show_summary: self.definition.show_summary,
+            problem_matcher: self.definition.problem_matcher.clone(),
+            spawn_nonce: SpawnInTerminal::next_spawn_nonce(),
         })
     }
 
+    fn variable_templates(&self, cx: &TaskContext) -> Vec<String> {
+        let (command, args) = self.command_and_args(&cx.task_variables, &cx.args_override);
+        self.templates(&command, &args)
+            .map(ToOwned::to_owned)
+            .collect()
+    }
+
+    fn resolved_label(&self, cx: &TaskContext) -> String {
+        let task_variables = cx
+            .task_variables
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_owned()))
+            .collect::<HashMap<_, _>>();
+        subst::substitute(&self.definition.label, &task_variables)
+            .unwrap_or_else(|_| self.definition.label.clone())
+    }
+
+    fn command_line_preview(&self, cx: &TaskContext) -> String {
+        let (command, args) = self.command_and_args(&cx.task_variables, &cx.args_override);
+        std::iter::once(command)
+            .chain(args)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
     fn name(&self) -> &str {
         &self.definition.label
     }
@@ -84,8 +248,37 @@ impl Task for StaticTask {
     fn cwd(&self) -> Option<&str> {
         self.definition.cwd.as_deref()
     }
+
+    fn run_on_project_open(&self) -> bool {
+        self.definition.on_project_open
+    }
+
+    fn restore_on_reopen(&self) -> bool {
+        self.definition.restore_on_reopen
+    }
+
+    fn worktrees(&self) -> &[String] {
+        &self.definition.worktrees
+    }
+}
+
+/// Replaces every `$$` in the argument with a `$` escaped from the shell, so that it's passed to the command literally,
+/// instead of starting a variable (e.g. `$$ZED_FILE` becomes a literal `$ZED_FILE` string).
+fn escape_literal_dollars(arg: &str) -> Cow<'_, str> {
+    if arg.contains("$$") {
+        Cow::Owned(arg.replace("$$", "\\$"))
+    } else {
+        Cow::Borrowed(arg)
+    }
 }
 
+/// A special `cwd` value, that makes the task to be spawned in the current directory of the Zed process.
+pub const INHERIT_CWD: &str = "inherit";
+
+/// A prefix of the env values, that are read from the secret store by the key after it (e.g. `secret:deploy-token`),
+/// instead of being used literally.
+pub const SECRET_ENV_PREFIX: &str = "secret:";
+
 /// The source of tasks defined in a tasks config file.
 pub struct StaticSource {
     tasks: Vec<Arc<StaticTask>>,
@@ -94,20 +287,26 @@ pub struct StaticSource {
 }
 
 /// Static task definition from the tasks config file.
-#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct Definition {
-    /// Human readable name of the task to display in the UI.
+    /// Human readable name of the task to display in the UI, with the task variables substituted, when they resolve.
     pub label: String,
-    /// Executable command to spawn.
+    /// Executable command to spawn, unused by the `parallel` tasks.
+    #[serde(default)]
     pub command: String,
     /// Arguments to the command.
+    /// Variables like `$ZED_FILE` are expanded in arguments, use `$$` to pass a literal `$` instead.
     #[serde(default)]
     pub args: Vec<String>,
     /// Env overrides for the command, will be appended to the terminal's environment from the settings.
+    /// Values like `secret:<key>` are read from the OS keychain instead, right before the task is spawned.
     #[serde(default)]
     pub env: HashMap<String, String>,
     /// Current working directory to spawn the command into, defaults to current project root.
+    /// Variables are expanded in the path, relative paths are resolved against the project root the task is spawned for.
+    /// Use `inherit` to spawn the command in the directory Zed itself was launched from, ignoring the project:
+    /// unlike the `$ZED_WORKTREE_ROOT`-based paths, it does not depend on the file or worktree the task is spawned for.
     #[serde(default)]
     pub cwd: Option<String>,
     /// Whether to use a new terminal tab or reuse the existing one to spawn the process.
@@ -116,11 +315,308 @@ pub struct Definition {
     /// Whether to allow multiple instances of the same task to be run, or rather wait for the existing ones to finish.
     #[serde(default)]
     pub allow_concurrent_runs: bool,
+    /// Whether to kill the instances of the task that are still running, when the task gets scheduled again.
+    #[serde(default)]
+    pub cancel_previous: bool,
     /// What to do with the terminal pane and tab, after the command was started:
-    /// * `always` — always show the terminal pane, add and focus the corresponding task's tab in it (default)
+    /// * `always` — always show the terminal pane, add and focus the corresponding task's tab in it
+    /// * `no_focus` — show the terminal pane and the task's tab in it, but keep the focus where it was
     /// * `never` — avoid changing current terminal pane focus, but still add/reuse the task's tab there
+    ///
+    /// Defaults to the `task.default_reveal` setting.
+    #[serde(default)]
+    pub reveal: Option<RevealStrategy>,
+    /// Which dock to place the terminal panel with the task's tab in: `left`, `bottom` or `right`.
+    ///
+    /// Defaults to the dock from the terminal settings.
+    #[serde(default)]
+    pub dock: Option<TaskDockPosition>,
+    /// A container to run the task in, instead of the host:
+    /// * `{ "image": "<image>" }` — a fresh container of the given image, with the cwd mounted into it
+    /// * `devcontainer` — the devcontainer of the directory the task is spawned in
+    #[serde(default)]
+    pub container: Option<TaskContainer>,
+    /// Whether to show the stderr of the command interleaved with its stdout (default),
+    /// or capture it separately into a temporary file.
+    #[serde(default = "default_true")]
+    pub combine_output: bool,
+    /// How many bytes of the output to keep when it's captured outside of the terminal,
+    /// the rest is dropped with an "output truncated" marker. Defaults to 1 MiB.
+    #[serde(default = "default_max_output_bytes")]
+    pub max_output_bytes: usize,
+    /// Whether to kill the process once its captured output exceeds `max_output_bytes`.
+    #[serde(default)]
+    pub kill_on_output_limit: bool,
+    /// Overrides of the command and args, applied when the task is spawned on a certain platform: `macos`, `linux` or `windows`.
+    #[serde(default)]
+    pub by_platform: HashMap<String, TaskOverrides>,
+    /// Overrides of the command and args, applied when the task is spawned for a certain language (e.g. `Rust`),
+    /// on top of the platform ones: the language command wins, the args are layered as described in [`resolve_args`].
+    /// The language is determined by the latest cursor/selection position, the base command and args are used if nothing matches.
+    #[serde(default)]
+    pub by_language: HashMap<String, TaskOverrides>,
+    /// Whether to show all task variables resolved and ask for a confirmation before spawning the task.
+    /// Useful for the destructive tasks.
+    #[serde(default)]
+    pub confirm: bool,
+    /// What to use as `$ZED_SELECTED_TEXT` when nothing is selected:
+    /// * `none` — leave it empty (default)
+    /// * `whole_file` — the full contents of the current buffer
+    /// * `current_line` — the line with the cursor
+    #[serde(default)]
+    pub selection_fallback: SelectionFallback,
+    /// Where to show the output of the command:
+    /// * `terminal` — run the command in a terminal tab (default)
+    /// * `clipboard` — run the command in the background and copy its trimmed stdout into the clipboard
+    #[serde(default)]
+    pub show: ShowStrategy,
+    /// Whether to run the task automatically, once per session, when the project is opened.
+    #[serde(default)]
+    pub on_project_open: bool,
+    /// Whether to offer restarting the task when the project is reopened, if the task was still running when Zed quit,
+    /// e.g. for the dev servers and other long-running background tasks.
+    #[serde(default)]
+    pub restore_on_reopen: bool,
+    /// Niceness to lower the OS priority of the command with, from 0 (default priority) to 19 (the lowest one),
+    /// so that heavy builds do not starve the editor. On Windows, any positive value means the below normal priority.
+    #[serde(default)]
+    pub nice: Option<i32>,
+    /// Whether to run the command with the elevated privileges: with `sudo` on Unix, and with `Start-Process -Verb RunAs` on Windows.
+    /// The password is asked for on every run, the credentials cached by `sudo` are never reused.
+    #[serde(default)]
+    pub elevate: bool,
+    /// Executables the task needs, checked on `PATH` before spawning the task, to report the missing ones instead of a failed run.
+    /// Not checked for the tasks run in containers.
+    #[serde(default)]
+    pub requires: Vec<String>,
+    /// Whether to rerun the command when it fails, and how long to wait between the attempts.
+    #[serde(default)]
+    pub retry: Option<TaskRetry>,
+    /// A file to open and follow while the task runs, showing what the task appends to it (e.g. a test report).
+    /// Variables are expanded in the path, relative paths are resolved against the task's cwd.
+    #[serde(default)]
+    pub tail_file: Option<String>,
+    /// Which changes of the worktree's git repository to supply to the task: `unstaged` or `staged` ones.
+    /// The diff is written into a temporary file, passed to the command as its stdin and with the path in `$ZED_GIT_DIFF_FILE`.
+    /// Nothing is supplied, if the task is spawned outside of a git repository.
+    #[serde(default)]
+    pub git_diff: Option<GitDiff>,
+    /// A group to show the running task under in the task status indicator, together with the other tasks of the group.
+    #[serde(default)]
+    pub group: Option<String>,
+    /// Whether to replace every `$(command)` in the args with the trimmed stdout of the command, run in the task's cwd before spawning the task.
+    /// Off by default, as a command from the task definitions gets run with no confirmation whenever the task is resolved.
+    #[serde(default)]
+    pub command_substitution: bool,
+    /// A condition of the environment Zed runs in, that the task is only loaded under, e.g. to have the tasks for CI or local runs only.
+    /// Checked every time the tasks file is loaded, the tasks that do not meet it are not shown or spawned at all.
+    #[serde(default)]
+    pub when: Option<TaskCondition>,
+    /// A command line to run before the command, in the same shell, e.g. to set up the environment for it.
+    /// Variables are expanded the same way as in the command, the task is aborted if the command line fails.
+    #[serde(default)]
+    pub pre: Option<String>,
+    /// A command line to run after the command, in the same shell and whether or not the command fails, e.g. to clean up after it.
+    /// Variables are expanded the same way as in the command, the exit status of the command is kept for the task.
+    #[serde(default)]
+    pub post: Option<String>,
+    /// A format of the test results the task reports, `tap` or `junit`, to show the passed, failed and skipped test counts once the task finishes.
+    /// The results are read from the stdout of the command, or from the `results_file`, if it's set.
+    #[serde(default)]
+    pub results_format: Option<TestResultsFormat>,
+    /// A file the command writes its test results into, in the `results_format`.
+    /// Variables are expanded in the path, relative paths are resolved against the task's cwd.
+    #[serde(default)]
+    pub results_file: Option<String>,
+    /// Whether to reveal and select the task's cwd in the project panel when the task is spawned, to browse the files there.
+    /// Nothing is revealed, if the cwd is not within any of the project's worktrees.
+    #[serde(default)]
+    pub reveal_cwd_in_project_panel: bool,
+    /// What to do after the command succeeds, e.g. commit the changes it made with `{ "git_commit": { "message": "..." } }`.
+    /// Nothing is done after the failed commands.
+    #[serde(default)]
+    pub then: Option<TaskThen>,
+    /// Labels of the tasks to run before this one, in order, in the same terminal: the command is only run
+    /// after all of them succeed. Dependencies run in their own cwd and env, resolved in the same context as this task.
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// Whether the task is a compound one, that spawns all tasks of `depends_on` at once, each in its own terminal,
+    /// instead of running them before its own command, which is not run then.
+    /// The terminals of the tasks spawned are shown in one terminal tab of the compound task, as its sub-tabs.
+    /// Every task spawned runs its own `depends_on` before its command as usual, sequentially and in its own terminal;
+    /// the `parallel` tasks among them spawn their tasks at once in turn.
+    #[serde(default)]
+    pub parallel: bool,
+    /// A group of the tasks, only one of which may run at a time, e.g. the different dev servers of the project:
+    /// spawning a task cancels the running tasks of the same group, whichever their definitions are.
+    #[serde(default)]
+    pub singleton_group: Option<String>,
+    /// Whether to print a summary line into the terminal after the task finishes, e.g. `✓ Done in 3.2s (exit 0)`.
+    #[serde(default)]
+    pub show_summary: bool,
+    /// A matcher of the problems in the task output, e.g. compiler errors, to show in the project diagnostics while the task runs:
+    /// `"rustc"`, `"tsc"`, or `{ "regexp": "...", "file": 1, "line": 2, "column": 3, "message": 4 }` with the capture groups of the problem parts.
+    /// Relative paths are resolved against `$ZED_WORKTREE_ROOT`; the problems of the previous run are cleared when the task is rerun.
+    #[serde(default)]
+    pub problem_matcher: Option<ProblemMatcher>,
+    /// Root names of the worktrees the task is restricted to, in a multi-root workspace: the task is only listed and spawned
+    /// when one of them is the active worktree, or is present in the project, if no worktree is active.
+    /// Available in all worktrees, if empty.
+    #[serde(default)]
+    pub worktrees: Vec<String>,
+}
+
+/// Actions to take after the command of the task succeeds.
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct TaskThen {
+    /// Stage all changes of the git repository of the task's cwd and commit them, if there are any.
+    #[serde(default)]
+    pub git_commit: Option<GitCommit>,
+}
+
+/// A commit of all changes of the git repository, made after the command succeeds.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct GitCommit {
+    /// A message of the commit, variables are expanded in it.
+    pub message: String,
+}
+
+/// A condition of the environment Zed runs in, that has to be met for the task to be loaded.
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct TaskCondition {
+    /// Env variables that all have to be set, to any value, for the task to be loaded.
+    #[serde(default)]
+    pub env_set: Vec<String>,
+    /// Env variables that all have to be unset for the task to be loaded.
+    #[serde(default)]
+    pub env_unset: Vec<String>,
+}
+
+impl TaskCondition {
+    /// Whether the condition is met, given a check of whether an env variable is set.
+    pub fn is_met(&self, env_is_set: impl Fn(&str) -> bool) -> bool {
+        self.env_set.iter().all(|name| env_is_set(name))
+            && !self.env_unset.iter().any(|name| env_is_set(name))
+    }
+}
+
+/// Tasks of the definitions, that meet their `when` conditions, with their ids based on the position in the file.
+fn load_tasks(
+    definitions: &[Definition],
+    id_base: &str,
+    env_is_set: impl Fn(&str) -> bool,
+) -> Vec<Arc<StaticTask>> {
+    definitions
+        .iter()
+        .enumerate()
+        .filter(|(_, definition)| {
+            definition
+                .when
+                .as_ref()
+                .map_or(true, |condition| condition.is_met(&env_is_set))
+        })
+        .map(|(i, definition)| StaticTask::new(definition.clone(), (id_base, i)))
+        .collect()
+}
+
+/// Parts of the task definition, that are replaced when the task is spawned on a certain platform or for a certain language.
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct TaskOverrides {
+    /// Executable command to spawn instead of the base one.
     #[serde(default)]
-    pub reveal: RevealStrategy,
+    pub command: Option<String>,
+    /// Arguments to the command, replacing or extending the ones of the layers below.
+    #[serde(flatten)]
+    pub args_override: ArgsOverride,
+}
+
+/// A layer of the task args, on top of the args resolved from the layers below it.
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ArgsOverride {
+    /// Arguments to use instead of the ones of all layers below.
+    #[serde(default)]
+    pub args: Option<Vec<String>>,
+    /// Arguments to add after the ones of the layers below, or after `args` of the same layer, if they're set.
+    #[serde(default)]
+    pub append_args: Vec<String>,
+}
+
+/// Resolves the args of a task from its layers, applied in order from the lowest one: base → platform → language → inline.
+/// Every layer first replaces all args resolved so far with its `args`, if they're set, then appends its `append_args`.
+pub fn resolve_args<'a>(
+    base: &[String],
+    layers: impl IntoIterator<Item = &'a ArgsOverride>,
+) -> Vec<String> {
+    let mut args = base.to_vec();
+    for layer in layers {
+        if let Some(replacement) = &layer.args {
+            args.clone_from(replacement);
+        }
+        args.extend(layer.append_args.iter().cloned());
+    }
+    args
+}
+
+fn default_max_output_bytes() -> usize {
+    DEFAULT_MAX_OUTPUT_BYTES
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for Definition {
+    fn default() -> Self {
+        Self {
+            label: String::new(),
+            command: String::new(),
+            args: Vec::new(),
+            env: HashMap::default(),
+            cwd: None,
+            use_new_terminal: false,
+            allow_concurrent_runs: false,
+            cancel_previous: false,
+            reveal: None,
+            dock: None,
+            container: None,
+            combine_output: true,
+            max_output_bytes: DEFAULT_MAX_OUTPUT_BYTES,
+            kill_on_output_limit: false,
+            by_platform: HashMap::default(),
+            by_language: HashMap::default(),
+            confirm: false,
+            selection_fallback: SelectionFallback::default(),
+            show: ShowStrategy::default(),
+            on_project_open: false,
+            restore_on_reopen: false,
+            nice: None,
+            elevate: false,
+            requires: Vec::new(),
+            retry: None,
+            tail_file: None,
+            git_diff: None,
+            group: None,
+            command_substitution: false,
+            when: None,
+            pre: None,
+            post: None,
+            results_format: None,
+            results_file: None,
+            reveal_cwd_in_project_panel: false,
+            then: None,
+            depends_on: Vec::new(),
+            parallel: false,
+            singleton_group: None,
+            show_summary: false,
+            problem_matcher: None,
+            worktrees: Vec::new(),
+        }
+    }
 }
 
 /// What to do with the terminal pane and tab, after the command was started.
@@ -130,10 +626,126 @@ pub enum RevealStrategy {
     /// Always show the terminal pane, add and focus the corresponding task's tab in it.
     #[default]
     Always,
+    /// Show the terminal pane and the task's tab in it, but do not move the focus there.
+    NoFocus,
     /// Do not change terminal pane focus, but still add/reuse the task's tab there.
     Never,
 }
 
+/// Which changes of the git repository to supply to the task.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GitDiff {
+    /// Changes of the working directory, not added to the index yet.
+    Unstaged,
+    /// Changes added to the index, but not committed yet.
+    Staged,
+}
+
+/// A dock to place the task's terminal in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskDockPosition {
+    Left,
+    Bottom,
+    Right,
+}
+
+/// Where to show the output of the command.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ShowStrategy {
+    /// Run the command in a terminal tab.
+    #[default]
+    Terminal,
+    /// Run the command in the background and copy its trimmed stdout into the clipboard.
+    Clipboard,
+}
+
+/// What to use as the selected text, when nothing is selected.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionFallback {
+    /// Keep the selected text empty.
+    #[default]
+    None,
+    /// Use the full contents of the current buffer.
+    WholeFile,
+    /// Use the line with the cursor.
+    CurrentLine,
+}
+
+/// A container to run the task in, instead of the host.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskContainer {
+    /// Run the task in a new, throwaway container of the given image, with `docker run`.
+    Image(String),
+    /// Run the task in an already running container with the given name or id, with `docker exec`.
+    /// The cwd of the task is expected to exist at the same path in the container.
+    Exec(String),
+    /// Run the task in the devcontainer of the task's working directory.
+    Devcontainer,
+}
+
+/// How to rerun the command of the task, when it fails.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct TaskRetry {
+    /// How many times to rerun the command after it fails.
+    pub attempts: u32,
+    /// Delay before the first rerun, in milliseconds. Defaults to 1000.
+    #[serde(default = "default_retry_delay_ms")]
+    pub delay_ms: u64,
+    /// How the delay grows with every next rerun:
+    /// * `constant` — wait `delay_ms` before every rerun (default)
+    /// * `linear` — wait `delay_ms` more before every next rerun
+    /// * `exponential` — wait twice as long before every next rerun
+    #[serde(default)]
+    pub backoff: RetryBackoff,
+    /// The longest delay between the attempts, in milliseconds, no matter the backoff. Defaults to 60000.
+    #[serde(default = "default_retry_max_delay_ms")]
+    pub max_delay_ms: u64,
+}
+
+impl TaskRetry {
+    /// Delay before the `retry`-th rerun of the command, starting from 1.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let retry = retry.max(1);
+        let delay_ms = match self.backoff {
+            RetryBackoff::Constant => self.delay_ms,
+            RetryBackoff::Linear => self.delay_ms.saturating_mul(u64::from(retry)),
+            RetryBackoff::Exponential => {
+                2u64.checked_pow(retry - 1).map_or(u64::MAX, |multiplier| {
+                    self.delay_ms.saturating_mul(multiplier)
+                })
+            }
+        };
+        Duration::from_millis(delay_ms.min(self.max_delay_ms))
+    }
+}
+
+/// How the delay between the task reruns grows.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryBackoff {
+    /// Wait the same base delay before every rerun.
+    #[default]
+    Constant,
+    /// Increase the delay by the base delay with every rerun.
+    Linear,
+    /// Double the delay with every rerun.
+    Exponential,
+}
+
+fn default_retry_delay_ms() -> u64 {
+    1000
+}
+
+fn default_retry_max_delay_ms() -> u64 {
+    60_000
+}
+
 /// A group of Tasks defined in a JSON file.
 #[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub struct TaskDefinitions(pub Vec<Definition>);
@@ -245,15 +857,10 @@ impl StaticSource {
                 &definitions,
                 move |source: &mut Box<(dyn TaskSource + 'static)>, new_definitions, cx| {
                     if let Some(static_source) = source.as_any().downcast_mut::<Self>() {
-                        static_source.tasks = new_definitions
-                            .read(cx)
-                            .get()
-                            .0
-                            .clone()
-                            .into_iter()
-                            .enumerate()
-                            .map(|(i, definition)| StaticTask::new(definition, (&id_base, i)))
-                            .collect();
+                        static_source.tasks =
+                            load_tasks(&new_definitions.read(cx).get().0, &id_base, |name| {
+                                std::env::var_os(name).is_some()
+                            });
                         cx.notify();
                     }
                 },
@@ -282,3 +889,474 @@ impl TaskSource for StaticSource {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::PathBuf,
+        sync::atomic::{self, AtomicUsize},
+    };
+
+    use super::*;
+    use crate::{LazyVariable, SelectionFallbacks, TaskVariables};
+
+    fn prepare_with_cwd(cwd: Option<&str>) -> Option<PathBuf> {
+        let tasks = tasks_for(
+            TaskDefinitions(vec![Definition {
+                label: "test".to_owned(),
+                command: "ls".to_owned(),
+                cwd: cwd.map(ToOwned::to_owned),
+                ..Definition::default()
+            }]),
+            "test",
+        );
+        tasks[0]
+            .prepare_exec(TaskContext {
+                cwd: Some(PathBuf::from("/project")),
+                task_variables: Default::default(),
+                selection_fallbacks: Default::default(),
+                args_override: Default::default(),
+            })
+            .and_then(|spawn_in_terminal| spawn_in_terminal.cwd)
+    }
+
+    #[test]
+    fn test_args_by_language() {
+        let tasks = tasks_for(
+            TaskDefinitions(vec![Definition {
+                label: "run".to_owned(),
+                command: "make".to_owned(),
+                args: vec!["run".to_owned()],
+                by_language: HashMap::from_iter([
+                    (
+                        "Rust".to_owned(),
+                        TaskOverrides {
+                            command: Some("cargo".to_owned()),
+                            args_override: ArgsOverride {
+                                args: Some(vec!["run".to_owned(), "--release".to_owned()]),
+                                append_args: Vec::new(),
+                            },
+                        },
+                    ),
+                    (
+                        "TypeScript".to_owned(),
+                        TaskOverrides {
+                            command: None,
+                            args_override: ArgsOverride {
+                                args: Some(vec!["run-ts".to_owned()]),
+                                append_args: Vec::new(),
+                            },
+                        },
+                    ),
+                ]),
+                ..Definition::default()
+            }]),
+            "test",
+        );
+        let prepare_for_language = |language: Option<&str>| {
+            let task_variables = language
+                .map(|language| {
+                    TaskVariables::from_iter([(VariableName::Language, language.to_owned())])
+                })
+                .unwrap_or_default();
+            let spawn_in_terminal = tasks[0]
+                .prepare_exec(TaskContext {
+                    cwd: None,
+                    task_variables,
+                    selection_fallbacks: Default::default(),
+                    args_override: Default::default(),
+                })
+                .unwrap();
+            (spawn_in_terminal.command, spawn_in_terminal.args)
+        };
+
+        assert_eq!(
+            prepare_for_language(Some("Rust")),
+            (
+                "cargo".to_owned(),
+                vec!["run".to_owned(), "--release".to_owned()]
+            )
+        );
+        assert_eq!(
+            prepare_for_language(Some("TypeScript")),
+            ("make".to_owned(), vec!["run-ts".to_owned()])
+        );
+        assert_eq!(
+            prepare_for_language(Some("Python")),
+            ("make".to_owned(), vec!["run".to_owned()])
+        );
+        assert_eq!(
+            prepare_for_language(None),
+            ("make".to_owned(), vec!["run".to_owned()])
+        );
+    }
+
+    #[test]
+    fn test_args_layers() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        let tasks = tasks_for(
+            TaskDefinitions(vec![Definition {
+                label: "build".to_owned(),
+                command: "make".to_owned(),
+                args: args(&["build"]),
+                by_platform: HashMap::from_iter([(
+                    std::env::consts::OS.to_owned(),
+                    TaskOverrides {
+                        command: Some("gmake".to_owned()),
+                        args_override: ArgsOverride {
+                            args: Some(args(&["build", "--target", "native"])),
+                            append_args: args(&["--locked"]),
+                        },
+                    },
+                )]),
+                by_language: HashMap::from_iter([(
+                    "Rust".to_owned(),
+                    TaskOverrides {
+                        command: None,
+                        args_override: ArgsOverride {
+                            args: None,
+                            append_args: args(&["--features", "rust"]),
+                        },
+                    },
+                )]),
+                ..Definition::default()
+            }]),
+            "test",
+        );
+        let prepare = |args_override: ArgsOverride| {
+            let spawn_in_terminal = tasks[0]
+                .prepare_exec(TaskContext {
+                    cwd: None,
+                    task_variables: TaskVariables::from_iter([(
+                        VariableName::Language,
+                        "Rust".to_owned(),
+                    )]),
+                    selection_fallbacks: Default::default(),
+                    args_override,
+                })
+                .unwrap();
+            (spawn_in_terminal.command, spawn_in_terminal.args)
+        };
+
+        assert_eq!(
+            prepare(ArgsOverride {
+                args: None,
+                append_args: args(&["--verbose"]),
+            }),
+            (
+                "gmake".to_owned(),
+                args(&[
+                    "build",
+                    "--target",
+                    "native",
+                    "--locked",
+                    "--features",
+                    "rust",
+                    "--verbose"
+                ])
+            ),
+            "Platform args should replace the base ones, then the language and inline args should be appended in order"
+        );
+        assert_eq!(
+            prepare(ArgsOverride {
+                args: Some(args(&["clean"])),
+                append_args: args(&["--all"]),
+            }),
+            ("gmake".to_owned(), args(&["clean", "--all"])),
+            "Inline args should replace the args of all layers below"
+        );
+    }
+
+    #[test]
+    fn test_lazy_variables_resolved_on_reference() {
+        let resolutions = Arc::new(AtomicUsize::new(0));
+        let mut task_variables =
+            TaskVariables::from_iter([(VariableName::File, "/project/main.rs".to_owned())]);
+        task_variables.insert_lazy(VariableName::ProjectVersion, {
+            let resolutions = resolutions.clone();
+            Arc::new(move || {
+                resolutions.fetch_add(1, atomic::Ordering::SeqCst);
+                Some("1.2.3".to_owned())
+            })
+        });
+        let task_context = TaskContext {
+            cwd: None,
+            task_variables,
+            selection_fallbacks: Default::default(),
+            args_override: Default::default(),
+        };
+        let prepare = |args: &[&str]| {
+            let tasks = tasks_for(
+                TaskDefinitions(vec![Definition {
+                    label: "release".to_owned(),
+                    command: "release".to_owned(),
+                    args: args.iter().map(|arg| arg.to_string()).collect(),
+                    ..Definition::default()
+                }]),
+                "test",
+            );
+            tasks[0].prepare_exec(task_context.clone()).unwrap().env
+        };
+
+        let env = prepare(&["$ZED_FILE", "$ZED_PROJECT_VERSION_SUFFIX"]);
+        assert_eq!(
+            resolutions.load(atomic::Ordering::SeqCst),
+            0,
+            "Lazy variable should not be computed for the tasks that do not reference it"
+        );
+        assert_eq!(
+            env.get("ZED_FILE").map(String::as_str),
+            Some("/project/main.rs")
+        );
+        assert!(!env.contains_key("ZED_PROJECT_VERSION"));
+
+        for _ in 0..2 {
+            let env = prepare(&["--version=${ZED_PROJECT_VERSION}"]);
+            assert_eq!(
+                env.get("ZED_PROJECT_VERSION").map(String::as_str),
+                Some("1.2.3")
+            );
+        }
+        assert_eq!(
+            resolutions.load(atomic::Ordering::SeqCst),
+            1,
+            "Lazy variable should be computed once per context, on its first reference"
+        );
+    }
+
+    #[test]
+    fn test_previews_do_not_resolve_lazy_variables() {
+        let resolutions = Arc::new(AtomicUsize::new(0));
+        let mut task_variables =
+            TaskVariables::from_iter([(VariableName::File, "/project/main.rs".to_owned())]);
+        task_variables.insert_lazy(VariableName::ProjectVersion, {
+            let resolutions = resolutions.clone();
+            Arc::new(move || {
+                resolutions.fetch_add(1, atomic::Ordering::SeqCst);
+                Some("1.2.3".to_owned())
+            })
+        });
+        let task_context = TaskContext {
+            task_variables,
+            ..TaskContext::default()
+        };
+        let task = tasks_for(
+            TaskDefinitions(vec![Definition {
+                label: "release $ZED_FILE ${ZED_PROJECT_VERSION}".to_owned(),
+                command: "release".to_owned(),
+                args: vec!["--version=${ZED_PROJECT_VERSION}".to_owned()],
+                ..Definition::default()
+            }]),
+            "test",
+        )
+        .remove(0);
+
+        assert_eq!(
+            task.resolved_label(&task_context),
+            "release $ZED_FILE ${ZED_PROJECT_VERSION}",
+            "Label with the lazy variables should be kept as is"
+        );
+        assert_eq!(
+            task.command_line_preview(&task_context),
+            "release --version=${ZED_PROJECT_VERSION}"
+        );
+        assert_eq!(resolutions.load(atomic::Ordering::SeqCst), 0);
+
+        let lazy_variables = task_context
+            .task_variables
+            .lazy_referenced(&task.variable_templates(&task_context));
+        assert_eq!(lazy_variables.len(), 1);
+        for lazy_variable in lazy_variables {
+            lazy_variable.resolve();
+        }
+        let spawn_in_terminal = task.prepare_exec(task_context).unwrap();
+        assert_eq!(spawn_in_terminal.label, "release /project/main.rs 1.2.3");
+        assert_eq!(
+            resolutions.load(atomic::Ordering::SeqCst),
+            1,
+            "Lazy variable resolved ahead should not be computed again by the task"
+        );
+    }
+
+    #[test]
+    fn test_inherit_cwd() {
+        assert_eq!(prepare_with_cwd(None), Some(PathBuf::from("/project")));
+        assert_eq!(
+            prepare_with_cwd(Some("/tmp/elsewhere")),
+            Some(PathBuf::from("/tmp/elsewhere"))
+        );
+        assert_eq!(
+            prepare_with_cwd(Some("packages/server")),
+            Some(PathBuf::from("/project/packages/server")),
+            "Relative cwd should be resolved against the context one"
+        );
+        assert_eq!(
+            prepare_with_cwd(Some("$ZED_UNKNOWN_VARIABLE/server")),
+            None,
+            "Task should not be spawned in a cwd with the variables that cannot be expanded"
+        );
+        assert_eq!(
+            prepare_with_cwd(Some(INHERIT_CWD)),
+            Some(std::env::current_dir().unwrap()),
+            "Inherit mode should use the process cwd instead of the context one"
+        );
+    }
+
+    #[test]
+    fn test_selection_fallback() {
+        let prepare = |selection_fallback: SelectionFallback, selected_text: &str| {
+            let tasks = tasks_for(
+                TaskDefinitions(vec![Definition {
+                    label: "format".to_owned(),
+                    command: "fmt".to_owned(),
+                    selection_fallback,
+                    ..Definition::default()
+                }]),
+                "test",
+            );
+            let spawn_in_terminal = tasks[0]
+                .prepare_exec(TaskContext {
+                    cwd: None,
+                    task_variables: TaskVariables::from_iter([(
+                        VariableName::SelectedText,
+                        selected_text.to_owned(),
+                    )]),
+                    selection_fallbacks: SelectionFallbacks {
+                        whole_file: Some(LazyVariable::new(Arc::new(|| {
+                            Some("fn main() {\n    a();\n}\n".to_owned())
+                        }))),
+                        current_line: Some("    a();".to_owned()),
+                    },
+                    args_override: Default::default(),
+                })
+                .unwrap();
+            spawn_in_terminal.env["ZED_SELECTED_TEXT"].clone()
+        };
+
+        assert_eq!(
+            prepare(SelectionFallback::WholeFile, ""),
+            "fn main() {\n    a();\n}\n",
+            "Whole file fallback should use the full buffer contents when nothing is selected"
+        );
+        assert_eq!(prepare(SelectionFallback::CurrentLine, ""), "    a();");
+        assert_eq!(prepare(SelectionFallback::None, ""), "");
+        assert_eq!(
+            prepare(SelectionFallback::WholeFile, "a()"),
+            "a()",
+            "Fallback should not replace the actual selection"
+        );
+    }
+
+    #[test]
+    fn test_escape_literal_dollars() {
+        assert_eq!(escape_literal_dollars("$$"), "\\$");
+        assert_eq!(escape_literal_dollars("price: 5$$"), "price: 5\\$");
+        assert_eq!(
+            escape_literal_dollars("$$ZED_FILE"),
+            "\\$ZED_FILE",
+            "Escaped dollar should not start a variable"
+        );
+        assert_eq!(
+            escape_literal_dollars("$ZED_FILE"),
+            "$ZED_FILE",
+            "A single dollar should still start a variable"
+        );
+        assert_eq!(escape_literal_dollars("$$$ZED_FILE"), "\\$$ZED_FILE");
+        assert!(matches!(
+            escape_literal_dollars("$ZED_FILE"),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn test_tasks_gated_on_env() {
+        let definitions = vec![
+            Definition {
+                label: "always".to_owned(),
+                command: "make".to_owned(),
+                ..Definition::default()
+            },
+            Definition {
+                label: "ci only".to_owned(),
+                command: "make".to_owned(),
+                args: vec!["ci".to_owned()],
+                when: Some(TaskCondition {
+                    env_set: vec!["CI".to_owned()],
+                    env_unset: Vec::new(),
+                }),
+                ..Definition::default()
+            },
+            Definition {
+                label: "local only".to_owned(),
+                command: "make".to_owned(),
+                args: vec!["dev".to_owned()],
+                when: Some(TaskCondition {
+                    env_set: Vec::new(),
+                    env_unset: vec!["CI".to_owned()],
+                }),
+                ..Definition::default()
+            },
+        ];
+        let loaded = |env: &[&str]| {
+            load_tasks(&definitions, "test", |name| env.contains(&name))
+                .into_iter()
+                .map(|task| (task.definition.label.clone(), task.id.0.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            loaded(&["CI"]),
+            vec![
+                ("always".to_owned(), "static_test_0_always".to_owned()),
+                ("ci only".to_owned(), "static_test_1_ci only".to_owned()),
+            ],
+            "Task gated on the env variable should only be loaded when it is set"
+        );
+        assert_eq!(
+            loaded(&[]),
+            vec![
+                ("always".to_owned(), "static_test_0_always".to_owned()),
+                (
+                    "local only".to_owned(),
+                    "static_test_2_local only".to_owned()
+                ),
+            ],
+            "Task ids should not change with the other tasks gated out"
+        );
+
+        let condition: TaskCondition =
+            serde_json_lenient::from_str(r#"{ "env_set": ["CI"] }"#).unwrap();
+        assert!(condition.is_met(|name| name == "CI"));
+        assert!(!condition.is_met(|_| false));
+    }
+
+    #[test]
+    fn test_retry_delays() {
+        let delays = |backoff| {
+            let retry = TaskRetry {
+                attempts: 5,
+                delay_ms: 100,
+                backoff,
+                max_delay_ms: 1000,
+            };
+            (1..=5)
+                .map(|attempt| retry.delay(attempt).as_millis())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            delays(RetryBackoff::Constant),
+            vec![100, 100, 100, 100, 100]
+        );
+        assert_eq!(delays(RetryBackoff::Linear), vec![100, 200, 300, 400, 500]);
+        assert_eq!(
+            delays(RetryBackoff::Exponential),
+            vec![100, 200, 400, 800, 1000],
+            "Delays should not exceed the configured maximum"
+        );
+
+        let retry: TaskRetry =
+            serde_json_lenient::from_str(r#"{ "attempts": 3, "backoff": "exponential" }"#).unwrap();
+        assert_eq!(retry.delay(1), Duration::from_secs(1));
+        assert_eq!(retry.delay(7), Duration::from_secs(60));
+        assert_eq!(retry.delay(100), Duration::from_secs(60));
+    }
+}
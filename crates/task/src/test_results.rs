@@ -0,0 +1,206 @@
+//! Summaries of the test results that tasks report in the TAP or JUnit XML formats.
+
+use std::fmt::{self, Display};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A format of the test results a task reports, in its output or in a results file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TestResultsFormat {
+    /// Test Anything Protocol: `ok` and `not ok` lines, with the `# SKIP` and `# TODO` directives.
+    Tap,
+    /// JUnit XML: `<testcase>` elements, with the `<failure>`, `<error>` or `<skipped>` children.
+    Junit,
+}
+
+/// How many tests have passed, failed and were skipped in the results of a task.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TestResultsSummary {
+    /// Tests that have passed.
+    pub passed: usize,
+    /// Tests that have failed, or errored.
+    pub failed: usize,
+    /// Tests that were skipped, or are marked as not done yet.
+    pub skipped: usize,
+}
+
+impl TestResultsSummary {
+    /// Summarizes the test results, reported in the given format.
+    pub fn parse(format: TestResultsFormat, results: &str) -> Self {
+        match format {
+            TestResultsFormat::Tap => parse_tap(results),
+            TestResultsFormat::Junit => parse_junit(results),
+        }
+    }
+
+    /// Whether none of the tests have failed.
+    pub fn is_success(&self) -> bool {
+        self.failed == 0
+    }
+}
+
+impl Display for TestResultsSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counts = [
+            (self.passed, "passed"),
+            (self.failed, "failed"),
+            (self.skipped, "skipped"),
+        ]
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, outcome)| format!("{count} {outcome}"))
+        .collect::<Vec<_>>();
+        if counts.is_empty() {
+            write!(f, "no tests")
+        } else {
+            write!(f, "{}", counts.join(", "))
+        }
+    }
+}
+
+/// Counts the top level test lines of a TAP stream; the indented lines of the subtests are summarized by their parent test line.
+/// `TODO` tests are counted as skipped, since their failures are expected.
+fn parse_tap(results: &str) -> TestResultsSummary {
+    let mut summary = TestResultsSummary::default();
+    for line in results.lines() {
+        let (ok, rest) = if let Some(rest) = line.strip_prefix("not ok") {
+            (false, rest)
+        } else if let Some(rest) = line.strip_prefix("ok") {
+            (true, rest)
+        } else {
+            continue;
+        };
+        if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+            continue;
+        }
+        let directive = rest
+            .split_once('#')
+            .map(|(_, directive)| directive.trim_start().to_ascii_uppercase())
+            .unwrap_or_default();
+        if directive.starts_with("SKIP") || directive.starts_with("TODO") {
+            summary.skipped += 1;
+        } else if ok {
+            summary.passed += 1;
+        } else {
+            summary.failed += 1;
+        }
+    }
+    summary
+}
+
+/// Counts the `<testcase>` elements of a JUnit XML report: failed if they have a `<failure>` or `<error>`, skipped if they have a `<skipped>`.
+fn parse_junit(results: &str) -> TestResultsSummary {
+    const TESTCASE_START: &str = "<testcase";
+    const TESTCASE_END: &str = "</testcase>";
+
+    let mut summary = TestResultsSummary::default();
+    let mut rest = results;
+    while let Some(start) = rest.find(TESTCASE_START) {
+        rest = &rest[start + TESTCASE_START.len()..];
+        if !rest.starts_with(|c: char| c.is_whitespace() || c == '/' || c == '>') {
+            continue;
+        }
+        let Some(tag_end) = rest.find('>') else {
+            break;
+        };
+        let self_closing = rest[..tag_end].ends_with('/');
+        rest = &rest[tag_end + 1..];
+        let body = if self_closing {
+            ""
+        } else {
+            let body_end = rest.find(TESTCASE_END).unwrap_or(rest.len());
+            let body = &rest[..body_end];
+            rest = &rest[body_end..];
+            body
+        };
+        if body.contains("<failure") || body.contains("<error") {
+            summary.failed += 1;
+        } else if body.contains("<skipped") {
+            summary.skipped += 1;
+        } else {
+            summary.passed += 1;
+        }
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tap_summary() {
+        let tap = "\
+TAP version 14
+1..6
+ok 1 - parses the config
+not ok 2 - writes the output
+  ---
+  message: 'expected 2, got 3'
+  ...
+ok 3 - reads the cache # SKIP no cache in CI
+not ok 4 - handles unicode # TODO not implemented yet
+# Subtest: nested
+    ok 1 - inner test
+    not ok 2 - inner failure
+not ok 5 - nested
+ok 6
+okay, not a test line
+# tests 6
+";
+        let summary = TestResultsSummary::parse(TestResultsFormat::Tap, tap);
+        assert_eq!(
+            summary,
+            TestResultsSummary {
+                passed: 2,
+                failed: 2,
+                skipped: 2,
+            },
+            "Only the top level test lines should be counted, with SKIP and TODO tests skipped"
+        );
+        assert_eq!(summary.to_string(), "2 passed, 2 failed, 2 skipped");
+        assert!(!summary.is_success());
+    }
+
+    #[test]
+    fn test_junit_summary() {
+        let junit = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites tests="5" failures="1" errors="1" skipped="1">
+  <testsuite name="parser" tests="3">
+    <testcase name="parses numbers" classname="parser" time="0.01"/>
+    <testcase name="parses strings" classname="parser" time="0.02">
+      <failure message="expected a string">assertion failed</failure>
+    </testcase>
+    <testcase name="parses comments" classname="parser">
+      <skipped/>
+    </testcase>
+  </testsuite>
+  <testsuite name="writer" tests="2">
+    <testcase name="writes files" classname="writer">
+      <system-out>wrote 3 files</system-out>
+    </testcase>
+    <testcase name="writes links" classname="writer">
+      <error type="io">permission denied</error>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#;
+        let summary = TestResultsSummary::parse(TestResultsFormat::Junit, junit);
+        assert_eq!(
+            summary,
+            TestResultsSummary {
+                passed: 2,
+                failed: 2,
+                skipped: 1,
+            },
+            "Test cases with errors should be counted as failed"
+        );
+        assert_eq!(summary.to_string(), "2 passed, 2 failed, 1 skipped");
+        assert_eq!(
+            TestResultsSummary::parse(TestResultsFormat::Junit, "<testsuites/>").to_string(),
+            "no tests"
+        );
+    }
+}
@@ -137,6 +137,18 @@ impl TryFrom<VsCodeTaskFile> for TaskDefinitions {
                 VariableName::WorktreeRoot.to_string(),
             ),
             ("file".to_owned(), VariableName::File.to_string()),
+            (
+                "relativeFile".to_owned(),
+                VariableName::RelativeFile.to_string(),
+            ),
+            (
+                "fileDirname".to_owned(),
+                VariableName::FileDirname.to_string(),
+            ),
+            (
+                "fileBasenameNoExtension".to_owned(),
+                VariableName::Stem.to_string(),
+            ),
             ("lineNumber".to_owned(), VariableName::Row.to_string()),
             (
                 "selectedText".to_owned(),
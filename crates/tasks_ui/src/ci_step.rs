@@ -0,0 +1,159 @@
+use std::{collections::BTreeMap, path::Path};
+
+use serde::Deserialize;
+use task::{SpawnInTerminal, VariableName};
+
+/// A CI provider to render the step of a task for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CiProvider {
+    /// A step of a GitHub Actions job.
+    #[default]
+    GithubActions,
+    /// A GitLab CI job.
+    Gitlab,
+}
+
+/// A YAML snippet of the CI step that runs the resolved task: its command, cwd relative to the worktree root and env.
+/// Zed task variables missing from the context are left as the `$ZED_*` references, with a note to provide them in CI.
+pub(crate) fn ci_step_snippet(spawn_in_terminal: &SpawnInTerminal, provider: CiProvider) -> String {
+    let (command, missing_variables) = spawn_in_terminal.resolve_available_variables();
+    let worktree_root = spawn_in_terminal
+        .env
+        .get(&VariableName::WorktreeRoot.to_string())
+        .map(Path::new);
+    let cwd = spawn_in_terminal.cwd.as_deref().and_then(|cwd| {
+        let cwd = match worktree_root {
+            Some(root) => cwd.strip_prefix(root).unwrap_or(cwd),
+            None => cwd,
+        };
+        let cwd = cwd.to_string_lossy().into_owned();
+        (!cwd.is_empty()).then_some(cwd)
+    });
+    // Zed task variables describe the editor state, and are substituted into the command instead.
+    let env = spawn_in_terminal
+        .env
+        .iter()
+        .filter(|(name, _)| !name.starts_with("ZED_"))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut snippet = String::new();
+    for variable in &missing_variables {
+        snippet.push_str(&format!(
+            "# ${variable} is only known inside of Zed: provide it in the CI environment.\n"
+        ));
+    }
+    let mut secrets = spawn_in_terminal.secrets.keys().collect::<Vec<_>>();
+    secrets.sort();
+    for secret in secrets {
+        snippet.push_str(&format!(
+            "# ${secret} is a secret: provide it from the CI secrets.\n"
+        ));
+    }
+    let label = yaml_string(&spawn_in_terminal.label);
+    match provider {
+        CiProvider::GithubActions => {
+            snippet.push_str(&format!("- name: {label}\n"));
+            if let Some(cwd) = cwd {
+                snippet.push_str(&format!("  working-directory: {}\n", yaml_string(&cwd)));
+            }
+            if !env.is_empty() {
+                snippet.push_str("  env:\n");
+                for (name, value) in env {
+                    snippet.push_str(&format!("    {name}: {}\n", yaml_string(value)));
+                }
+            }
+            snippet.push_str(&format!("  run: {}\n", yaml_string(&command)));
+        }
+        CiProvider::Gitlab => {
+            snippet.push_str(&format!("{label}:\n"));
+            if !env.is_empty() {
+                snippet.push_str("  variables:\n");
+                for (name, value) in env {
+                    snippet.push_str(&format!("    {name}: {}\n", yaml_string(value)));
+                }
+            }
+            snippet.push_str("  script:\n");
+            if let Some(cwd) = cwd {
+                snippet.push_str(&format!("    - {}\n", yaml_string(&format!("cd {cwd}"))));
+            }
+            snippet.push_str(&format!("    - {}\n", yaml_string(&command)));
+        }
+    }
+    snippet
+}
+
+/// A double-quoted YAML scalar: JSON strings are valid ones, with all special characters escaped.
+fn yaml_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| format!("\"{value}\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use collections::HashMap;
+    use task::{
+        static_source::{tasks_for, Definition, TaskDefinitions},
+        TaskContext, TaskVariables,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_ci_step_snippet() {
+        let task = tasks_for(
+            TaskDefinitions(vec![Definition {
+                label: "test $ZED_FILENAME_NO_EXT".to_owned(),
+                command: "cargo".to_owned(),
+                args: vec![
+                    "test".to_owned(),
+                    "--test".to_owned(),
+                    "$ZED_FILENAME_NO_EXT".to_owned(),
+                    "${ZED_SYMBOL}".to_owned(),
+                ],
+                cwd: Some("crates/task".to_owned()),
+                env: HashMap::from_iter([
+                    ("RUST_LOG".to_owned(), "info".to_owned()),
+                    ("TOKEN".to_owned(), "secret:deploy-token".to_owned()),
+                ]),
+                ..Definition::default()
+            }]),
+            "test",
+        )
+        .pop()
+        .unwrap();
+        let spawn_in_terminal = task
+            .prepare_exec(TaskContext {
+                cwd: Some("/project".into()),
+                task_variables: TaskVariables::from_iter([
+                    (VariableName::WorktreeRoot, "/project".to_owned()),
+                    (VariableName::Stem, "lib".to_owned()),
+                ]),
+                ..TaskContext::default()
+            })
+            .unwrap();
+
+        assert_eq!(
+            ci_step_snippet(&spawn_in_terminal, CiProvider::GithubActions),
+            r#"# $ZED_SYMBOL is only known inside of Zed: provide it in the CI environment.
+# $TOKEN is a secret: provide it from the CI secrets.
+- name: "test lib"
+  working-directory: "crates/task"
+  env:
+    RUST_LOG: "info"
+  run: "cargo test --test lib ${ZED_SYMBOL}"
+"#
+        );
+        assert_eq!(
+            ci_step_snippet(&spawn_in_terminal, CiProvider::Gitlab),
+            r#"# $ZED_SYMBOL is only known inside of Zed: provide it in the CI environment.
+# $TOKEN is a secret: provide it from the CI secrets.
+"test lib":
+  variables:
+    RUST_LOG: "info"
+  script:
+    - "cd crates/task"
+    - "cargo test --test lib ${ZED_SYMBOL}"
+"#
+        );
+    }
+}
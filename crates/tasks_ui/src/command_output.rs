@@ -0,0 +1,355 @@
+use std::process::Stdio;
+
+use anyhow::Context as _;
+use collections::HashMap;
+use gpui::{AppContext, ClipboardItem, ViewContext};
+use settings::Settings;
+use task::{captured_output::CapturedOutput, SpawnInTerminal};
+use terminal::terminal_settings::{Shell, TerminalSettings};
+use workspace::{Toast, Workspace};
+
+use crate::git_diff::spawn_with_git_diff;
+
+const TASK_OUTPUT_COPIED_TOAST_ID: usize = 0x7a5c0b1e;
+
+/// Runs the task in the background, and copies its trimmed stdout into the clipboard if the task succeeds.
+pub(crate) fn copy_task_output(
+    spawn_in_terminal: SpawnInTerminal,
+    cx: &mut ViewContext<'_, Workspace>,
+) -> gpui::Task<()> {
+    let label = spawn_in_terminal.label.clone();
+    let output = cx
+        .background_executor()
+        .spawn(capture_stdout(task_shell(cx), spawn_in_terminal));
+    cx.spawn(|workspace, mut cx| async move {
+        let output = output.await;
+        workspace
+            .update(&mut cx, |workspace, cx| match output {
+                Ok(stdout) => {
+                    cx.write_to_clipboard(ClipboardItem::new(stdout));
+                    workspace.show_toast(
+                        Toast::new(
+                            TASK_OUTPUT_COPIED_TOAST_ID,
+                            format!("Copied output of \"{label}\" to clipboard"),
+                        ),
+                        cx,
+                    );
+                }
+                Err(e) => workspace.show_error(&e, cx),
+            })
+            .ok();
+    })
+}
+
+/// The shell the terminal panel spawns the tasks in, with the args to run a command line with,
+/// so that the tasks run in the background get the same rc files loaded and their args treated as the same shell words.
+/// Falls back to `sh` when the system shell is unknown.
+fn task_shell(cx: &AppContext) -> (String, Vec<String>) {
+    let (program, mut args) = match TerminalSettings::get_global(cx).shell.clone() {
+        Shell::System => (
+            std::env::var("SHELL").unwrap_or_else(|_| "sh".to_owned()),
+            Vec::new(),
+        ),
+        Shell::Program(program) => (program, Vec::new()),
+        Shell::WithArguments { program, args } => (program, args),
+    };
+    args.extend(["-i".to_owned(), "-c".to_owned()]);
+    (program, args)
+}
+
+/// Runs the task's command line with the [`task_shell`] given, returning its stdout.
+pub(crate) async fn capture_stdout(
+    (shell, shell_args): (String, Vec<String>),
+    spawn_in_terminal: SpawnInTerminal,
+) -> anyhow::Result<String> {
+    let mut command_line = spawn_in_terminal.command;
+    for arg in &spawn_in_terminal.args {
+        command_line.push(' ');
+        command_line.push_str(arg);
+    }
+    let mut command = smol::process::Command::new(shell);
+    command
+        .args(shell_args)
+        .arg(&command_line)
+        .envs(&spawn_in_terminal.env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(cwd) = &spawn_in_terminal.cwd {
+        command.current_dir(cwd);
+    }
+    let output = command
+        .output()
+        .await
+        .with_context(|| format!("spawning task {:?}", spawn_in_terminal.label))?;
+    anyhow::ensure!(
+        output.status.success(),
+        "Task {:?} failed with {}: {}",
+        spawn_in_terminal.label,
+        output.status,
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    let mut stdout = CapturedOutput::new(spawn_in_terminal.max_output_bytes);
+    stdout.push_str(String::from_utf8_lossy(&output.stdout).trim());
+    Ok(stdout.text().to_owned())
+}
+
+/// Runs the commands of the task's `$(command)` substitutions in its cwd, substitutes their outputs into the args and spawns the task,
+/// showing an error instead if any of the commands fails.
+/// The commands run in the [`task_shell`] with the env of the task, except for its secrets: those are only for the task itself.
+pub(crate) fn spawn_with_command_output(
+    workspace: &Workspace,
+    mut spawn_in_terminal: SpawnInTerminal,
+    spawn: impl FnOnce(&Workspace, SpawnInTerminal, &mut ViewContext<'_, Workspace>) + 'static,
+    cx: &mut ViewContext<'_, Workspace>,
+) {
+    let commands = spawn_in_terminal.command_substitutions();
+    if commands.is_empty() {
+        spawn_with_git_diff(workspace, spawn_in_terminal, spawn, cx);
+        return;
+    }
+    let mut env = spawn_in_terminal.env.clone();
+    env.retain(|name, _| !spawn_in_terminal.secrets.contains_key(name));
+    let runs = commands
+        .into_iter()
+        .map(|command| SpawnInTerminal {
+            command,
+            args: Vec::new(),
+            env: env.clone(),
+            ..spawn_in_terminal.clone()
+        })
+        .collect::<Vec<_>>();
+    let shell = task_shell(cx);
+    let outputs = cx.background_executor().spawn(async move {
+        let mut outputs = HashMap::default();
+        for run in runs {
+            let command = run.command.clone();
+            let output = capture_stdout(shell.clone(), run)
+                .await
+                .with_context(|| format!("running the command substitution `$({command})`"))?;
+            outputs.insert(command, output);
+        }
+        anyhow::Ok(outputs)
+    });
+    cx.spawn(|workspace, mut cx| async move {
+        let outputs = outputs.await;
+        workspace
+            .update(&mut cx, |workspace, cx| match outputs {
+                Ok(outputs) => {
+                    spawn_in_terminal.substitute_commands(&outputs);
+                    spawn_with_git_diff(workspace, spawn_in_terminal, spawn, cx);
+                }
+                Err(e) => workspace.show_error(&e, cx),
+            })
+            .ok();
+    })
+    .detach();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc, sync::Arc};
+
+    use gpui::TestAppContext;
+    use project::{FakeFs, Project};
+    use serde_json::json;
+    use task::{captured_output::DEFAULT_MAX_OUTPUT_BYTES, static_source::ShowStrategy, TaskId};
+
+    use crate::{
+        modal::Spawn,
+        secrets::TaskSecrets,
+        tests::{init_test, FakeSecretStore},
+    };
+
+    use super::*;
+
+    #[gpui::test]
+    async fn test_copy_task_output(cx: &mut TestAppContext) {
+        init_test(cx);
+        cx.executor().allow_parking();
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree("/dir", json!({ "a.ts": "a" })).await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        let spawn_task = |command: &str, args: &[&str]| SpawnInTerminal {
+            id: TaskId("copy_output".to_owned()),
+            label: "copy output".to_owned(),
+            command: command.to_owned(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            cwd: None,
+            env: Default::default(),
+            use_new_terminal: false,
+            allow_concurrent_runs: false,
+            cancel_previous: false,
+            reveal: None,
+            dock: None,
+            container: None,
+            combine_output: true,
+            max_output_bytes: DEFAULT_MAX_OUTPUT_BYTES,
+            kill_on_output_limit: false,
+            confirm: false,
+            show: ShowStrategy::Clipboard,
+            nice: None,
+            elevate: false,
+            requires: Vec::new(),
+            retry: None,
+            secrets: Default::default(),
+            tail_file: None,
+            git_diff: None,
+            group: None,
+            terminal_group: None,
+            command_substitution: false,
+            pre: None,
+            post: None,
+            results_format: None,
+            results_file: None,
+            reveal_cwd_in_project_panel: false,
+            split: None,
+            git_commit_message: None,
+            depends_on: Vec::new(),
+            parallel: false,
+            singleton_group: None,
+            show_summary: false,
+            problem_matcher: None,
+            spawn_nonce: 0,
+        };
+
+        workspace
+            .update(cx, |_, cx| {
+                copy_task_output(spawn_task("echo", &["'  1.2.3  '"]), cx)
+            })
+            .await;
+        assert_eq!(
+            cx.read_from_clipboard().map(|item| item.text().to_owned()),
+            Some("1.2.3".to_owned()),
+            "Trimmed stdout of the task should be copied"
+        );
+
+        workspace
+            .update(cx, |_, cx| {
+                copy_task_output(spawn_task("echo 4.5.6 && false", &[]), cx)
+            })
+            .await;
+        assert_eq!(
+            cx.read_from_clipboard().map(|item| item.text().to_owned()),
+            Some("1.2.3".to_owned()),
+            "Output of a failed task should not be copied"
+        );
+    }
+
+    #[cfg(not(windows))]
+    #[gpui::test]
+    async fn test_command_substitution(cx: &mut TestAppContext) {
+        init_test(cx);
+        cx.executor().allow_parking();
+        let fs = FakeFs::new(cx.executor());
+        // Inner commands are run in the task's cwd, so the project has to be in an existing directory.
+        let dir = std::env::temp_dir();
+        fs.insert_tree(
+            &dir,
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[
+                        {
+                            "label": "release",
+                            "command": "release",
+                            "args": ["--tag", "$(echo '  v1.2.3 ')", "--notes=$(echo notes)", "$((1 + 2))"],
+                            "command_substitution": true
+                        },
+                        { "label": "not opted in", "command": "release", "args": ["$(echo v1.2.3)"] },
+                        { "label": "failing", "command": "release", "args": ["$(exit 3)"], "command_substitution": true },
+                        {
+                            "label": "retried",
+                            "command": "deploy",
+                            "args": ["$(echo v1.2.3)"],
+                            "pre": "make",
+                            "retry": { "attempts": 1, "delay_ms": 0 },
+                            "command_substitution": true
+                        },
+                        {
+                            "label": "with secret",
+                            "command": "deploy",
+                            "args": ["$(echo \"token:$TOKEN.\")"],
+                            "env": { "TOKEN": "secret:deploy-token" },
+                            "command_substitution": true
+                        }
+                    ]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        let project = Project::test(fs, [dir.as_path()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        let spawned_tasks = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let spawned_tasks = spawned_tasks.clone();
+            cx.subscribe(&workspace, move |_, event, _| {
+                if let workspace::Event::SpawnTask(spawn_in_terminal) = event {
+                    spawned_tasks.borrow_mut().push((
+                        spawn_in_terminal.label.clone(),
+                        spawn_in_terminal.command.clone(),
+                        spawn_in_terminal.args.clone(),
+                    ));
+                }
+            })
+            .detach();
+        });
+        cx.run_until_parked();
+
+        for task_name in ["release", "not opted in", "failing", "retried"] {
+            cx.dispatch_action(Spawn {
+                task_name: Some(task_name.to_string()),
+                omit_history: false,
+                split: None,
+            });
+            cx.run_until_parked();
+        }
+        let (retried_label, retried_command, retried_args) =
+            spawned_tasks.borrow_mut().pop().unwrap();
+        assert_eq!(retried_label, "retried");
+        let retried_command_line = format!("{retried_command} {}", retried_args.join(" "));
+        assert!(
+            !retried_command_line.contains("$(echo") && retried_command_line.contains("deploy v1.2.3"),
+            "Outputs should be substituted before the hooks and retries fold the args into the command, got: {retried_command_line}"
+        );
+        assert_eq!(
+            *spawned_tasks.borrow(),
+            vec![
+                (
+                    "release".to_owned(),
+                    "release".to_owned(),
+                    vec![
+                        "--tag".to_owned(),
+                        "v1.2.3".to_owned(),
+                        "--notes=notes".to_owned(),
+                        "$((1 + 2))".to_owned(),
+                    ]
+                ),
+                (
+                    "not opted in".to_owned(),
+                    "release".to_owned(),
+                    vec!["$(echo v1.2.3)".to_owned()]
+                ),
+            ],
+            "Inner command outputs should be substituted only for the opted in tasks, and a failing inner command should abort the spawn"
+        );
+
+        cx.update(|cx| {
+            cx.set_global(TaskSecrets(Arc::new(FakeSecretStore(HashMap::from_iter(
+                [("deploy-token".to_string(), "s3cr3t".to_string())],
+            )))))
+        });
+        cx.dispatch_action(Spawn {
+            task_name: Some("with secret".to_string()),
+            omit_history: false,
+            split: None,
+        });
+        cx.run_until_parked();
+        assert_eq!(
+            spawned_tasks.borrow_mut().pop().map(|(_, _, args)| args),
+            Some(vec!["token:.".to_owned()]),
+            "Inner commands should not get the secrets of the task"
+        );
+    }
+}
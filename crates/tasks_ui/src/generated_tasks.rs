@@ -0,0 +1,198 @@
+use anyhow::Context as _;
+use gpui::{PromptLevel, ViewContext};
+use project::Worktree;
+use serde_json::{json, Value};
+use util::paths::LOCAL_TASKS_RELATIVE_PATH;
+use workspace::{Toast, Workspace};
+
+const TASKS_NOT_GENERATED_TOAST_ID: usize = 0x7a5c6e00;
+
+/// Project manifests the tasks are generated for, with the build, test and run commands of their tool.
+const PROJECT_KINDS: &[(&str, &[(&str, &str, &[&str])])] = &[
+    (
+        "Cargo.toml",
+        &[
+            ("cargo build", "cargo", &["build"]),
+            ("cargo test", "cargo", &["test"]),
+            ("cargo run", "cargo", &["run"]),
+        ],
+    ),
+    (
+        "package.json",
+        &[
+            ("npm build", "npm", &["run", "build"]),
+            ("npm test", "npm", &["test"]),
+            ("npm start", "npm", &["start"]),
+        ],
+    ),
+    (
+        "pyproject.toml",
+        &[
+            ("python build", "python", &["-m", "build"]),
+            ("python test", "python", &["-m", "pytest"]),
+        ],
+    ),
+    (
+        "Makefile",
+        &[
+            ("make", "make", &[]),
+            ("make test", "make", &["test"]),
+            ("make run", "make", &["run"]),
+        ],
+    ),
+];
+
+/// Starter task definitions for all project manifests found in the worktree root, `None` if there are none.
+pub(crate) fn generated_tasks_content(worktree: &Worktree) -> Option<String> {
+    let definitions = PROJECT_KINDS
+        .iter()
+        .filter(|(manifest, _)| worktree.entry_for_path(manifest).is_some())
+        .flat_map(|(_, tasks)| tasks.iter())
+        .map(|(label, command, args)| json!({ "label": label, "command": command, "args": args }))
+        .collect::<Vec<_>>();
+    if definitions.is_empty() {
+        return None;
+    }
+    serde_json::to_string_pretty(&Value::Array(definitions)).ok()
+}
+
+/// Writes the tasks generated for the project type into `.zed/tasks.json` of the first worktree and opens it,
+/// asking before overwriting the existing file.
+pub(crate) fn generate_tasks(workspace: &mut Workspace, cx: &mut ViewContext<Workspace>) {
+    let Some(worktree) = workspace
+        .project()
+        .read(cx)
+        .visible_worktrees(cx)
+        .find(|worktree| {
+            worktree
+                .read(cx)
+                .root_entry()
+                .map_or(false, |entry| entry.is_dir())
+        })
+    else {
+        return;
+    };
+    let worktree = worktree.read(cx);
+    let content = generated_tasks_content(worktree);
+    let tasks_exist = worktree
+        .entry_for_path(*LOCAL_TASKS_RELATIVE_PATH)
+        .is_some();
+    let abs_path = worktree.abs_path().join(*LOCAL_TASKS_RELATIVE_PATH);
+    let Some(content) = content else {
+        workspace.show_toast(
+            Toast::new(
+                TASKS_NOT_GENERATED_TOAST_ID,
+                "No Cargo.toml, package.json, pyproject.toml or Makefile to generate the tasks for",
+            ),
+            cx,
+        );
+        return;
+    };
+    let overwrite_answer = tasks_exist.then(|| {
+        cx.prompt(
+            PromptLevel::Warning,
+            &format!(
+                "Overwrite the existing {}?",
+                LOCAL_TASKS_RELATIVE_PATH.display()
+            ),
+            Some("It will be replaced by the tasks generated for the project type."),
+            &["Overwrite", "Cancel"],
+        )
+    });
+    let fs = workspace.app_state().fs.clone();
+    cx.spawn(|workspace, mut cx| async move {
+        if let Some(answer) = overwrite_answer {
+            if answer.await != Ok(0) {
+                return anyhow::Ok(());
+            }
+        }
+        if let Some(dir) = abs_path.parent() {
+            fs.create_dir(dir)
+                .await
+                .with_context(|| format!("creating the tasks directory {dir:?}"))?;
+        }
+        fs.atomic_write(abs_path.clone(), content)
+            .await
+            .with_context(|| format!("writing the generated tasks into {abs_path:?}"))?;
+        workspace
+            .update(&mut cx, |workspace, cx| {
+                workspace.open_abs_path(abs_path.clone(), false, cx)
+            })?
+            .await?;
+        anyhow::Ok(())
+    })
+    .detach_and_log_err(cx);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use gpui::TestAppContext;
+    use project::{FakeFs, Fs as _, Project};
+    use task::static_source::TaskDefinitions;
+
+    use super::*;
+
+    #[gpui::test]
+    async fn test_tasks_generated_for_cargo_project(cx: &mut TestAppContext) {
+        crate::tests::init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                "Cargo.toml": "[package]\nname = \"app\"",
+                "src": { "main.rs": "fn main() {}" },
+            }),
+        )
+        .await;
+        let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        cx.dispatch_action(crate::modal::GenerateTasks);
+        cx.run_until_parked();
+
+        let tasks_path = Path::new("/dir").join(*LOCAL_TASKS_RELATIVE_PATH);
+        let generated_tasks = fs.load(&tasks_path).await.unwrap();
+        let TaskDefinitions(definitions) = serde_json::from_str(&generated_tasks).unwrap();
+        assert_eq!(
+            definitions
+                .iter()
+                .map(|definition| (
+                    definition.label.as_str(),
+                    definition.command.as_str(),
+                    definition.args.clone()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                ("cargo build", "cargo", vec!["build".to_owned()]),
+                ("cargo test", "cargo", vec!["test".to_owned()]),
+                ("cargo run", "cargo", vec!["run".to_owned()]),
+            ]
+        );
+        let opened_path = workspace.update(cx, |workspace, cx| {
+            workspace
+                .active_item(cx)
+                .and_then(|item| item.project_path(cx))
+                .map(|project_path| project_path.path.to_path_buf())
+        });
+        assert_eq!(
+            opened_path.as_deref(),
+            Some(*LOCAL_TASKS_RELATIVE_PATH),
+            "Generated tasks should be opened for review"
+        );
+
+        fs.atomic_write(tasks_path.clone(), "[]".to_owned())
+            .await
+            .unwrap();
+        cx.run_until_parked();
+        cx.dispatch_action(crate::modal::GenerateTasks);
+        cx.run_until_parked();
+        assert!(
+            cx.has_pending_prompt(),
+            "Existing tasks should not be overwritten without confirmation"
+        );
+        cx.simulate_prompt_answer(1);
+        cx.run_until_parked();
+        assert_eq!(fs.load(&tasks_path).await.unwrap(), "[]");
+    }
+}
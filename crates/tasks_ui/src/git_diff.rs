@@ -0,0 +1,146 @@
+use anyhow::Context as _;
+use gpui::ViewContext;
+use project::ProjectPath;
+use task::{static_source::GitDiff, SpawnInTerminal};
+use workspace::Workspace;
+
+use crate::test_results::spawn_with_test_results;
+
+/// Writes the git diff the task asks for into its [`SpawnInTerminal::git_diff_file`] and supplies it to the task, before spawning it.
+/// Tasks spawned outside of git repositories get no diff supplied.
+pub(crate) fn spawn_with_git_diff(
+    workspace: &Workspace,
+    mut spawn_in_terminal: SpawnInTerminal,
+    spawn: impl FnOnce(&Workspace, SpawnInTerminal, &mut ViewContext<'_, Workspace>) + 'static,
+    cx: &mut ViewContext<'_, Workspace>,
+) {
+    let (Some(git_diff), Some(diff_file)) = (
+        spawn_in_terminal.git_diff,
+        spawn_in_terminal.git_diff_file(),
+    ) else {
+        spawn_with_test_results(workspace, spawn_in_terminal, spawn, cx);
+        return;
+    };
+    let project = workspace.project().read(cx);
+    let Some(repository) = spawn_in_terminal.cwd.as_deref().and_then(|cwd| {
+        let (worktree, relative_path) = project.find_local_worktree(cwd, cx)?;
+        project.get_repo(
+            &ProjectPath {
+                worktree_id: worktree.read(cx).id(),
+                path: relative_path.into(),
+            },
+            cx,
+        )
+    }) else {
+        spawn_with_test_results(workspace, spawn_in_terminal, spawn, cx);
+        return;
+    };
+    let fs = workspace.app_state().fs.clone();
+    cx.spawn(|workspace, mut cx| async move {
+        let diff = cx
+            .background_executor()
+            .spawn(async move { repository.lock().diff(git_diff == GitDiff::Staged) })
+            .await;
+        let written = match diff {
+            Ok(diff) => fs
+                .atomic_write(diff_file.clone(), diff)
+                .await
+                .with_context(|| format!("writing git diff into {diff_file:?}")),
+            Err(e) => Err(e),
+        };
+        workspace
+            .update(&mut cx, |workspace, cx| match written {
+                Ok(()) => {
+                    spawn_in_terminal.supply_git_diff();
+                    spawn_with_test_results(workspace, spawn_in_terminal, spawn, cx);
+                }
+                Err(e) => workspace.show_error(&e, cx),
+            })
+            .ok();
+    })
+    .detach();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, path::Path, rc::Rc};
+
+    use gpui::TestAppContext;
+    use project::{FakeFs, Fs as _, Project};
+    use serde_json::json;
+    use task::{posix_script_command, shell_quote};
+
+    use crate::{modal::Spawn, tests::init_test};
+
+    use super::*;
+
+    #[gpui::test]
+    async fn test_git_diff_supplied(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".git": {},
+                ".zed": {
+                    "tasks.json": r#"[
+                        { "label": "review", "command": "review-changes", "git_diff": "unstaged" },
+                        { "label": "review staged", "command": "review-changes", "git_diff": "staged" }
+                    ]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        fs.create_dir(&std::env::temp_dir()).await.unwrap();
+        let unstaged_diff = "--- a/a.ts\n+++ b/a.ts\n@@ -1 +1 @@\n-a\n+b\n";
+        let staged_diff = "--- /dev/null\n+++ b/b.ts\n@@ -0,0 +1 @@\n+b\n";
+        fs.set_diff_for_repo(Path::new("/dir/.git"), false, unstaged_diff);
+        fs.set_diff_for_repo(Path::new("/dir/.git"), true, staged_diff);
+        let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        let spawned_tasks = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let spawned_tasks = spawned_tasks.clone();
+            cx.subscribe(&workspace, move |_, event, _| {
+                if let workspace::Event::SpawnTask(spawn_in_terminal) = event {
+                    spawned_tasks.borrow_mut().push(spawn_in_terminal.clone());
+                }
+            })
+            .detach();
+        });
+        cx.run_until_parked();
+
+        for (task_name, expected_diff) in
+            [("review", unstaged_diff), ("review staged", staged_diff)]
+        {
+            cx.dispatch_action(Spawn {
+                task_name: Some(task_name.to_string()),
+                omit_history: false,
+                split: None,
+            });
+            cx.run_until_parked();
+            let spawn_in_terminal = spawned_tasks
+                .borrow_mut()
+                .pop()
+                .expect("Task should be spawned after the diff is written");
+            let diff_file = spawn_in_terminal.git_diff_file().unwrap();
+            assert_eq!(
+                spawn_in_terminal
+                    .env
+                    .get("ZED_GIT_DIFF_FILE")
+                    .map(String::as_str),
+                Some(diff_file.to_string_lossy().as_ref())
+            );
+            assert_eq!(
+                (spawn_in_terminal.command, spawn_in_terminal.args),
+                posix_script_command(&format!(
+                    "{{ review-changes; }} < {}",
+                    shell_quote(&diff_file.to_string_lossy())
+                )),
+                "Diff should be supplied as the command's stdin"
+            );
+            assert_eq!(fs.load(&diff_file).await.unwrap(), expected_diff);
+        }
+    }
+}
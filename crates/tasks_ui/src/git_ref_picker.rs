@@ -0,0 +1,396 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::Arc,
+};
+
+use anyhow::Context as _;
+use fuzzy::{StringMatch, StringMatchCandidate};
+use gpui::{
+    rems, DismissEvent, EventEmitter, FocusableView, InteractiveElement, ParentElement, Render,
+    SharedString, Styled, Subscription, View, ViewContext, VisualContext, WeakView,
+};
+use picker::{highlighted_match_with_paths::HighlightedText, Picker, PickerDelegate};
+use task::VariableName;
+use ui::{
+    v_flex, Color, Label, LabelCommon, ListItem, ListItemSpacing, RenderOnce, Selectable,
+    WindowContext,
+};
+use util::ResultExt;
+use workspace::{ModalView, Workspace};
+
+use crate::{modal::TasksModal, task_context, task_cwd};
+
+/// How many of the latest commits to offer in the picker, after all stashes.
+const RECENT_COMMITS: usize = 50;
+
+/// A commit or a stash to spawn the task for: its ref, e.g. `1a2b3c4` or `stash@{0}`, and its subject.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct GitRefCandidate {
+    pub(crate) git_ref: SharedString,
+    pub(crate) subject: SharedString,
+}
+
+impl GitRefCandidate {
+    /// Parses a `ref<TAB>subject` line of the `git stash list` and `git log` output.
+    fn parse(line: &str) -> Option<Self> {
+        let (git_ref, subject) = line.split_once('\t')?;
+        Some(Self {
+            git_ref: SharedString::from(git_ref.trim().to_owned()),
+            subject: SharedString::from(subject.trim().to_owned()),
+        })
+    }
+}
+
+/// Lists the stashes and the recent commits of the git repository of the task cwd, to pick the `ZED_GIT_REF` from.
+pub(crate) fn pick_git_ref(workspace: &mut Workspace, cx: &mut ViewContext<Workspace>) {
+    let cwd = task_cwd(workspace, cx).log_err().flatten();
+    let Some(repository_dir) = cwd.clone() else {
+        return;
+    };
+    let git_refs = cx
+        .background_executor()
+        .spawn(async move { recent_git_refs(&repository_dir).await });
+    cx.spawn(|workspace, mut cx| async move {
+        let git_refs = git_refs.await?;
+        workspace.update(&mut cx, |workspace, cx| {
+            let workspace_handle = workspace.weak_handle();
+            workspace.toggle_modal(cx, |cx| {
+                GitRefPickerModal::new(git_refs, cwd, workspace_handle, cx)
+            })
+        })
+    })
+    .detach_and_log_err(cx);
+}
+
+/// The stashes and the latest commits of the git repository that contains `dir`, the stashes first.
+async fn recent_git_refs(dir: &Path) -> anyhow::Result<Vec<GitRefCandidate>> {
+    let stashes = git_output(dir, &["stash", "list", "--format=%gd%x09%s"]).await?;
+    let commits = git_output(
+        dir,
+        &[
+            "log",
+            "--format=%h%x09%s",
+            &format!("--max-count={RECENT_COMMITS}"),
+        ],
+    )
+    .await?;
+    Ok(stashes
+        .lines()
+        .chain(commits.lines())
+        .filter_map(GitRefCandidate::parse)
+        .collect())
+}
+
+async fn git_output(dir: &Path, args: &[&str]) -> anyhow::Result<String> {
+    let output = smol::process::Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .with_context(|| format!("running git {args:?} in {dir:?}"))?;
+    anyhow::ensure!(
+        output.status.success(),
+        "git {args:?} failed in {dir:?} with {}: {}",
+        output.status,
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// A modal to choose the commit or the stash to set `ZED_GIT_REF` to, before choosing the task to spawn.
+pub(crate) struct GitRefPickerModal {
+    pub(crate) picker: View<Picker<GitRefPickerDelegate>>,
+    _subscription: Subscription,
+}
+
+impl GitRefPickerModal {
+    pub(crate) fn new(
+        git_refs: Vec<GitRefCandidate>,
+        cwd: Option<PathBuf>,
+        workspace: WeakView<Workspace>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let picker = cx.new_view(|cx| {
+            Picker::uniform_list(
+                GitRefPickerDelegate {
+                    git_refs,
+                    cwd,
+                    matches: Vec::new(),
+                    selected_index: 0,
+                    workspace,
+                },
+                cx,
+            )
+        });
+        let _subscription = cx.subscribe(&picker, |_, _, _, cx| {
+            cx.emit(DismissEvent);
+        });
+        Self {
+            picker,
+            _subscription,
+        }
+    }
+}
+
+impl Render for GitRefPickerModal {
+    fn render(&mut self, _: &mut ViewContext<Self>) -> impl gpui::prelude::IntoElement {
+        v_flex()
+            .key_context("TaskGitRefPickerModal")
+            .w(rems(34.))
+            .child(self.picker.clone())
+    }
+}
+
+impl EventEmitter<DismissEvent> for GitRefPickerModal {}
+
+impl FocusableView for GitRefPickerModal {
+    fn focus_handle(&self, cx: &gpui::AppContext) -> gpui::FocusHandle {
+        self.picker.read(cx).focus_handle(cx)
+    }
+}
+
+impl ModalView for GitRefPickerModal {}
+
+pub(crate) struct GitRefPickerDelegate {
+    git_refs: Vec<GitRefCandidate>,
+    cwd: Option<PathBuf>,
+    matches: Vec<StringMatch>,
+    selected_index: usize,
+    workspace: WeakView<Workspace>,
+}
+
+impl PickerDelegate for GitRefPickerDelegate {
+    type ListItem = ListItem;
+
+    fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    fn selected_index(&self) -> usize {
+        self.selected_index
+    }
+
+    fn set_selected_index(&mut self, ix: usize, _: &mut ViewContext<Picker<Self>>) {
+        self.selected_index = ix;
+    }
+
+    fn placeholder_text(&self, _: &mut WindowContext) -> Arc<str> {
+        Arc::from("Select a commit or a stash to run the task for...")
+    }
+
+    fn update_matches(
+        &mut self,
+        query: String,
+        cx: &mut ViewContext<Picker<Self>>,
+    ) -> gpui::Task<()> {
+        let candidates = self
+            .git_refs
+            .iter()
+            .enumerate()
+            .map(|(id, git_ref)| {
+                let string = format!("{} {}", git_ref.git_ref, git_ref.subject);
+                StringMatchCandidate {
+                    id,
+                    char_bag: string.chars().collect(),
+                    string,
+                }
+            })
+            .collect::<Vec<_>>();
+        cx.spawn(move |picker, mut cx| async move {
+            let matches = fuzzy::match_strings(
+                &candidates,
+                &query,
+                true,
+                1000,
+                &Default::default(),
+                cx.background_executor().clone(),
+            )
+            .await;
+            picker
+                .update(&mut cx, |picker, _| {
+                    let delegate = &mut picker.delegate;
+                    delegate.matches = matches;
+                    if delegate.matches.is_empty() {
+                        delegate.selected_index = 0;
+                    } else {
+                        delegate.selected_index =
+                            delegate.selected_index.min(delegate.matches.len() - 1);
+                    }
+                })
+                .log_err();
+        })
+    }
+
+    fn confirm(&mut self, _: bool, cx: &mut ViewContext<Picker<Self>>) {
+        let Some(git_ref) = self
+            .matches
+            .get(self.selected_index)
+            .and_then(|hit| self.git_refs.get(hit.candidate_id))
+        else {
+            return;
+        };
+
+        cx.emit(DismissEvent);
+        self.workspace
+            .update(cx, |workspace, cx| {
+                let mut task_context = task_context(workspace, self.cwd.clone(), cx);
+                task_context
+                    .task_variables
+                    .insert(VariableName::GitRef, git_ref.git_ref.to_string());
+                let inventory = workspace.project().read(cx).task_inventory().clone();
+                let workspace_handle = workspace.weak_handle();
+                workspace.toggle_modal(cx, |cx| {
+                    TasksModal::new(inventory, task_context, false, workspace_handle, cx)
+                });
+            })
+            .ok();
+    }
+
+    fn dismissed(&mut self, cx: &mut ViewContext<Picker<Self>>) {
+        cx.emit(DismissEvent);
+    }
+
+    fn render_match(
+        &self,
+        ix: usize,
+        selected: bool,
+        cx: &mut ViewContext<Picker<Self>>,
+    ) -> Option<Self::ListItem> {
+        let hit = self.matches.get(ix)?;
+        let highlighted_text = HighlightedText {
+            text: hit.string.clone(),
+            highlight_positions: hit.positions.clone(),
+            char_count: hit.string.chars().count(),
+        };
+        let git_ref = self.git_refs.get(hit.candidate_id)?;
+        let kind = if git_ref.git_ref.starts_with("stash@") {
+            "stash"
+        } else {
+            "commit"
+        };
+        Some(
+            ListItem::new(SharedString::from(format!("task-git-ref-{ix}")))
+                .inset(true)
+                .spacing(ListItemSpacing::Sparse)
+                .selected(selected)
+                .end_slot(Label::new(kind).color(Color::Muted))
+                .child(highlighted_text.render(cx)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use gpui::{TestAppContext, VisualTestContext};
+    use project::{FakeFs, Project};
+    use serde_json::json;
+
+    use super::*;
+
+    #[gpui::test]
+    async fn test_git_ref_is_threaded_into_task(cx: &mut TestAppContext) {
+        crate::tests::init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[{ "label": "test ref", "command": "git", "args": ["worktree", "add", "/tmp/ref", "$ZED_GIT_REF"] }]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        let spawned_tasks = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let spawned_tasks = spawned_tasks.clone();
+            cx.subscribe(&workspace, move |_, event, _| {
+                if let workspace::Event::SpawnTask(spawn_in_terminal) = event {
+                    spawned_tasks.borrow_mut().push(spawn_in_terminal.clone());
+                }
+            })
+            .detach();
+        });
+        cx.run_until_parked();
+
+        let git_refs = [
+            "stash@{0}\tWIP on main: 1a2b3c4 Fix",
+            "1a2b3c4\tFix the parser",
+        ]
+        .into_iter()
+        .filter_map(GitRefCandidate::parse)
+        .collect::<Vec<_>>();
+        workspace.update(cx, |workspace, cx| {
+            let workspace_handle = workspace.weak_handle();
+            workspace.toggle_modal(cx, |cx| {
+                GitRefPickerModal::new(git_refs, Some(PathBuf::from("/dir")), workspace_handle, cx)
+            })
+        });
+        cx.run_until_parked();
+        let picker = workspace.update(cx, |workspace, cx| {
+            workspace
+                .active_modal::<GitRefPickerModal>(cx)
+                .expect("Git ref picker should be shown")
+                .read(cx)
+                .picker
+                .clone()
+        });
+        assert_eq!(
+            git_ref_names(&picker, cx),
+            vec![
+                "stash@{0} WIP on main: 1a2b3c4 Fix",
+                "1a2b3c4 Fix the parser"
+            ]
+        );
+        picker.update(cx, |picker, cx| {
+            picker.delegate.set_selected_index(1, cx);
+            picker.delegate.confirm(false, cx);
+        });
+        cx.run_until_parked();
+
+        let tasks_picker = workspace.update(cx, |workspace, cx| {
+            workspace
+                .active_modal::<TasksModal>(cx)
+                .expect("Tasks modal should be shown for the chosen git ref")
+                .read(cx)
+                .picker
+                .clone()
+        });
+        cx.simulate_input("test ref");
+        tasks_picker.update(cx, |picker, cx| picker.delegate.confirm(false, cx));
+        cx.run_until_parked();
+        let spawned_tasks = spawned_tasks.borrow();
+        assert_eq!(spawned_tasks.len(), 1);
+        assert_eq!(
+            spawned_tasks[0].args,
+            vec!["worktree", "add", "/tmp/ref", "1a2b3c4"],
+            "Chosen git ref should be substituted into the command"
+        );
+        assert_eq!(
+            spawned_tasks[0].env.get("ZED_GIT_REF").map(String::as_str),
+            Some("1a2b3c4")
+        );
+    }
+
+    fn git_ref_names(
+        picker: &View<Picker<GitRefPickerDelegate>>,
+        cx: &mut VisualTestContext,
+    ) -> Vec<String> {
+        picker.update(cx, |picker, _| {
+            picker
+                .delegate
+                .matches
+                .iter()
+                .map(|hit| hit.string.clone())
+                .collect()
+        })
+    }
+}
@@ -0,0 +1,339 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use fuzzy::{StringMatch, StringMatchCandidate};
+use gpui::{
+    rems, AppContext, DismissEvent, EventEmitter, FocusableView, Global, InteractiveElement, Model,
+    ParentElement, Render, SharedString, Styled, Subscription, View, ViewContext, VisualContext,
+    WeakView,
+};
+use picker::{highlighted_match_with_paths::HighlightedText, Picker, PickerDelegate};
+use project::Inventory;
+use task::{oneshot_source::OneshotSource, TaskContext};
+use ui::{
+    v_flex, Color, Label, LabelCommon, ListItem, ListItemSpacing, RenderOnce, Selectable,
+    WindowContext,
+};
+use util::ResultExt;
+use workspace::{ModalView, Workspace};
+
+use crate::schedule_task;
+
+const MAX_GLOBAL_HISTORY_ENTRIES: usize = 1_000;
+
+/// A command that was run in some project, recorded when the global task history is enabled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct GlobalHistoryEntry {
+    pub(crate) project: SharedString,
+    pub(crate) command: String,
+}
+
+/// Commands of the tasks scheduled in all projects, across all windows.
+#[derive(Default)]
+pub(crate) struct GlobalTaskHistory {
+    entries: VecDeque<GlobalHistoryEntry>,
+}
+
+impl Global for GlobalTaskHistory {}
+
+impl GlobalTaskHistory {
+    /// Records the entry as the most recent one, deduplicating it with the older entries.
+    pub(crate) fn record(entry: GlobalHistoryEntry, cx: &mut AppContext) {
+        let history = cx.default_global::<Self>();
+        history.entries.retain(|old_entry| old_entry != &entry);
+        history.entries.push_front(entry);
+        history.entries.truncate(MAX_GLOBAL_HISTORY_ENTRIES);
+    }
+
+    /// All entries recorded, most recent first.
+    pub(crate) fn entries(cx: &AppContext) -> Vec<GlobalHistoryEntry> {
+        cx.try_global::<Self>()
+            .map(|history| history.entries.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// A modal to search the commands run in all projects, and rerun them as oneshot tasks in the current context.
+pub(crate) struct GlobalHistoryModal {
+    picker: View<Picker<GlobalHistoryDelegate>>,
+    _subscription: Subscription,
+}
+
+impl GlobalHistoryModal {
+    pub(crate) fn new(
+        inventory: Model<Inventory>,
+        task_context: TaskContext,
+        workspace: WeakView<Workspace>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let entries = GlobalTaskHistory::entries(cx);
+        let picker = cx.new_view(|cx| {
+            Picker::uniform_list(
+                GlobalHistoryDelegate {
+                    inventory,
+                    entries,
+                    matches: Vec::new(),
+                    selected_index: 0,
+                    workspace,
+                    task_context,
+                },
+                cx,
+            )
+        });
+        let _subscription = cx.subscribe(&picker, |_, _, _, cx| {
+            cx.emit(DismissEvent);
+        });
+        Self {
+            picker,
+            _subscription,
+        }
+    }
+}
+
+impl Render for GlobalHistoryModal {
+    fn render(&mut self, _: &mut ViewContext<Self>) -> impl gpui::prelude::IntoElement {
+        v_flex()
+            .key_context("GlobalTaskHistoryModal")
+            .w(rems(34.))
+            .child(self.picker.clone())
+    }
+}
+
+impl EventEmitter<DismissEvent> for GlobalHistoryModal {}
+
+impl FocusableView for GlobalHistoryModal {
+    fn focus_handle(&self, cx: &gpui::AppContext) -> gpui::FocusHandle {
+        self.picker.read(cx).focus_handle(cx)
+    }
+}
+
+impl ModalView for GlobalHistoryModal {}
+
+pub(crate) struct GlobalHistoryDelegate {
+    inventory: Model<Inventory>,
+    entries: Vec<GlobalHistoryEntry>,
+    matches: Vec<StringMatch>,
+    selected_index: usize,
+    workspace: WeakView<Workspace>,
+    task_context: TaskContext,
+}
+
+impl PickerDelegate for GlobalHistoryDelegate {
+    type ListItem = ListItem;
+
+    fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    fn selected_index(&self) -> usize {
+        self.selected_index
+    }
+
+    fn set_selected_index(&mut self, ix: usize, _: &mut ViewContext<Picker<Self>>) {
+        self.selected_index = ix;
+    }
+
+    fn placeholder_text(&self, _: &mut WindowContext) -> Arc<str> {
+        Arc::from("Search commands run in all projects...")
+    }
+
+    fn update_matches(
+        &mut self,
+        query: String,
+        cx: &mut ViewContext<Picker<Self>>,
+    ) -> gpui::Task<()> {
+        let candidates = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(id, entry)| StringMatchCandidate {
+                id,
+                char_bag: entry.command.chars().collect(),
+                string: entry.command.clone(),
+            })
+            .collect::<Vec<_>>();
+        cx.spawn(move |picker, mut cx| async move {
+            let matches = fuzzy::match_strings(
+                &candidates,
+                &query,
+                true,
+                1000,
+                &Default::default(),
+                cx.background_executor().clone(),
+            )
+            .await;
+            picker
+                .update(&mut cx, |picker, _| {
+                    let delegate = &mut picker.delegate;
+                    delegate.matches = matches;
+                    if delegate.matches.is_empty() {
+                        delegate.selected_index = 0;
+                    } else {
+                        delegate.selected_index =
+                            delegate.selected_index.min(delegate.matches.len() - 1);
+                    }
+                })
+                .log_err();
+        })
+    }
+
+    fn confirm(&mut self, omit_history_entry: bool, cx: &mut ViewContext<Picker<Self>>) {
+        let Some(entry) = self
+            .matches
+            .get(self.selected_index)
+            .and_then(|hit| self.entries.get(hit.candidate_id))
+        else {
+            return;
+        };
+        let Some(task) = self
+            .inventory
+            .update(cx, |inventory, _| inventory.source::<OneshotSource>())
+            .and_then(|oneshot_source| {
+                oneshot_source.update(cx, |oneshot_source, _| {
+                    Some(
+                        oneshot_source
+                            .as_any()
+                            .downcast_mut::<OneshotSource>()?
+                            .spawn(entry.command.clone()),
+                    )
+                })
+            })
+        else {
+            return;
+        };
+
+        self.workspace
+            .update(cx, |workspace, cx| {
+                schedule_task(
+                    workspace,
+                    &task,
+                    self.task_context.clone(),
+                    omit_history_entry,
+                    cx,
+                );
+            })
+            .ok();
+        cx.emit(DismissEvent);
+    }
+
+    fn dismissed(&mut self, cx: &mut ViewContext<Picker<Self>>) {
+        cx.emit(DismissEvent);
+    }
+
+    fn render_match(
+        &self,
+        ix: usize,
+        selected: bool,
+        cx: &mut ViewContext<Picker<Self>>,
+    ) -> Option<Self::ListItem> {
+        let hit = self.matches.get(ix)?;
+        let entry = self.entries.get(hit.candidate_id)?;
+        let highlighted_command = HighlightedText {
+            text: hit.string.clone(),
+            highlight_positions: hit.positions.clone(),
+            char_count: hit.string.chars().count(),
+        };
+        Some(
+            ListItem::new(SharedString::from(format!("global-task-history-{ix}")))
+                .inset(true)
+                .spacing(ListItemSpacing::Sparse)
+                .selected(selected)
+                .end_slot(Label::new(entry.project.clone()).color(Color::Muted))
+                .child(highlighted_command.render(cx)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gpui::{TestAppContext, VisualTestContext};
+    use project::{FakeFs, Project, TaskSourceKind};
+    use serde_json::json;
+    use task::Task;
+
+    use super::*;
+
+    #[gpui::test]
+    async fn test_global_history_search(cx: &mut TestAppContext) {
+        crate::tests::init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree("/dir", json!({ "a.ts": "a" })).await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        project.update(cx, |project, cx| {
+            project.task_inventory().update(cx, |inventory, cx| {
+                inventory.add_source(TaskSourceKind::UserInput, |cx| OneshotSource::new(cx), cx)
+            })
+        });
+        cx.update(|cx| {
+            GlobalTaskHistory::record(
+                GlobalHistoryEntry {
+                    project: "backend".into(),
+                    command: "cargo test -p server".to_string(),
+                },
+                cx,
+            );
+            GlobalTaskHistory::record(
+                GlobalHistoryEntry {
+                    project: "frontend".into(),
+                    command: "npm run lint".to_string(),
+                },
+                cx,
+            );
+        });
+
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        cx.dispatch_action(crate::modal::SearchGlobalHistory);
+        let picker = workspace.update(cx, |workspace, cx| {
+            workspace
+                .active_modal::<GlobalHistoryModal>(cx)
+                .unwrap()
+                .read(cx)
+                .picker
+                .clone()
+        });
+        assert_eq!(
+            history_matches(&picker, cx),
+            vec![
+                ("npm run lint".to_string(), "frontend".into()),
+                ("cargo test -p server".to_string(), "backend".into()),
+            ],
+            "Entries from all projects should be listed, the most recent first"
+        );
+
+        cx.simulate_input("cargo");
+        assert_eq!(
+            history_matches(&picker, cx),
+            vec![("cargo test -p server".to_string(), "backend".into())],
+        );
+
+        picker.update(cx, |picker, cx| picker.delegate.confirm(false, cx));
+        let last_scheduled_task = project.update(cx, |project, cx| {
+            project
+                .task_inventory()
+                .read(cx)
+                .last_scheduled_task()
+                .map(|(task, _)| task.name().to_string())
+        });
+        assert_eq!(
+            last_scheduled_task,
+            Some("cargo test -p server".to_string()),
+            "Confirmed entry should be rerun as a oneshot task in the current project"
+        );
+    }
+
+    fn history_matches(
+        picker: &View<Picker<GlobalHistoryDelegate>>,
+        cx: &mut VisualTestContext,
+    ) -> Vec<(String, SharedString)> {
+        picker.update(cx, |picker, _| {
+            picker
+                .delegate
+                .matches
+                .iter()
+                .map(|hit| {
+                    let entry = &picker.delegate.entries[hit.candidate_id];
+                    (hit.string.clone(), entry.project.clone())
+                })
+                .collect()
+        })
+    }
+}
@@ -1,27 +1,145 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{self, AtomicUsize},
+        Arc,
+    },
+    time::Duration,
+};
 
 use ::settings::Settings;
-use editor::Editor;
-use gpui::{AppContext, ViewContext, WeakView, WindowContext};
-use language::{Language, Point};
-use modal::{Spawn, TasksModal};
-use project::{Location, WorktreeId};
-use task::{Task, TaskContext, TaskVariables, VariableName};
+use collections::{HashMap, HashSet, VecDeque};
+use editor::{
+    display_map::{DisplaySnapshot, HighlightStyles, ToDisplayPoint},
+    Editor, MultiBuffer,
+};
+use global_history::{GlobalHistoryEntry, GlobalHistoryModal, GlobalTaskHistory};
+use gpui::{
+    AppContext, Context as _, Global, Model, PromptLevel, ViewContext, VisualContext, WeakView,
+    WindowContext,
+};
+use language::{language_settings::language_settings, Language, LineEnding, Point, Rope};
+use modal::{
+    CancelAllTasks, CancelTask, CheckTasks, CycleRecentTasks, GenerateTasks, RerunLastFailed,
+    RerunVerbose, RunNearestTest, RunSelectionAsScript, SearchGlobalHistory, ShowTasksOverview,
+    Spawn, SpawnWithGitRef, TasksModal, ToggleRerunOnChange,
+};
+use project::{
+    DiagnosticSummary, Location, Project, ProjectPath, TaskSourceKind, Worktree, WorktreeId,
+};
+use task::{
+    posix_script_command, prepare_steps, shell_quote,
+    static_source::{tasks_for, Definition, RevealStrategy, ShowStrategy, TaskDefinitions},
+    LazyVariable, SelectionFallbacks, SpawnInTerminal, Task, TaskContext, TaskId,
+    TaskSplitDirection, TaskVariables, VariableName, VariableResolver,
+};
 use util::ResultExt;
-use workspace::Workspace;
+use workspace::{Toast, Workspace};
 
+mod ci_step;
+mod command_output;
+mod generated_tasks;
+mod git_diff;
+mod git_ref_picker;
+mod global_history;
 mod modal;
+mod persisted_history;
+mod recent_tasks;
+mod restorable_tasks;
+mod secrets;
 mod settings;
+mod shell_history;
 mod status_indicator;
+mod test_results;
+mod watching;
+mod worktree_picker;
 
+use crate::settings::{SelectedTextMode, TaskSettings};
+use command_output::copy_task_output;
+use secrets::{secrets_summary, spawn_with_secrets, KeychainSecrets, TaskSecrets};
+use shell_history::track_shell_history;
 pub use status_indicator::TaskStatusIndicator;
+use watching::{follow_file, run_tasks_on_project_open, watch_task_definitions};
+use worktree_picker::schedule_task_in_chosen_worktree;
 
+const TASK_MISSING_TOOLS_TOAST_ID: usize = 0x7a5c7001;
+const TASK_HIDDEN_TOAST_ID: usize = 0x7a5c41d3;
+const TASK_DEPENDENCIES_TOAST_ID: usize = 0x7a5cde95;
+const TASK_COPIED_AS_CI_STEP_TOAST_ID: usize = 0x7a5cc1c1;
 pub fn init(cx: &mut AppContext) {
-    settings::TaskSettings::register(cx);
+    TaskSettings::register(cx);
+    cx.set_global(TaskSecrets(Arc::new(KeychainSecrets)));
     cx.observe_new_views(
-        |workspace: &mut Workspace, _: &mut ViewContext<Workspace>| {
+        |workspace: &mut Workspace, cx: &mut ViewContext<Workspace>| {
+            watch_task_definitions(workspace, cx);
+            run_tasks_on_project_open(workspace, cx);
+            track_shell_history(workspace, cx);
+            restorable_tasks::restore_tasks_on_reopen(workspace, cx);
+            persisted_history::persist_task_history(workspace, cx);
             workspace
                 .register_action(spawn_task_or_modal)
+                .register_action(|workspace, _: &SearchGlobalHistory, cx| {
+                    let inventory = workspace.project().read(cx).task_inventory().clone();
+                    let workspace_handle = workspace.weak_handle();
+                    let cwd = task_cwd(workspace, cx).log_err().flatten();
+                    let task_context = task_context(workspace, cwd, cx);
+                    workspace.toggle_modal(cx, |cx| {
+                        GlobalHistoryModal::new(inventory, task_context, workspace_handle, cx)
+                    })
+                })
+                .register_action(|workspace, _: &RunNearestTest, cx| {
+                    run_nearest_test(workspace, cx)
+                })
+                .register_action(|workspace, _: &RunSelectionAsScript, cx| {
+                    run_selection_as_script(workspace, cx)
+                })
+                .register_action(|workspace, _: &ShowTasksOverview, cx| {
+                    show_tasks_overview(workspace, cx)
+                })
+                .register_action(|workspace, _: &CheckTasks, cx| check_tasks(workspace, cx))
+                .register_action(|workspace, _: &RerunVerbose, cx| rerun_verbose(workspace, cx))
+                .register_action(|workspace, _: &GenerateTasks, cx| {
+                    generated_tasks::generate_tasks(workspace, cx)
+                })
+                .register_action(|workspace, _: &CycleRecentTasks, cx| {
+                    recent_tasks::cycle_recent_tasks(workspace, cx)
+                })
+                .register_action(|workspace, _: &SpawnWithGitRef, cx| {
+                    git_ref_picker::pick_git_ref(workspace, cx)
+                })
+                .register_action(|workspace, _: &CancelTask, cx| cancel_tasks(workspace, false, cx))
+                .register_action(|workspace, _: &CancelAllTasks, cx| {
+                    cancel_tasks(workspace, true, cx)
+                })
+                .register_action(|workspace, _: &RerunLastFailed, cx| {
+                    let last_failed_task = workspace
+                        .project()
+                        .read(cx)
+                        .task_inventory()
+                        .read(cx)
+                        .last_failed_task();
+                    match last_failed_task {
+                        Some((task, task_context)) => {
+                            schedule_task(workspace, &task, task_context, false, cx)
+                        }
+                        None => spawn_task_or_modal(workspace, &Spawn::modal(), cx),
+                    }
+                })
+                .register_action(|workspace, _: &ToggleRerunOnChange, cx| {
+                    workspace.project().update(cx, |project, cx| {
+                        project.task_inventory().update(cx, |inventory, _| {
+                            let task_to_watch = if inventory.watched_task().is_some() {
+                                None
+                            } else {
+                                inventory
+                                    .last_scheduled_task()
+                                    .map(|(task, _)| task.id().clone())
+                            };
+                            inventory.set_watched_task(task_to_watch);
+                        })
+                    });
+                })
                 .register_action(move |workspace, action: &modal::Rerun, cx| {
                     if let Some((task, old_context)) =
                         workspace.project().update(cx, |project, cx| {
@@ -29,6 +147,17 @@ pub fn init(cx: &mut AppContext) {
                         })
                     {
                         let task_context = if action.reevaluate_context {
+                            if task_cwd(workspace, cx).is_err() {
+                                schedule_task_in_chosen_worktree(
+                                    workspace,
+                                    task,
+                                    false,
+                                    Vec::new(),
+                                    Vec::new(),
+                                    cx,
+                                );
+                                return;
+                            }
                             let cwd = task_cwd(workspace, cx).log_err().flatten();
                             task_context(workspace, cwd, cx)
                         } else {
@@ -44,20 +173,456 @@ pub fn init(cx: &mut AppContext) {
 
 fn spawn_task_or_modal(workspace: &mut Workspace, action: &Spawn, cx: &mut ViewContext<Workspace>) {
     match &action.task_name {
-        Some(name) => spawn_task_with_name(name.clone(), cx),
+        Some(name) => spawn_task_with_name(name.clone(), action.omit_history, action.split, cx),
         None => {
             let inventory = workspace.project().read(cx).task_inventory().clone();
             let workspace_handle = workspace.weak_handle();
             let cwd = task_cwd(workspace, cx).log_err().flatten();
             let task_context = task_context(workspace, cwd, cx);
+            let omit_history = action.omit_history;
             workspace.toggle_modal(cx, |cx| {
-                TasksModal::new(inventory, task_context, workspace_handle, cx)
+                TasksModal::new(inventory, task_context, omit_history, workspace_handle, cx)
             })
         }
     }
 }
 
-fn spawn_task_with_name(name: String, cx: &mut ViewContext<Workspace>) {
+/// Runs the test that encloses the cursor, with the task the active editor's language provides for running a single test.
+fn run_nearest_test(workspace: &mut Workspace, cx: &mut ViewContext<Workspace>) {
+    let cwd = task_cwd(workspace, cx).log_err().flatten();
+    let task_context = task_context(workspace, cwd, cx);
+    if task_context
+        .task_variables
+        .get(&VariableName::NearestTest)
+        .is_none()
+    {
+        return;
+    }
+    let language = active_editor_language(workspace, cx);
+    let Some((language, definition)) = language.and_then(|language| {
+        let definition = language.context_provider()?.nearest_test_task()?;
+        Some((language, definition))
+    }) else {
+        return;
+    };
+    let id_base = format!("nearest_test_{}", language.name());
+    if let Some(task) = tasks_for(TaskDefinitions(vec![definition]), &id_base).pop() {
+        schedule_task(workspace, &task, task_context, false, cx);
+    }
+}
+
+/// The task, spawned in a split of the active pane instead of the terminal panel.
+struct SplitTask(Arc<dyn Task>, TaskSplitDirection);
+
+impl Task for SplitTask {
+    fn id(&self) -> &TaskId {
+        self.0.id()
+    }
+
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    fn cwd(&self) -> Option<&str> {
+        self.0.cwd()
+    }
+
+    fn prepare_exec(&self, cx: TaskContext) -> Option<SpawnInTerminal> {
+        let mut spawn_in_terminal = self.0.prepare_exec(cx)?;
+        spawn_in_terminal.split = Some(self.1);
+        Some(spawn_in_terminal)
+    }
+
+    fn variable_templates(&self, cx: &TaskContext) -> Vec<String> {
+        self.0.variable_templates(cx)
+    }
+
+    fn resolved_label(&self, cx: &TaskContext) -> String {
+        self.0.resolved_label(cx)
+    }
+
+    fn command_line_preview(&self, cx: &TaskContext) -> String {
+        self.0.command_line_preview(cx)
+    }
+}
+
+/// The task, spawned with the verbosity flag of its runner added.
+struct VerboseTask(Arc<dyn Task>);
+
+impl Task for VerboseTask {
+    fn id(&self) -> &TaskId {
+        self.0.id()
+    }
+
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    fn cwd(&self) -> Option<&str> {
+        self.0.cwd()
+    }
+
+    fn prepare_exec(&self, cx: TaskContext) -> Option<SpawnInTerminal> {
+        let mut spawn_in_terminal = self.0.prepare_exec(cx)?;
+        spawn_in_terminal.add_verbosity_flag();
+        Some(spawn_in_terminal)
+    }
+
+    fn variable_templates(&self, cx: &TaskContext) -> Vec<String> {
+        self.0.variable_templates(cx)
+    }
+
+    fn resolved_label(&self, cx: &TaskContext) -> String {
+        self.0.resolved_label(cx)
+    }
+
+    fn command_line_preview(&self, cx: &TaskContext) -> String {
+        self.0.command_line_preview(cx)
+    }
+}
+
+/// The task of a `parallel` task, spawned in a sub-tab of the terminal tab of the `parallel` task.
+struct TerminalGroupTask(Arc<dyn Task>, String);
+
+impl Task for TerminalGroupTask {
+    fn id(&self) -> &TaskId {
+        self.0.id()
+    }
+
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    fn cwd(&self) -> Option<&str> {
+        self.0.cwd()
+    }
+
+    fn prepare_exec(&self, cx: TaskContext) -> Option<SpawnInTerminal> {
+        let mut spawn_in_terminal = self.0.prepare_exec(cx)?;
+        spawn_in_terminal.terminal_group = Some(self.1.clone());
+        Some(spawn_in_terminal)
+    }
+
+    fn variable_templates(&self, cx: &TaskContext) -> Vec<String> {
+        self.0.variable_templates(cx)
+    }
+
+    fn resolved_label(&self, cx: &TaskContext) -> String {
+        self.0.resolved_label(cx)
+    }
+
+    fn command_line_preview(&self, cx: &TaskContext) -> String {
+        self.0.command_line_preview(cx)
+    }
+}
+
+/// Reruns the last scheduled task with the verbosity flag of its runner, keeping it out of the history, so that the task is not changed for `task::Rerun`.
+/// For the unknown runners, opens the tasks modal with the last command, to add the extra arguments to it and spawn it as a oneshot task.
+fn rerun_verbose(workspace: &mut Workspace, cx: &mut ViewContext<Workspace>) {
+    let inventory = workspace.project().read(cx).task_inventory().clone();
+    let Some((task, task_context)) = inventory.read(cx).last_scheduled_task() else {
+        return;
+    };
+    let Some(spawn_in_terminal) = task.prepare_exec(task_context.clone()) else {
+        return;
+    };
+    if spawn_in_terminal.verbosity_flag().is_some() {
+        let task = Arc::new(VerboseTask(task)) as Arc<dyn Task>;
+        schedule_task(workspace, &task, task_context, true, cx);
+        return;
+    }
+
+    let Ok(command) = spawn_in_terminal.resolve_command() else {
+        return;
+    };
+    let workspace_handle = workspace.weak_handle();
+    workspace.toggle_modal(cx, |cx| {
+        TasksModal::new(inventory, task_context, false, workspace_handle, cx)
+    });
+    if let Some(tasks_modal) = workspace.active_modal::<TasksModal>(cx) {
+        tasks_modal.update(cx, |tasks_modal, cx| {
+            tasks_modal.set_query(format!("{command} "), cx)
+        });
+    }
+}
+
+/// Interpreters to run the scratch scripts of a certain language with, and extensions of the script files for them.
+const SCRIPT_INTERPRETERS: &[(&str, &str, &str)] = &[
+    ("JavaScript", "node", "js"),
+    ("Lua", "lua", "lua"),
+    ("Perl", "perl", "pl"),
+    ("Python", "python3", "py"),
+    ("Ruby", "ruby", "rb"),
+];
+
+/// Runs the selected text as a script, written into a temporary file by the task, and removed after the script finishes.
+fn run_selection_as_script(workspace: &mut Workspace, cx: &mut ViewContext<Workspace>) {
+    let cwd = task_cwd(workspace, cx).log_err().flatten();
+    let task_context = task_context(workspace, cwd, cx);
+    let Some(script) = task_context
+        .task_variables
+        .get(&VariableName::SelectedText)
+        .filter(|script| !script.trim().is_empty())
+    else {
+        return;
+    };
+    let language = active_editor_language(workspace, cx);
+    let definition = script_task_definition(script, language.as_deref());
+    if let Some(task) = tasks_for(TaskDefinitions(vec![definition]), "scratch_script").pop() {
+        schedule_task(workspace, &task, task_context, true, cx);
+    }
+}
+
+/// A task that runs the script from `$ZED_SELECTED_TEXT` with the interpreter from its shebang, or from the language given (`sh` by default).
+/// The task writes the script into a temporary file only once it's spawned, and removes the file after the run, keeping the exit status of the script.
+fn script_task_definition(script: &str, language: Option<&Language>) -> Definition {
+    static SCRIPTS_CREATED: AtomicUsize = AtomicUsize::new(0);
+
+    let shebang = script
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("#!"))
+        .map(str::trim)
+        .filter(|interpreter| !interpreter.is_empty());
+    let language_interpreter = language.and_then(|language| {
+        SCRIPT_INTERPRETERS
+            .iter()
+            .find(|(name, _, _)| language.name().as_ref() == *name)
+    });
+    let (interpreter, extension) = match (shebang, language_interpreter) {
+        (Some(shebang), _) => (shebang, "script"),
+        (None, Some((_, interpreter, extension))) => (*interpreter, *extension),
+        (None, None) => ("sh", "sh"),
+    };
+
+    let script_path = std::env::temp_dir().join(format!(
+        "zed-scratch-{}-{}.{extension}",
+        std::process::id(),
+        SCRIPTS_CREATED.fetch_add(1, atomic::Ordering::Relaxed)
+    ));
+    let script_path = shell_quote(&script_path.to_string_lossy());
+    let selected_text = VariableName::SelectedText;
+    let (command, args) = posix_script_command(&format!(
+        "printf '%s' \"${selected_text}\" > {script_path} && {interpreter} {script_path}; status=$?; rm -f {script_path}; exit $status"
+    ));
+    Definition {
+        label: format!("Run selection as script ({interpreter})"),
+        command,
+        args,
+        use_new_terminal: true,
+        allow_concurrent_runs: true,
+        ..Definition::default()
+    }
+}
+
+/// Resolves every task available for the active item, and opens the commands they would run in a new buffer.
+fn show_tasks_overview(workspace: &mut Workspace, cx: &mut ViewContext<Workspace>) {
+    let (tasks, task_context) = active_item_tasks(workspace, cx);
+    let overview = tasks_overview(&tasks, &task_context);
+    open_tasks_report(workspace, &overview, "Tasks Overview", cx);
+}
+
+/// Checks that every task available for the active item can be spawned, and opens the report in a new buffer.
+fn check_tasks(workspace: &mut Workspace, cx: &mut ViewContext<Workspace>) {
+    let (tasks, task_context) = active_item_tasks(workspace, cx);
+    let report = tasks_check_report(&tasks, &task_context);
+    open_tasks_report(workspace, &report, "Tasks Check", cx);
+}
+
+/// All tasks available for the active item, with the context to resolve them in.
+fn active_item_tasks(
+    workspace: &Workspace,
+    cx: &mut ViewContext<Workspace>,
+) -> (Vec<(TaskSourceKind, Arc<dyn Task>)>, TaskContext) {
+    let cwd = task_cwd(workspace, cx).log_err().flatten();
+    let task_context = task_context(workspace, cwd, cx);
+    let worktree = workspace
+        .active_item(cx)
+        .and_then(|item| item.project_path(cx))
+        .map(|path| path.worktree_id);
+    let language = active_editor_language(workspace, cx);
+    let tasks = workspace.project().update(cx, |project, cx| {
+        project.task_inventory().update(cx, |inventory, cx| {
+            inventory.list_tasks(language, worktree, false, cx)
+        })
+    });
+    (tasks, task_context)
+}
+
+fn open_tasks_report(
+    workspace: &mut Workspace,
+    report: &str,
+    title: &str,
+    cx: &mut ViewContext<Workspace>,
+) {
+    let project = workspace.project().clone();
+    let Some(buffer) = project
+        .update(cx, |project, cx| project.create_buffer(report, None, cx))
+        .log_err()
+    else {
+        return;
+    };
+    let buffer = cx.new_model(|cx| MultiBuffer::singleton(buffer, cx).with_title(title.to_owned()));
+    workspace.add_item_to_active_pane(
+        Box::new(cx.new_view(|cx| Editor::for_multibuffer(buffer, Some(project), cx))),
+        cx,
+    );
+}
+
+/// A line per task: its name and the command it resolves into, or the reason it cannot be resolved.
+fn tasks_overview(tasks: &[(TaskSourceKind, Arc<dyn Task>)], task_context: &TaskContext) -> String {
+    tasks
+        .iter()
+        .map(|(_, task)| {
+            let resolution = match task.prepare_exec(task_context.clone()) {
+                Some(spawn_in_terminal) => match spawn_in_terminal.resolve_command() {
+                    Ok(command) if spawn_in_terminal.elevate => format!("[elevated] {command}"),
+                    Ok(command) => command,
+                    Err(missing_variables) => {
+                        format!("<unresolved: missing {}>", missing_variables.join(", "))
+                    }
+                },
+                None => "<unresolved: cannot be prepared in the current context>".to_owned(),
+            };
+            format!("{} → {resolution}", task.name())
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A table row per task, whether it can be spawned in the context: its command resolves and the tools it needs are on `PATH`.
+/// Tools of the tasks running in a container are not checked, as they are looked up inside of it.
+fn tasks_check_report(
+    tasks: &[(TaskSourceKind, Arc<dyn Task>)],
+    task_context: &TaskContext,
+) -> String {
+    let rows = tasks.iter().map(|(_, task)| {
+        let Some(spawn_in_terminal) = task.prepare_exec(task_context.clone()) else {
+            return (
+                task.name(),
+                "cannot be prepared in the current context".to_owned(),
+                false,
+            );
+        };
+        let mut problems = Vec::new();
+        let command = match spawn_in_terminal.resolve_command() {
+            Ok(command) => Some(command),
+            Err(missing_variables) => {
+                problems.push(format!(
+                    "missing variables: {}",
+                    missing_variables.join(", ")
+                ));
+                None
+            }
+        };
+        if spawn_in_terminal.container.is_none() {
+            let mut missing = missing_tools(&spawn_in_terminal.requires);
+            if !command_exists(&spawn_in_terminal.command, spawn_in_terminal.cwd.as_deref()) {
+                missing.insert(0, &spawn_in_terminal.command);
+            }
+            if !missing.is_empty() {
+                problems.push(format!("missing tools: {}", missing.join(", ")));
+            }
+        }
+        if problems.is_empty() {
+            (task.name(), command.unwrap_or_default(), true)
+        } else {
+            (task.name(), problems.join("; "), false)
+        }
+    });
+    let mut report = "| Task | Result | Details |\n| --- | --- | --- |".to_owned();
+    for (name, details, passed) in rows {
+        let result = if passed { "pass" } else { "fail" };
+        report.push_str(&format!("\n| {name} | {result} | {details} |"));
+    }
+    report
+}
+
+/// Whether the program of the command can be found: on `PATH`, or relative to the `cwd` if it's a path.
+/// Commands referencing variables are not checked, as their program is only known once the shell expands them.
+fn command_exists(command: &str, cwd: Option<&Path>) -> bool {
+    let Some(program) = command.split_whitespace().next() else {
+        return false;
+    };
+    if program.contains('$') {
+        return true;
+    }
+    let cwd = cwd.map_or_else(|| PathBuf::from("."), Path::to_path_buf);
+    which::which_in(program, std::env::var_os("PATH"), cwd).is_ok()
+}
+
+/// Language at the newest selection of the active editor, if any.
+fn active_editor_language(workspace: &Workspace, cx: &mut WindowContext) -> Option<Arc<Language>> {
+    workspace
+        .active_item(cx)
+        .and_then(|item| item.act_as::<Editor>(cx))
+        .and_then(|editor| {
+            editor.update(cx, |editor, cx| {
+                let selection = editor.selections.newest::<usize>(cx);
+                let (buffer, buffer_position, _) = editor
+                    .buffer()
+                    .read(cx)
+                    .point_to_buffer_offset(selection.start, cx)?;
+                buffer.read(cx).language_at(buffer_position)
+            })
+        })
+}
+
+/// Project root of the first language server running for the buffer at the newest selection of the active editor, if any.
+fn active_language_server_root(workspace: &Workspace, cx: &mut WindowContext) -> Option<PathBuf> {
+    let editor = workspace
+        .active_item(cx)
+        .and_then(|item| item.act_as::<Editor>(cx))?;
+    let buffer = editor.update(cx, |editor, cx| {
+        let selection = editor.selections.newest::<usize>(cx);
+        let (buffer, _, _) = editor
+            .buffer()
+            .read(cx)
+            .point_to_buffer_offset(selection.start, cx)?;
+        Some(buffer)
+    })?;
+    let project = workspace.project().read(cx);
+    let (_, language_server) = project
+        .language_servers_for_buffer(buffer.read(cx), cx)
+        .next()?;
+    Some(language_server.root_path().clone())
+}
+
+/// Finds a task to spawn by its name: the task with exactly this label, or the one with the label differing in case only,
+/// or, if no label matches, the one whose command, alone or with its args, is the name.
+/// Commands are compared by their [`Task::command_line_preview`], so that no variables get computed for the lookup.
+fn find_task_by_name(
+    tasks: Vec<(TaskSourceKind, Arc<dyn Task>)>,
+    name: &str,
+    task_context: &TaskContext,
+) -> Option<Arc<dyn Task>> {
+    let tasks = tasks.into_iter().map(|(_, task)| task).collect::<Vec<_>>();
+    let lowercase_name = name.to_lowercase();
+    tasks
+        .iter()
+        .find(|task| task.name() == name)
+        .or_else(|| {
+            tasks
+                .iter()
+                .find(|task| task.name().to_lowercase() == lowercase_name)
+        })
+        .or_else(|| {
+            tasks.iter().find(|task| {
+                let command_line = task.command_line_preview(task_context);
+                !command_line.is_empty()
+                    && (command_line == name
+                        || (!name.contains(' ') && command_line.split(' ').next() == Some(name)))
+            })
+        })
+        .cloned()
+}
+
+fn spawn_task_with_name(
+    name: String,
+    omit_history: bool,
+    split: Option<TaskSplitDirection>,
+    cx: &mut ViewContext<Workspace>,
+) {
     cx.spawn(|workspace, mut cx| async move {
         let did_spawn = workspace
             .update(&mut cx, |this, cx| {
@@ -67,10 +632,24 @@ fn spawn_task_with_name(name: String, cx: &mut ViewContext<Workspace>) {
                         inventory.list_tasks(language, worktree, false, cx)
                     })
                 });
-                let (_, target_task) = tasks.into_iter().find(|(_, task)| task.name() == name)?;
-                let cwd = task_cwd(this, cx).log_err().flatten();
-                let task_context = task_context(this, cwd, cx);
-                schedule_task(this, &target_task, task_context, false, cx);
+                let cwd = task_cwd(this, cx);
+                let task_context = task_context(this, cwd.as_ref().ok().cloned().flatten(), cx);
+                let mut target_task = find_task_by_name(tasks, &name, &task_context)?;
+                if let Some(split) = split {
+                    target_task = Arc::new(SplitTask(target_task, split));
+                }
+                if cwd.is_err() {
+                    schedule_task_in_chosen_worktree(
+                        this,
+                        target_task,
+                        omit_history,
+                        Vec::new(),
+                        Vec::new(),
+                        cx,
+                    );
+                    return Some(());
+                }
+                schedule_task(this, &target_task, task_context, omit_history, cx);
                 Some(())
             })
             .ok()
@@ -79,7 +658,15 @@ fn spawn_task_with_name(name: String, cx: &mut ViewContext<Workspace>) {
         if !did_spawn {
             workspace
                 .update(&mut cx, |workspace, cx| {
-                    spawn_task_or_modal(workspace, &Spawn::default(), cx);
+                    spawn_task_or_modal(
+                        workspace,
+                        &Spawn {
+                            task_name: None,
+                            omit_history,
+                            split: None,
+                        },
+                        cx,
+                    );
                 })
                 .ok();
         }
@@ -119,20 +706,37 @@ fn task_context(
     cwd: Option<PathBuf>,
     cx: &mut WindowContext<'_>,
 ) -> TaskContext {
+    let open_files = open_file_paths(workspace, cx);
+    let staged_files = cwd
+        .as_deref()
+        .and_then(|cwd| staged_file_paths(workspace, cwd, cx));
     let current_editor = workspace
         .active_item(cx)
         .and_then(|item| item.act_as::<Editor>(cx));
-    if let Some(current_editor) = current_editor {
+    let mut task_context = if let Some(current_editor) = current_editor {
         (|| {
             let editor = current_editor.read(cx);
             let selection = editor.selections.newest::<usize>(cx);
-            let (buffer, _, _) = editor
+            let (buffer, buffer_offset, _) = editor
                 .buffer()
                 .read(cx)
                 .point_to_buffer_offset(selection.start, cx)?;
 
             current_editor.update(cx, |editor, cx| {
                 let snapshot = editor.snapshot(cx);
+                let all_selected_text = editor
+                    .selections
+                    .all::<usize>(cx)
+                    .into_iter()
+                    .filter(|selection| !selection.is_empty())
+                    .map(|selection| {
+                        snapshot
+                            .display_snapshot
+                            .buffer_snapshot
+                            .text_for_range(selection.range())
+                            .collect::<String>()
+                    })
+                    .collect::<Vec<_>>();
                 let selection_range = selection.range();
                 let start = snapshot
                     .display_snapshot
@@ -150,6 +754,15 @@ fn task_context(
                     .offset_to_point(selection_range.start);
                 let row = row + 1;
                 let column = column + 1;
+                let Point {
+                    row: row_end,
+                    column: column_end,
+                } = snapshot
+                    .display_snapshot
+                    .buffer_snapshot
+                    .offset_to_point(selection_range.end);
+                let row_end = row_end + 1;
+                let column_end = column_end + 1;
                 let location = Location {
                     buffer: buffer.clone(),
                     range: start..end,
@@ -166,11 +779,50 @@ fn task_context(
                     .read(cx)
                     .file()
                     .map(|file| WorktreeId::from_usize(file.worktree_id()));
-                let context = buffer
+                let diagnostic_summary = location.buffer.read(cx).file().map(|file| {
+                    buffer_diagnostic_summary(
+                        workspace.project().read(cx),
+                        &ProjectPath {
+                            worktree_id: WorktreeId::from_usize(file.worktree_id()),
+                            path: file.path().clone(),
+                        },
+                        cx,
+                    )
+                });
+                let git_branch = location.buffer.read(cx).file().and_then(|file| {
+                    git_branch(
+                        workspace.project().read(cx),
+                        WorktreeId::from_usize(file.worktree_id()),
+                        file.path(),
+                        cx,
+                    )
+                });
+                let line_author = location.buffer.read(cx).file().and_then(|file| {
+                    line_author(
+                        workspace.project().read(cx),
+                        WorktreeId::from_usize(file.worktree_id()),
+                        file.path(),
+                        location.buffer.read(cx).as_rope().clone(),
+                        row - 1,
+                        cx,
+                    )
+                });
+                let context_provider = buffer
                     .read(cx)
                     .language()
-                    .and_then(|language| language.context_provider())
-                    .and_then(|provider| provider.build_context(location, cx).ok());
+                    .and_then(|language| language.context_provider());
+                let cwd = context_provider
+                    .as_ref()
+                    .and_then(|provider| provider.suggested_cwd(&location, cx))
+                    .or_else(|| cwd.clone());
+                let nearest_test = context_provider
+                    .as_ref()
+                    .and_then(|provider| provider.enclosing_test(&location, cx));
+                let project_version = context_provider
+                    .as_ref()
+                    .and_then(|provider| provider.project_version(&location, cx));
+                let context =
+                    context_provider.and_then(|provider| provider.build_context(location, cx).ok());
 
                 let worktree_path = worktree_id.and_then(|worktree_id| {
                     workspace
@@ -180,110 +832,1254 @@ fn task_context(
                         .map(|worktree| worktree.read(cx).abs_path().to_string_lossy().to_string())
                 });
 
-                let selected_text = buffer.read(cx).chars_for_range(selection_range).collect();
+                let selection_fallbacks = if selection_range.is_empty() {
+                    let buffer = buffer.read(cx);
+                    let cursor_row = buffer.offset_to_point(buffer_offset).row;
+                    let current_line = buffer
+                        .text_for_range(
+                            Point::new(cursor_row, 0)
+                                ..Point::new(cursor_row, buffer.line_len(cursor_row)),
+                        )
+                        .collect();
+                    let text = buffer.as_rope().clone();
+                    SelectionFallbacks {
+                        whole_file: Some(LazyVariable::new(Arc::new(move || {
+                            Some(text.to_string())
+                        }))),
+                        current_line: Some(current_line),
+                    }
+                } else {
+                    SelectionFallbacks::default()
+                };
+                let selection_indent = buffer
+                    .read(cx)
+                    .chars_at(Point::new(
+                        buffer.read(cx).offset_to_point(buffer_offset).row,
+                        0,
+                    ))
+                    .take_while(|c| *c == ' ' || *c == '\t')
+                    .collect();
+                let selected_text = match TaskSettings::get_global(cx).selected_text_mode {
+                    SelectedTextMode::Raw => {
+                        buffer.read(cx).chars_for_range(selection_range).collect()
+                    }
+                    SelectedTextMode::Display => {
+                        display_text_for_range(&snapshot.display_snapshot, selection_range)
+                    }
+                };
 
                 let mut task_variables = TaskVariables::from_iter([
                     (VariableName::Row, row.to_string()),
                     (VariableName::Column, column.to_string()),
+                    (VariableName::RowEnd, row_end.to_string()),
+                    (VariableName::ColumnEnd, column_end.to_string()),
                     (VariableName::SelectedText, selected_text),
+                    (VariableName::SelectionIndent, selection_indent),
                 ]);
+                if !all_selected_text.is_empty() {
+                    task_variables
+                        .insert(VariableName::AllSelectedText, all_selected_text.join("\n"));
+                }
+                let relative_file = current_file
+                    .as_deref()
+                    .zip(worktree_path.as_deref())
+                    .and_then(|(file, worktree_root)| relative_file_path(file, worktree_root));
+                if let Some(relative_file) = relative_file {
+                    task_variables.insert(VariableName::RelativeFile, relative_file);
+                }
+                if let Some(file_dirname) = current_file
+                    .as_deref()
+                    .and_then(|file| file_dirname(file, worktree_path.as_deref()))
+                {
+                    task_variables.insert(VariableName::FileDirname, file_dirname);
+                }
+                if let Some(path) = current_file.as_deref().map(Path::new) {
+                    if let Some(stem) = path.file_stem() {
+                        task_variables
+                            .insert(VariableName::Stem, stem.to_string_lossy().into_owned());
+                    }
+                    if let Some(extension) = path.extension() {
+                        task_variables.insert(
+                            VariableName::Extension,
+                            extension.to_string_lossy().into_owned(),
+                        );
+                    }
+                }
                 if let Some(path) = current_file {
                     task_variables.insert(VariableName::File, path);
                 }
                 if let Some(worktree_path) = worktree_path {
                     task_variables.insert(VariableName::WorktreeRoot, worktree_path);
                 }
+                if let Some(git_branch) = git_branch {
+                    task_variables.insert(VariableName::GitBranch, git_branch);
+                }
+                if let Some(line_author) = line_author {
+                    task_variables.insert_lazy(VariableName::LineAuthor, line_author);
+                }
+                if let Some(diagnostic_summary) = diagnostic_summary {
+                    task_variables.insert(
+                        VariableName::ErrorCount,
+                        diagnostic_summary.error_count.to_string(),
+                    );
+                    task_variables.insert(
+                        VariableName::WarningCount,
+                        diagnostic_summary.warning_count.to_string(),
+                    );
+                }
+                if let Some(language) = buffer
+                    .read(cx)
+                    .language_at(buffer_offset)
+                    .filter(|language| !language.name().is_empty())
+                {
+                    task_variables.insert(VariableName::Language, language.name().to_string());
+                }
+                let buffer_settings = language_settings(
+                    buffer.read(cx).language_at(buffer_offset).as_ref(),
+                    buffer.read(cx).file(),
+                    cx,
+                );
+                task_variables.insert(VariableName::TabSize, buffer_settings.tab_size.to_string());
+                let indent_style = if buffer_settings.hard_tabs {
+                    "tab"
+                } else {
+                    "space"
+                };
+                task_variables.insert(VariableName::IndentStyle, indent_style.to_owned());
+                // Buffers are always decoded as UTF-8.
+                task_variables.insert(VariableName::FileEncoding, "utf-8".to_owned());
+                let line_ending = match buffer.read(cx).line_ending() {
+                    LineEnding::Unix => "lf",
+                    LineEnding::Windows => "crlf",
+                };
+                task_variables.insert(VariableName::LineEnding, line_ending.to_owned());
+                if let Some(nearest_test) = nearest_test {
+                    task_variables.insert(VariableName::NearestTest, nearest_test);
+                }
+                if let Some(project_version) = project_version {
+                    task_variables.insert_lazy(VariableName::ProjectVersion, project_version);
+                }
                 if let Some(language_context) = context {
                     task_variables.extend(language_context);
                 }
 
                 Some(TaskContext {
-                    cwd: cwd.clone(),
+                    cwd,
                     task_variables,
+                    selection_fallbacks,
+                    args_override: Default::default(),
                 })
             })
         })()
         .unwrap_or_else(|| TaskContext {
             cwd,
             task_variables: Default::default(),
+            selection_fallbacks: Default::default(),
+            args_override: Default::default(),
         })
     } else {
         TaskContext {
             cwd,
             task_variables: Default::default(),
+            selection_fallbacks: Default::default(),
+            args_override: Default::default(),
         }
+    };
+    if !open_files.is_empty() {
+        task_context
+            .task_variables
+            .insert(VariableName::OpenFiles, open_files.join("\n"));
+    }
+    if let Some(staged_files) = staged_files {
+        task_context
+            .task_variables
+            .insert_lazy(VariableName::StagedFiles, staged_files);
     }
+    task_context
+        .task_variables
+        .insert(VariableName::FocusedView, focused_view(workspace, cx));
+    task_context
+        .task_variables
+        .insert(VariableName::WorkspaceName, workspace.worktrees_title(cx));
+    task_context
 }
 
-fn schedule_task(
+/// Diagnostics of the file, summed up over all language servers that reported them.
+fn buffer_diagnostic_summary(
+    project: &Project,
+    project_path: &ProjectPath,
+    cx: &AppContext,
+) -> DiagnosticSummary {
+    let mut summary = DiagnosticSummary::default();
+    for (_, _, path_summary) in project
+        .diagnostic_summaries(true, cx)
+        .filter(|(path, _, _)| path == project_path)
+    {
+        summary.error_count += path_summary.error_count;
+        summary.warning_count += path_summary.warning_count;
+    }
+    summary
+}
+
+/// A name of the branch checked out in the git repository with the worktree path, read from the worktree's repository snapshot.
+/// `None` outside of the git repositories and for the detached HEAD.
+fn git_branch(
+    project: &Project,
+    worktree_id: WorktreeId,
+    path: &Path,
+    cx: &AppContext,
+) -> Option<String> {
+    let worktree = project.worktree_for_id(worktree_id, cx)?;
+    let branch = worktree.read(cx).repository_for_path(path)?.branch()?;
+    (!branch.is_empty() && branch.as_ref() != "HEAD").then(|| branch.to_string())
+}
+
+/// An author of the zero-based row of the file with the `content`, as reported by git blame in the file's repository.
+/// Resolved only when referenced, in the background, as it runs the blame; `None` outside of the git repositories and for the unblamed rows.
+fn line_author(
+    project: &Project,
+    worktree_id: WorktreeId,
+    path: &Path,
+    content: Rope,
+    row: u32,
+    cx: &AppContext,
+) -> Option<VariableResolver> {
+    let worktree = project.worktree_for_id(worktree_id, cx)?;
+    let snapshot = worktree.read(cx).as_local()?.snapshot();
+    let (work_directory, _) = snapshot.repository_and_work_directory_for_path(path)?;
+    let repository = snapshot.local_git_repo(path)?;
+    let relative_path = path.strip_prefix(&work_directory).ok()?.to_path_buf();
+    Some(Arc::new(move || {
+        let blame = repository
+            .lock()
+            .blame(&relative_path, content.clone())
+            .ok()?;
+        blame
+            .entries
+            .into_iter()
+            .find(|entry| entry.range.contains(&row))?
+            .author
+    }))
+}
+
+/// The text of the multibuffer range as displayed in the editor: with the folded regions replaced by their placeholders
+/// and the tabs expanded.
+fn display_text_for_range(display_snapshot: &DisplaySnapshot, range: Range<usize>) -> String {
+    let start = range.start.to_display_point(display_snapshot);
+    let end = range.end.to_display_point(display_snapshot);
+    let rows_text = display_snapshot
+        .chunks(
+            start.row()..end.row() + 1,
+            false,
+            HighlightStyles::default(),
+        )
+        .map(|chunk| chunk.text)
+        .collect::<String>();
+    let start_offset = start.column() as usize;
+    let end_offset = rows_text
+        .split('\n')
+        .take((end.row() - start.row()) as usize)
+        .map(|line| line.len() + 1)
+        .sum::<usize>()
+        + end.column() as usize;
+    rows_text
+        .get(start_offset..end_offset.min(rows_text.len()))
+        .unwrap_or_default()
+        .to_owned()
+}
+
+/// A path of the file relative to the worktree root, `None` if the file is not inside of it.
+fn relative_file_path(file: &str, worktree_root: &str) -> Option<String> {
+    let relative_path = Path::new(file).strip_prefix(worktree_root).ok()?;
+    if relative_path.as_os_str().is_empty() {
+        return None;
+    }
+    Some(relative_path.to_string_lossy().into_owned())
+}
+
+/// An absolute path of the directory with the file, falling back to the worktree root if the file has no parent directory.
+fn file_dirname(file: &str, worktree_root: Option<&str>) -> Option<String> {
+    match Path::new(file).parent() {
+        Some(dirname) if !dirname.as_os_str().is_empty() => {
+            Some(dirname.to_string_lossy().into_owned())
+        }
+        _ => worktree_root.map(ToOwned::to_owned),
+    }
+}
+
+/// A name of the workspace surface that has the focus, or had it before the active modal was opened:
+/// a snake cased name of the panel or the item kind (`editor`, `terminal`, `terminal_panel`, `project_panel`, etc.),
+/// `pane` for the items of unknown kind, and `none` if nothing in the workspace is focused.
+fn focused_view(workspace: &Workspace, cx: &WindowContext) -> String {
+    let Some(focused) = workspace.focus_before_modal(cx).or_else(|| cx.focused()) else {
+        return "none".to_owned();
+    };
+    for dock in [
+        workspace.left_dock(),
+        workspace.bottom_dock(),
+        workspace.right_dock(),
+    ] {
+        if let Some(panel) = dock.read(cx).active_panel() {
+            if panel.focus_handle(cx).contains(&focused, cx) {
+                return view_name(panel.persistent_name());
+            }
+        }
+    }
+    for pane in workspace.panes() {
+        if let Some(item) = pane.read(cx).active_item() {
+            if item.focus_handle(cx).contains(&focused, cx) {
+                return item
+                    .serialized_item_kind()
+                    .map_or_else(|| "pane".to_owned(), view_name);
+            }
+        }
+    }
+    "none".to_owned()
+}
+
+/// Turns `TerminalPanel` or `Project Panel` into `terminal_panel` and `project_panel`.
+fn view_name(name: &str) -> String {
+    let mut view_name = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            if c.is_ascii_uppercase() && !view_name.is_empty() && !view_name.ends_with('_') {
+                view_name.push('_');
+            }
+            view_name.push(c.to_ascii_lowercase());
+        } else if !view_name.is_empty() && !view_name.ends_with('_') {
+            view_name.push('_');
+        }
+    }
+    view_name
+}
+
+/// Absolute paths of the files staged in the git repository that contains `cwd`, separated by newlines.
+/// Resolved only when referenced, in the background, as it queries the repository status; `None` if nothing is staged.
+fn staged_file_paths(
     workspace: &Workspace,
+    cwd: &Path,
+    cx: &AppContext,
+) -> Option<VariableResolver> {
+    let (worktree, relative_path) = workspace.project().read(cx).find_local_worktree(cwd, cx)?;
+    let worktree = worktree.read(cx).as_local()?;
+    let snapshot = worktree.snapshot();
+    let (work_directory, _) = snapshot.repository_and_work_directory_for_path(&relative_path)?;
+    let repository = snapshot.local_git_repo(&relative_path)?;
+    let work_directory = worktree.abs_path().join(work_directory);
+    Some(Arc::new(move || {
+        let staged_files = repository
+            .lock()
+            .staged_statuses(Path::new(""))
+            .iter()
+            .map(|(repo_path, _)| {
+                work_directory
+                    .join(repo_path)
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect::<Vec<_>>();
+        (!staged_files.is_empty()).then(|| staged_files.join("\n"))
+    }))
+}
+
+/// Absolute paths of all files opened in the workspace, in the order of their panes and tabs.
+fn open_file_paths(workspace: &Workspace, cx: &WindowContext) -> Vec<String> {
+    let project = workspace.project().read(cx);
+    let mut open_files = Vec::new();
+    for item in workspace.items(cx) {
+        let Some(abs_path) = item
+            .project_path(cx)
+            .and_then(|project_path| project.absolute_path(&project_path, cx))
+        else {
+            continue;
+        };
+        let abs_path = abs_path.to_string_lossy().to_string();
+        if !open_files.contains(&abs_path) {
+            open_files.push(abs_path);
+        }
+    }
+    open_files
+}
+
+fn schedule_task(
+    workspace: &mut Workspace,
     task: &Arc<dyn Task>,
     task_cx: TaskContext,
     omit_history: bool,
     cx: &mut ViewContext<'_, Workspace>,
 ) {
-    let spawn_in_terminal = task.prepare_exec(task_cx.clone());
-    if let Some(spawn_in_terminal) = spawn_in_terminal {
-        if !omit_history {
-            workspace.project().update(cx, |project, cx| {
-                project.task_inventory().update(cx, |inventory, _| {
-                    inventory.task_scheduled(Arc::clone(task), task_cx);
-                })
-            });
-        }
-        cx.emit(workspace::Event::SpawnTask(spawn_in_terminal));
-    }
+    confirm_and_spawn(
+        workspace,
+        task,
+        task_cx,
+        omit_history,
+        |_, spawn_in_terminal, cx| match spawn_in_terminal.show {
+            ShowStrategy::Terminal => {
+                let tail_file = spawn_in_terminal.tail_file.clone();
+                cx.emit(workspace::Event::SpawnTask(spawn_in_terminal));
+                if let Some(tail_file) = tail_file {
+                    follow_file(tail_file, cx);
+                }
+            }
+            ShowStrategy::Clipboard => copy_task_output(spawn_in_terminal, cx).detach(),
+        },
+        cx,
+    );
 }
 
-fn task_cwd(workspace: &Workspace, cx: &mut WindowContext) -> anyhow::Result<Option<PathBuf>> {
-    let project = workspace.project().read(cx);
-    let available_worktrees = project
-        .worktrees()
-        .filter(|worktree| {
-            let worktree = worktree.read(cx);
-            worktree.is_visible()
-                && worktree.is_local()
-                && worktree.root_entry().map_or(false, |e| e.is_dir())
+/// Kills the process of the most recently spawned task that is still running, or of all such tasks,
+/// and refreshes the workspace, including the task status indicator, once they stop.
+fn cancel_tasks(workspace: &mut Workspace, all: bool, cx: &mut ViewContext<Workspace>) {
+    let mut running_terminals = workspace
+        .project()
+        .read(cx)
+        .task_inventory()
+        .read(cx)
+        .running_task_terminals(cx);
+    if !all {
+        running_terminals = running_terminals.pop().into_iter().collect();
+    }
+    if running_terminals.is_empty() {
+        return;
+    }
+    let completions = running_terminals
+        .into_iter()
+        .map(|(_, terminal)| {
+            terminal.update(cx, |terminal, cx| {
+                let completion = terminal.wait_for_completed_task(cx);
+                terminal.kill_active_task();
+                completion
+            })
         })
         .collect::<Vec<_>>();
-    let cwd = match available_worktrees.len() {
-        0 => None,
-        1 => Some(available_worktrees[0].read(cx).abs_path()),
-        _ => {
-            let cwd_for_active_entry = project.active_entry().and_then(|entry_id| {
-                available_worktrees.into_iter().find_map(|worktree| {
-                    let worktree = worktree.read(cx);
-                    if worktree.contains_entry(entry_id) {
-                        Some(worktree.abs_path())
-                    } else {
-                        None
-                    }
-                })
-            });
-            anyhow::ensure!(
-                cwd_for_active_entry.is_some(),
-                "Cannot determine task cwd for multiple worktrees"
-            );
-            cwd_for_active_entry
+    cx.spawn(|workspace, mut cx| async move {
+        for completion in completions {
+            completion.await;
         }
-    };
-    Ok(cwd.map(|path| path.to_path_buf()))
+        workspace.update(&mut cx, |_, cx| cx.notify()).ok();
+    })
+    .detach();
 }
 
-#[cfg(test)]
-mod tests {
-    use std::sync::Arc;
+/// Same as [`schedule_task`], but spawns the task's terminal in a new workspace window for the same project.
+fn schedule_task_in_new_window(
+    workspace: &mut Workspace,
+    task: &Arc<dyn Task>,
+    task_cx: TaskContext,
+    omit_history: bool,
+    cx: &mut ViewContext<'_, Workspace>,
+) {
+    confirm_and_spawn(
+        workspace,
+        task,
+        task_cx,
+        omit_history,
+        |workspace, spawn_in_terminal, cx| {
+            let project = workspace.project().clone();
+            let app_state = workspace.app_state().clone();
+            cx.spawn(|_, mut cx| async move {
+                let window = cx.update(|cx| {
+                    let options = (app_state.build_window_options)(None, cx);
+                    cx.open_window(options, |cx| {
+                        cx.new_view(|cx| {
+                            Workspace::new(Default::default(), project, app_state.clone(), cx)
+                        })
+                    })
+                })?;
+                window.update(&mut cx, |workspace, cx| {
+                    // The new window's terminal panel is not loaded yet, so let it pick the task up later.
+                    workspace.queue_task_spawn(spawn_in_terminal);
+                    cx.activate_window();
+                })?;
+                anyhow::Ok(())
+            })
+            .detach_and_log_err(cx);
+        },
+        cx,
+    );
+}
+
+/// Prepares the task to be spawned and, if the task requires it, asks the user to confirm the variables resolved first.
+/// The lazy variables the task references run git and the likes, so they get computed in the background before the task is prepared.
+fn confirm_and_spawn(
+    workspace: &mut Workspace,
+    task: &Arc<dyn Task>,
+    task_cx: TaskContext,
+    omit_history: bool,
+    spawn: impl FnOnce(&Workspace, SpawnInTerminal, &mut ViewContext<'_, Workspace>) + 'static,
+    cx: &mut ViewContext<'_, Workspace>,
+) {
+    let lazy_variables = task_cx
+        .task_variables
+        .lazy_referenced(&task.variable_templates(&task_cx));
+    if lazy_variables.is_empty() {
+        prepare_confirm_and_spawn(workspace, task, task_cx, omit_history, spawn, cx);
+        return;
+    }
+    let resolve_lazy_variables = cx.background_executor().spawn(async move {
+        for lazy_variable in lazy_variables {
+            lazy_variable.resolve();
+        }
+    });
+    let task = Arc::clone(task);
+    cx.spawn(|workspace, mut cx| async move {
+        resolve_lazy_variables.await;
+        workspace
+            .update(&mut cx, |workspace, cx| {
+                prepare_confirm_and_spawn(workspace, &task, task_cx, omit_history, spawn, cx)
+            })
+            .ok();
+    })
+    .detach();
+}
+
+/// [`confirm_and_spawn`], once the lazy variables the task references are computed.
+fn prepare_confirm_and_spawn(
+    workspace: &mut Workspace,
+    task: &Arc<dyn Task>,
+    task_cx: TaskContext,
+    omit_history: bool,
+    spawn: impl FnOnce(&Workspace, SpawnInTerminal, &mut ViewContext<'_, Workspace>) + 'static,
+    cx: &mut ViewContext<'_, Workspace>,
+) {
+    let Some(mut spawn_in_terminal) = task.prepare_exec(task_cx.clone()) else {
+        return;
+    };
+    if spawn_in_terminal.parallel {
+        spawn_parallel_tasks(
+            workspace,
+            task,
+            task_cx,
+            &spawn_in_terminal.depends_on,
+            omit_history,
+            cx,
+        );
+        return;
+    }
+    let dependencies = if spawn_in_terminal.depends_on.is_empty() {
+        Vec::new()
+    } else {
+        let tasks = workspace
+            .project()
+            .read(cx)
+            .task_inventory()
+            .clone()
+            .update(cx, |inventory, cx| {
+                inventory.list_tasks(None, None, false, cx)
+            });
+        let mut steps =
+            match task_dependencies(&tasks, task.name(), &spawn_in_terminal.depends_on, &task_cx) {
+                Ok(dependencies) => dependencies,
+                Err(error) => {
+                    log::error!("Not spawning task \"{}\": {error}", spawn_in_terminal.label);
+                    workspace.show_toast(Toast::new(TASK_DEPENDENCIES_TOAST_ID, error), cx);
+                    return;
+                }
+            };
+        // The task goes last among the steps, to refer to the dependencies with `${steps.<label>.cwd}` and the likes.
+        steps.push(Arc::clone(task));
+        let Some(mut prepared_steps) = prepare_steps(&steps, &task_cx) else {
+            return;
+        };
+        let Some(task_step) = prepared_steps.pop() else {
+            return;
+        };
+        spawn_in_terminal = task_step;
+        steps
+            .iter()
+            .map(|step| step.cwd().is_some())
+            .zip(prepared_steps)
+            .collect::<Vec<_>>()
+    };
+    if !task_steps(&dependencies, &spawn_in_terminal)
+        .all(|step| has_required_tools(workspace, step, cx))
+    {
+        return;
+    }
+    spawn_in_terminal
+        .reveal
+        .get_or_insert_with(|| TaskSettings::get_global(cx).default_reveal.into());
+    limit_revealed_terminals(workspace, &mut spawn_in_terminal, cx);
+    let spawn: SpawnFn = Box::new(spawn);
+
+    let defines_cwd = task.cwd().is_some();
+    let task = Arc::clone(task);
+    let mut confirm_details = dependencies
+        .iter()
+        .filter(|(_, dependency)| dependency.confirm)
+        .map(|(_, dependency)| {
+            format!(
+                "Dependency \"{}\":\n{}",
+                dependency.label,
+                confirm_summary(dependency)
+            )
+            .trim_end()
+            .to_owned()
+        })
+        .collect::<Vec<_>>();
+    if spawn_in_terminal.confirm {
+        confirm_details.insert(0, confirm_summary(&spawn_in_terminal));
+    }
+    if !confirm_details.is_empty() {
+        let message = if task_steps(&dependencies, &spawn_in_terminal).any(|step| step.elevate) {
+            format!(
+                "Run task \"{}\" with elevated privileges?",
+                spawn_in_terminal.label
+            )
+        } else {
+            format!("Run task \"{}\"?", spawn_in_terminal.label)
+        };
+        let detail = confirm_details
+            .into_iter()
+            .filter(|summary| !summary.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let answer = cx.prompt(
+            PromptLevel::Warning,
+            &message,
+            Some(&detail),
+            &["Run", "Cancel"],
+        );
+        cx.spawn(|workspace, mut cx| async move {
+            if answer.await != Ok(0) {
+                return;
+            }
+            workspace
+                .update(&mut cx, |workspace, cx| {
+                    if is_duplicate_spawn(&spawn_in_terminal, cx) {
+                        return;
+                    }
+                    if !omit_history {
+                        record_task_usage(workspace, task, task_cx, &spawn_in_terminal, cx);
+                    }
+                    reveal_cwd_in_project_panel(workspace, &spawn_in_terminal, cx);
+                    spawn_with_dependencies(
+                        workspace,
+                        dependencies.into(),
+                        Vec::new(),
+                        (defines_cwd, spawn_in_terminal),
+                        spawn,
+                        cx,
+                    );
+                })
+                .ok();
+        })
+        .detach();
+    } else {
+        if is_duplicate_spawn(&spawn_in_terminal, cx) {
+            return;
+        }
+        if !omit_history {
+            record_task_usage(workspace, task, task_cx, &spawn_in_terminal, cx);
+        }
+        reveal_cwd_in_project_panel(workspace, &spawn_in_terminal, cx);
+        spawn_with_dependencies(
+            workspace,
+            dependencies.into(),
+            Vec::new(),
+            (defines_cwd, spawn_in_terminal),
+            spawn,
+            cx,
+        );
+    }
+}
+
+/// The dependencies of the task prepared, in the order they run in, followed by the task itself.
+fn task_steps<'a>(
+    dependencies: &'a [(bool, SpawnInTerminal)],
+    spawn_in_terminal: &'a SpawnInTerminal,
+) -> impl Iterator<Item = &'a SpawnInTerminal> {
+    dependencies
+        .iter()
+        .map(|(_, dependency)| dependency)
+        .chain(Some(spawn_in_terminal))
+}
+
+/// Shows a toast about the executables the task requires that are missing from `PATH`, returning whether all of them are present.
+/// The tasks run in containers are not checked, as their executables come from the container.
+fn has_required_tools(
+    workspace: &mut Workspace,
+    spawn_in_terminal: &SpawnInTerminal,
+    cx: &mut ViewContext<'_, Workspace>,
+) -> bool {
+    if spawn_in_terminal.container.is_some() {
+        return true;
+    }
+    let missing_tools = missing_tools(&spawn_in_terminal.requires);
+    if missing_tools.is_empty() {
+        return true;
+    }
+    workspace.show_toast(
+        Toast::new(
+            TASK_MISSING_TOOLS_TOAST_ID,
+            format!(
+                "Task \"{}\" requires tools missing from PATH: {}",
+                spawn_in_terminal.label,
+                missing_tools.join(", ")
+            ),
+        ),
+        cx,
+    );
+    false
+}
+
+/// The variables and the secrets of the task to confirm before spawning it.
+fn confirm_summary(spawn_in_terminal: &SpawnInTerminal) -> String {
+    [
+        variables_summary(&spawn_in_terminal.env),
+        secrets_summary(&spawn_in_terminal.secrets),
+    ]
+    .into_iter()
+    .filter(|summary| !summary.is_empty())
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+/// A spawn of the task, type-erased to pass it along through preparing all dependencies of the task.
+type SpawnFn = Box<dyn FnOnce(&Workspace, SpawnInTerminal, &mut ViewContext<'_, Workspace>)>;
+
+/// Prepares the dependencies of the task one after another, each through the same [`spawn_in_defined_cwd`] steps as the task itself:
+/// with their cwd checked, secrets read, commands substituted and git diffs supplied. Then prepares the task the same way,
+/// and spawns it [finalized](SpawnInTerminal::finalize) to run after all of its dependencies. Stops at the first step that fails to prepare.
+fn spawn_with_dependencies(
+    workspace: &Workspace,
+    mut dependencies: VecDeque<(bool, SpawnInTerminal)>,
+    mut prepared_dependencies: Vec<SpawnInTerminal>,
+    (defines_cwd, spawn_in_terminal): (bool, SpawnInTerminal),
+    spawn: SpawnFn,
+    cx: &mut ViewContext<'_, Workspace>,
+) {
+    let Some((dependency_defines_cwd, dependency)) = dependencies.pop_front() else {
+        spawn_in_defined_cwd(
+            workspace,
+            defines_cwd,
+            spawn_in_terminal,
+            move |workspace, mut spawn_in_terminal, cx| {
+                // The rewrites fold the args into the command, so they go last: after the args get their `$(command)` outputs substituted.
+                spawn_in_terminal.finalize(prepared_dependencies);
+                spawn(workspace, spawn_in_terminal, cx)
+            },
+            cx,
+        );
+        return;
+    };
+    spawn_in_defined_cwd(
+        workspace,
+        dependency_defines_cwd,
+        dependency,
+        move |workspace, dependency, cx| {
+            prepared_dependencies.push(dependency);
+            spawn_with_dependencies(
+                workspace,
+                dependencies,
+                prepared_dependencies,
+                (defines_cwd, spawn_in_terminal),
+                spawn,
+                cx,
+            )
+        },
+        cx,
+    );
+}
+
+/// Keeps the terminal of the task hidden, if as many revealed task terminals as the settings allow are running already.
+fn limit_revealed_terminals(
+    workspace: &mut Workspace,
+    spawn_in_terminal: &mut SpawnInTerminal,
+    cx: &mut ViewContext<'_, Workspace>,
+) {
+    let max_revealed_terminals = TaskSettings::get_global(cx).max_revealed_terminals;
+    if max_revealed_terminals == 0
+        || spawn_in_terminal.show != ShowStrategy::Terminal
+        || spawn_in_terminal.reveal == Some(RevealStrategy::Never)
+    {
+        return;
+    }
+    let revealed_terminals = workspace
+        .project()
+        .read(cx)
+        .local_terminal_handles()
+        .iter()
+        .filter_map(|handle| handle.upgrade())
+        .filter(|terminal| {
+            terminal.read(cx).task().map_or(false, |task| {
+                task.status == terminal::TaskStatus::Running && task.reveal != RevealStrategy::Never
+            })
+        })
+        .count();
+    if revealed_terminals < max_revealed_terminals {
+        return;
+    }
+    spawn_in_terminal.reveal = Some(RevealStrategy::Never);
+    workspace.show_toast(
+        Toast::new(
+            TASK_HIDDEN_TOAST_ID,
+            format!(
+                "Task \"{}\" runs in a hidden terminal, as {revealed_terminals} task terminals are revealed already",
+                spawn_in_terminal.label
+            ),
+        ),
+        cx,
+    );
+}
+
+/// Reveals and selects the cwd of the task in the project panel, if the task asks for it and the cwd is within a worktree.
+fn reveal_cwd_in_project_panel(
+    workspace: &Workspace,
+    spawn_in_terminal: &SpawnInTerminal,
+    cx: &mut ViewContext<'_, Workspace>,
+) {
+    if !spawn_in_terminal.reveal_cwd_in_project_panel {
+        return;
+    }
+    let Some(cwd) = spawn_in_terminal.cwd.as_deref() else {
+        return;
+    };
+    workspace.project().update(cx, |project, cx| {
+        let Some(entry_id) = project
+            .find_local_worktree(cwd, cx)
+            .and_then(|(worktree, path)| Some(worktree.read(cx).entry_for_path(path)?.id))
+        else {
+            return;
+        };
+        cx.emit(project::Event::RevealInProjectPanel(entry_id));
+    });
+}
+
+/// Spawns the task, if the cwd its definition has exists, logging an error instead otherwise.
+fn spawn_in_defined_cwd(
+    workspace: &Workspace,
+    defines_cwd: bool,
+    spawn_in_terminal: SpawnInTerminal,
+    spawn: impl FnOnce(&Workspace, SpawnInTerminal, &mut ViewContext<'_, Workspace>) + 'static,
+    cx: &mut ViewContext<'_, Workspace>,
+) {
+    let Some(cwd) = spawn_in_terminal.cwd.clone().filter(|_| defines_cwd) else {
+        spawn_with_secrets(workspace, spawn_in_terminal, spawn, cx);
+        return;
+    };
+    let fs = workspace.app_state().fs.clone();
+    cx.spawn(|workspace, mut cx| async move {
+        anyhow::ensure!(
+            fs.is_dir(&cwd).await,
+            "cwd {cwd:?} of task {:?} does not exist",
+            spawn_in_terminal.label
+        );
+        workspace.update(&mut cx, |workspace, cx| {
+            spawn_with_secrets(workspace, spawn_in_terminal, spawn, cx)
+        })?;
+        anyhow::Ok(())
+    })
+    .detach_and_log_err(cx);
+}
+
+/// Resolved commands of the tasks spawned recently, kept for the `duplicate_spawn_window_ms` period.
+#[derive(Default)]
+struct RecentSpawns {
+    spawns: HashSet<RecentSpawn>,
+}
+
+impl Global for RecentSpawns {}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct RecentSpawn {
+    command: String,
+    args: Vec<String>,
+    cwd: Option<PathBuf>,
+}
+
+/// Checks whether the same command was spawned in the same cwd within the configured window,
+/// remembering the spawn for the next checks otherwise.
+fn is_duplicate_spawn(spawn_in_terminal: &SpawnInTerminal, cx: &mut AppContext) -> bool {
+    let window = Duration::from_millis(TaskSettings::get_global(cx).duplicate_spawn_window_ms);
+    if window.is_zero() {
+        return false;
+    }
+    let recent_spawn = RecentSpawn {
+        command: spawn_in_terminal.command.clone(),
+        args: spawn_in_terminal.args.clone(),
+        cwd: spawn_in_terminal.cwd.clone(),
+    };
+    if !cx
+        .default_global::<RecentSpawns>()
+        .spawns
+        .insert(recent_spawn.clone())
+    {
+        return true;
+    }
+    cx.spawn(|mut cx| async move {
+        cx.background_executor().timer(window).await;
+        cx.update(|cx| {
+            cx.default_global::<RecentSpawns>()
+                .spawns
+                .remove(&recent_spawn);
+        })
+        .ok();
+    })
+    .detach();
+    false
+}
+
+/// Spawns the tasks of a `parallel` task at once, each in its own terminal, recording the `parallel` task itself in the history,
+/// so that rerunning it spawns all of its tasks again.
+/// The terminals are grouped under one terminal tab of the `parallel` task.
+fn spawn_parallel_tasks(
+    workspace: &mut Workspace,
+    task: &Arc<dyn Task>,
+    task_cx: TaskContext,
+    depends_on: &[String],
+    omit_history: bool,
+    cx: &mut ViewContext<'_, Workspace>,
+) {
+    let inventory = workspace.project().read(cx).task_inventory().clone();
+    let tasks = inventory.update(cx, |inventory, cx| {
+        inventory.list_tasks(None, None, false, cx)
+    });
+    let parallel_tasks = match parallel_tasks(&tasks, task.name(), depends_on, &task_cx) {
+        Ok(parallel_tasks) => parallel_tasks,
+        Err(error) => {
+            log::error!("Not spawning task \"{}\": {error}", task.name());
+            workspace.show_toast(Toast::new(TASK_DEPENDENCIES_TOAST_ID, error), cx);
+            return;
+        }
+    };
+    if !omit_history {
+        inventory.update(cx, |inventory, _| {
+            inventory.task_scheduled(Arc::clone(task), task_cx.clone())
+        });
+    }
+    for parallel_task in parallel_tasks {
+        let parallel_task: Arc<dyn Task> =
+            Arc::new(TerminalGroupTask(parallel_task, task.name().to_owned()));
+        schedule_task(workspace, &parallel_task, task_cx.clone(), true, cx);
+    }
+}
 
+/// The tasks a `parallel` task spawns at once: the ones it lists, with the nested `parallel` tasks replaced by their own tasks.
+/// An error if any of the tasks is not among the tasks given, cannot be resolved, or the `parallel` tasks form a cycle.
+fn parallel_tasks(
+    tasks: &[(TaskSourceKind, Arc<dyn Task>)],
+    task_name: &str,
+    depends_on: &[String],
+    task_cx: &TaskContext,
+) -> Result<Vec<Arc<dyn Task>>, String> {
+    fn collect(
+        label: &str,
+        depends_on: &[String],
+        tasks: &[(TaskSourceKind, Arc<dyn Task>)],
+        task_cx: &TaskContext,
+        path: &mut Vec<String>,
+        parallel_tasks: &mut Vec<Arc<dyn Task>>,
+    ) -> Result<(), String> {
+        path.push(label.to_owned());
+        for member_label in depends_on {
+            if let Some(cycle_start) = path.iter().position(|label| label == member_label) {
+                let cycle = path[cycle_start..]
+                    .iter()
+                    .chain(Some(member_label))
+                    .map(|label| format!("\"{label}\""))
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+                return Err(format!("Parallel tasks form a cycle: {cycle}"));
+            }
+            if parallel_tasks
+                .iter()
+                .any(|task| task.name() == member_label)
+            {
+                continue;
+            }
+            let (_, task) = tasks
+                .iter()
+                .find(|(_, task)| task.name() == member_label)
+                .ok_or_else(|| {
+                    format!("Task \"{member_label}\" that \"{label}\" runs is not found")
+                })?;
+            let spawn_in_terminal = task.prepare_exec(task_cx.clone()).ok_or_else(|| {
+                format!("Task \"{member_label}\" that \"{label}\" runs cannot be resolved")
+            })?;
+            if spawn_in_terminal.parallel {
+                collect(
+                    member_label,
+                    &spawn_in_terminal.depends_on,
+                    tasks,
+                    task_cx,
+                    path,
+                    parallel_tasks,
+                )?;
+            } else {
+                parallel_tasks.push(Arc::clone(task));
+            }
+        }
+        path.pop();
+        Ok(())
+    }
+
+    let mut parallel_tasks = Vec::new();
+    collect(
+        task_name,
+        depends_on,
+        tasks,
+        task_cx,
+        &mut Vec::new(),
+        &mut parallel_tasks,
+    )?;
+    Ok(parallel_tasks)
+}
+
+/// The tasks the task depends on, transitively, in the order to run them in, to be prepared as the steps with [`prepare_steps`]:
+/// every dependency goes after its own dependencies, and is run once, even if several tasks depend on it.
+/// An error if any of the dependencies is not among the tasks given, cannot be resolved, or the dependencies form a cycle.
+fn task_dependencies(
+    tasks: &[(TaskSourceKind, Arc<dyn Task>)],
+    task_name: &str,
+    depends_on: &[String],
+    task_cx: &TaskContext,
+) -> Result<Vec<Arc<dyn Task>>, String> {
+    fn collect(
+        label: &str,
+        depends_on: &[String],
+        tasks: &[(TaskSourceKind, Arc<dyn Task>)],
+        task_cx: &TaskContext,
+        path: &mut Vec<String>,
+        dependencies: &mut Vec<Arc<dyn Task>>,
+    ) -> Result<(), String> {
+        path.push(label.to_owned());
+        for dependency_label in depends_on {
+            if let Some(cycle_start) = path.iter().position(|label| label == dependency_label) {
+                let cycle = path[cycle_start..]
+                    .iter()
+                    .chain(Some(dependency_label))
+                    .map(|label| format!("\"{label}\""))
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+                return Err(format!("Task dependencies form a cycle: {cycle}"));
+            }
+            if dependencies
+                .iter()
+                .any(|dependency| dependency.name() == dependency_label)
+            {
+                continue;
+            }
+            let (_, task) = tasks
+                .iter()
+                .find(|(_, task)| task.name() == dependency_label)
+                .ok_or_else(|| {
+                    format!("Task \"{dependency_label}\" that \"{label}\" depends on is not found")
+                })?;
+            let dependency = task.prepare_exec(task_cx.clone()).ok_or_else(|| {
+                format!(
+                    "Task \"{dependency_label}\" that \"{label}\" depends on cannot be resolved"
+                )
+            })?;
+            collect(
+                dependency_label,
+                &dependency.depends_on,
+                tasks,
+                task_cx,
+                path,
+                dependencies,
+            )?;
+            dependencies.push(Arc::clone(task));
+        }
+        path.pop();
+        Ok(())
+    }
+
+    let mut dependencies = Vec::new();
+    collect(
+        task_name,
+        depends_on,
+        tasks,
+        task_cx,
+        &mut Vec::new(),
+        &mut dependencies,
+    )?;
+    Ok(dependencies)
+}
+
+/// Required executables that cannot be found on `PATH`.
+fn missing_tools(requires: &[String]) -> Vec<&str> {
+    requires
+        .iter()
+        .map(String::as_str)
+        .filter(|tool| which::which(tool).is_err())
+        .collect()
+}
+
+/// All task variables resolved into the env of the task, the lazily resolved ones included,
+/// one `NAME: value` pair per line, sorted by the variable name.
+fn variables_summary(env: &HashMap<String, String>) -> String {
+    let mut variables = env
+        .iter()
+        .filter(|(name, _)| name.starts_with("ZED_"))
+        .map(|(name, value)| format!("{name}: {value}"))
+        .collect::<Vec<_>>();
+    variables.sort();
+    variables.join("\n")
+}
+
+fn record_task_usage(
+    workspace: &Workspace,
+    task: Arc<dyn Task>,
+    task_cx: TaskContext,
+    spawn_in_terminal: &SpawnInTerminal,
+    cx: &mut ViewContext<'_, Workspace>,
+) {
+    if TaskSettings::get_global(cx).global_history {
+        let project = workspace
+            .project()
+            .read(cx)
+            .worktree_root_names(cx)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut command = spawn_in_terminal.command.clone();
+        for arg in &spawn_in_terminal.args {
+            command.push(' ');
+            command.push_str(arg);
+        }
+        GlobalTaskHistory::record(
+            GlobalHistoryEntry {
+                project: project.into(),
+                command,
+            },
+            cx,
+        );
+    }
+    workspace.project().update(cx, |project, cx| {
+        project.task_inventory().update(cx, |inventory, _| {
+            inventory.task_scheduled(task, task_cx);
+        })
+    });
+}
+
+/// Visible local directory worktrees of the project, the tasks can be spawned in.
+fn task_worktrees(project: &Project, cx: &AppContext) -> Vec<Model<Worktree>> {
+    project
+        .worktrees()
+        .filter(|worktree| {
+            let worktree = worktree.read(cx);
+            worktree.is_visible()
+                && worktree.is_local()
+                && worktree.root_entry().map_or(false, |e| e.is_dir())
+        })
+        .collect()
+}
+
+/// The command line of the last scheduled task, resolved in the context it was scheduled in, the way it's passed to the shell.
+/// `None` if no task was scheduled yet, or the command cannot be resolved anymore.
+pub fn last_task_command(workspace: &Workspace, cx: &AppContext) -> Option<String> {
+    let (task, task_context) = workspace
+        .project()
+        .read(cx)
+        .task_inventory()
+        .read(cx)
+        .last_scheduled_task()?;
+    task.prepare_exec(task_context)?.resolve_command().ok()
+}
+
+/// The directory the tasks are spawned in, unless they specify their own `cwd`: the only local worktree of the project,
+/// or the one with the active entry if there are several (an error if there's no active entry then).
+pub fn default_task_cwd(
+    workspace: &Workspace,
+    cx: &mut WindowContext,
+) -> anyhow::Result<Option<PathBuf>> {
+    task_cwd(workspace, cx)
+}
+
+/// Spawns the task in the context given, the same way as the tasks spawned from the tasks modal:
+/// with the confirmation, the required tools check and the task settings applied.
+/// With `omit_history: true`, the task is not recorded as scheduled: `last_scheduled_task` and the recent tasks
+/// stay unaffected, so that `task::Rerun` keeps rerunning the task the user has spawned the last time.
+pub fn spawn_task(
+    workspace: &mut Workspace,
+    task: &Arc<dyn Task>,
+    task_context: TaskContext,
+    omit_history: bool,
+    cx: &mut ViewContext<Workspace>,
+) {
+    schedule_task(workspace, task, task_context, omit_history, cx);
+}
+
+fn task_cwd(workspace: &Workspace, cx: &mut WindowContext) -> anyhow::Result<Option<PathBuf>> {
+    if TaskSettings::get_global(cx).cwd_from_language_server {
+        if let Some(root) = active_language_server_root(workspace, cx) {
+            return Ok(Some(root));
+        }
+    }
+    let project = workspace.project().read(cx);
+    let available_worktrees = task_worktrees(project, cx);
+    let cwd = match available_worktrees.len() {
+        0 => None,
+        1 => Some(available_worktrees[0].read(cx).abs_path()),
+        _ => {
+            let cwd_for_active_entry = project.active_entry().and_then(|entry_id| {
+                available_worktrees.into_iter().find_map(|worktree| {
+                    let worktree = worktree.read(cx);
+                    if worktree.contains_entry(entry_id) {
+                        Some(worktree.abs_path())
+                    } else {
+                        None
+                    }
+                })
+            });
+            anyhow::ensure!(
+                cwd_for_active_entry.is_some(),
+                "Cannot determine task cwd for multiple worktrees"
+            );
+            cwd_for_active_entry
+        }
+    };
+    Ok(cwd.map(|path| path.to_path_buf()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::RefCell,
+        path::{Path, PathBuf},
+        rc::Rc,
+        sync::Arc,
+        time::Duration,
+    };
+
+    use ::settings::SettingsStore;
+    use collections::HashMap;
     use editor::Editor;
-    use gpui::{Entity, TestAppContext};
-    use language::{Language, LanguageConfig, SymbolContextProvider};
-    use project::{FakeFs, Project, TaskSourceKind};
+    use git::blame::{Blame, BlameEntry};
+    use gpui::{
+        AppContext, DismissEvent, Entity, Model, TestAppContext, View, VisualTestContext,
+        WindowContext,
+    };
+    use language::{
+        Diagnostic, DiagnosticEntry, DiagnosticSeverity, FakeLspAdapter, Language, LanguageConfig,
+        LanguageMatcher, LanguageServerId, PointUtf16, SymbolContextProvider, Unclipped,
+    };
+    use project::{repository::GitFileStatus, FakeFs, Project, ProjectPath, TaskSourceKind};
     use serde_json::json;
-    use task::{oneshot_source::OneshotSource, TaskContext, TaskVariables, VariableName};
+    use smol::stream::StreamExt as _;
+    use task::{
+        in_memory_source::{InMemorySource, TaskBuilder},
+        oneshot_source::OneshotSource,
+        static_source::{tasks_for, Definition, RevealStrategy, TaskDefinitions, TaskDockPosition},
+        SelectionFallbacks, TaskContext, TaskId, TaskVariables, VariableName,
+    };
+    use terminal::{TaskStatus, Terminal};
     use ui::VisualContext;
-    use workspace::{AppState, Workspace};
+    use workspace::{
+        dock::{test::TestPanel, DockPosition},
+        AppState, Workspace,
+    };
 
-    use crate::{task_context, task_cwd};
+    use crate::{
+        command_output::capture_stdout,
+        default_task_cwd, file_dirname, last_task_command, missing_tools,
+        modal::{
+            CancelAllTasks, CancelTask, Rerun, RerunLastFailed, RerunVerbose, Spawn, TasksModal,
+        },
+        relative_file_path, script_task_definition,
+        secrets::{SecretStore, TaskSecrets},
+        task_context, task_cwd, tasks_overview, variables_summary, view_name,
+    };
 
     #[gpui::test]
     async fn test_default_language_context(cx: &mut TestAppContext) {
@@ -383,64 +2179,2168 @@ mod tests {
             this.add_item_to_center(Box::new(editor2.clone()), cx);
             assert_eq!(this.active_item(cx).unwrap().item_id(), editor2.entity_id());
             assert_eq!(
-                task_context(this, task_cwd(this, cx).unwrap(), cx),
-                TaskContext {
-                    cwd: Some("/dir".into()),
-                    task_variables: TaskVariables::from_iter([
-                        (VariableName::File, "/dir/rust/b.rs".into()),
-                        (VariableName::WorktreeRoot, "/dir".into()),
-                        (VariableName::Row, "1".into()),
-                        (VariableName::Column, "1".into()),
-                        (VariableName::SelectedText, "".into())
-                    ])
-                }
+                task_context_with_whole_file(this, cx),
+                (
+                    TaskContext {
+                        cwd: Some("/dir".into()),
+                        task_variables: TaskVariables::from_iter([
+                            (VariableName::File, "/dir/rust/b.rs".into()),
+                            (VariableName::RelativeFile, "rust/b.rs".into()),
+                            (VariableName::FileDirname, "/dir/rust".into()),
+                            (VariableName::Stem, "b".into()),
+                            (VariableName::Extension, "rs".into()),
+                            (VariableName::WorktreeRoot, "/dir".into()),
+                            (VariableName::ErrorCount, "0".into()),
+                            (VariableName::WarningCount, "0".into()),
+                            (VariableName::Row, "1".into()),
+                            (VariableName::Column, "1".into()),
+                            (VariableName::RowEnd, "1".into()),
+                            (VariableName::ColumnEnd, "1".into()),
+                            (VariableName::SelectedText, "".into()),
+                            (VariableName::SelectionIndent, "".into()),
+                            (VariableName::TabSize, "4".into()),
+                            (VariableName::IndentStyle, "space".into()),
+                            (VariableName::FileEncoding, "utf-8".into()),
+                            (VariableName::LineEnding, "lf".into()),
+                            (VariableName::OpenFiles, "/dir/a.ts\n/dir/rust/b.rs".into()),
+                            (VariableName::FocusedView, "editor".into()),
+                            (VariableName::WorkspaceName, "dir".into()),
+                        ]),
+                        selection_fallbacks: SelectionFallbacks {
+                            whole_file: None,
+                            current_line: Some("use std; fn this_is_a_rust_file() { }".into()),
+                        },
+                        args_override: Default::default(),
+                    },
+                    Some("use std; fn this_is_a_rust_file() { }".to_owned())
+                )
             );
             // And now, let's select an identifier.
             editor2.update(cx, |this, cx| {
                 this.change_selections(None, cx, |selections| selections.select_ranges([14..18]))
             });
             assert_eq!(
-                task_context(this, task_cwd(this, cx).unwrap(), cx),
-                TaskContext {
-                    cwd: Some("/dir".into()),
-                    task_variables: TaskVariables::from_iter([
-                        (VariableName::File, "/dir/rust/b.rs".into()),
-                        (VariableName::WorktreeRoot, "/dir".into()),
-                        (VariableName::Row, "1".into()),
-                        (VariableName::Column, "15".into()),
-                        (VariableName::SelectedText, "is_i".into()),
-                        (VariableName::Symbol, "this_is_a_rust_file".into()),
-                    ])
-                }
+                task_context_with_whole_file(this, cx),
+                (
+                    TaskContext {
+                        cwd: Some("/dir".into()),
+                        task_variables: TaskVariables::from_iter([
+                            (VariableName::File, "/dir/rust/b.rs".into()),
+                            (VariableName::RelativeFile, "rust/b.rs".into()),
+                            (VariableName::FileDirname, "/dir/rust".into()),
+                            (VariableName::Stem, "b".into()),
+                            (VariableName::Extension, "rs".into()),
+                            (VariableName::WorktreeRoot, "/dir".into()),
+                            (VariableName::ErrorCount, "0".into()),
+                            (VariableName::WarningCount, "0".into()),
+                            (VariableName::Row, "1".into()),
+                            (VariableName::Column, "15".into()),
+                            (VariableName::RowEnd, "1".into()),
+                            (VariableName::ColumnEnd, "19".into()),
+                            (VariableName::SelectedText, "is_i".into()),
+                            (VariableName::AllSelectedText, "is_i".into()),
+                            (VariableName::SelectionIndent, "".into()),
+                            (VariableName::Symbol, "this_is_a_rust_file".into()),
+                            (VariableName::TabSize, "4".into()),
+                            (VariableName::IndentStyle, "space".into()),
+                            (VariableName::FileEncoding, "utf-8".into()),
+                            (VariableName::LineEnding, "lf".into()),
+                            (VariableName::OpenFiles, "/dir/a.ts\n/dir/rust/b.rs".into()),
+                            (VariableName::FocusedView, "editor".into()),
+                            (VariableName::WorkspaceName, "dir".into()),
+                        ]),
+                        selection_fallbacks: SelectionFallbacks::default(),
+                        args_override: Default::default(),
+                    },
+                    None
+                )
             );
 
             // Now, let's switch the active item to .ts file.
             this.activate_item(&editor1, cx);
             assert_eq!(
-                task_context(this, task_cwd(this, cx).unwrap(), cx),
-                TaskContext {
-                    cwd: Some("/dir".into()),
-                    task_variables: TaskVariables::from_iter([
-                        (VariableName::File, "/dir/a.ts".into()),
-                        (VariableName::WorktreeRoot, "/dir".into()),
-                        (VariableName::Row, "1".into()),
-                        (VariableName::Column, "1".into()),
-                        (VariableName::SelectedText, "".into()),
-                        (VariableName::Symbol, "this_is_a_test".into()),
-                    ])
-                }
+                task_context_with_whole_file(this, cx),
+                (
+                    TaskContext {
+                        cwd: Some("/dir".into()),
+                        task_variables: TaskVariables::from_iter([
+                            (VariableName::File, "/dir/a.ts".into()),
+                            (VariableName::RelativeFile, "a.ts".into()),
+                            (VariableName::FileDirname, "/dir".into()),
+                            (VariableName::Stem, "a".into()),
+                            (VariableName::Extension, "ts".into()),
+                            (VariableName::WorktreeRoot, "/dir".into()),
+                            (VariableName::ErrorCount, "0".into()),
+                            (VariableName::WarningCount, "0".into()),
+                            (VariableName::Row, "1".into()),
+                            (VariableName::Column, "1".into()),
+                            (VariableName::RowEnd, "1".into()),
+                            (VariableName::ColumnEnd, "1".into()),
+                            (VariableName::SelectedText, "".into()),
+                            (VariableName::SelectionIndent, "".into()),
+                            (VariableName::Symbol, "this_is_a_test".into()),
+                            (VariableName::TabSize, "4".into()),
+                            (VariableName::IndentStyle, "space".into()),
+                            (VariableName::FileEncoding, "utf-8".into()),
+                            (VariableName::LineEnding, "lf".into()),
+                            (VariableName::OpenFiles, "/dir/a.ts\n/dir/rust/b.rs".into()),
+                            (VariableName::FocusedView, "editor".into()),
+                            (VariableName::WorkspaceName, "dir".into()),
+                        ]),
+                        selection_fallbacks: SelectionFallbacks {
+                            whole_file: None,
+                            current_line: Some("function this_is_a_test() { }".into()),
+                        },
+                        args_override: Default::default(),
+                    },
+                    Some("function this_is_a_test() { }".to_owned())
+                )
             );
         });
     }
 
-    pub(crate) fn init_test(cx: &mut TestAppContext) -> Arc<AppState> {
-        cx.update(|cx| {
-            let state = AppState::test(cx);
-            language::init(cx);
-            crate::init(cx);
-            editor::init(cx);
-            workspace::init_settings(cx);
-            Project::init_settings(cx);
+    /// The task context, with the lazily copied whole file contents resolved separately, to compare them by value.
+    fn task_context_with_whole_file(
+        workspace: &Workspace,
+        cx: &mut WindowContext,
+    ) -> (TaskContext, Option<String>) {
+        let mut task_context = task_context(workspace, task_cwd(workspace, cx).unwrap(), cx);
+        let whole_file = task_context
+            .selection_fallbacks
+            .whole_file
+            .take()
+            .and_then(|whole_file| whole_file.resolve());
+        (task_context, whole_file)
+    }
+
+    #[test]
+    fn test_file_dirname() {
+        assert_eq!(
+            file_dirname("/dir/rust/b.rs", Some("/dir")),
+            Some("/dir/rust".to_owned())
+        );
+        assert_eq!(
+            file_dirname("/dir/a.ts", Some("/dir")),
+            Some("/dir".to_owned()),
+            "Files at the worktree root should resolve to the worktree root"
+        );
+        assert_eq!(
+            file_dirname("a.ts", Some("/dir")),
+            Some("/dir".to_owned()),
+            "Files without a parent directory should fall back to the worktree root"
+        );
+        assert_eq!(file_dirname("a.ts", None), None);
+    }
+
+    #[test]
+    fn test_relative_file_path() {
+        assert_eq!(
+            relative_file_path("/dir/rust/b.rs", "/dir"),
+            Some("rust/b.rs".to_owned())
+        );
+        assert_eq!(
+            relative_file_path("/dir/rust/b.rs", "/dir/"),
+            Some("rust/b.rs".to_owned()),
+            "Trailing slash of the worktree root should not matter"
+        );
+        assert_eq!(
+            relative_file_path("/elsewhere/b.rs", "/dir"),
+            None,
+            "Files outside of the worktree should have no relative path"
+        );
+        assert_eq!(
+            relative_file_path("/dir-other/b.rs", "/dir"),
+            None,
+            "Worktree root should be matched by the whole path components"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_workspace_name_variable(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({ "zed": { "a.rs": "" }, "docs": { "b.md": "" } }),
+        )
+        .await;
+        let project = Project::test(fs, ["/dir/zed".as_ref(), "/dir/docs".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+
+        workspace.update(cx, |workspace, cx| {
+            assert_eq!(
+                task_context(workspace, None, cx)
+                    .task_variables
+                    .get(&VariableName::WorkspaceName),
+                Some("zed, docs"),
+                "Workspace name should list all worktrees, as the window title does"
+            );
+        });
+    }
+
+    #[gpui::test]
+    async fn test_focused_view_variable(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree("/dir", json!({ "a.ts": "a" })).await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let worktree_id = project.update(cx, |project, cx| {
+            project.worktrees().next().unwrap().read(cx).id()
+        });
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        let buffer = project
+            .update(cx, |project, cx| {
+                project.open_buffer((worktree_id, "a.ts"), cx)
+            })
+            .await
+            .unwrap();
+        let editor = cx.new_view(|cx| Editor::for_buffer(buffer, Some(project), cx));
+        let panel = cx.new_view(|cx| TestPanel::new(DockPosition::Bottom, cx));
+        let focused_view = |cx: &mut VisualTestContext| {
+            workspace.update(cx, |workspace, cx| {
+                task_context(workspace, None, cx)
+                    .task_variables
+                    .get(&VariableName::FocusedView)
+                    .map(ToOwned::to_owned)
+            })
+        };
+
+        assert_eq!(focused_view(cx), Some("none".to_owned()));
+
+        workspace.update(cx, |workspace, cx| {
+            workspace.add_panel(panel.clone(), cx);
+            workspace.add_item_to_center(Box::new(editor.clone()), cx);
+        });
+        assert_eq!(focused_view(cx), Some("editor".to_owned()));
+
+        workspace.update(cx, |workspace, cx| {
+            workspace.toggle_panel_focus::<TestPanel>(cx);
+        });
+        assert_eq!(
+            focused_view(cx),
+            Some("test_panel".to_owned()),
+            "Focused panel should be reported by its name"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_default_task_cwd(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree("/dir", json!({ "a.ts": "a" })).await;
+        fs.insert_tree("/other", json!({ "b.ts": "b" })).await;
+
+        {
+            let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+            let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+            workspace.update(cx, |workspace, cx| {
+                assert_eq!(
+                    default_task_cwd(workspace, cx).unwrap(),
+                    task_cwd(workspace, cx).unwrap()
+                );
+                assert_eq!(
+                    default_task_cwd(workspace, cx).unwrap(),
+                    Some(PathBuf::from("/dir"))
+                );
+            });
+        }
+
+        let project = Project::test(fs, ["/dir".as_ref(), "/other".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        workspace.update(cx, |workspace, cx| {
+            assert!(
+                default_task_cwd(workspace, cx).is_err(),
+                "Multiple worktrees with no active entry should be ambiguous"
+            );
+            assert!(task_cwd(workspace, cx).is_err());
+        });
+
+        let other_worktree_id = project.update(cx, |project, cx| {
+            project
+                .worktrees()
+                .find(|worktree| worktree.read(cx).abs_path().as_ref() == Path::new("/other"))
+                .unwrap()
+                .read(cx)
+                .id()
+        });
+        project.update(cx, |project, cx| {
+            project.set_active_path(
+                Some(ProjectPath {
+                    worktree_id: other_worktree_id,
+                    path: Path::new("b.ts").into(),
+                }),
+                cx,
+            )
+        });
+        workspace.update(cx, |workspace, cx| {
+            assert_eq!(
+                default_task_cwd(workspace, cx).unwrap(),
+                task_cwd(workspace, cx).unwrap()
+            );
+            assert_eq!(
+                default_task_cwd(workspace, cx).unwrap(),
+                Some(PathBuf::from("/other")),
+                "Worktree of the active entry should be used"
+            );
+        });
+    }
+
+    #[gpui::test]
+    async fn test_indentation_variables(cx: &mut TestAppContext) {
+        init_test(cx);
+        cx.update(|cx| {
+            cx.update_global::<SettingsStore, _>(|store, cx| {
+                store
+                    .set_user_settings(r#"{ "tab_size": 2, "hard_tabs": true }"#, cx)
+                    .unwrap();
+            })
+        });
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree("/dir", json!({ "a.ts": "function a() { }" }))
+            .await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let worktree_id = project.update(cx, |project, cx| {
+            project.worktrees().next().unwrap().read(cx).id()
+        });
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        let buffer = project
+            .update(cx, |project, cx| {
+                project.open_buffer((worktree_id, "a.ts"), cx)
+            })
+            .await
+            .unwrap();
+        let editor = cx.new_view(|cx| Editor::for_buffer(buffer, Some(project), cx));
+        workspace.update(cx, |workspace, cx| {
+            workspace.add_item_to_center(Box::new(editor), cx);
+            let task_context = task_context(workspace, None, cx);
+            assert_eq!(
+                task_context.task_variables.get(&VariableName::TabSize),
+                Some("2")
+            );
+            assert_eq!(
+                task_context.task_variables.get(&VariableName::IndentStyle),
+                Some("tab")
+            );
+        });
+    }
+
+    #[gpui::test]
+    async fn test_selection_indent_variable(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({ "a.ts": "function a() {\n    let b = 1;\n}" }),
+        )
+        .await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let worktree_id = project.update(cx, |project, cx| {
+            project.worktrees().next().unwrap().read(cx).id()
+        });
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        let buffer = project
+            .update(cx, |project, cx| {
+                project.open_buffer((worktree_id, "a.ts"), cx)
+            })
+            .await
+            .unwrap();
+        let editor = cx.new_view(|cx| Editor::for_buffer(buffer, Some(project), cx));
+        workspace.update(cx, |workspace, cx| {
+            workspace.add_item_to_center(Box::new(editor.clone()), cx);
+            assert_eq!(
+                task_context(workspace, None, cx)
+                    .task_variables
+                    .get(&VariableName::SelectionIndent),
+                Some(""),
+                "Unindented line should have an empty indent"
+            );
+
+            editor.update(cx, |editor, cx| {
+                editor.change_selections(None, cx, |selections| selections.select_ranges([19..24]))
+            });
+            assert_eq!(
+                task_context(workspace, None, cx)
+                    .task_variables
+                    .get(&VariableName::SelectionIndent),
+                Some("    "),
+                "Indent of the line with the selection start should be captured"
+            );
+        });
+    }
+
+    #[gpui::test]
+    async fn test_git_branch_variable(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree("/dir", json!({ ".git": {}, "a.ts": "a" }))
+            .await;
+        fs.insert_tree("/other", json!({ "b.ts": "b" })).await;
+        fs.set_branch_name(Path::new("/dir/.git"), Some("feature/tags"));
+        let project = Project::test(fs.clone(), ["/dir".as_ref(), "/other".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        cx.run_until_parked();
+        let mut editors = Vec::new();
+        for path in ["/dir/a.ts", "/other/b.ts"] {
+            let (worktree, relative_path) = project
+                .update(cx, |project, cx| {
+                    project.find_local_worktree(path.as_ref(), cx)
+                })
+                .unwrap();
+            let worktree_id = worktree.update(cx, |worktree, _| worktree.id());
+            let buffer = project
+                .update(cx, |project, cx| {
+                    project.open_buffer((worktree_id, relative_path), cx)
+                })
+                .await
+                .unwrap();
+            editors.push(cx.new_view(|cx| Editor::for_buffer(buffer, Some(project.clone()), cx)));
+        }
+        let git_branch = |editor: &View<Editor>, cx: &mut VisualTestContext| {
+            workspace.update(cx, |workspace, cx| {
+                workspace.add_item_to_center(Box::new(editor.clone()), cx);
+                task_context(workspace, None, cx)
+                    .task_variables
+                    .get(&VariableName::GitBranch)
+                    .map(ToOwned::to_owned)
+            })
+        };
+
+        assert_eq!(git_branch(&editors[0], cx), Some("feature/tags".to_owned()));
+        assert_eq!(
+            git_branch(&editors[1], cx),
+            None,
+            "Variable should be unset outside of a git repository"
+        );
+
+        fs.set_branch_name(Path::new("/dir/.git"), None::<String>);
+        cx.run_until_parked();
+        assert_eq!(
+            git_branch(&editors[0], cx),
+            None,
+            "Variable should be unset for the detached HEAD"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_line_author_variable(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({ ".git": {}, "a.ts": "let a = 1;\nlet b = 2;\nlet c = 3;\n" }),
+        )
+        .await;
+        fs.insert_tree("/other", json!({ "b.ts": "b" })).await;
+        fs.set_blame_for_repo(
+            Path::new("/dir/.git"),
+            vec![(
+                Path::new("a.ts"),
+                Blame {
+                    entries: vec![
+                        BlameEntry {
+                            range: 0..1,
+                            author: Some("Alice".to_owned()),
+                            ..BlameEntry::default()
+                        },
+                        BlameEntry {
+                            range: 1..3,
+                            author: Some("Bob".to_owned()),
+                            ..BlameEntry::default()
+                        },
+                    ],
+                    ..Blame::default()
+                },
+            )],
+        );
+        let project = Project::test(fs.clone(), ["/dir".as_ref(), "/other".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        cx.run_until_parked();
+        let mut editors = Vec::new();
+        for path in ["/dir/a.ts", "/other/b.ts"] {
+            let (worktree, relative_path) = project
+                .update(cx, |project, cx| {
+                    project.find_local_worktree(path.as_ref(), cx)
+                })
+                .unwrap();
+            let worktree_id = worktree.update(cx, |worktree, _| worktree.id());
+            let buffer = project
+                .update(cx, |project, cx| {
+                    project.open_buffer((worktree_id, relative_path), cx)
+                })
+                .await
+                .unwrap();
+            editors.push(cx.new_view(|cx| Editor::for_buffer(buffer, Some(project.clone()), cx)));
+        }
+        let task = tasks_for(
+            TaskDefinitions(vec![Definition {
+                label: "review".to_owned(),
+                command: "review".to_owned(),
+                args: vec!["--author".to_owned(), "$ZED_LINE_AUTHOR".to_owned()],
+                ..Definition::default()
+            }]),
+            "test",
+        )
+        .pop()
+        .unwrap();
+        let line_author = |editor: &View<Editor>, cx: &mut VisualTestContext| {
+            let task_context = workspace.update(cx, |workspace, cx| {
+                workspace.add_item_to_center(Box::new(editor.clone()), cx);
+                task_context(workspace, None, cx)
+            });
+            task.prepare_exec(task_context)
+                .unwrap()
+                .env
+                .get("ZED_LINE_AUTHOR")
+                .cloned()
+        };
+
+        assert_eq!(line_author(&editors[0], cx), Some("Alice".to_owned()));
+        editors[0].update(cx, |editor, cx| {
+            editor.change_selections(None, cx, |selections| selections.select_ranges([15..15]))
+        });
+        assert_eq!(
+            line_author(&editors[0], cx),
+            Some("Bob".to_owned()),
+            "Author of the line with the cursor should be used"
+        );
+        assert_eq!(
+            line_author(&editors[1], cx),
+            None,
+            "Variable should be omitted outside of a git repository"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_diagnostic_count_variables(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree("/dir", json!({ "a.rs": "fn a() {}\nfn b() {}" }))
+            .await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let worktree_id = project.update(cx, |project, cx| {
+            project.worktrees().next().unwrap().read(cx).id()
+        });
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        let diagnostic = |severity, row| DiagnosticEntry {
+            range: Unclipped(PointUtf16::new(row, 0))..Unclipped(PointUtf16::new(row, 2)),
+            diagnostic: Diagnostic {
+                severity,
+                is_primary: true,
+                message: "diagnostic".to_string(),
+                ..Default::default()
+            },
+        };
+        project.update(cx, |project, cx| {
+            project
+                .update_diagnostic_entries(
+                    LanguageServerId(0),
+                    PathBuf::from("/dir/a.rs"),
+                    None,
+                    vec![
+                        diagnostic(DiagnosticSeverity::ERROR, 0),
+                        diagnostic(DiagnosticSeverity::WARNING, 1),
+                    ],
+                    cx,
+                )
+                .unwrap();
+            project
+                .update_diagnostic_entries(
+                    LanguageServerId(1),
+                    PathBuf::from("/dir/a.rs"),
+                    None,
+                    vec![diagnostic(DiagnosticSeverity::ERROR, 1)],
+                    cx,
+                )
+                .unwrap();
+        });
+        let buffer = project
+            .update(cx, |project, cx| {
+                project.open_buffer((worktree_id, "a.rs"), cx)
+            })
+            .await
+            .unwrap();
+        let editor = cx.new_view(|cx| Editor::for_buffer(buffer, Some(project), cx));
+        workspace.update(cx, |workspace, cx| {
+            workspace.add_item_to_center(Box::new(editor), cx);
+            let task_variables = task_context(workspace, None, cx).task_variables;
+            assert_eq!(
+                (
+                    task_variables.get(&VariableName::ErrorCount),
+                    task_variables.get(&VariableName::WarningCount),
+                ),
+                (Some("2"), Some("1")),
+                "Diagnostics of all language servers should be counted"
+            );
+        });
+    }
+
+    #[gpui::test]
+    async fn test_cwd_from_language_server(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree("/dir", json!({ "app": { "main.rs": "fn main() {}" } }))
+            .await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let language_registry = project.read_with(cx, |project, _| project.languages().clone());
+        language_registry.add(Arc::new(Language::new(
+            LanguageConfig {
+                name: "Rust".into(),
+                matcher: LanguageMatcher {
+                    path_suffixes: vec!["rs".to_owned()],
+                    ..LanguageMatcher::default()
+                },
+                ..LanguageConfig::default()
+            },
+            None,
+        )));
+        let mut fake_servers =
+            language_registry.register_fake_lsp_adapter("Rust", FakeLspAdapter::default());
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        workspace
+            .update(cx, |workspace, cx| {
+                workspace.open_abs_path(PathBuf::from("/dir/app/main.rs"), true, cx)
+            })
+            .await
+            .unwrap();
+        let fake_server = fake_servers.next().await.unwrap();
+        cx.run_until_parked();
+        // Fake language servers report `/` as their project root, unlike the worktree root of the buffer.
+        assert_eq!(fake_server.server.root_path(), Path::new("/"));
+
+        let cwd = |cx: &mut VisualTestContext| {
+            workspace.update(cx, |workspace, cx| task_cwd(workspace, cx).unwrap())
+        };
+        assert_eq!(
+            cwd(cx),
+            Some(PathBuf::from("/dir")),
+            "Worktree root should be used by default"
+        );
+        cx.update(|cx| {
+            cx.update_global::<SettingsStore, _>(|store, cx| {
+                store
+                    .set_user_settings(r#"{ "task": { "cwd_from_language_server": true } }"#, cx)
+                    .unwrap();
+            })
+        });
+        assert_eq!(
+            cwd(cx),
+            Some(PathBuf::from("/")),
+            "Language server root should be preferred when enabled"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_selected_text_mode(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({ "a.rs": "fn main() {\n    body();\n}\nfn other() {}" }),
+        )
+        .await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let worktree_id = project.update(cx, |project, cx| {
+            project.worktrees().next().unwrap().read(cx).id()
+        });
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        let buffer = project
+            .update(cx, |project, cx| {
+                project.open_buffer((worktree_id, "a.rs"), cx)
+            })
+            .await
+            .unwrap();
+        let editor = cx.new_view(|cx| Editor::for_buffer(buffer, Some(project), cx));
+        let selected_text = |cx: &mut VisualTestContext| {
+            workspace.update(cx, |workspace, cx| {
+                task_context(workspace, None, cx)
+                    .task_variables
+                    .get(&VariableName::SelectedText)
+                    .map(str::to_owned)
+            })
+        };
+        workspace.update(cx, |workspace, cx| {
+            workspace.add_item_to_center(Box::new(editor.clone()), cx);
+            editor.update(cx, |editor, cx| {
+                editor.fold_ranges([11..24], false, cx);
+                editor.change_selections(None, cx, |selections| selections.select_ranges([0..25]))
+            });
+        });
+
+        assert_eq!(
+            selected_text(cx).as_deref(),
+            Some("fn main() {\n    body();\n}"),
+            "Raw text should include the folded region"
+        );
+        cx.update(|cx| {
+            cx.update_global::<SettingsStore, _>(|store, cx| {
+                store
+                    .set_user_settings(r#"{ "task": { "selected_text_mode": "display" } }"#, cx)
+                    .unwrap();
+            })
+        });
+        assert_eq!(
+            selected_text(cx).as_deref(),
+            Some("fn main() {⋯}"),
+            "Display text should collapse the folded region"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_file_encoding_and_line_ending_variables(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                "unix.txt": "first\nsecond\n",
+                "windows.txt": "first\r\nsecond\r\n",
+            }),
+        )
+        .await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let worktree_id = project.update(cx, |project, cx| {
+            project.worktrees().next().unwrap().read(cx).id()
+        });
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        for (path, expected_line_ending) in [("unix.txt", "lf"), ("windows.txt", "crlf")] {
+            let buffer = project
+                .update(cx, |project, cx| {
+                    project.open_buffer((worktree_id, path), cx)
+                })
+                .await
+                .unwrap();
+            let editor = cx.new_view(|cx| Editor::for_buffer(buffer, Some(project.clone()), cx));
+            workspace.update(cx, |workspace, cx| {
+                workspace.add_item_to_center(Box::new(editor), cx);
+                let task_variables = task_context(workspace, None, cx).task_variables;
+                assert_eq!(
+                    (
+                        task_variables.get(&VariableName::FileEncoding),
+                        task_variables.get(&VariableName::LineEnding),
+                    ),
+                    (Some("utf-8"), Some(expected_line_ending)),
+                    "Unexpected encoding or line ending for {path}"
+                );
+            });
+        }
+    }
+
+    #[gpui::test]
+    async fn test_all_selected_text_variable(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree("/dir", json!({ "a.ts": "let first = 1;\nlet second = 2;" }))
+            .await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let worktree_id = project.update(cx, |project, cx| {
+            project.worktrees().next().unwrap().read(cx).id()
+        });
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        let buffer = project
+            .update(cx, |project, cx| {
+                project.open_buffer((worktree_id, "a.ts"), cx)
+            })
+            .await
+            .unwrap();
+        let editor = cx.new_view(|cx| Editor::for_buffer(buffer, Some(project), cx));
+        workspace.update(cx, |workspace, cx| {
+            workspace.add_item_to_center(Box::new(editor.clone()), cx);
+            assert_eq!(
+                task_context(workspace, None, cx)
+                    .task_variables
+                    .get(&VariableName::AllSelectedText),
+                None,
+                "Variable should be unset when nothing is selected"
+            );
+
+            editor.update(cx, |editor, cx| {
+                editor.change_selections(None, cx, |selections| {
+                    selections.select_ranges([4..9, 14..14, 19..25])
+                })
+            });
+            let task_variables = task_context(workspace, None, cx).task_variables;
+            assert_eq!(
+                task_variables.get(&VariableName::AllSelectedText),
+                Some("first\nsecond"),
+                "Non-empty selections should be joined in their order"
+            );
+            assert_eq!(
+                task_variables.get(&VariableName::SelectedText),
+                Some("second"),
+                "Newest selection should still be the selected text"
+            );
+        });
+    }
+
+    #[gpui::test]
+    async fn test_confirm_task_variables(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[{ "label": "remove file", "command": "rm", "args": ["$ZED_FILE"], "confirm": true }]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (_workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        let last_scheduled_task = |cx: &mut VisualTestContext| {
+            project.update(cx, |project, cx| {
+                project
+                    .task_inventory()
+                    .read(cx)
+                    .last_scheduled_task()
+                    .map(|(task, _)| task.name().to_string())
+            })
+        };
+
+        cx.dispatch_action(Spawn {
+            task_name: Some("remove file".to_string()),
+            omit_history: false,
+            split: None,
+        });
+        cx.run_until_parked();
+        assert!(
+            cx.has_pending_prompt(),
+            "Task with confirmation should ask before spawning"
+        );
+        cx.simulate_prompt_answer(1);
+        cx.run_until_parked();
+        assert_eq!(
+            last_scheduled_task(cx),
+            None,
+            "Cancelled task should not be spawned"
+        );
+
+        cx.dispatch_action(Spawn {
+            task_name: Some("remove file".to_string()),
+            omit_history: false,
+            split: None,
+        });
+        cx.run_until_parked();
+        cx.simulate_prompt_answer(0);
+        cx.run_until_parked();
+        assert_eq!(last_scheduled_task(cx), Some("remove file".to_string()));
+    }
+
+    #[gpui::test]
+    async fn test_last_task_command(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[{ "label": "list root", "command": "ls", "args": ["-la", "$ZED_FOCUSED_VIEW"] }]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        cx.run_until_parked();
+        assert_eq!(
+            workspace.update(cx, |workspace, cx| last_task_command(workspace, cx)),
+            None,
+            "No command should be reported before any task runs"
+        );
+
+        cx.dispatch_action(Spawn {
+            task_name: Some("list root".to_string()),
+            omit_history: false,
+            split: None,
+        });
+        cx.run_until_parked();
+        assert_eq!(
+            workspace.update(cx, |workspace, cx| last_task_command(workspace, cx)),
+            Some("ls -la none".to_string()),
+            "Last run command should have its variables resolved"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_default_reveal(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[
+                        { "label": "default reveal", "command": "echo" },
+                        { "label": "never reveal", "command": "echo", "reveal": "never" }
+                    ]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        let spawned_reveals = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let spawned_reveals = spawned_reveals.clone();
+            cx.subscribe(&workspace, move |_, event, _| {
+                if let workspace::Event::SpawnTask(spawn_in_terminal) = event {
+                    spawned_reveals.borrow_mut().push(spawn_in_terminal.reveal);
+                }
+            })
+            .detach();
+        });
+
+        for (setting, expected_reveal) in [
+            ("focus", RevealStrategy::Always),
+            ("no_focus", RevealStrategy::NoFocus),
+            ("never", RevealStrategy::Never),
+        ] {
+            cx.update(|cx| {
+                cx.update_global::<SettingsStore, _>(|store, cx| {
+                    store
+                        .set_user_settings(
+                            &format!(r#"{{ "task": {{ "default_reveal": "{setting}", "duplicate_spawn_window_ms": 0 }} }}"#),
+                            cx,
+                        )
+                        .unwrap();
+                })
+            });
+            for task_name in ["default reveal", "never reveal"] {
+                cx.dispatch_action(Spawn {
+                    task_name: Some(task_name.to_string()),
+                    omit_history: false,
+                    split: None,
+                });
+                cx.run_until_parked();
+            }
+            assert_eq!(
+                spawned_reveals.borrow_mut().drain(..).collect::<Vec<_>>(),
+                vec![Some(expected_reveal), Some(RevealStrategy::Never)],
+                "Default reveal setting {setting} should apply only to the tasks that do not override it"
+            );
+        }
+    }
+
+    #[gpui::test]
+    async fn test_rerun_verbose(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[{ "label": "test", "command": "cargo", "args": ["test", "--", "--nocapture"] }]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        let spawned_args = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let spawned_args = spawned_args.clone();
+            cx.subscribe(&workspace, move |_, event, _| {
+                if let workspace::Event::SpawnTask(spawn_in_terminal) = event {
+                    spawned_args
+                        .borrow_mut()
+                        .push(spawn_in_terminal.args.clone());
+                }
+            })
+            .detach();
+        });
+
+        cx.dispatch_action(Spawn {
+            task_name: Some("test".to_string()),
+            omit_history: false,
+            split: None,
+        });
+        cx.run_until_parked();
+        cx.dispatch_action(RerunVerbose);
+        cx.run_until_parked();
+        cx.dispatch_action(Rerun::default());
+        cx.run_until_parked();
+        assert_eq!(
+            spawned_args.borrow_mut().drain(..).collect::<Vec<_>>(),
+            vec![
+                vec!["test", "--", "--nocapture"],
+                vec!["test", "-v", "--", "--nocapture"],
+                vec!["test", "--", "--nocapture"],
+            ],
+            "Verbose rerun should add the flag for cargo, without changing the task for the regular reruns"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_max_revealed_terminals(cx: &mut TestAppContext) {
+        cx.executor().allow_parking();
+        init_test(cx);
+        cx.update(terminal::init);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[{ "label": "build", "command": "echo" }]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        let spawned_reveals = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let spawned_reveals = spawned_reveals.clone();
+            cx.subscribe(&workspace, move |_, event, _| {
+                if let workspace::Event::SpawnTask(spawn_in_terminal) = event {
+                    spawned_reveals.borrow_mut().push(spawn_in_terminal.reveal);
+                }
+            })
+            .detach();
+        });
+        let window = workspace.update(cx, |_, cx| cx.window_handle());
+        let mut running_terminals = Vec::new();
+        for (label, reveal) in [
+            ("server", RevealStrategy::Always),
+            ("watcher", RevealStrategy::NoFocus),
+            ("background", RevealStrategy::Never),
+        ] {
+            let spawn_task = terminal::SpawnTask {
+                id: TaskId(label.to_owned()),
+                label: label.to_owned(),
+                group: None,
+                singleton_group: None,
+                show_summary: false,
+                problem_parser: None,
+                command: "sleep".to_owned(),
+                args: vec!["30".to_owned()],
+                env: HashMap::default(),
+                reveal,
+            };
+            running_terminals.push(
+                project
+                    .update(cx, |project, cx| {
+                        project.create_terminal(None, Some(spawn_task), window, cx)
+                    })
+                    .unwrap(),
+            );
+        }
+
+        for (max_revealed_terminals, expected_reveal) in [
+            (0, RevealStrategy::Always),
+            (3, RevealStrategy::Always),
+            (2, RevealStrategy::Never),
+            (1, RevealStrategy::Never),
+        ] {
+            cx.update(|cx| {
+                cx.update_global::<SettingsStore, _>(|store, cx| {
+                    store
+                        .set_user_settings(
+                            &format!(
+                                r#"{{ "task": {{ "max_revealed_terminals": {max_revealed_terminals}, "duplicate_spawn_window_ms": 0 }} }}"#
+                            ),
+                            cx,
+                        )
+                        .unwrap();
+                })
+            });
+            cx.dispatch_action(Spawn {
+                task_name: Some("build".to_string()),
+                omit_history: true,
+                split: None,
+            });
+            cx.run_until_parked();
+            assert_eq!(
+                spawned_reveals.borrow_mut().drain(..).collect::<Vec<_>>(),
+                vec![Some(expected_reveal)],
+                "With 2 revealed task terminals running and the limit of {max_revealed_terminals}, unexpected reveal of the new task"
+            );
+        }
+    }
+
+    #[gpui::test]
+    async fn test_cancel_tasks(cx: &mut TestAppContext) {
+        cx.executor().allow_parking();
+        init_test(cx);
+        cx.update(terminal::init);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree("/dir", json!({ "a.ts": "a" })).await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        let window = workspace.update(cx, |_, cx| cx.window_handle());
+        let terminals = ["server", "watcher", "tests"]
+            .into_iter()
+            .map(|label| {
+                let spawn_task = terminal::SpawnTask {
+                    id: TaskId(label.to_owned()),
+                    label: label.to_owned(),
+                    group: None,
+                    singleton_group: None,
+                    show_summary: false,
+                    problem_parser: None,
+                    command: "sleep".to_owned(),
+                    args: vec!["30".to_owned()],
+                    env: HashMap::default(),
+                    reveal: RevealStrategy::Never,
+                };
+                project
+                    .update(cx, |project, cx| {
+                        project.create_terminal(None, Some(spawn_task), window, cx)
+                    })
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let running_tasks = |cx: &mut VisualTestContext| {
+            project.update(cx, |project, cx| {
+                project
+                    .task_inventory()
+                    .read(cx)
+                    .running_task_terminals(cx)
+                    .into_iter()
+                    .map(|(id, _)| id.0)
+                    .collect::<Vec<_>>()
+            })
+        };
+        let wait_for_stopped = |terminals: &[Model<Terminal>], cx: &mut VisualTestContext| {
+            for _ in 0..500 {
+                cx.run_until_parked();
+                let stopped = terminals.iter().all(|terminal| {
+                    cx.read(|cx| terminal.read(cx).task().unwrap().status != TaskStatus::Running)
+                });
+                if stopped {
+                    return;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            panic!("Cancelled tasks did not stop");
+        };
+        assert_eq!(running_tasks(cx), vec!["server", "watcher", "tests"]);
+
+        cx.dispatch_action(CancelTask);
+        wait_for_stopped(&terminals[2..], cx);
+        assert_eq!(
+            running_tasks(cx),
+            vec!["server", "watcher"],
+            "Only the most recently spawned task should be cancelled"
+        );
+
+        cx.dispatch_action(CancelAllTasks);
+        wait_for_stopped(&terminals, cx);
+        assert_eq!(running_tasks(cx), Vec::<String>::new());
+    }
+
+    #[gpui::test]
+    async fn test_rerun_last_failed_task(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[
+                        { "label": "build", "command": "make", "args": ["build"] },
+                        { "label": "lint", "command": "make", "args": ["lint"] }
+                    ]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        let spawned_tasks = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let spawned_tasks = spawned_tasks.clone();
+            cx.subscribe(&workspace, move |_, event, _| {
+                if let workspace::Event::SpawnTask(spawn_in_terminal) = event {
+                    spawned_tasks
+                        .borrow_mut()
+                        .push(spawn_in_terminal.label.clone());
+                }
+            })
+            .detach();
+        });
+        cx.run_until_parked();
+
+        cx.dispatch_action(RerunLastFailed);
+        cx.run_until_parked();
+        assert!(
+            spawned_tasks.borrow().is_empty(),
+            "Nothing should be rerun, if no task has failed"
+        );
+        workspace.update(cx, |workspace, cx| {
+            workspace
+                .active_modal::<TasksModal>(cx)
+                .expect("Tasks modal should be opened instead")
+                .update(cx, |_, cx| cx.emit(DismissEvent));
+        });
+
+        for task_name in ["build", "lint"] {
+            cx.dispatch_action(Spawn {
+                task_name: Some(task_name.to_string()),
+                omit_history: false,
+                split: None,
+            });
+            cx.run_until_parked();
+        }
+        project.update(cx, |project, cx| {
+            project.task_inventory().update(cx, |inventory, cx| {
+                let build_id = inventory.recently_scheduled_tasks(2)[1].task.id().clone();
+                let lint_id = inventory.recently_scheduled_tasks(2)[0].task.id().clone();
+                inventory.task_completed(build_id, 1, cx);
+                inventory.task_completed(lint_id, 0, cx);
+            })
+        });
+        spawned_tasks.borrow_mut().clear();
+        cx.dispatch_action(RerunLastFailed);
+        cx.run_until_parked();
+        assert_eq!(
+            *spawned_tasks.borrow(),
+            vec!["build".to_owned()],
+            "The failed task should be rerun, skipping the succeeded one scheduled after it"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_task_defined_cwd(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[
+                        { "label": "server", "command": "npm", "args": ["start"], "cwd": "packages/server" },
+                        { "label": "client", "command": "npm", "args": ["start"], "cwd": "packages/client" }
+                    ]"#,
+                },
+                "packages": {
+                    "server": { "package.json": "{}" },
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        let spawned_tasks = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let spawned_tasks = spawned_tasks.clone();
+            cx.subscribe(&workspace, move |_, event, _| {
+                if let workspace::Event::SpawnTask(spawn_in_terminal) = event {
+                    spawned_tasks.borrow_mut().push((
+                        spawn_in_terminal.label.clone(),
+                        spawn_in_terminal.cwd.clone(),
+                    ));
+                }
+            })
+            .detach();
+        });
+        cx.run_until_parked();
+
+        for task_name in ["server", "client"] {
+            cx.dispatch_action(Spawn {
+                task_name: Some(task_name.to_string()),
+                omit_history: false,
+                split: None,
+            });
+            cx.run_until_parked();
+        }
+        assert_eq!(
+            spawned_tasks.borrow().as_slice(),
+            &[(
+                "server".to_owned(),
+                Some(PathBuf::from("/dir/packages/server"))
+            )],
+            "Relative cwd should be resolved against the worktree, and the task with a missing cwd should not be spawned"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_task_cwd_revealed_in_project_panel(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[
+                        { "label": "server", "command": "npm", "args": ["start"], "cwd": "packages/server", "reveal_cwd_in_project_panel": true },
+                        { "label": "client", "command": "npm", "args": ["start"], "cwd": "packages/client" },
+                        { "label": "outside", "command": "ls", "cwd": "/outside", "reveal_cwd_in_project_panel": true }
+                    ]"#,
+                },
+                "packages": {
+                    "server": { "package.json": "{}" },
+                    "client": { "package.json": "{}" },
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        fs.insert_tree("/outside", json!({ "b.txt": "b" })).await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let revealed_entries = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let revealed_entries = revealed_entries.clone();
+            cx.subscribe(&project, move |_, event, _| {
+                if let project::Event::RevealInProjectPanel(entry_id) = event {
+                    revealed_entries.borrow_mut().push(*entry_id);
+                }
+            })
+            .detach();
+        });
+        let (_, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        cx.run_until_parked();
+
+        for task_name in ["server", "client", "outside"] {
+            cx.dispatch_action(Spawn {
+                task_name: Some(task_name.to_string()),
+                omit_history: false,
+                split: None,
+            });
+            cx.run_until_parked();
+        }
+        let server_entry = project.update(cx, |project, cx| {
+            project
+                .worktrees()
+                .next()
+                .unwrap()
+                .read(cx)
+                .entry_for_path("packages/server")
+                .unwrap()
+                .id
+        });
+        assert_eq!(
+            *revealed_entries.borrow(),
+            vec![server_entry],
+            "Only the resolved cwd of the task asking for it, within the worktree, should be revealed"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_task_built_from_code(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree("/dir", json!({ "a.ts": "a" })).await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let task = TaskBuilder::new("lint")
+            .command("eslint")
+            .args(["--fix", "."])
+            .env("NODE_ENV", "development")
+            .build("test extension");
+        project.update(cx, |project, cx| {
+            project.task_inventory().update(cx, |inventory, cx| {
+                inventory.add_source(
+                    TaskSourceKind::InMemory {
+                        name: "test extension".into(),
+                    },
+                    |cx| InMemorySource::new(vec![task], cx),
+                    cx,
+                )
+            })
+        });
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        let spawned_tasks = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let spawned_tasks = spawned_tasks.clone();
+            cx.subscribe(&workspace, move |_, event, _| {
+                if let workspace::Event::SpawnTask(spawn_in_terminal) = event {
+                    spawned_tasks.borrow_mut().push(spawn_in_terminal.clone());
+                }
+            })
+            .detach();
+        });
+        cx.run_until_parked();
+
+        cx.dispatch_action(Spawn {
+            task_name: Some("lint".to_string()),
+            omit_history: false,
+            split: None,
+        });
+        cx.run_until_parked();
+        let spawned_tasks = spawned_tasks.borrow();
+        assert_eq!(
+            spawned_tasks.len(),
+            1,
+            "Task built from code should be spawned"
+        );
+        assert_eq!(spawned_tasks[0].label, "lint");
+        assert_eq!(spawned_tasks[0].command, "eslint");
+        assert_eq!(spawned_tasks[0].args, vec!["--fix", "."]);
+        assert_eq!(
+            spawned_tasks[0].env.get("NODE_ENV").map(String::as_str),
+            Some("development")
+        );
+    }
+
+    #[gpui::test]
+    async fn test_spawn_task_by_loose_name(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[
+                        { "label": "Run Tests", "command": "make", "args": ["test"] },
+                        { "label": "build", "command": "make", "args": ["build"] },
+                        { "label": "Build", "command": "cargo", "args": ["build"] },
+                        { "label": "lint", "command": "cargo", "args": ["clippy"] }
+                    ]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        let spawned_tasks = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let spawned_tasks = spawned_tasks.clone();
+            cx.subscribe(&workspace, move |_, event, _| {
+                if let workspace::Event::SpawnTask(spawn_in_terminal) = event {
+                    spawned_tasks
+                        .borrow_mut()
+                        .push(spawn_in_terminal.label.clone());
+                }
+            })
+            .detach();
+        });
+        cx.run_until_parked();
+
+        for task_name in ["run tests", "Build", "build", "cargo clippy", "missing"] {
+            cx.dispatch_action(Spawn {
+                task_name: Some(task_name.to_string()),
+                omit_history: false,
+                split: None,
+            });
+            cx.run_until_parked();
+        }
+        assert_eq!(
+            *spawned_tasks.borrow(),
+            vec!["Run Tests", "Build", "build", "lint"],
+            "Labels should match regardless of their case, preferring the exact match, and by the command otherwise"
+        );
+        workspace.update(cx, |workspace, cx| {
+            assert!(
+                workspace.active_modal::<TasksModal>(cx).is_some(),
+                "Tasks modal should be opened only for the name nothing matches"
+            );
+        });
+    }
+
+    #[gpui::test]
+    async fn test_task_dock(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[
+                        { "label": "test", "command": "cargo", "args": ["test"], "dock": "right" },
+                        { "label": "build", "command": "cargo", "args": ["build"] }
+                    ]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        let spawned_docks = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let spawned_docks = spawned_docks.clone();
+            cx.subscribe(&workspace, move |_, event, _| {
+                if let workspace::Event::SpawnTask(spawn_in_terminal) = event {
+                    spawned_docks.borrow_mut().push(spawn_in_terminal.dock);
+                }
+            })
+            .detach();
+        });
+
+        for task_name in ["test", "build"] {
+            cx.dispatch_action(Spawn {
+                task_name: Some(task_name.to_string()),
+                omit_history: false,
+                split: None,
+            });
+            cx.run_until_parked();
+        }
+        assert_eq!(
+            spawned_docks.borrow().as_slice(),
+            &[Some(TaskDockPosition::Right), None],
+            "Only the task pinned to a dock should request it, the rest should use the terminal settings"
+        );
+    }
+
+    #[cfg(not(windows))]
+    #[gpui::test]
+    async fn test_staged_files_variable(cx: &mut TestAppContext) {
+        init_test(cx);
+        cx.executor().allow_parking();
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".git": {},
+                "a.ts": "a",
+                "src": { "b.ts": "b", "c.ts": "c" },
+            }),
+        )
+        .await;
+        fs.insert_tree("/other", json!({ "d.ts": "d" })).await;
+        let project = Project::test(fs.clone(), ["/dir".as_ref(), "/other".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        cx.run_until_parked();
+        let task = tasks_for(
+            TaskDefinitions(vec![Definition {
+                label: "lint staged".to_owned(),
+                command: "printf".to_owned(),
+                args: vec!["'%s|'".to_owned(), "$ZED_STAGED_FILES".to_owned()],
+                ..Definition::default()
+            }]),
+            "test",
+        )
+        .pop()
+        .unwrap();
+        let staged_files_env = |cwd: &str, cx: &mut VisualTestContext| {
+            let task_context = workspace.update(cx, |workspace, cx| {
+                task_context(workspace, Some(cwd.into()), cx)
+            });
+            let spawn_in_terminal = task.prepare_exec(task_context).unwrap();
+            let staged_files = spawn_in_terminal.env.get("ZED_STAGED_FILES").cloned();
+            (staged_files, spawn_in_terminal)
+        };
+
+        assert_eq!(
+            staged_files_env("/dir", cx).0,
+            None,
+            "Variable should be omitted when nothing is staged"
+        );
+        assert_eq!(
+            staged_files_env("/other", cx).0,
+            None,
+            "Variable should be omitted outside of a git repository"
+        );
+
+        fs.set_status_for_repo_via_git_operation(
+            Path::new("/dir/.git"),
+            &[
+                (Path::new("a.ts"), GitFileStatus::Modified),
+                (Path::new("src/c.ts"), GitFileStatus::Added),
+            ],
+        );
+        cx.run_until_parked();
+        let (staged_files, spawn_in_terminal) = staged_files_env("/dir", cx);
+        assert_eq!(staged_files.as_deref(), Some("/dir/a.ts\n/dir/src/c.ts"));
+        assert_eq!(
+            capture_stdout(("sh".to_owned(), vec!["-c".to_owned()]), spawn_in_terminal)
+                .await
+                .unwrap(),
+            "/dir/a.ts|/dir/src/c.ts|",
+            "Unquoted variable should expand into an argument per staged file"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_duplicate_spawns_are_ignored(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[
+                        { "label": "build", "command": "cargo", "args": ["build"] },
+                        { "label": "check", "command": "cargo", "args": ["check"] }
+                    ]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        cx.update(|cx| {
+            cx.update_global::<SettingsStore, _>(|store, cx| {
+                store
+                    .set_user_settings(r#"{ "task": { "duplicate_spawn_window_ms": 500 } }"#, cx)
+                    .unwrap();
+            })
+        });
+        let spawned_labels = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let spawned_labels = spawned_labels.clone();
+            cx.subscribe(&workspace, move |_, event, _| {
+                if let workspace::Event::SpawnTask(spawn_in_terminal) = event {
+                    spawned_labels
+                        .borrow_mut()
+                        .push(spawn_in_terminal.label.clone());
+                }
+            })
+            .detach();
+        });
+        let spawn = |task_name: &str, cx: &mut VisualTestContext| {
+            cx.dispatch_action(Spawn {
+                task_name: Some(task_name.to_string()),
+                omit_history: false,
+                split: None,
+            });
+            cx.run_until_parked();
+        };
+
+        spawn("build", cx);
+        spawn("build", cx);
+        spawn("check", cx);
+        assert_eq!(
+            spawned_labels.borrow_mut().drain(..).collect::<Vec<_>>(),
+            vec!["build".to_string(), "check".to_string()],
+            "A rapid double spawn should run the task once, other commands should not be affected"
+        );
+
+        cx.executor().advance_clock(Duration::from_millis(500));
+        cx.run_until_parked();
+        spawn("build", cx);
+        assert_eq!(
+            spawned_labels.borrow_mut().drain(..).collect::<Vec<_>>(),
+            vec!["build".to_string()],
+            "After the window passes, the same task should be spawned again"
+        );
+    }
+
+    pub(crate) struct FakeSecretStore(pub(crate) HashMap<String, String>);
+
+    impl SecretStore for FakeSecretStore {
+        fn read_secret(
+            &self,
+            key: &str,
+            _: &AppContext,
+        ) -> gpui::Task<anyhow::Result<Option<String>>> {
+            gpui::Task::ready(Ok(self.0.get(key).cloned()))
+        }
+    }
+
+    #[gpui::test]
+    async fn test_missing_required_tools(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[{ "label": "deploy", "command": "zed-test-missing-tool", "requires": ["sh", "zed-test-missing-tool"] }]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (_workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+
+        assert_eq!(
+            missing_tools(&["sh".to_owned(), "zed-test-missing-tool".to_owned()]),
+            vec!["zed-test-missing-tool"],
+        );
+        cx.dispatch_action(Spawn {
+            task_name: Some("deploy".to_string()),
+            omit_history: false,
+            split: None,
+        });
+        cx.run_until_parked();
+        let last_scheduled_task = project.update(cx, |project, cx| {
+            project.task_inventory().read(cx).last_scheduled_task()
+        });
+        assert!(
+            last_scheduled_task.is_none(),
+            "Task with missing tools should not be spawned"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_spawn_task_without_history(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[
+                        { "label": "build", "command": "make", "args": ["build"] },
+                        { "label": "test", "command": "make", "args": ["test"] }
+                    ]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        let spawned_tasks = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let spawned_tasks = spawned_tasks.clone();
+            cx.subscribe(&workspace, move |_, event, _| {
+                if let workspace::Event::SpawnTask(spawn_in_terminal) = event {
+                    spawned_tasks
+                        .borrow_mut()
+                        .push(spawn_in_terminal.label.clone());
+                }
+            })
+            .detach();
+        });
+        cx.run_until_parked();
+        let last_scheduled_task_name = |cx: &mut VisualTestContext| {
+            project.update(cx, |project, cx| {
+                project
+                    .task_inventory()
+                    .read(cx)
+                    .last_scheduled_task()
+                    .map(|(task, _)| task.name().to_owned())
+            })
+        };
+        let task_named = |name: &str, cx: &mut VisualTestContext| {
+            project.update(cx, |project, cx| {
+                project.task_inventory().update(cx, |inventory, cx| {
+                    inventory
+                        .list_tasks(None, None, false, cx)
+                        .into_iter()
+                        .find(|(_, task)| task.name() == name)
+                        .unwrap()
+                        .1
+                })
+            })
+        };
+
+        let build_task = task_named("build", cx);
+        workspace.update(cx, |workspace, cx| {
+            spawn_task(workspace, &build_task, TaskContext::default(), false, cx)
+        });
+        cx.run_until_parked();
+        let test_task = task_named("test", cx);
+        workspace.update(cx, |workspace, cx| {
+            spawn_task(workspace, &test_task, TaskContext::default(), true, cx)
+        });
+        cx.run_until_parked();
+        assert_eq!(*spawned_tasks.borrow(), vec!["build", "test"]);
+        assert_eq!(
+            last_scheduled_task_name(cx),
+            Some("build".to_owned()),
+            "Task spawned without the history should not become the last scheduled one"
+        );
+    }
+
+    #[test]
+    fn test_variables_summary() {
+        let env = HashMap::from_iter([
+            (VariableName::Row.to_string(), "3".to_string()),
+            (VariableName::File.to_string(), "/dir/a.ts".to_string()),
+            (
+                VariableName::Custom("KEY".into()).to_string(),
+                "value".to_string(),
+            ),
+            ("RUST_LOG".to_string(), "info".to_string()),
+        ]);
+        assert_eq!(
+            variables_summary(&env),
+            "ZED_FILE: /dir/a.ts\nZED_KEY: value\nZED_ROW: 3",
+            "Only the task variables of the env should be listed"
+        );
+    }
+
+    #[cfg(not(windows))]
+    #[gpui::test]
+    async fn test_selection_as_script(cx: &mut TestAppContext) {
+        cx.executor().allow_parking();
+        let script = "echo scratch $((1 + 2))";
+        let definition = script_task_definition(script, None);
+        assert_eq!(definition.command, "sh");
+        let script_path = definition.args[1]
+            .split_once("> ")
+            .and_then(|(_, rest)| rest.split_once(" &&"))
+            .map(|(path, _)| PathBuf::from(path.trim_matches('\'')))
+            .unwrap();
+        assert!(
+            !script_path.exists(),
+            "Temporary script should only be written when the task is spawned"
+        );
+
+        let task = tasks_for(TaskDefinitions(vec![definition]), "test")
+            .pop()
+            .unwrap();
+        let spawn_in_terminal = task
+            .prepare_exec(TaskContext {
+                task_variables: TaskVariables::from_iter([(
+                    VariableName::SelectedText,
+                    script.to_owned(),
+                )]),
+                ..TaskContext::default()
+            })
+            .unwrap();
+        assert_eq!(
+            capture_stdout(("sh".to_owned(), vec!["-c".to_owned()]), spawn_in_terminal)
+                .await
+                .unwrap(),
+            "scratch 3"
+        );
+        assert!(
+            !script_path.exists(),
+            "Temporary script should be removed after the run"
+        );
+
+        let python = Language::new(
+            LanguageConfig {
+                name: "Python".into(),
+                ..LanguageConfig::default()
+            },
+            None,
+        );
+        let python_definition = script_task_definition("print(1)", Some(&python));
+        assert!(python_definition.args[1].contains("&& python3 "));
+        assert!(python_definition.args[1].contains(".py"));
+        let shebang_definition =
+            script_task_definition("#!/usr/bin/env bash\necho 1", Some(&python));
+        assert!(
+            shebang_definition.args[1].contains("&& /usr/bin/env bash "),
+            "Shebang should take precedence over the language"
+        );
+    }
+
+    #[test]
+    fn test_view_name() {
+        assert_eq!(view_name("Editor"), "editor");
+        assert_eq!(view_name("TerminalPanel"), "terminal_panel");
+        assert_eq!(view_name("Project Panel"), "project_panel");
+    }
+
+    #[gpui::test]
+    async fn test_parallel_tasks(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[
+                        { "label": "backend", "command": "cargo", "args": ["run"] },
+                        { "label": "frontend", "command": "npm", "args": ["run", "watch"] },
+                        { "label": "tailwind", "command": "npx", "args": ["tailwindcss", "--watch"] },
+                        { "label": "styles", "parallel": true, "depends_on": ["tailwind"] },
+                        { "label": "dev", "parallel": true, "depends_on": ["backend", "frontend", "styles"] },
+                        { "label": "loop", "parallel": true, "depends_on": ["backend", "loop"] }
+                    ]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        let spawned_tasks = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let spawned_tasks = spawned_tasks.clone();
+            cx.subscribe(&workspace, move |_, event, _| {
+                if let workspace::Event::SpawnTask(spawn_in_terminal) = event {
+                    spawned_tasks.borrow_mut().push((
+                        spawn_in_terminal.label.clone(),
+                        spawn_in_terminal.terminal_group.clone(),
+                    ));
+                }
+            })
+            .detach();
+        });
+        cx.run_until_parked();
+
+        cx.dispatch_action(Spawn {
+            task_name: Some("dev".to_string()),
+            omit_history: false,
+            split: None,
+        });
+        cx.run_until_parked();
+        assert_eq!(
+            spawned_tasks.borrow_mut().drain(..).collect::<Vec<_>>(),
+            ["backend", "frontend", "tailwind"]
+                .map(|label| (label.to_owned(), Some("dev".to_owned())))
+                .to_vec(),
+            "Every task should be spawned separately, including the ones of the nested parallel task, grouped under the terminal tab of the task spawned"
+        );
+        let last_scheduled_task_name = |cx: &mut VisualTestContext| {
+            project.update(cx, |project, cx| {
+                project
+                    .task_inventory()
+                    .read(cx)
+                    .last_scheduled_task()
+                    .map(|(task, _)| task.name().to_owned())
+            })
+        };
+        assert_eq!(
+            last_scheduled_task_name(cx),
+            Some("dev".to_owned()),
+            "Parallel task should be rerun as a whole"
+        );
+
+        cx.dispatch_action(Spawn {
+            task_name: Some("loop".to_string()),
+            omit_history: false,
+            split: None,
+        });
+        cx.run_until_parked();
+        assert!(
+            spawned_tasks.borrow().is_empty(),
+            "Nothing should be spawned for the parallel tasks that form a cycle"
+        );
+        assert_eq!(last_scheduled_task_name(cx), Some("dev".to_owned()));
+    }
+
+    #[test]
+    fn test_task_dependencies() {
+        let definition = |label: &str, depends_on: &[&str]| Definition {
+            label: label.to_owned(),
+            command: "make".to_owned(),
+            args: vec![label.to_owned()],
+            depends_on: depends_on.iter().map(|label| label.to_string()).collect(),
+            ..Definition::default()
+        };
+        let tasks = tasks_for(
+            TaskDefinitions(vec![
+                definition("build", &[]),
+                definition("generate", &[]),
+                definition("lint", &["generate"]),
+                definition("test", &["build", "lint", "generate"]),
+                definition("a", &["b"]),
+                definition("b", &["a"]),
+                definition("deploy", &["missing"]),
+            ]),
+            "test",
+        )
+        .into_iter()
+        .map(|task| (TaskSourceKind::UserInput, task))
+        .collect::<Vec<_>>();
+        let dependencies = |task_name: &str| {
+            let (_, task) = tasks
+                .iter()
+                .find(|(_, task)| task.name() == task_name)
+                .unwrap();
+            let spawn_in_terminal = task.prepare_exec(TaskContext::default()).unwrap();
+            task_dependencies(
+                &tasks,
+                task_name,
+                &spawn_in_terminal.depends_on,
+                &TaskContext::default(),
+            )
+            .map(|dependencies| {
+                dependencies
+                    .into_iter()
+                    .map(|dependency| dependency.name().to_owned())
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        assert_eq!(dependencies("build"), Ok(Vec::new()));
+        assert_eq!(
+            dependencies("test"),
+            Ok(vec![
+                "build".to_owned(),
+                "generate".to_owned(),
+                "lint".to_owned()
+            ]),
+            "Dependencies should go after their own dependencies, each run once"
+        );
+        assert_eq!(
+            dependencies("a"),
+            Err(r#"Task dependencies form a cycle: "a" -> "b" -> "a""#.to_owned())
+        );
+        assert_eq!(
+            dependencies("deploy"),
+            Err(r#"Task "missing" that "deploy" depends on is not found"#.to_owned())
+        );
+    }
+
+    #[cfg(not(windows))]
+    #[gpui::test]
+    async fn test_dependencies_prepared_as_tasks(cx: &mut TestAppContext) {
+        init_test(cx);
+        cx.executor().allow_parking();
+        cx.update(|cx| {
+            cx.set_global(TaskSecrets(Arc::new(FakeSecretStore(HashMap::from_iter(
+                [("deploy-token".to_string(), "s3cr3t".to_string())],
+            )))))
+        });
+        let fs = FakeFs::new(cx.executor());
+        // Substituted commands are run in the task's cwd, so the project has to be in an existing directory.
+        let dir = std::env::temp_dir();
+        fs.insert_tree(
+            &dir,
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[
+                        {
+                            "label": "login",
+                            "command": "login",
+                            "args": ["$(echo v1)"],
+                            "env": { "TOKEN": "secret:deploy-token" },
+                            "command_substitution": true
+                        },
+                        { "label": "install", "command": "install", "requires": ["zed-test-missing-tool"] },
+                        { "label": "deploy", "command": "deploy", "depends_on": ["login"] },
+                        { "label": "release", "command": "release", "depends_on": ["install"] }
+                    ]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        let project = Project::test(fs, [dir.as_path()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        let spawned_tasks = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let spawned_tasks = spawned_tasks.clone();
+            cx.subscribe(&workspace, move |_, event, _| {
+                if let workspace::Event::SpawnTask(spawn_in_terminal) = event {
+                    spawned_tasks.borrow_mut().push(spawn_in_terminal.clone());
+                }
+            })
+            .detach();
+        });
+        cx.run_until_parked();
+
+        for task_name in ["deploy", "release"] {
+            cx.dispatch_action(Spawn {
+                task_name: Some(task_name.to_string()),
+                omit_history: false,
+                split: None,
+            });
+            cx.run_until_parked();
+        }
+        let spawned_tasks = spawned_tasks.borrow_mut().drain(..).collect::<Vec<_>>();
+        assert_eq!(
+            spawned_tasks
+                .iter()
+                .map(|spawned_task| spawned_task.label.as_str())
+                .collect::<Vec<_>>(),
+            vec!["deploy"],
+            "Task should not be spawned when its dependency misses the tools it requires"
+        );
+        let command_line = spawned_tasks[0].args.join(" ");
+        assert!(
+            command_line.contains("login v1") && !command_line.contains("s3cr3t"),
+            "Dependency should get its command substituted and its secret kept off the command line, got: {command_line}"
+        );
+        assert!(
+            spawned_tasks[0].env.values().any(|value| value == "s3cr3t"),
+            "Dependency secret should be passed through the env"
+        );
+    }
+
+    #[test]
+    fn test_tasks_check_report() {
+        let tasks = tasks_for(
+            TaskDefinitions(vec![
+                Definition {
+                    label: "script".to_owned(),
+                    command: "sh".to_owned(),
+                    args: vec!["-c".to_owned(), "echo $ZED_FILE".to_owned()],
+                    requires: vec!["sh".to_owned()],
+                    ..Definition::default()
+                },
+                Definition {
+                    label: "deploy".to_owned(),
+                    command: "sh".to_owned(),
+                    args: vec!["deploy.sh".to_owned()],
+                    requires: vec!["zed-test-missing-tool".to_owned()],
+                    ..Definition::default()
+                },
+                Definition {
+                    label: "test symbol".to_owned(),
+                    command: "zed-test-missing-runner".to_owned(),
+                    args: vec!["$ZED_SYMBOL".to_owned()],
+                    ..Definition::default()
+                },
+            ]),
+            "test",
+        )
+        .into_iter()
+        .map(|task| (TaskSourceKind::UserInput, task))
+        .collect::<Vec<_>>();
+        let task_context = TaskContext {
+            cwd: None,
+            task_variables: TaskVariables::from_iter([(
+                VariableName::File,
+                "/dir/a.ts".to_string(),
+            )]),
+            selection_fallbacks: SelectionFallbacks::default(),
+            args_override: Default::default(),
+        };
+        assert_eq!(
+            tasks_check_report(&tasks, &task_context),
+            "| Task | Result | Details |\n\
+             | --- | --- | --- |\n\
+             | script | pass | sh -c echo /dir/a.ts |\n\
+             | deploy | fail | missing tools: zed-test-missing-tool |\n\
+             | test symbol | fail | missing variables: ZED_SYMBOL; missing tools: zed-test-missing-runner |"
+        );
+    }
+
+    #[test]
+    fn test_tasks_overview() {
+        let tasks = tasks_for(
+            TaskDefinitions(vec![
+                Definition {
+                    label: "lint file".to_owned(),
+                    command: "eslint".to_owned(),
+                    args: vec!["$ZED_FILE".to_owned()],
+                    ..Definition::default()
+                },
+                Definition {
+                    label: "test symbol".to_owned(),
+                    command: "cargo".to_owned(),
+                    args: vec!["test".to_owned(), "$ZED_SYMBOL".to_owned()],
+                    ..Definition::default()
+                },
+            ]),
+            "test",
+        )
+        .into_iter()
+        .map(|task| (TaskSourceKind::UserInput, task))
+        .collect::<Vec<_>>();
+        let task_context = TaskContext {
+            cwd: Some("/dir".into()),
+            task_variables: TaskVariables::from_iter([(
+                VariableName::File,
+                "/dir/a.ts".to_string(),
+            )]),
+            selection_fallbacks: SelectionFallbacks::default(),
+            args_override: Default::default(),
+        };
+        assert_eq!(
+            tasks_overview(&tasks, &task_context),
+            "lint file → eslint /dir/a.ts\ntest symbol → <unresolved: missing ZED_SYMBOL>"
+        );
+    }
+
+    pub(crate) fn init_test(cx: &mut TestAppContext) -> Arc<AppState> {
+        cx.update(|cx| {
+            let state = AppState::test(cx);
+            language::init(cx);
+            crate::init(cx);
+            editor::init(cx);
+            terminal::init(cx);
+            workspace::init_settings(cx);
+            Project::init_settings(cx);
+            // Most of the tests spawn the same task repeatedly without advancing the clock,
+            // and the ones running tasks in the background should not depend on the shell of the machine.
+            cx.update_global::<SettingsStore, _>(|store, cx| {
+                store
+                    .set_user_settings(
+                        r#"{ "task": { "duplicate_spawn_window_ms": 0 }, "terminal": { "shell": { "program": "sh" } } }"#,
+                        cx,
+                    )
+                    .unwrap();
+            });
             state
         })
     }
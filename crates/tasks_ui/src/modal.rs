@@ -1,22 +1,35 @@
 use std::sync::Arc;
 
-use crate::{active_item_selection_properties, schedule_task};
+use crate::{
+    active_item_selection_properties,
+    ci_step::{ci_step_snippet, CiProvider},
+    schedule_task, schedule_task_in_new_window,
+    settings::{TaskSettings, TasksModalScope, TasksModalSort},
+    task_context, task_cwd,
+    worktree_picker::schedule_task_in_chosen_worktree,
+    TASK_COPIED_AS_CI_STEP_TOAST_ID,
+};
+use collections::{HashMap, HashSet};
 use fuzzy::{StringMatch, StringMatchCandidate};
 use gpui::{
-    impl_actions, rems, AppContext, DismissEvent, EventEmitter, FocusableView, Global,
-    InteractiveElement, Model, ParentElement, Render, SharedString, Styled, Subscription, View,
-    ViewContext, VisualContext, WeakView,
+    actions, impl_actions, rems, AnyElement, AppContext, ClipboardItem, DismissEvent, EventEmitter,
+    FocusHandle, FocusableView, Global, InteractiveElement, Model, ParentElement, Render,
+    SharedString, Styled, Subscription, View, ViewContext, VisualContext, WeakView,
+};
+use picker::{
+    highlighted_match_with_paths::{HighlightedMatchWithPaths, HighlightedText},
+    Picker, PickerDelegate,
 };
-use picker::{highlighted_match_with_paths::HighlightedText, Picker, PickerDelegate};
 use project::{Inventory, TaskSourceKind};
-use task::{oneshot_source::OneshotSource, Task, TaskContext};
+use settings::Settings;
+use task::{oneshot_source::OneshotSource, Task, TaskContext, TaskSplitDirection, VariableName};
 use ui::{
-    div, v_flex, ButtonCommon, ButtonSize, Clickable, Color, FluentBuilder as _, Icon, IconButton,
-    IconButtonShape, IconName, IconSize, ListItem, ListItemSpacing, RenderOnce, Selectable,
-    Tooltip, WindowContext,
+    div, h_flex, v_flex, ButtonCommon, ButtonSize, Clickable, Color, FluentBuilder as _, Icon,
+    IconButton, IconButtonShape, IconName, IconSize, IntoElement, KeyBinding, Label, LabelCommon,
+    LabelSize, ListItem, ListItemSpacing, RenderOnce, Selectable, Tooltip, WindowContext,
 };
 use util::ResultExt;
-use workspace::{ModalView, Workspace};
+use workspace::{ModalView, Toast, Workspace};
 
 use serde::Deserialize;
 
@@ -28,11 +41,25 @@ pub struct Spawn {
     /// If it is not set, a modal with a list of available tasks is opened instead.
     /// Defaults to None.
     pub task_name: Option<String>,
+    #[serde(default)]
+    /// Whether the spawned task should be kept out of the task history (and so, out of the recently used ones and `task::Rerun`).
+    /// For the modal, this applies to every task confirmed in it.
+    /// Defaults to false.
+    pub omit_history: bool,
+    #[serde(default)]
+    /// A split of the active pane to open the terminal of the named task in, `right` or `down`, instead of the terminal panel.
+    /// Ignored for the tasks spawned from the modal.
+    /// Defaults to None.
+    pub split: Option<TaskSplitDirection>,
 }
 
 impl Spawn {
     pub(crate) fn modal() -> Self {
-        Self { task_name: None }
+        Self {
+            task_name: None,
+            omit_history: false,
+            split: None,
+        }
     }
 }
 /// Rerun last task
@@ -46,38 +73,173 @@ pub struct Rerun {
     pub reevaluate_context: bool,
 }
 
-impl_actions!(task, [Rerun, Spawn]);
+/// Copy the task highlighted in the tasks modal as a CI step, that runs the task the same way
+#[derive(PartialEq, Clone, Deserialize, Default)]
+pub struct CopyAsCiStep {
+    #[serde(default)]
+    /// A CI provider to render the step for: `github_actions` or `gitlab`.
+    /// Defaults to `github_actions`.
+    pub provider: CiProvider,
+}
+
+impl_actions!(task, [Rerun, Spawn, CopyAsCiStep]);
+
+// Toggles rerunning the last scheduled task every time the task definitions change, useful when authoring a task.
+actions!(task, [ToggleRerunOnChange]);
+// Searches the commands run in all projects, when the global task history is enabled.
+actions!(task, [SearchGlobalHistory]);
+// Spawns the task highlighted in the tasks modal in a new workspace window, instead of the current one.
+actions!(task, [SpawnInNewWindow]);
+// Runs the test that encloses the cursor, if the language of the active editor is able to find one.
+actions!(task, [RunNearestTest]);
+// Resolves every task against the current context, and lists the commands they would run in a new buffer.
+actions!(task, [ShowTasksOverview]);
+// Checks that every task can be spawned in the current context, and reports which cannot and why in a new buffer.
+actions!(task, [CheckTasks]);
+// Runs the text selected in the active editor as a script, with the interpreter from its shebang or language.
+actions!(task, [RunSelectionAsScript]);
+// Switches the tasks modal between listing the tasks for the active file and all tasks.
+actions!(task, [ToggleTasksScope]);
+// Switches the tasks modal between listing the recently used tasks first and all tasks alphabetically, while its query is empty.
+actions!(task, [ToggleTasksSort]);
+// Pins the task selected in the tasks modal to the top of the list, or unpins it, if it was pinned.
+actions!(task, [TogglePinnedTask]);
+// Reruns the last scheduled task with the verbosity flag of its runner, or asks for the extra arguments, if the runner is unknown.
+actions!(task, [RerunVerbose]);
+// Writes a starter `.zed/tasks.json` with the build, test and run tasks for the project type detected from its manifests.
+actions!(task, [GenerateTasks]);
+// Shows the recently scheduled tasks while the modifiers are held, highlighting the next one on every repeated press,
+// and reruns the highlighted task once the modifiers are released.
+actions!(task, [CycleRecentTasks]);
+// Reruns the most recently scheduled task whose last run has failed, in the context it was scheduled in,
+// or opens the tasks modal, if no task has failed.
+actions!(task, [RerunLastFailed]);
+// Picks one of the recent commits or stashes of the git repository, and opens the tasks modal with `ZED_GIT_REF` set to it.
+actions!(task, [SpawnWithGitRef]);
+// Cancels the most recently spawned task that is still running, killing the process in its terminal.
+actions!(task, [CancelTask]);
+// Cancels all tasks that are still running, killing the processes in their terminals.
+actions!(task, [CancelAllTasks]);
 
 /// A modal used to spawn new tasks.
 pub(crate) struct TasksModalDelegate {
     inventory: Model<Inventory>,
     candidates: Option<Vec<(TaskSourceKind, Arc<dyn Task>)>>,
+    /// Resolved `command args` of every candidate, by its index, to match the query against.
+    /// Resolved once, in the context the modal was opened with, and not recomputed when a spawn resolves the context anew:
+    /// the modal is dismissed right after the spawn.
+    candidate_commands: Vec<String>,
     matches: Vec<StringMatch>,
+    /// Positions of the query in the commands of the matches, that matched by their command only.
+    command_match_positions: HashMap<usize, Vec<usize>>,
     selected_index: usize,
     workspace: WeakView<Workspace>,
     prompt: String,
     task_context: TaskContext,
+    /// Variables overridden with the `NAME=value` words at the end of the query, for the next spawn only.
+    variable_overrides: Vec<(VariableName, String)>,
+    /// Args typed after ` -- ` in the query, appended to the args of the next spawned task only.
+    inline_args: Vec<String>,
+    omit_history: bool,
+    scope: TasksModalScope,
+    sort: TasksModalSort,
     placeholder_text: Arc<str>,
+    /// Focus before the modal was opened, to resolve the keybindings of the tasks in its context.
+    previous_focus_handle: Option<FocusHandle>,
 }
 
 impl TasksModalDelegate {
     fn new(
         inventory: Model<Inventory>,
         task_context: TaskContext,
+        omit_history: bool,
+        scope: TasksModalScope,
+        sort: TasksModalSort,
+        previous_focus_handle: Option<FocusHandle>,
         workspace: WeakView<Workspace>,
     ) -> Self {
         Self {
             inventory,
             workspace,
             candidates: None,
+            candidate_commands: Vec::new(),
             matches: Vec::new(),
+            command_match_positions: HashMap::default(),
             selected_index: 0,
             prompt: String::default(),
             task_context,
+            variable_overrides: Vec::new(),
+            inline_args: Vec::new(),
+            omit_history,
+            scope,
+            sort,
             placeholder_text: Arc::from("Run a task..."),
+            previous_focus_handle,
         }
     }
 
+    /// Switches between listing the tasks for the active file and all tasks, requerying the candidates.
+    fn toggle_scope(&mut self) {
+        self.scope = self.scope.toggled();
+        self.candidates = None;
+        self.selected_index = 0;
+    }
+
+    /// Switches between listing the recently used tasks first and all tasks alphabetically, when the query is empty.
+    fn toggle_sort(&mut self) {
+        self.sort = self.sort.toggled();
+        self.selected_index = 0;
+    }
+
+    /// Pins the selected task to the top of the list or unpins it, requerying the candidates to reorder them.
+    fn toggle_pinned_selected(&mut self, cx: &mut AppContext) {
+        let Some(task) = self.selected_task() else {
+            return;
+        };
+        self.inventory
+            .update(cx, |inventory, _| inventory.toggle_pinned(task.id()));
+        self.candidates = None;
+    }
+
+    /// Copies the selected task, resolved in the current context, as a YAML snippet of the CI step into the clipboard.
+    fn copy_selected_as_ci_step(&self, provider: CiProvider, cx: &mut ViewContext<Picker<Self>>) {
+        let Some(task) = self.selected_task() else {
+            return;
+        };
+        let Some(spawn_in_terminal) = task.prepare_exec(self.task_context.clone()) else {
+            return;
+        };
+        cx.write_to_clipboard(ClipboardItem::new(ci_step_snippet(
+            &spawn_in_terminal,
+            provider,
+        )));
+        self.workspace
+            .update(cx, |workspace, cx| {
+                workspace.show_toast(
+                    Toast::new(
+                        TASK_COPIED_AS_CI_STEP_TOAST_ID,
+                        format!("Copied \"{}\" as a CI step", spawn_in_terminal.label),
+                    ),
+                    cx,
+                )
+            })
+            .ok();
+    }
+
+    /// A keybinding that spawns the task by its name, if bound in the keymap.
+    fn task_key_binding(&self, task: &dyn Task, cx: &WindowContext) -> Option<gpui::KeyBinding> {
+        let spawn = Spawn {
+            task_name: Some(task.name().to_owned()),
+            omit_history: false,
+            split: None,
+        };
+        let bindings = match &self.previous_focus_handle {
+            Some(focus_handle) => cx.bindings_for_action_in(&spawn, focus_handle),
+            None => cx.bindings_for_action(&spawn),
+        };
+        bindings.last().cloned()
+    }
+
     fn spawn_oneshot(&mut self, cx: &mut AppContext) -> Option<Arc<dyn Task>> {
         if self.prompt.trim().is_empty() {
             return None;
@@ -95,6 +257,31 @@ impl TasksModalDelegate {
             })
     }
 
+    fn selected_task(&self) -> Option<Arc<dyn Task>> {
+        let current_match = self.matches.get(self.selected_index)?;
+        let (_, task) = self.candidates.as_ref()?.get(current_match.candidate_id)?;
+        Some(task.clone())
+    }
+
+    fn spawn_selected_in_new_window(&mut self, cx: &mut ViewContext<Picker<Self>>) {
+        let Some(task) = self.selected_task() else {
+            return;
+        };
+        self.workspace
+            .update(cx, |workspace, cx| {
+                self.task_context = current_task_context(workspace, cx);
+                schedule_task_in_new_window(
+                    workspace,
+                    &task,
+                    self.task_context.clone(),
+                    self.omit_history,
+                    cx,
+                );
+            })
+            .ok();
+        cx.emit(DismissEvent);
+    }
+
     fn delete_oneshot(&mut self, ix: usize, cx: &mut AppContext) {
         let Some(candidates) = self.candidates.as_mut() else {
             return;
@@ -106,6 +293,9 @@ impl TasksModalDelegate {
         // it doesn't make sense to requery the inventory for new candidates, as that's potentially costly and more often than not it should just return back
         // the original list without a removed entry.
         candidates.remove(ix);
+        if ix < self.candidate_commands.len() {
+            self.candidate_commands.remove(ix);
+        }
         self.inventory.update(cx, |inventory, cx| {
             let oneshot_source = inventory.source::<OneshotSource>()?;
             let task_id = task.id();
@@ -120,8 +310,57 @@ impl TasksModalDelegate {
     }
 }
 
+/// Splits the `NAME=value` words off the end of the query, returning the rest of the query to match the tasks with,
+/// and the variables to override in the context of the spawned task, e.g. `test ZED_FILE=src/main.rs` or `deploy TARGET=staging`.
+fn split_variable_overrides(query: &str) -> (&str, Vec<(VariableName, String)>) {
+    let mut task_query = query.trim_end();
+    let mut overrides = Vec::new();
+    while let Some((rest, word)) = task_query
+        .rsplit_once(char::is_whitespace)
+        .or_else(|| Some(("", task_query)).filter(|(_, word)| !word.is_empty()))
+    {
+        let Some((name, value)) = word.split_once('=') else {
+            break;
+        };
+        let is_variable_name = name
+            .chars()
+            .next()
+            .map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_variable_name {
+            break;
+        }
+        overrides.push((VariableName::from(name), value.to_owned()));
+        task_query = rest.trim_end();
+    }
+    overrides.reverse();
+    (task_query, overrides)
+}
+
+/// Splits the args after the ` -- ` separator off the query, returning the rest of the query to match the tasks with,
+/// and the args to append to the args of the spawned task, e.g. `test -- --nocapture`.
+fn split_inline_args(query: &str) -> (&str, Vec<String>) {
+    match query
+        .split_once(" -- ")
+        .or_else(|| Some((query.strip_suffix(" --")?, "")))
+    {
+        Some((task_query, args)) => (
+            task_query.trim_end(),
+            args.split_whitespace().map(ToOwned::to_owned).collect(),
+        ),
+        None => (query, Vec::new()),
+    }
+}
+
+/// Resolves the context anew for every spawn, as the active editor and its selections may change
+/// since the modal was opened, if the modal stays open.
+fn current_task_context(workspace: &Workspace, cx: &mut WindowContext) -> TaskContext {
+    let cwd = task_cwd(workspace, cx).log_err().flatten();
+    task_context(workspace, cwd, cx)
+}
+
 pub(crate) struct TasksModal {
-    picker: View<Picker<TasksModalDelegate>>,
+    pub(crate) picker: View<Picker<TasksModalDelegate>>,
     _subscription: Subscription,
 }
 
@@ -129,12 +368,24 @@ impl TasksModal {
     pub(crate) fn new(
         inventory: Model<Inventory>,
         task_context: TaskContext,
+        omit_history: bool,
         workspace: WeakView<Workspace>,
         cx: &mut ViewContext<Self>,
     ) -> Self {
+        let settings = TaskSettings::get_global(cx);
+        let (modal_scope, modal_sort) = (settings.modal_scope, settings.modal_sort);
+        let previous_focus_handle = cx.focused();
         let picker = cx.new_view(|cx| {
             Picker::uniform_list(
-                TasksModalDelegate::new(inventory, task_context, workspace),
+                TasksModalDelegate::new(
+                    inventory,
+                    task_context,
+                    omit_history,
+                    modal_scope,
+                    modal_sort,
+                    previous_focus_handle,
+                    workspace,
+                ),
                 cx,
             )
         });
@@ -148,10 +399,48 @@ impl TasksModal {
     }
 }
 
+impl TasksModal {
+    /// Replaces the query of the modal, e.g. to let the user edit a command before spawning it as a oneshot task.
+    pub(crate) fn set_query(&self, query: String, cx: &mut ViewContext<Self>) {
+        self.picker
+            .update(cx, |picker, cx| picker.set_query(query, cx));
+    }
+}
+
 impl Render for TasksModal {
-    fn render(&mut self, _: &mut ViewContext<Self>) -> impl gpui::prelude::IntoElement {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl gpui::prelude::IntoElement {
         v_flex()
             .key_context("TasksModal")
+            .on_action(cx.listener(|this, _: &SpawnInNewWindow, cx| {
+                this.picker.update(cx, |picker, cx| {
+                    picker.delegate.spawn_selected_in_new_window(cx)
+                })
+            }))
+            .on_action(cx.listener(|this, _: &ToggleTasksScope, cx| {
+                this.picker.update(cx, |picker, cx| {
+                    picker.delegate.toggle_scope();
+                    picker.refresh(cx);
+                })
+            }))
+            .on_action(cx.listener(|this, _: &ToggleTasksSort, cx| {
+                this.picker.update(cx, |picker, cx| {
+                    picker.delegate.toggle_sort();
+                    picker.refresh(cx);
+                })
+            }))
+            .on_action(cx.listener(|this, _: &TogglePinnedTask, cx| {
+                this.picker.update(cx, |picker, cx| {
+                    picker.delegate.toggle_pinned_selected(cx);
+                    picker.refresh(cx);
+                })
+            }))
+            .on_action(cx.listener(|this, action: &CopyAsCiStep, cx| {
+                this.picker.update(cx, |picker, cx| {
+                    picker
+                        .delegate
+                        .copy_selected_as_ci_step(action.provider, cx)
+                })
+            }))
             .w(rems(34.))
             .child(self.picker.clone())
     }
@@ -191,18 +480,33 @@ impl PickerDelegate for TasksModalDelegate {
         query: String,
         cx: &mut ViewContext<picker::Picker<Self>>,
     ) -> gpui::Task<()> {
+        let (task_query, variable_overrides) = split_variable_overrides(&query);
+        let (task_query, inline_args) = split_inline_args(task_query);
+        let task_query = task_query.to_owned();
         cx.spawn(move |picker, mut cx| async move {
-            let Some(candidates) = picker
+            let Some((candidates, command_candidates)) = picker
                 .update(&mut cx, |picker, cx| {
-                    let candidates = picker.delegate.candidates.get_or_insert_with(|| {
-                        let (worktree, language) =
-                            active_item_selection_properties(&picker.delegate.workspace, cx);
-                        picker.delegate.inventory.update(cx, |inventory, cx| {
+                    if picker.delegate.candidates.is_none() {
+                        let (worktree, language) = match picker.delegate.scope {
+                            TasksModalScope::CurrentFile => {
+                                active_item_selection_properties(&picker.delegate.workspace, cx)
+                            }
+                            TasksModalScope::All => (None, None),
+                        };
+                        let candidates = picker.delegate.inventory.update(cx, |inventory, cx| {
                             inventory.list_tasks(language, worktree, true, cx)
-                        })
-                    });
+                        });
+                        picker.delegate.candidate_commands = candidates
+                            .iter()
+                            .map(|(_, task)| {
+                                task.command_line_preview(&picker.delegate.task_context)
+                            })
+                            .collect();
+                        picker.delegate.candidates = Some(candidates);
+                    }
+                    let candidates = picker.delegate.candidates.as_deref().unwrap_or_default();
 
-                    candidates
+                    let label_candidates = candidates
                         .iter()
                         .enumerate()
                         .map(|(index, (_, candidate))| StringMatchCandidate {
@@ -210,26 +514,89 @@ impl PickerDelegate for TasksModalDelegate {
                             char_bag: candidate.name().chars().collect(),
                             string: candidate.name().into(),
                         })
-                        .collect::<Vec<_>>()
+                        .collect::<Vec<_>>();
+                    let command_candidates = picker
+                        .delegate
+                        .candidate_commands
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, command)| !command.is_empty())
+                        .map(|(index, command)| StringMatchCandidate {
+                            id: index,
+                            char_bag: command.chars().collect(),
+                            string: command.clone(),
+                        })
+                        .collect::<Vec<_>>();
+                    (label_candidates, command_candidates)
                 })
                 .ok()
             else {
                 return;
             };
-            let matches = fuzzy::match_strings(
+            let mut matches = fuzzy::match_strings(
                 &candidates,
-                &query,
+                &task_query,
                 true,
                 1000,
                 &Default::default(),
                 cx.background_executor().clone(),
             )
             .await;
+            // Without a query, every task matches by its label already.
+            let command_matches = if task_query.trim().is_empty() {
+                Vec::new()
+            } else {
+                fuzzy::match_strings(
+                    &command_candidates,
+                    &task_query,
+                    true,
+                    1000,
+                    &Default::default(),
+                    cx.background_executor().clone(),
+                )
+                .await
+            };
             picker
-                .update(&mut cx, |picker, _| {
+                .update(&mut cx, |picker, cx| {
                     let delegate = &mut picker.delegate;
+                    // Tasks matching by their label are listed first, the ones matching by the command only after them.
+                    let label_matched = matches
+                        .iter()
+                        .map(|hit| hit.candidate_id)
+                        .collect::<HashSet<_>>();
+                    delegate.command_match_positions.clear();
+                    for command_match in command_matches {
+                        if label_matched.contains(&command_match.candidate_id) {
+                            continue;
+                        }
+                        let Some(label) = candidates.get(command_match.candidate_id) else {
+                            continue;
+                        };
+                        delegate
+                            .command_match_positions
+                            .insert(command_match.candidate_id, command_match.positions);
+                        matches.push(StringMatch {
+                            candidate_id: command_match.candidate_id,
+                            score: command_match.score,
+                            positions: Vec::new(),
+                            string: label.string.clone(),
+                        });
+                    }
+                    if delegate.sort == TasksModalSort::Alphabetical && task_query.trim().is_empty()
+                    {
+                        let inventory = delegate.inventory.read(cx);
+                        let candidates = delegate.candidates.as_deref().unwrap_or_default();
+                        matches.sort_by_cached_key(|hit| {
+                            let pinned = candidates
+                                .get(hit.candidate_id)
+                                .map_or(false, |(_, task)| inventory.is_pinned(task.id()));
+                            (!pinned, hit.string.to_lowercase())
+                        });
+                    }
                     delegate.matches = matches;
                     delegate.prompt = query;
+                    delegate.variable_overrides = variable_overrides;
+                    delegate.inline_args = inline_args;
 
                     if delegate.matches.is_empty() {
                         delegate.selected_index = 0;
@@ -243,29 +610,33 @@ impl PickerDelegate for TasksModalDelegate {
     }
 
     fn confirm(&mut self, omit_history_entry: bool, cx: &mut ViewContext<picker::Picker<Self>>) {
-        let current_match_index = self.selected_index();
-        let task = self
-            .matches
-            .get(current_match_index)
-            .and_then(|current_match| {
-                let ix = current_match.candidate_id;
-                self.candidates
-                    .as_ref()
-                    .map(|candidates| candidates[ix].1.clone())
-            });
-        let Some(task) = task else {
+        let Some(task) = self.selected_task() else {
             return;
         };
 
         self.workspace
             .update(cx, |workspace, cx| {
-                schedule_task(
-                    workspace,
-                    &task,
-                    self.task_context.clone(),
-                    omit_history_entry,
-                    cx,
-                );
+                let omit_history = omit_history_entry || self.omit_history;
+                if task_cwd(workspace, cx).is_err() {
+                    schedule_task_in_chosen_worktree(
+                        workspace,
+                        task,
+                        omit_history,
+                        self.variable_overrides.clone(),
+                        self.inline_args.clone(),
+                        cx,
+                    );
+                    return;
+                }
+                self.task_context = current_task_context(workspace, cx);
+                let mut task_context = self.task_context.clone();
+                for (variable, value) in &self.variable_overrides {
+                    task_context
+                        .task_variables
+                        .insert(variable.clone(), value.clone());
+                }
+                task_context.args_override.append_args = self.inline_args.clone();
+                schedule_task(workspace, &task, task_context, omit_history, cx);
             })
             .ok();
         cx.emit(DismissEvent);
@@ -283,17 +654,34 @@ impl PickerDelegate for TasksModalDelegate {
     ) -> Option<Self::ListItem> {
         let candidates = self.candidates.as_ref()?;
         let hit = &self.matches[ix];
-        let (source_kind, _) = &candidates[hit.candidate_id];
+        let (source_kind, task) = &candidates[hit.candidate_id];
+        let key_binding = self
+            .task_key_binding(task.as_ref(), cx)
+            .map(KeyBinding::new);
+        let pinned = self.inventory.read(cx).is_pinned(task.id());
         let language_name = if let TaskSourceKind::Language { name } = source_kind {
             Some(name)
         } else {
             None
         };
 
-        let highlighted_location = HighlightedText {
-            text: hit.string.clone(),
-            highlight_positions: hit.positions.clone(),
-            char_count: hit.string.chars().count(),
+        let highlighted_location = HighlightedMatchWithPaths {
+            match_label: HighlightedText {
+                text: hit.string.clone(),
+                highlight_positions: hit.positions.clone(),
+                char_count: hit.string.chars().count(),
+            },
+            paths: self
+                .command_match_positions
+                .get(&hit.candidate_id)
+                .zip(self.candidate_commands.get(hit.candidate_id))
+                .map(|(positions, command)| HighlightedText {
+                    text: command.clone(),
+                    highlight_positions: positions.clone(),
+                    char_count: command.chars().count(),
+                })
+                .into_iter()
+                .collect(),
         };
         let language_icon = language_name
             .and_then(|language| {
@@ -334,23 +722,51 @@ impl PickerDelegate for TasksModalDelegate {
                     }
                 })
                 .selected(selected)
-                .child(highlighted_location.render(cx)),
+                .child(
+                    h_flex()
+                        .w_full()
+                        .justify_between()
+                        .child(highlighted_location.render(cx))
+                        .child(
+                            h_flex()
+                                .gap_2()
+                                .when(pinned, |this| {
+                                    this.child(
+                                        Label::new("pinned")
+                                            .size(LabelSize::Small)
+                                            .color(Color::Muted),
+                                    )
+                                })
+                                .children(key_binding),
+                        ),
+                ),
+        )
+    }
+
+    fn render_header(&self, _: &mut ViewContext<Picker<Self>>) -> Option<AnyElement> {
+        let scope = match self.scope {
+            TasksModalScope::CurrentFile => "Tasks for this file",
+            TasksModalScope::All => "All tasks",
+        };
+        let scope = match self.sort {
+            TasksModalSort::Default => scope.to_owned(),
+            TasksModalSort::Alphabetical => format!("{scope}, A–Z"),
+        };
+        Some(
+            h_flex()
+                .px_3()
+                .mt_1()
+                .child(Label::new(scope).size(LabelSize::Small).color(Color::Muted))
+                .into_any_element(),
         )
     }
 
     fn selected_as_query(&self) -> Option<String> {
-        use itertools::intersperse;
         let task_index = self.matches.get(self.selected_index())?.candidate_id;
         let tasks = self.candidates.as_ref()?;
         let (_, task) = tasks.get(task_index)?;
-        let mut spawn_prompt = task.prepare_exec(self.task_context.clone())?;
-        if !spawn_prompt.args.is_empty() {
-            spawn_prompt.command.push(' ');
-            spawn_prompt
-                .command
-                .extend(intersperse(spawn_prompt.args, " ".to_string()));
-        }
-        Some(spawn_prompt.command)
+        let command_line = task.command_line_preview(&self.task_context);
+        (!command_line.is_empty()).then_some(command_line)
     }
 
     fn confirm_input(&mut self, omit_history_entry: bool, cx: &mut ViewContext<Picker<Self>>) {
@@ -359,11 +775,24 @@ impl PickerDelegate for TasksModalDelegate {
         };
         self.workspace
             .update(cx, |workspace, cx| {
+                let omit_history = omit_history_entry || self.omit_history;
+                if task_cwd(workspace, cx).is_err() {
+                    schedule_task_in_chosen_worktree(
+                        workspace,
+                        task,
+                        omit_history,
+                        Vec::new(),
+                        Vec::new(),
+                        cx,
+                    );
+                    return;
+                }
+                self.task_context = current_task_context(workspace, cx);
                 schedule_task(
                     workspace,
                     &task,
                     self.task_context.clone(),
-                    omit_history_entry,
+                    omit_history,
                     cx,
                 );
             })
@@ -374,9 +803,10 @@ impl PickerDelegate for TasksModalDelegate {
 
 #[cfg(test)]
 mod tests {
-    use gpui::{TestAppContext, VisualTestContext};
+    use gpui::{Entity, Keystroke, TestAppContext, VisualTestContext};
     use project::{FakeFs, Project};
     use serde_json::json;
+    use settings::SettingsStore;
 
     use super::*;
 
@@ -444,8 +874,8 @@ mod tests {
         );
         assert_eq!(
             task_names(&tasks_picker, cx),
-            Vec::<String>::new(),
-            "No task should be listed"
+            vec!["example task"],
+            "Only the task with the selected command should be listed"
         );
         cx.dispatch_action(picker::ConfirmInput { secondary: false });
 
@@ -466,8 +896,8 @@ mod tests {
         assert_eq!(query(&tasks_picker, cx), query_str);
         assert_eq!(
             task_names(&tasks_picker, cx),
-            vec!["echo 4"],
-            "New oneshot should match custom command query"
+            vec!["echo 4", "example task"],
+            "New oneshot should match custom command query, above the task matching by its command"
         );
 
         cx.dispatch_action(picker::ConfirmInput { secondary: false });
@@ -491,8 +921,8 @@ mod tests {
         );
         assert_eq!(
             task_names(&tasks_picker, cx),
-            vec![query_str],
-            "Only custom task should be listed"
+            vec![query_str, "example task"],
+            "Custom task should be listed first"
         );
 
         let query_str = "0";
@@ -518,6 +948,627 @@ mod tests {
         );
     }
 
+    #[gpui::test]
+    async fn test_spawn_without_history(cx: &mut TestAppContext) {
+        crate::tests::init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[
+                        {
+                            "label": "example task",
+                            "command": "echo",
+                            "args": ["4"]
+                        },
+                        {
+                            "label": "another one",
+                            "command": "echo",
+                            "args": ["55"]
+                        },
+                    ]"#,
+                },
+                "a.ts": "a"
+            }),
+        )
+        .await;
+
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        let last_scheduled_task_name = |cx: &mut VisualTestContext| {
+            project.update(cx, |project, cx| {
+                project
+                    .task_inventory()
+                    .read(cx)
+                    .last_scheduled_task()
+                    .map(|(task, _)| task.name().to_string())
+            })
+        };
+
+        cx.dispatch_action(Spawn {
+            task_name: Some("example task".to_string()),
+            omit_history: true,
+            split: None,
+        });
+        cx.run_until_parked();
+        assert_eq!(
+            last_scheduled_task_name(cx),
+            None,
+            "Task spawned without history should not be recorded"
+        );
+
+        cx.dispatch_action(Spawn {
+            task_name: Some("another one".to_string()),
+            omit_history: false,
+            split: None,
+        });
+        cx.run_until_parked();
+        assert_eq!(
+            last_scheduled_task_name(cx),
+            Some("another one".to_string()),
+            "Task spawned normally should be recorded"
+        );
+
+        let tasks_picker = open_spawn_tasks(&workspace, cx);
+        assert_eq!(
+            task_names(&tasks_picker, cx),
+            vec!["another one", "example task"],
+            "Only the recorded task should be listed as recently used"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_spawn_in_new_window(cx: &mut TestAppContext) {
+        crate::tests::init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[
+                        {
+                            "label": "example task",
+                            "command": "echo",
+                            "args": ["4"]
+                        },
+                    ]"#,
+                },
+                "a.ts": "a"
+            }),
+        )
+        .await;
+
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        let window_count = cx.update(|cx| cx.windows().len());
+
+        let tasks_picker = open_spawn_tasks(&workspace, cx);
+        assert_eq!(task_names(&tasks_picker, cx), vec!["example task"]);
+        cx.dispatch_action(SpawnInNewWindow);
+        cx.run_until_parked();
+
+        let windows = cx.update(|cx| cx.windows());
+        assert_eq!(
+            windows.len(),
+            window_count + 1,
+            "Spawning in a new window should open a new workspace window"
+        );
+        let new_window = windows
+            .into_iter()
+            .filter_map(|window| window.downcast::<Workspace>())
+            .last()
+            .unwrap();
+        let queued_labels = new_window
+            .update(cx, |new_workspace, cx| {
+                assert_eq!(
+                    new_workspace.project().entity_id(),
+                    project.entity_id(),
+                    "New window should be scoped to the same project"
+                );
+                new_workspace
+                    .queued_task_spawns()
+                    .iter()
+                    .map(|spawn_in_terminal| spawn_in_terminal.label.clone())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap();
+        assert_eq!(queued_labels, vec!["example task"]);
+        workspace.update(cx, |workspace, _| {
+            assert!(
+                workspace.queued_task_spawns().is_empty(),
+                "Current workspace should not spawn the task"
+            );
+        });
+    }
+
+    #[gpui::test]
+    async fn test_context_is_resolved_per_spawn(cx: &mut TestAppContext) {
+        crate::tests::init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[
+                        {
+                            "label": "example task",
+                            "command": "echo",
+                            "args": ["$ZED_FILE"]
+                        },
+                    ]"#,
+                },
+                "a.ts": "a",
+                "b.ts": "b",
+            }),
+        )
+        .await;
+
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        let worktree_id = project.update(cx, |project, cx| {
+            project.worktrees().next().unwrap().read(cx).id()
+        });
+        let mut editors = Vec::new();
+        for path in ["a.ts", "b.ts"] {
+            let buffer = project
+                .update(cx, |project, cx| {
+                    project.open_buffer((worktree_id, path), cx)
+                })
+                .await
+                .unwrap();
+            let editor =
+                cx.new_view(|cx| editor::Editor::for_buffer(buffer, Some(project.clone()), cx));
+            workspace.update(cx, |workspace, cx| {
+                workspace.add_item_to_center(Box::new(editor.clone()), cx)
+            });
+            editors.push(editor);
+        }
+        workspace.update(cx, |workspace, cx| workspace.activate_item(&editors[0], cx));
+        let last_scheduled_file = |cx: &mut VisualTestContext| {
+            project.update(cx, |project, cx| {
+                let (_, task_context) = project.task_inventory().read(cx).last_scheduled_task()?;
+                task_context
+                    .task_variables
+                    .get(&task::VariableName::File)
+                    .map(ToOwned::to_owned)
+            })
+        };
+
+        let tasks_picker = open_spawn_tasks(&workspace, cx);
+        tasks_picker.update(cx, |picker, cx| picker.delegate.confirm(false, cx));
+        assert_eq!(last_scheduled_file(cx), Some("/dir/a.ts".to_string()));
+
+        workspace.update(cx, |workspace, cx| workspace.activate_item(&editors[1], cx));
+        tasks_picker.update(cx, |picker, cx| picker.delegate.confirm(false, cx));
+        assert_eq!(
+            last_scheduled_file(cx),
+            Some("/dir/b.ts".to_string()),
+            "Second spawn from the same modal should use the context of the newly active file"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_toggle_tasks_scope(cx: &mut TestAppContext) {
+        crate::tests::init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/a",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[{ "label": "task in a", "command": "echo", "args": ["a"] }]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        fs.insert_tree(
+            "/b",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[{ "label": "task in b", "command": "echo", "args": ["b"] }]"#,
+                },
+                "b.ts": "b",
+            }),
+        )
+        .await;
+
+        let project = Project::test(fs, ["/a".as_ref(), "/b".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        let worktree_id = project.update(cx, |project, cx| {
+            project.worktrees().next().unwrap().read(cx).id()
+        });
+        let buffer = project
+            .update(cx, |project, cx| {
+                project.open_buffer((worktree_id, "a.ts"), cx)
+            })
+            .await
+            .unwrap();
+        let editor = cx.new_view(|cx| editor::Editor::for_buffer(buffer, Some(project), cx));
+        workspace.update(cx, |workspace, cx| {
+            workspace.add_item_to_center(Box::new(editor), cx)
+        });
+        cx.run_until_parked();
+
+        let tasks_picker = open_spawn_tasks(&workspace, cx);
+        assert_eq!(
+            task_names(&tasks_picker, cx),
+            vec!["task in a"],
+            "Modal should list the tasks for the active file by default"
+        );
+
+        cx.dispatch_action(ToggleTasksScope);
+        cx.run_until_parked();
+        let mut all_tasks = task_names(&tasks_picker, cx);
+        all_tasks.sort();
+        assert_eq!(
+            all_tasks,
+            vec!["task in a", "task in b"],
+            "Toggled modal should list the tasks of all worktrees"
+        );
+
+        cx.dispatch_action(ToggleTasksScope);
+        cx.run_until_parked();
+        assert_eq!(
+            task_names(&tasks_picker, cx),
+            vec!["task in a"],
+            "Toggling again should list the tasks for the active file"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_toggle_tasks_sort(cx: &mut TestAppContext) {
+        crate::tests::init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[
+                        { "label": "zeta", "command": "echo", "args": ["z"] },
+                        { "label": "alpha", "command": "echo", "args": ["a"] },
+                        { "label": "Beta", "command": "echo", "args": ["b"] }
+                    ]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        cx.update(|cx| {
+            cx.update_global::<SettingsStore, _>(|store, cx| {
+                store
+                    .set_user_settings(r#"{ "task": { "modal_sort": "alphabetical" } }"#, cx)
+                    .unwrap();
+            })
+        });
+
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        cx.run_until_parked();
+        cx.dispatch_action(Spawn {
+            task_name: Some("zeta".to_string()),
+            omit_history: false,
+            split: None,
+        });
+        cx.run_until_parked();
+
+        let tasks_picker = open_spawn_tasks(&workspace, cx);
+        assert_eq!(
+            task_names(&tasks_picker, cx),
+            vec!["alpha", "Beta", "zeta"],
+            "Tasks should be sorted by their labels, including the recently used one"
+        );
+
+        cx.dispatch_action(ToggleTasksSort);
+        cx.run_until_parked();
+        assert_eq!(
+            task_names(&tasks_picker, cx),
+            vec!["zeta", "Beta", "alpha"],
+            "Toggled modal should list the recently used task first"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_pinned_tasks_listed_first(cx: &mut TestAppContext) {
+        crate::tests::init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[
+                        { "label": "zeta", "command": "echo", "args": ["z"] },
+                        { "label": "alpha", "command": "echo", "args": ["a"] },
+                        { "label": "Beta", "command": "echo", "args": ["b"] }
+                    ]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        cx.run_until_parked();
+        cx.dispatch_action(Spawn {
+            task_name: Some("zeta".to_string()),
+            omit_history: false,
+            split: None,
+        });
+        cx.run_until_parked();
+
+        let tasks_picker = open_spawn_tasks(&workspace, cx);
+        assert_eq!(task_names(&tasks_picker, cx), vec!["zeta", "Beta", "alpha"]);
+        tasks_picker.update(cx, |picker, _| picker.delegate.selected_index = 2);
+        cx.dispatch_action(TogglePinnedTask);
+        cx.run_until_parked();
+        assert_eq!(
+            task_names(&tasks_picker, cx),
+            vec!["alpha", "zeta", "Beta"],
+            "Pinned task should be listed before the recently used one"
+        );
+
+        cx.dispatch_action(ToggleTasksSort);
+        cx.run_until_parked();
+        assert_eq!(
+            task_names(&tasks_picker, cx),
+            vec!["alpha", "Beta", "zeta"],
+            "Pinned task should stay first in the alphabetical order"
+        );
+
+        tasks_picker.update(cx, |picker, _| picker.delegate.selected_index = 0);
+        cx.dispatch_action(TogglePinnedTask);
+        cx.dispatch_action(ToggleTasksSort);
+        cx.run_until_parked();
+        assert_eq!(
+            task_names(&tasks_picker, cx),
+            vec!["zeta", "Beta", "alpha"],
+            "Unpinned task should be listed as before pinning"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_match_tasks_by_command(cx: &mut TestAppContext) {
+        crate::tests::init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[
+                        { "label": "check", "command": "cargo", "args": ["clippy", "--workspace"] },
+                        { "label": "format", "command": "rustfmt", "args": ["src/main.rs"] },
+                        { "label": "rust tests", "command": "cargo", "args": ["test"] }
+                    ]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        cx.run_until_parked();
+        let matched_commands = |tasks_picker: &View<Picker<TasksModalDelegate>>,
+                                cx: &mut VisualTestContext| {
+            tasks_picker.update(cx, |picker, _| {
+                let delegate = &picker.delegate;
+                delegate
+                    .matches
+                    .iter()
+                    .filter_map(|hit| {
+                        delegate.command_match_positions.get(&hit.candidate_id)?;
+                        Some(delegate.candidate_commands[hit.candidate_id].clone())
+                    })
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        let tasks_picker = open_spawn_tasks(&workspace, cx);
+        cx.simulate_input("clippy");
+        assert_eq!(
+            task_names(&tasks_picker, cx),
+            vec!["check"],
+            "Task should be found by its command"
+        );
+        assert_eq!(
+            matched_commands(&tasks_picker, cx),
+            vec!["cargo clippy --workspace"],
+            "Matched command should be shown for the task"
+        );
+
+        tasks_picker.update(cx, |picker, cx| picker.set_query("rust", cx));
+        cx.run_until_parked();
+        assert_eq!(
+            task_names(&tasks_picker, cx),
+            vec!["rust tests", "format"],
+            "Label matches should be listed above the command-only ones"
+        );
+        assert_eq!(
+            matched_commands(&tasks_picker, cx),
+            vec!["rustfmt src/main.rs"],
+            "Command should be shown for the command-only matches only"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_task_key_bindings(cx: &mut TestAppContext) {
+        crate::tests::init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[
+                        { "label": "build", "command": "make", "args": ["build"] },
+                        { "label": "test", "command": "make", "args": ["test"] }
+                    ]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        cx.update(|cx| {
+            cx.bind_keys([gpui::KeyBinding::new(
+                "ctrl-shift-b",
+                Spawn {
+                    task_name: Some("build".to_string()),
+                    omit_history: false,
+                    split: None,
+                },
+                None,
+            )])
+        });
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        cx.run_until_parked();
+
+        let tasks_picker = open_spawn_tasks(&workspace, cx);
+        cx.run_until_parked();
+        let key_bindings = tasks_picker.update(cx, |picker, cx| {
+            let delegate = &picker.delegate;
+            delegate
+                .matches
+                .iter()
+                .map(|hit| {
+                    let (_, task) = &delegate.candidates.as_ref().unwrap()[hit.candidate_id];
+                    let keystrokes = delegate
+                        .task_key_binding(task.as_ref(), cx)
+                        .map(|binding| binding.keystrokes().to_vec());
+                    (hit.string.clone(), keystrokes)
+                })
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(
+            key_bindings,
+            vec![
+                (
+                    "build".to_string(),
+                    Some(vec![Keystroke::parse("ctrl-shift-b").unwrap()])
+                ),
+                ("test".to_string(), None),
+            ],
+            "Only the task bound by its name should have a keybinding"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_inline_variable_overrides(cx: &mut TestAppContext) {
+        crate::tests::init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[{ "label": "test file", "command": "cargo", "args": ["test", "$ZED_FILE", "${ZED_PROFILE}"] }]"#,
+                },
+                "a.rs": "a",
+            }),
+        )
+        .await;
+
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        let worktree_id = project.update(cx, |project, cx| {
+            project.worktrees().next().unwrap().read(cx).id()
+        });
+        let buffer = project
+            .update(cx, |project, cx| {
+                project.open_buffer((worktree_id, "a.rs"), cx)
+            })
+            .await
+            .unwrap();
+        let editor = cx.new_view(|cx| editor::Editor::for_buffer(buffer, Some(project), cx));
+        workspace.update(cx, |workspace, cx| {
+            workspace.add_item_to_center(Box::new(editor), cx)
+        });
+        cx.run_until_parked();
+        let last_task_command = |cx: &mut VisualTestContext| {
+            workspace.update(cx, |workspace, cx| crate::last_task_command(workspace, cx))
+        };
+
+        let tasks_picker = open_spawn_tasks(&workspace, cx);
+        cx.simulate_input("test ZED_FILE=/dir/b.rs PROFILE=release");
+        assert_eq!(
+            task_names(&tasks_picker, cx),
+            vec!["test file"],
+            "Overrides should not be matched against the task names"
+        );
+        tasks_picker.update(cx, |picker, cx| picker.delegate.confirm(false, cx));
+        cx.run_until_parked();
+        assert_eq!(
+            last_task_command(cx),
+            Some("cargo test /dir/b.rs release".to_owned())
+        );
+
+        let tasks_picker = open_spawn_tasks(&workspace, cx);
+        cx.simulate_input("test");
+        tasks_picker.update(cx, |picker, cx| picker.delegate.confirm(false, cx));
+        cx.run_until_parked();
+        let (file, profile) = project.update(cx, |project, cx| {
+            let (_, task_context) = project
+                .task_inventory()
+                .read(cx)
+                .last_scheduled_task()
+                .unwrap();
+            let variables = task_context.task_variables;
+            (
+                variables.get(&VariableName::File).map(ToOwned::to_owned),
+                variables
+                    .get(&VariableName::from("PROFILE"))
+                    .map(ToOwned::to_owned),
+            )
+        });
+        assert_eq!(
+            (file.as_deref(), profile),
+            (Some("/dir/a.rs"), None),
+            "Overrides should only apply to the run they were typed for"
+        );
+    }
+
+    #[test]
+    fn test_split_variable_overrides() {
+        assert_eq!(
+            split_variable_overrides("test ZED_FILE=a.rs TARGET=x86 "),
+            (
+                "test",
+                vec![
+                    (VariableName::File, "a.rs".to_owned()),
+                    (VariableName::from("TARGET"), "x86".to_owned()),
+                ]
+            )
+        );
+        assert_eq!(
+            split_variable_overrides("echo a=b c"),
+            ("echo a=b c", Vec::new()),
+            "Only the words at the end of the query should be overrides"
+        );
+        assert_eq!(
+            split_variable_overrides("run --flag=1"),
+            ("run --flag=1", Vec::new()),
+            "Flags should not be taken for the variables"
+        );
+    }
+
+    #[test]
+    fn test_split_inline_args() {
+        assert_eq!(
+            split_inline_args("test  -- --nocapture --test-threads 1"),
+            (
+                "test",
+                vec![
+                    "--nocapture".to_owned(),
+                    "--test-threads".to_owned(),
+                    "1".to_owned()
+                ]
+            )
+        );
+        assert_eq!(split_inline_args("test --"), ("test", Vec::new()));
+        assert_eq!(
+            split_inline_args("run --release"),
+            ("run --release", Vec::new()),
+            "Flags without the separator should stay in the query"
+        );
+    }
+
     fn open_spawn_tasks(
         workspace: &View<Workspace>,
         cx: &mut VisualTestContext,
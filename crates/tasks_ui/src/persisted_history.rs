@@ -0,0 +1,437 @@
+use std::{cell::Cell, path::PathBuf, rc::Rc};
+
+use collections::HashMap;
+use db::kvp::KEY_VALUE_STORE;
+use gpui::{AppContext, Model, ViewContext};
+use itertools::Itertools;
+use project::{Inventory, TaskSourceKind};
+use serde::{Deserialize, Serialize};
+use settings::Settings;
+use task::{oneshot_source::OneshotSource, TaskId};
+use util::ResultExt;
+use workspace::Workspace;
+
+use crate::{
+    restorable_tasks::{project_key, SerializedTask},
+    settings::TaskSettings,
+    watching::PROJECT_OPEN_DEBOUNCE,
+};
+
+const TASK_HISTORY_KEY_PREFIX: &str = "task_history";
+const PINNED_TASKS_KEY_PREFIX: &str = "pinned_tasks";
+
+/// A scheduled task of the project's history, stored when the app quits.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct SerializedScheduledTask {
+    pub(crate) name: String,
+    pub(crate) source: SerializedTaskSource,
+    #[serde(flatten)]
+    pub(crate) task: SerializedTask,
+}
+
+/// The source of a stored task, without the ids that change between the app restarts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum SerializedTaskSource {
+    UserInput,
+    AbsPath(PathBuf),
+    Worktree(PathBuf),
+    InMemory(String),
+}
+
+impl SerializedTaskSource {
+    /// Language tasks are not stored, as they are only listed for the buffers of their language.
+    fn new(source_kind: &TaskSourceKind) -> Option<Self> {
+        match source_kind {
+            TaskSourceKind::UserInput => Some(Self::UserInput),
+            TaskSourceKind::AbsPath(abs_path) => Some(Self::AbsPath(abs_path.clone())),
+            TaskSourceKind::Worktree { abs_path, .. } => Some(Self::Worktree(abs_path.clone())),
+            TaskSourceKind::InMemory { name } => Some(Self::InMemory(name.to_string())),
+            TaskSourceKind::Language { .. } => None,
+        }
+    }
+
+    fn matches(&self, source_kind: &TaskSourceKind) -> bool {
+        Self::new(source_kind).as_ref() == Some(self)
+    }
+}
+
+/// The most recently scheduled tasks of the inventory, the oldest first, at most `limit` of them.
+pub(crate) fn task_history_to_persist(
+    inventory: &Model<Inventory>,
+    limit: usize,
+    cx: &mut AppContext,
+) -> Vec<SerializedScheduledTask> {
+    inventory.update(cx, |inventory, cx| {
+        let source_kinds = inventory
+            .list_tasks(None, None, false, cx)
+            .into_iter()
+            .map(|(source_kind, task)| (task.id().clone(), source_kind))
+            .collect::<HashMap<_, _>>();
+        let mut history = inventory
+            .scheduled_tasks_history()
+            .rev()
+            .filter_map(|scheduled| {
+                let source = SerializedTaskSource::new(source_kinds.get(scheduled.task.id())?)?;
+                Some(SerializedScheduledTask {
+                    name: scheduled.task.name().to_owned(),
+                    source,
+                    task: SerializedTask::new(scheduled.task.id(), scheduled.task_context.clone()),
+                })
+            })
+            .take(limit)
+            .collect::<Vec<_>>();
+        history.reverse();
+        history
+    })
+}
+
+/// Stores the recently scheduled and the pinned tasks of the project when the app quits,
+/// and restores them into the inventory, once the project is reopened.
+pub(crate) fn persist_task_history(workspace: &Workspace, cx: &mut ViewContext<Workspace>) {
+    workspace.project().update(cx, |_, cx| {
+        cx.on_app_quit(|project, cx| {
+            let key = project_key(TASK_HISTORY_KEY_PREFIX, project, cx);
+            let pinned_key = project_key(PINNED_TASKS_KEY_PREFIX, project, cx);
+            let limit = TaskSettings::get_global(cx).persisted_history_size;
+            let history = task_history_to_persist(project.task_inventory(), limit, cx);
+            let pinned_tasks = pinned_tasks_to_persist(project.task_inventory(), cx);
+            async move {
+                if let Some(key) = key {
+                    if history.is_empty() {
+                        KEY_VALUE_STORE.delete_kvp(key).await.log_err();
+                    } else if let Some(history) = serde_json::to_string(&history).log_err() {
+                        KEY_VALUE_STORE.write_kvp(key, history).await.log_err();
+                    }
+                }
+                if let Some(pinned_key) = pinned_key {
+                    if pinned_tasks.is_empty() {
+                        KEY_VALUE_STORE.delete_kvp(pinned_key).await.log_err();
+                    } else if let Some(pinned_tasks) =
+                        serde_json::to_string(&pinned_tasks).log_err()
+                    {
+                        KEY_VALUE_STORE
+                            .write_kvp(pinned_key, pinned_tasks)
+                            .await
+                            .log_err();
+                    }
+                }
+            }
+        })
+        .detach();
+    });
+
+    let inventory = workspace.project().read(cx).task_inventory().clone();
+    restore_pinned_tasks(cx).detach();
+    let restored = Rc::new(Cell::new(false));
+    let mut pending_restore = Some(restore_task_history(restored.clone(), cx));
+    cx.observe(&inventory, move |_, _, cx| {
+        if !restored.get() {
+            pending_restore = Some(restore_task_history(restored.clone(), cx));
+        }
+    })
+    .detach();
+}
+
+/// Ids of the pinned tasks of the inventory, sorted to store them the same way on every quit.
+pub(crate) fn pinned_tasks_to_persist(
+    inventory: &Model<Inventory>,
+    cx: &AppContext,
+) -> Vec<String> {
+    inventory
+        .read(cx)
+        .pinned_tasks()
+        .map(|id| id.0.clone())
+        .sorted()
+        .collect()
+}
+
+/// Pins the stored pinned tasks in the inventory, once the project settles down.
+/// The ids of the tasks that are not present in the inventory are kept, in case their sources are loaded later.
+fn restore_pinned_tasks(cx: &mut ViewContext<Workspace>) -> gpui::Task<()> {
+    cx.spawn(|workspace, mut cx| async move {
+        cx.background_executor().timer(PROJECT_OPEN_DEBOUNCE).await;
+        workspace
+            .update(&mut cx, |workspace, cx| {
+                let project = workspace.project().read(cx);
+                let Some(pinned_tasks) = project_key(PINNED_TASKS_KEY_PREFIX, project, cx)
+                    .and_then(|key| KEY_VALUE_STORE.read_kvp(&key).log_err().flatten())
+                    .and_then(|pinned_tasks| {
+                        serde_json::from_str::<Vec<String>>(&pinned_tasks).log_err()
+                    })
+                else {
+                    return;
+                };
+                project
+                    .task_inventory()
+                    .clone()
+                    .update(cx, |inventory, cx| {
+                        inventory.restore_pinned_tasks(pinned_tasks.into_iter().map(TaskId));
+                        cx.notify();
+                    });
+            })
+            .ok();
+    })
+}
+
+/// Adds the stored tasks that are still present in the inventory to its history, once the task sources settle down.
+/// Oneshot tasks are recreated from their commands.
+fn restore_task_history(
+    restored: Rc<Cell<bool>>,
+    cx: &mut ViewContext<Workspace>,
+) -> gpui::Task<()> {
+    cx.spawn(|workspace, mut cx| async move {
+        cx.background_executor().timer(PROJECT_OPEN_DEBOUNCE).await;
+        workspace
+            .update(&mut cx, |workspace, cx| {
+                if restored.get() {
+                    return;
+                }
+                let project = workspace.project().read(cx);
+                let Some(history) = project_key(TASK_HISTORY_KEY_PREFIX, project, cx)
+                    .and_then(|key| KEY_VALUE_STORE.read_kvp(&key).log_err().flatten())
+                    .and_then(|history| {
+                        serde_json::from_str::<Vec<SerializedScheduledTask>>(&history).log_err()
+                    })
+                else {
+                    restored.set(true);
+                    return;
+                };
+                let inventory = project.task_inventory().clone();
+                let oneshot_source = inventory.read(cx).source::<OneshotSource>();
+                let tasks = history
+                    .into_iter()
+                    .filter_map(|serialized| {
+                        let task = if serialized.source == SerializedTaskSource::UserInput {
+                            oneshot_source.as_ref()?.update(cx, |oneshot_source, _| {
+                                Some(
+                                    oneshot_source
+                                        .as_any()
+                                        .downcast_mut::<OneshotSource>()?
+                                        .spawn(serialized.name.clone()),
+                                )
+                            })?
+                        } else {
+                            let (source_kind, task) = inventory
+                                .read(cx)
+                                .task_by_id(&TaskId(serialized.task.id.clone()))?;
+                            if !serialized.source.matches(&source_kind)
+                                || task.name() != serialized.name
+                            {
+                                return None;
+                            }
+                            task
+                        };
+                        Some((task, serialized.task.task_context()))
+                    })
+                    .collect::<Vec<_>>();
+                if tasks.is_empty() {
+                    return;
+                }
+
+                restored.set(true);
+                inventory.update(cx, |inventory, _| inventory.restore_scheduled_tasks(tasks));
+            })
+            .ok();
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use gpui::TestAppContext;
+    use project::{FakeFs, Project};
+    use serde_json::json;
+    use task::{TaskContext, TaskVariables, VariableName};
+
+    use super::*;
+
+    #[gpui::test]
+    async fn test_task_history_restored_on_reopen(cx: &mut TestAppContext) {
+        crate::tests::init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/history_project",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[
+                        { "label": "build", "command": "make", "args": ["build"] },
+                        { "label": "test $ZED_SYMBOL", "command": "make", "args": ["test", "$ZED_SYMBOL"] }
+                    ]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        let symbol_context = |symbol: &str| TaskContext {
+            cwd: Some(PathBuf::from("/history_project")),
+            task_variables: TaskVariables::from_iter([(VariableName::Symbol, symbol.to_owned())]),
+            ..TaskContext::default()
+        };
+
+        let project = Project::test(fs.clone(), ["/history_project".as_ref()], cx).await;
+        let inventory = project.update(cx, |project, cx| {
+            let inventory = project.task_inventory().clone();
+            inventory.update(cx, |inventory, cx| {
+                inventory.add_source(TaskSourceKind::UserInput, |cx| OneshotSource::new(cx), cx)
+            });
+            inventory
+        });
+        cx.run_until_parked();
+        inventory.update(cx, |inventory, cx| {
+            let tasks = inventory.list_tasks(None, None, false, cx);
+            let task_named = |name: &str| {
+                tasks
+                    .iter()
+                    .find(|(_, task)| task.name() == name)
+                    .unwrap()
+                    .1
+                    .clone()
+            };
+            let oneshot_task =
+                inventory
+                    .source::<OneshotSource>()
+                    .unwrap()
+                    .update(cx, |oneshot_source, _| {
+                        oneshot_source
+                            .as_any()
+                            .downcast_mut::<OneshotSource>()
+                            .unwrap()
+                            .spawn("echo hello".to_owned())
+                    });
+            inventory.task_scheduled(task_named("build"), TaskContext::default());
+            inventory.task_scheduled(task_named("test $ZED_SYMBOL"), symbol_context("foo"));
+            inventory.task_scheduled(oneshot_task, TaskContext::default());
+            inventory.task_scheduled(task_named("test $ZED_SYMBOL"), symbol_context("bar"));
+        });
+        let (key, mut history) = cx.update(|cx| {
+            (
+                project_key(TASK_HISTORY_KEY_PREFIX, project.read(cx), cx).unwrap(),
+                task_history_to_persist(&inventory, 3, cx),
+            )
+        });
+        assert_eq!(
+            history
+                .iter()
+                .map(|serialized| serialized.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["test $ZED_SYMBOL", "echo hello", "test $ZED_SYMBOL"],
+            "Only the most recent tasks should be stored, the oldest first"
+        );
+        history.insert(
+            0,
+            SerializedScheduledTask {
+                name: "removed task".to_owned(),
+                source: SerializedTaskSource::Worktree(PathBuf::from(
+                    "/history_project/.zed/tasks.json",
+                )),
+                task: SerializedTask::new(
+                    &TaskId("removed_task".to_owned()),
+                    TaskContext::default(),
+                ),
+            },
+        );
+        KEY_VALUE_STORE
+            .write_kvp(key, serde_json::to_string(&history).unwrap())
+            .await
+            .unwrap();
+
+        let reopened_project = Project::test(fs, ["/history_project".as_ref()], cx).await;
+        let reopened_inventory = reopened_project.update(cx, |project, cx| {
+            let inventory = project.task_inventory().clone();
+            inventory.update(cx, |inventory, cx| {
+                inventory.add_source(TaskSourceKind::UserInput, |cx| OneshotSource::new(cx), cx)
+            });
+            inventory
+        });
+        cx.run_until_parked();
+        let (_, cx) = cx.add_window_view(|cx| Workspace::test_new(reopened_project.clone(), cx));
+        cx.executor().advance_clock(PROJECT_OPEN_DEBOUNCE);
+        cx.run_until_parked();
+        reopened_inventory.update(cx, |inventory, _| {
+            assert_eq!(
+                inventory
+                    .scheduled_tasks_history()
+                    .map(|scheduled| (
+                        scheduled.resolved_label.clone(),
+                        scheduled.task_context.clone()
+                    ))
+                    .collect::<Vec<_>>(),
+                vec![
+                    ("test foo".to_owned(), symbol_context("foo")),
+                    ("echo hello".to_owned(), TaskContext::default()),
+                    ("test bar".to_owned(), symbol_context("bar")),
+                ],
+                "Stored tasks present in the inventory should be restored with their contexts"
+            );
+            assert_eq!(
+                inventory
+                    .last_scheduled_task()
+                    .map(|(task, _)| task.name().to_owned()),
+                Some("test $ZED_SYMBOL".to_owned())
+            );
+        });
+    }
+
+    #[gpui::test]
+    async fn test_pinned_tasks_restored_on_reopen(cx: &mut TestAppContext) {
+        crate::tests::init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/pinned_project",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[
+                        { "label": "build", "command": "make", "args": ["build"] },
+                        { "label": "test", "command": "make", "args": ["test"] }
+                    ]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+
+        let project = Project::test(fs.clone(), ["/pinned_project".as_ref()], cx).await;
+        let inventory = project.read_with(cx, |project, _| project.task_inventory().clone());
+        cx.run_until_parked();
+        let test_task_id = inventory.update(cx, |inventory, cx| {
+            let (_, task) = inventory
+                .list_tasks(None, None, false, cx)
+                .into_iter()
+                .find(|(_, task)| task.name() == "test")
+                .unwrap();
+            inventory.toggle_pinned(task.id());
+            task.id().clone()
+        });
+        let (key, pinned_tasks) = cx.update(|cx| {
+            (
+                project_key(PINNED_TASKS_KEY_PREFIX, project.read(cx), cx).unwrap(),
+                pinned_tasks_to_persist(&inventory, cx),
+            )
+        });
+        assert_eq!(pinned_tasks, vec![test_task_id.0.clone()]);
+        KEY_VALUE_STORE
+            .write_kvp(key, serde_json::to_string(&pinned_tasks).unwrap())
+            .await
+            .unwrap();
+
+        let reopened_project = Project::test(fs, ["/pinned_project".as_ref()], cx).await;
+        let reopened_inventory =
+            reopened_project.read_with(cx, |project, _| project.task_inventory().clone());
+        cx.run_until_parked();
+        let (_, cx) = cx.add_window_view(|cx| Workspace::test_new(reopened_project.clone(), cx));
+        cx.executor().advance_clock(PROJECT_OPEN_DEBOUNCE);
+        cx.run_until_parked();
+        reopened_inventory.update(cx, |inventory, cx| {
+            assert!(inventory.is_pinned(&test_task_id));
+            assert_eq!(
+                inventory
+                    .list_tasks(None, None, true, cx)
+                    .into_iter()
+                    .map(|(_, task)| task.name().to_owned())
+                    .collect::<Vec<_>>(),
+                vec!["test".to_owned(), "build".to_owned()],
+                "Restored pinned task should be listed first"
+            );
+        });
+    }
+}
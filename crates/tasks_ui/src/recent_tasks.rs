@@ -0,0 +1,304 @@
+use std::sync::Arc;
+
+use gpui::{
+    rems, DismissEvent, EventEmitter, FocusableView, InteractiveElement, Modifiers,
+    ModifiersChangedEvent, ParentElement, Render, SharedString, Styled, Subscription, View,
+    ViewContext, VisualContext, WeakView,
+};
+use picker::{Picker, PickerDelegate};
+use project::ScheduledTask;
+use ui::{v_flex, Label, ListItem, ListItemSpacing, Selectable, WindowContext};
+use util::ResultExt;
+use workspace::{ModalView, Workspace};
+
+use crate::schedule_task;
+
+/// How many of the recently scheduled tasks the switcher cycles through.
+const RECENT_TASKS_LIMIT: usize = 5;
+
+/// Opens the switcher of the recent tasks, or highlights the next task in it, if it's open already.
+pub(crate) fn cycle_recent_tasks(workspace: &mut Workspace, cx: &mut ViewContext<Workspace>) {
+    if let Some(switcher) = workspace.active_modal::<RecentTasksSwitcher>(cx) {
+        switcher.update(cx, |switcher, cx| {
+            switcher
+                .picker
+                .update(cx, |picker, cx| picker.cycle_selection(cx))
+        });
+        return;
+    }
+    let recent_tasks = workspace
+        .project()
+        .read(cx)
+        .task_inventory()
+        .read(cx)
+        .recently_scheduled_tasks(RECENT_TASKS_LIMIT);
+    if recent_tasks.is_empty() {
+        return;
+    }
+    let workspace_handle = workspace.weak_handle();
+    workspace.toggle_modal(cx, |cx| {
+        RecentTasksSwitcher::new(recent_tasks, workspace_handle, cx)
+    });
+}
+
+/// A transient overlay with the recently scheduled tasks, that reruns the highlighted one in its last context,
+/// once the modifiers held while opening it are released.
+pub(crate) struct RecentTasksSwitcher {
+    pub(crate) picker: View<Picker<RecentTasksDelegate>>,
+    init_modifiers: Option<Modifiers>,
+    _subscription: Subscription,
+}
+
+impl RecentTasksSwitcher {
+    fn new(
+        recent_tasks: Vec<ScheduledTask>,
+        workspace: WeakView<Workspace>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let picker = cx.new_view(|cx| {
+            Picker::nonsearchable_uniform_list(
+                RecentTasksDelegate {
+                    recent_tasks,
+                    selected_index: 0,
+                    workspace,
+                },
+                cx,
+            )
+        });
+        let _subscription = cx.subscribe(&picker, |_, _, _, cx| {
+            cx.emit(DismissEvent);
+        });
+        Self {
+            picker,
+            init_modifiers: cx.modifiers().modified().then_some(cx.modifiers()),
+            _subscription,
+        }
+    }
+
+    fn handle_modifiers_changed(
+        &mut self,
+        event: &ModifiersChangedEvent,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let Some(init_modifiers) = self.init_modifiers else {
+            return;
+        };
+        if !event.modified() || !init_modifiers.is_subset_of(event) {
+            self.init_modifiers = None;
+            self.picker
+                .update(cx, |picker, cx| picker.delegate.confirm(false, cx));
+        }
+    }
+}
+
+impl Render for RecentTasksSwitcher {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl gpui::prelude::IntoElement {
+        v_flex()
+            .key_context("RecentTasksSwitcher")
+            .w(rems(28.))
+            .on_modifiers_changed(cx.listener(Self::handle_modifiers_changed))
+            .child(self.picker.clone())
+    }
+}
+
+impl EventEmitter<DismissEvent> for RecentTasksSwitcher {}
+
+impl FocusableView for RecentTasksSwitcher {
+    fn focus_handle(&self, cx: &gpui::AppContext) -> gpui::FocusHandle {
+        self.picker.read(cx).focus_handle(cx)
+    }
+}
+
+impl ModalView for RecentTasksSwitcher {}
+
+pub(crate) struct RecentTasksDelegate {
+    recent_tasks: Vec<ScheduledTask>,
+    selected_index: usize,
+    workspace: WeakView<Workspace>,
+}
+
+impl PickerDelegate for RecentTasksDelegate {
+    type ListItem = ListItem;
+
+    fn match_count(&self) -> usize {
+        self.recent_tasks.len()
+    }
+
+    fn selected_index(&self) -> usize {
+        self.selected_index
+    }
+
+    fn set_selected_index(&mut self, ix: usize, cx: &mut ViewContext<Picker<Self>>) {
+        self.selected_index = ix;
+        cx.notify();
+    }
+
+    fn placeholder_text(&self, _: &mut WindowContext) -> Arc<str> {
+        "".into()
+    }
+
+    fn no_matches_text(&self, _: &mut WindowContext) -> SharedString {
+        "No recent tasks".into()
+    }
+
+    fn update_matches(&mut self, _: String, _: &mut ViewContext<Picker<Self>>) -> gpui::Task<()> {
+        gpui::Task::ready(())
+    }
+
+    fn confirm(&mut self, omit_history_entry: bool, cx: &mut ViewContext<Picker<Self>>) {
+        let Some(scheduled) = self.recent_tasks.get(self.selected_index).cloned() else {
+            return;
+        };
+        self.workspace
+            .update(cx, |workspace, cx| {
+                schedule_task(
+                    workspace,
+                    &scheduled.task,
+                    scheduled.task_context,
+                    omit_history_entry,
+                    cx,
+                );
+            })
+            .log_err();
+        cx.emit(DismissEvent);
+    }
+
+    fn dismissed(&mut self, cx: &mut ViewContext<Picker<Self>>) {
+        cx.emit(DismissEvent);
+    }
+
+    fn render_match(
+        &self,
+        ix: usize,
+        selected: bool,
+        _: &mut ViewContext<Picker<Self>>,
+    ) -> Option<Self::ListItem> {
+        let scheduled = self.recent_tasks.get(ix)?;
+        Some(
+            ListItem::new(SharedString::from(format!("recent-task-{ix}")))
+                .inset(true)
+                .spacing(ListItemSpacing::Sparse)
+                .selected(selected)
+                .child(Label::new(scheduled.resolved_label.clone())),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use gpui::{TestAppContext, VisualTestContext};
+    use project::{FakeFs, Project};
+    use serde_json::json;
+
+    use super::*;
+    use crate::modal::{CycleRecentTasks, Spawn};
+
+    #[gpui::test]
+    async fn test_recent_task_spawned_on_modifiers_release(cx: &mut TestAppContext) {
+        crate::tests::init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[
+                        { "label": "build", "command": "make", "args": ["build"] },
+                        { "label": "test", "command": "make", "args": ["test"] },
+                        { "label": "lint", "command": "make", "args": ["lint"] }
+                    ]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        let spawned_tasks = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let spawned_tasks = spawned_tasks.clone();
+            cx.subscribe(&workspace, move |_, event, _| {
+                if let workspace::Event::SpawnTask(spawn_in_terminal) = event {
+                    spawned_tasks
+                        .borrow_mut()
+                        .push(spawn_in_terminal.label.clone());
+                }
+            })
+            .detach();
+        });
+        cx.run_until_parked();
+        for task_name in ["build", "test", "lint", "test"] {
+            cx.dispatch_action(Spawn {
+                task_name: Some(task_name.to_string()),
+                omit_history: false,
+                split: None,
+            });
+            cx.run_until_parked();
+        }
+        spawned_tasks.borrow_mut().clear();
+
+        cx.simulate_modifiers_change(Modifiers::control());
+        cx.dispatch_action(CycleRecentTasks);
+        assert_eq!(
+            recent_task_names(&workspace, cx),
+            vec!["test", "lint", "build"],
+            "Recent tasks should be listed once, the latest first"
+        );
+        assert_eq!(selected_task_name(&workspace, cx), "test");
+        cx.dispatch_action(CycleRecentTasks);
+        cx.dispatch_action(CycleRecentTasks);
+        assert_eq!(
+            selected_task_name(&workspace, cx),
+            "build",
+            "Repeated presses should advance the selection"
+        );
+        assert!(
+            spawned_tasks.borrow().is_empty(),
+            "No task should be spawned while the modifiers are held"
+        );
+
+        cx.simulate_modifiers_change(Modifiers::none());
+        cx.run_until_parked();
+        assert_eq!(*spawned_tasks.borrow(), vec!["build".to_owned()]);
+        workspace.update(cx, |workspace, cx| {
+            assert!(
+                workspace.active_modal::<RecentTasksSwitcher>(cx).is_none(),
+                "Switcher should be closed after spawning the task"
+            );
+        });
+    }
+
+    fn switcher_picker(
+        workspace: &View<Workspace>,
+        cx: &mut VisualTestContext,
+    ) -> View<Picker<RecentTasksDelegate>> {
+        workspace.update(cx, |workspace, cx| {
+            workspace
+                .active_modal::<RecentTasksSwitcher>(cx)
+                .expect("Recent tasks switcher should be open")
+                .read(cx)
+                .picker
+                .clone()
+        })
+    }
+
+    fn recent_task_names(workspace: &View<Workspace>, cx: &mut VisualTestContext) -> Vec<String> {
+        switcher_picker(workspace, cx).update(cx, |picker, _| {
+            picker
+                .delegate
+                .recent_tasks
+                .iter()
+                .map(|scheduled| scheduled.resolved_label.clone())
+                .collect()
+        })
+    }
+
+    fn selected_task_name(workspace: &View<Workspace>, cx: &mut VisualTestContext) -> String {
+        switcher_picker(workspace, cx).update(cx, |picker, _| {
+            picker.delegate.recent_tasks[picker.delegate.selected_index]
+                .resolved_label
+                .clone()
+        })
+    }
+}
@@ -0,0 +1,285 @@
+use std::{cell::Cell, path::PathBuf, rc::Rc};
+
+use db::kvp::KEY_VALUE_STORE;
+use gpui::{AppContext, PromptLevel, ViewContext};
+use project::Project;
+use serde::{Deserialize, Serialize};
+use task::{TaskContext, TaskId, TaskVariables, VariableName};
+use util::ResultExt;
+use workspace::Workspace;
+
+use crate::{schedule_task, watching::PROJECT_OPEN_DEBOUNCE};
+
+/// A task with `restore_on_reopen` that was running when the app quit, with the context it was spawned in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct SerializedTask {
+    pub(crate) id: String,
+    pub(crate) cwd: Option<PathBuf>,
+    pub(crate) variables: Vec<(VariableName, String)>,
+}
+
+impl SerializedTask {
+    pub(crate) fn new(id: &TaskId, task_context: TaskContext) -> Self {
+        Self {
+            id: id.0.clone(),
+            cwd: task_context.cwd,
+            variables: task_context
+                .task_variables
+                .iter()
+                .map(|(name, value)| (name.clone(), value.to_owned()))
+                .collect(),
+        }
+    }
+
+    pub(crate) fn task_context(self) -> TaskContext {
+        TaskContext {
+            cwd: self.cwd,
+            task_variables: TaskVariables::from_iter(self.variables),
+            selection_fallbacks: Default::default(),
+            args_override: Default::default(),
+        }
+    }
+}
+
+/// A key to store the running tasks of the project under, based on the paths of its worktrees.
+pub(crate) fn restorable_tasks_key(project: &Project, cx: &AppContext) -> Option<String> {
+    project_key("running_restorable_tasks", project, cx)
+}
+
+/// A key to store the project's data under, based on the paths of its worktrees.
+pub(crate) fn project_key(prefix: &str, project: &Project, cx: &AppContext) -> Option<String> {
+    let mut worktree_paths = project
+        .visible_worktrees(cx)
+        .map(|worktree| worktree.read(cx).abs_path().to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    if worktree_paths.is_empty() {
+        return None;
+    }
+    worktree_paths.sort();
+    Some(format!("{prefix}-{}", worktree_paths.join(":")))
+}
+
+/// Tasks with `restore_on_reopen` that are running in the project's terminals.
+fn running_restorable_tasks(project: &Project, cx: &AppContext) -> Vec<SerializedTask> {
+    let inventory = project.task_inventory().read(cx);
+    project
+        .local_terminal_handles()
+        .iter()
+        .filter_map(|handle| handle.upgrade())
+        .filter_map(|terminal| {
+            let task_state = terminal.read(cx).task()?;
+            if task_state.status != terminal::TaskStatus::Running {
+                return None;
+            }
+            let (_, task) = inventory.task_by_id(&task_state.id)?;
+            if !task.restore_on_reopen() {
+                return None;
+            }
+            let task_context = inventory
+                .last_scheduled_context(&task_state.id)
+                .unwrap_or_else(|| TaskContext {
+                    cwd: task_state.cwd.clone(),
+                    ..TaskContext::default()
+                });
+            Some(SerializedTask::new(&task_state.id, task_context))
+        })
+        .collect()
+}
+
+/// Records the restorable tasks running when the app quits, and offers restarting them, once the project is reopened.
+pub(crate) fn restore_tasks_on_reopen(workspace: &Workspace, cx: &mut ViewContext<Workspace>) {
+    workspace.project().update(cx, |_, cx| {
+        cx.on_app_quit(|project, cx| {
+            let key = restorable_tasks_key(project, cx);
+            let running_tasks = running_restorable_tasks(project, cx);
+            async move {
+                let Some(key) = key else {
+                    return;
+                };
+                if running_tasks.is_empty() {
+                    KEY_VALUE_STORE.delete_kvp(key).await.log_err();
+                } else if let Some(running_tasks) = serde_json::to_string(&running_tasks).log_err()
+                {
+                    KEY_VALUE_STORE
+                        .write_kvp(key, running_tasks)
+                        .await
+                        .log_err();
+                }
+            }
+        })
+        .detach();
+    });
+
+    let inventory = workspace.project().read(cx).task_inventory().clone();
+    let restart_offered = Rc::new(Cell::new(false));
+    let mut pending_offer = Some(offer_restart(restart_offered.clone(), cx));
+    cx.observe(&inventory, move |_, _, cx| {
+        if !restart_offered.get() {
+            pending_offer = Some(offer_restart(restart_offered.clone(), cx));
+        }
+    })
+    .detach();
+}
+
+/// Asks whether to restart the recorded tasks that are still present in the inventory, once the task sources settle down.
+fn offer_restart(
+    restart_offered: Rc<Cell<bool>>,
+    cx: &mut ViewContext<Workspace>,
+) -> gpui::Task<()> {
+    cx.spawn(|workspace, mut cx| async move {
+        cx.background_executor().timer(PROJECT_OPEN_DEBOUNCE).await;
+        workspace
+            .update(&mut cx, |workspace, cx| {
+                if restart_offered.get() {
+                    return;
+                }
+                let project = workspace.project().read(cx);
+                let Some(key) = restorable_tasks_key(project, cx) else {
+                    return;
+                };
+                let Some(serialized_tasks) = KEY_VALUE_STORE
+                    .read_kvp(&key)
+                    .log_err()
+                    .flatten()
+                    .and_then(|tasks| {
+                        serde_json::from_str::<Vec<SerializedTask>>(&tasks).log_err()
+                    })
+                else {
+                    return;
+                };
+                let inventory = project.task_inventory().read(cx);
+                let tasks = serialized_tasks
+                    .into_iter()
+                    .filter_map(|serialized_task| {
+                        let (_, task) =
+                            inventory.task_by_id(&TaskId(serialized_task.id.clone()))?;
+                        task.restore_on_reopen()
+                            .then(|| (task, serialized_task.task_context()))
+                    })
+                    .collect::<Vec<_>>();
+                if tasks.is_empty() {
+                    return;
+                }
+
+                restart_offered.set(true);
+                db::write_and_log(cx, move || KEY_VALUE_STORE.delete_kvp(key));
+                let task_names = tasks
+                    .iter()
+                    .map(|(task, _)| task.name())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let answer = cx.prompt(
+                    PromptLevel::Info,
+                    "Restart the tasks that were running before Zed quit?",
+                    Some(&task_names),
+                    &["Restart", "Cancel"],
+                );
+                cx.spawn(|workspace, mut cx| async move {
+                    if answer.await != Ok(0) {
+                        return;
+                    }
+                    workspace
+                        .update(&mut cx, |workspace, cx| {
+                            for (task, task_context) in tasks {
+                                schedule_task(workspace, &task, task_context, false, cx);
+                            }
+                        })
+                        .ok();
+                })
+                .detach();
+            })
+            .ok();
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use gpui::TestAppContext;
+    use project::{FakeFs, Project};
+    use serde_json::json;
+
+    use super::*;
+
+    #[gpui::test]
+    async fn test_running_task_is_offered_for_restart(cx: &mut TestAppContext) {
+        crate::tests::init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/restored_project",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[
+                        { "label": "dev server", "command": "npm", "args": ["run", "dev"], "restore_on_reopen": true },
+                        { "label": "build", "command": "npm", "args": ["run", "build"] }
+                    ]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        let project = Project::test(fs, ["/restored_project".as_ref()], cx).await;
+        cx.run_until_parked();
+        let (key, task_ids) = project.update(cx, |project, cx| {
+            let task_ids = project
+                .task_inventory()
+                .update(cx, |inventory, cx| {
+                    inventory.list_tasks(None, None, false, cx)
+                })
+                .into_iter()
+                .map(|(_, task)| task.id().0.clone())
+                .collect::<Vec<_>>();
+            (restorable_tasks_key(project, cx).unwrap(), task_ids)
+        });
+        let recorded_tasks = task_ids
+            .into_iter()
+            .chain(["removed_task".to_owned()])
+            .map(|id| SerializedTask {
+                id,
+                cwd: Some(PathBuf::from("/restored_project/server")),
+                variables: vec![(VariableName::WorktreeRoot, "/restored_project".to_owned())],
+            })
+            .collect::<Vec<_>>();
+        KEY_VALUE_STORE
+            .write_kvp(key.clone(), serde_json::to_string(&recorded_tasks).unwrap())
+            .await
+            .unwrap();
+
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        let spawned_tasks = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let spawned_tasks = spawned_tasks.clone();
+            cx.subscribe(&workspace, move |_, event, _| {
+                if let workspace::Event::SpawnTask(spawn_in_terminal) = event {
+                    spawned_tasks.borrow_mut().push((
+                        spawn_in_terminal.label.clone(),
+                        spawn_in_terminal.cwd.clone(),
+                    ));
+                }
+            })
+            .detach();
+        });
+        cx.executor().advance_clock(PROJECT_OPEN_DEBOUNCE);
+        cx.run_until_parked();
+        assert!(
+            cx.has_pending_prompt(),
+            "Recorded task should be offered for restart on reopen"
+        );
+        cx.simulate_prompt_answer(0);
+        cx.run_until_parked();
+        assert_eq!(
+            *spawned_tasks.borrow(),
+            vec![(
+                "dev server".to_owned(),
+                Some(PathBuf::from("/restored_project/server"))
+            )],
+            "Only the recorded tasks present in the inventory and restorable should be restarted, in their prior context"
+        );
+        assert_eq!(
+            KEY_VALUE_STORE.read_kvp(&key).unwrap(),
+            None,
+            "Recorded tasks should be offered for restart once"
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use std::sync::Arc;
+
+use anyhow::Context as _;
+use collections::HashMap;
+use gpui::{AppContext, Global, ViewContext};
+use task::SpawnInTerminal;
+use workspace::Workspace;
+
+use crate::command_output::spawn_with_command_output;
+
+/// A storage of the values for the `secret:<key>` env variables of the tasks.
+pub(crate) trait SecretStore {
+    fn read_secret(&self, key: &str, cx: &AppContext)
+        -> gpui::Task<anyhow::Result<Option<String>>>;
+}
+
+/// Secrets stored in the OS keychain, as the passwords of the credentials with the secret key for the URL.
+pub(crate) struct KeychainSecrets;
+
+impl SecretStore for KeychainSecrets {
+    fn read_secret(
+        &self,
+        key: &str,
+        cx: &AppContext,
+    ) -> gpui::Task<anyhow::Result<Option<String>>> {
+        let credentials = cx.read_credentials(key);
+        cx.spawn(|_| async move {
+            let Some((_, password)) = credentials.await? else {
+                return Ok(None);
+            };
+            Ok(Some(
+                String::from_utf8(password).context("secret is not valid UTF-8")?,
+            ))
+        })
+    }
+}
+
+/// The store to read the task secrets from.
+pub(crate) struct TaskSecrets(pub(crate) Arc<dyn SecretStore>);
+
+impl Global for TaskSecrets {}
+
+/// Reads the secrets of the task into its env and spawns it, showing an error instead if any of them cannot be read.
+pub(crate) fn spawn_with_secrets(
+    workspace: &Workspace,
+    mut spawn_in_terminal: SpawnInTerminal,
+    spawn: impl FnOnce(&Workspace, SpawnInTerminal, &mut ViewContext<'_, Workspace>) + 'static,
+    cx: &mut ViewContext<'_, Workspace>,
+) {
+    if spawn_in_terminal.secrets.is_empty() {
+        spawn_with_command_output(workspace, spawn_in_terminal, spawn, cx);
+        return;
+    }
+    let store = cx.global::<TaskSecrets>().0.clone();
+    let reads = spawn_in_terminal
+        .secrets
+        .clone()
+        .into_iter()
+        .map(|(name, key)| {
+            let read = store.read_secret(&key, cx);
+            async move {
+                let value = read
+                    .await
+                    .with_context(|| format!("reading secret {key:?}"))?
+                    .with_context(|| format!("secret {key:?} is not found"))?;
+                anyhow::Ok((name, value))
+            }
+        })
+        .collect::<Vec<_>>();
+    cx.spawn(|workspace, mut cx| async move {
+        let secrets = async {
+            let mut secrets = Vec::with_capacity(reads.len());
+            for read in reads {
+                secrets.push(read.await?);
+            }
+            anyhow::Ok(secrets)
+        }
+        .await;
+        workspace
+            .update(&mut cx, |workspace, cx| match secrets {
+                Ok(secrets) => {
+                    spawn_in_terminal.env.extend(secrets);
+                    spawn_with_command_output(workspace, spawn_in_terminal, spawn, cx);
+                }
+                Err(e) => workspace.show_error(&e, cx),
+            })
+            .ok();
+    })
+    .detach();
+}
+
+/// Env variables set from the secrets, with the values masked, one line per variable, sorted by the variable name.
+pub(crate) fn secrets_summary(secrets: &HashMap<String, String>) -> String {
+    let mut secrets = secrets
+        .iter()
+        .map(|(name, key)| format!("{name}: ******** (secret {key:?})"))
+        .collect::<Vec<_>>();
+    secrets.sort();
+    secrets.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use gpui::TestAppContext;
+    use project::{FakeFs, Project};
+    use serde_json::json;
+
+    use crate::{
+        modal::Spawn,
+        tests::{init_test, FakeSecretStore},
+    };
+
+    use super::*;
+
+    #[gpui::test]
+    async fn test_task_secrets(cx: &mut TestAppContext) {
+        init_test(cx);
+        cx.update(|cx| {
+            cx.set_global(TaskSecrets(Arc::new(FakeSecretStore(HashMap::from_iter(
+                [("deploy-token".to_string(), "s3cr3t".to_string())],
+            )))))
+        });
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[{ "label": "deploy", "command": "deploy", "args": ["--token-from-env"], "env": { "TOKEN": "secret:deploy-token" } }]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        let spawned_tasks = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let spawned_tasks = spawned_tasks.clone();
+            cx.subscribe(&workspace, move |_, event, _| {
+                if let workspace::Event::SpawnTask(spawn_in_terminal) = event {
+                    spawned_tasks.borrow_mut().push(spawn_in_terminal.clone());
+                }
+            })
+            .detach();
+        });
+
+        cx.dispatch_action(Spawn {
+            task_name: Some("deploy".to_string()),
+            omit_history: false,
+            split: None,
+        });
+        cx.run_until_parked();
+        let spawned_tasks = spawned_tasks.borrow_mut().drain(..).collect::<Vec<_>>();
+        assert_eq!(spawned_tasks.len(), 1);
+        assert_eq!(
+            spawned_tasks[0].env.get("TOKEN").map(String::as_str),
+            Some("s3cr3t"),
+            "Secret should reach the env of the spawned task"
+        );
+        assert_eq!(
+            spawned_tasks[0].args,
+            vec!["--token-from-env".to_string()],
+            "Secret should never get into the args"
+        );
+
+        let secrets = HashMap::from_iter([("TOKEN".to_string(), "deploy-token".to_string())]);
+        assert_eq!(
+            secrets_summary(&secrets),
+            "TOKEN: ******** (secret \"deploy-token\")",
+            "Secret values should be masked in the previews"
+        );
+    }
+}
@@ -1,10 +1,96 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use settings::{Settings, SettingsSources};
+use task::static_source::RevealStrategy;
 
 #[derive(Serialize, Deserialize, PartialEq, Default)]
 pub(crate) struct TaskSettings {
     pub(crate) show_status_indicator: bool,
+    pub(crate) global_history: bool,
+    pub(crate) default_reveal: DefaultReveal,
+    pub(crate) duplicate_spawn_window_ms: u64,
+    pub(crate) modal_scope: TasksModalScope,
+    pub(crate) modal_sort: TasksModalSort,
+    pub(crate) max_revealed_terminals: usize,
+    pub(crate) shell_history_file: Option<String>,
+    pub(crate) persisted_history_size: usize,
+    pub(crate) selected_text_mode: SelectedTextMode,
+    pub(crate) cwd_from_language_server: bool,
+}
+
+/// Which tasks the tasks modal lists, toggled with `task::ToggleTasksScope` while it is open.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum TasksModalScope {
+    /// Only the tasks for the active file: of its language and its worktree, and the global ones.
+    #[default]
+    CurrentFile,
+    /// All tasks of the inventory, regardless of the active file.
+    All,
+}
+
+impl TasksModalScope {
+    pub(crate) fn toggled(self) -> Self {
+        match self {
+            Self::CurrentFile => Self::All,
+            Self::All => Self::CurrentFile,
+        }
+    }
+}
+
+/// How the tasks modal orders the tasks while its query is empty, toggled with `task::ToggleTasksSort` while it is open.
+/// A non-empty query always orders the tasks by how well they match it.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum TasksModalSort {
+    /// The pinned tasks first, then the recently used ones, the rest in the order of their sources.
+    #[default]
+    Default,
+    /// The pinned tasks first, then all other tasks by their labels, case-insensitively: the recently used ones are not listed first.
+    Alphabetical,
+}
+
+impl TasksModalSort {
+    pub(crate) fn toggled(self) -> Self {
+        match self {
+            Self::Default => Self::Alphabetical,
+            Self::Alphabetical => Self::Default,
+        }
+    }
+}
+
+/// What to do with the terminal of a task that does not specify its `reveal` behavior.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum DefaultReveal {
+    /// Show the terminal pane and focus the task's tab in it.
+    #[default]
+    Focus,
+    /// Show the terminal pane and the task's tab in it, but keep the focus where it was.
+    NoFocus,
+    /// Do not change the terminal pane, but still add/reuse the task's tab there.
+    Never,
+}
+
+impl From<DefaultReveal> for RevealStrategy {
+    fn from(reveal: DefaultReveal) -> Self {
+        match reveal {
+            DefaultReveal::Focus => Self::Always,
+            DefaultReveal::NoFocus => Self::NoFocus,
+            DefaultReveal::Never => Self::Never,
+        }
+    }
+}
+
+/// Which text of the newest selection `ZED_SELECTED_TEXT` is set to.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum SelectedTextMode {
+    /// The text of the buffer, including the text of the folded regions.
+    #[default]
+    Raw,
+    /// The text as displayed in the editor: with the folded regions replaced by their placeholders.
+    Display,
 }
 
 /// Task-related settings.
@@ -12,6 +98,33 @@ pub(crate) struct TaskSettings {
 pub(crate) struct TaskSettingsContent {
     /// Whether to show task status indicator in the status bar. Default: true
     show_status_indicator: Option<bool>,
+    /// Whether to record the commands run in every project, to be searched and rerun from any other project. Default: false
+    global_history: Option<bool>,
+    /// What to do with the terminal of a task that does not specify `reveal` itself. Default: focus
+    default_reveal: Option<DefaultReveal>,
+    /// Time window in milliseconds, in which a task with the same resolved command and cwd is spawned only once,
+    /// ignoring the accidental duplicate spawns. `0` disables the deduplication. Default: 500
+    duplicate_spawn_window_ms: Option<u64>,
+    /// Which tasks the tasks modal lists when opened: only the ones for the active file, or all of them. Default: current_file
+    modal_scope: Option<TasksModalScope>,
+    /// How the tasks modal orders the tasks while its query is empty: the recently used ones first, or all of them
+    /// alphabetically. Pinned tasks go first either way. Default: default
+    modal_sort: Option<TasksModalSort>,
+    /// How many running task terminals may be revealed at once: beyond that, the terminals of the new tasks are kept hidden,
+    /// reachable from the task status indicator. `0` disables the limit. Default: 0
+    max_revealed_terminals: Option<usize>,
+    /// A shell history file (e.g. `~/.bash_history` or `~/.zsh_history`) to offer the recent commands of in the tasks modal,
+    /// as oneshot tasks. Nothing is read from the shell history, unless the file is set. Default: null
+    shell_history_file: Option<String>,
+    /// How many of the recently scheduled tasks of the project to store when the app quits, restoring their history
+    /// and contexts once the project is reopened. `0` disables storing the history. Default: 100
+    persisted_history_size: Option<usize>,
+    /// Whether `ZED_SELECTED_TEXT` is the raw text of the buffer, or the text displayed in the editor,
+    /// with the folded regions collapsed. Default: raw
+    selected_text_mode: Option<SelectedTextMode>,
+    /// Whether the tasks spawned without their own `cwd` run in the project root reported by the language server of the active buffer,
+    /// instead of the root of its worktree, e.g. in the nested projects of a monorepo. Default: false
+    cwd_from_language_server: Option<bool>,
 }
 
 impl Settings for TaskSettings {
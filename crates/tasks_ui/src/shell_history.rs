@@ -0,0 +1,143 @@
+use std::path::PathBuf;
+
+use gpui::{AppContext, Model, ViewContext};
+use project::{Project, TaskSourceKind};
+use settings::{watch_config_file, Settings, SettingsStore};
+use task::shell_history_source::ShellHistorySource;
+use workspace::Workspace;
+
+use crate::settings::TaskSettings;
+
+/// Offers the recent commands from the configured shell history file as oneshot tasks,
+/// replacing the source of the previous file when the setting changes.
+pub(crate) fn track_shell_history(workspace: &Workspace, cx: &mut ViewContext<Workspace>) {
+    if !workspace.project().read(cx).is_local() {
+        return;
+    }
+    let fs = workspace.app_state().fs.clone();
+    let mut tracked_file = None::<PathBuf>;
+    let mut update_history_source = move |project: &Model<Project>, cx: &mut AppContext| {
+        let history_file = TaskSettings::get_global(cx)
+            .shell_history_file
+            .as_deref()
+            .map(|path| match path.strip_prefix("~/") {
+                Some(relative_path) => util::paths::HOME.join(relative_path),
+                None => PathBuf::from(path),
+            });
+        if history_file == tracked_file {
+            return;
+        }
+        project.update(cx, |project, cx| {
+            project.task_inventory().update(cx, |inventory, cx| {
+                if let Some(old_file) = &tracked_file {
+                    inventory.remove_local_static_source(old_file);
+                }
+                if let Some(history_file) = history_file.clone() {
+                    let fs = fs.clone();
+                    inventory.add_source(
+                        TaskSourceKind::AbsPath(history_file.clone()),
+                        |cx| {
+                            let history_rx =
+                                watch_config_file(&cx.background_executor(), fs, history_file);
+                            ShellHistorySource::new(history_rx, cx)
+                        },
+                        cx,
+                    );
+                }
+                cx.notify();
+            })
+        });
+        tracked_file = history_file;
+    };
+    update_history_source(workspace.project(), cx);
+    cx.observe_global::<SettingsStore>(move |workspace, cx| {
+        update_history_source(workspace.project(), cx)
+    })
+    .detach();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use gpui::{TestAppContext, VisualTestContext};
+    use project::FakeFs;
+    use serde_json::json;
+
+    use crate::{modal::Spawn, tests::init_test};
+
+    use super::*;
+
+    #[gpui::test]
+    async fn test_shell_history_tasks(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree("/dir", json!({ "a.ts": "a" })).await;
+        fs.insert_tree(
+            "/home",
+            json!({
+                ".zsh_history": ": 1700000000:0;git status\n: 1700000010:0;cargo check\n: 1700000020:0;git status\n",
+            }),
+        )
+        .await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        let spawned_commands = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let spawned_commands = spawned_commands.clone();
+            cx.subscribe(&workspace, move |_, event, _| {
+                if let workspace::Event::SpawnTask(spawn_in_terminal) = event {
+                    spawned_commands
+                        .borrow_mut()
+                        .push(spawn_in_terminal.command.clone());
+                }
+            })
+            .detach();
+        });
+        let history_tasks = |cx: &mut VisualTestContext| {
+            project.update(cx, |project, cx| {
+                project
+                    .task_inventory()
+                    .update(cx, |inventory, cx| {
+                        inventory.list_tasks(None, None, false, cx)
+                    })
+                    .into_iter()
+                    .filter(|(kind, _)| {
+                        kind == &TaskSourceKind::AbsPath(PathBuf::from("/home/.zsh_history"))
+                    })
+                    .map(|(_, task)| task.name().to_owned())
+                    .collect::<Vec<_>>()
+            })
+        };
+        cx.run_until_parked();
+        assert!(
+            history_tasks(cx).is_empty(),
+            "Shell history should not be read unless opted in"
+        );
+
+        cx.update(|cx| {
+            cx.update_global::<SettingsStore, _>(|store, cx| {
+                store
+                    .set_user_settings(
+                        r#"{ "task": { "shell_history_file": "/home/.zsh_history" } }"#,
+                        cx,
+                    )
+                    .unwrap();
+            })
+        });
+        cx.run_until_parked();
+        assert_eq!(history_tasks(cx), vec!["cargo check", "git status"]);
+
+        cx.dispatch_action(Spawn {
+            task_name: Some("cargo check".to_string()),
+            omit_history: false,
+            split: None,
+        });
+        cx.run_until_parked();
+        assert_eq!(
+            *spawned_commands.borrow(),
+            vec!["cargo check".to_owned()],
+            "History entries should be runnable as oneshot tasks"
+        );
+    }
+}
@@ -1,90 +1,266 @@
-use gpui::{IntoElement, Render, View, WeakView};
-use settings::Settings;
-use ui::{
-    div, ButtonCommon, Clickable, Color, FluentBuilder, IconButton, IconName, Tooltip,
-    VisualContext, WindowContext,
+use std::time::Duration;
+
+use collections::HashSet;
+use gpui::{
+    AnchorCorner, DismissEvent, EventEmitter, FocusHandle, FocusableView, Model, Subscription,
+    Task, View, WeakView,
 };
+use project::Inventory;
+use settings::Settings;
+use ui::{popover_menu, prelude::*, ListHeader, ListItem, ListItemSpacing, Tooltip};
 use workspace::{item::ItemHandle, StatusItemView, Workspace};
 
-use crate::{modal::Spawn, settings::TaskSettings};
+use crate::{modal::Spawn, settings::TaskSettings, test_results::TaskTestResults};
 
+/// A name of the group to show the tasks without a `group` under.
+const UNGROUPED_TASKS: &str = "Other";
+/// How long the indicator shows the exit status of the last completed task for.
+const LAST_EXIT_STATUS_DURATION: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum TaskStatus {
     Failed,
     Running,
     Succeeded,
 }
 
+impl TaskStatus {
+    fn color(self) -> Color {
+        match self {
+            TaskStatus::Failed => Color::Error,
+            TaskStatus::Running => Color::Warning,
+            TaskStatus::Succeeded => Color::Success,
+        }
+    }
+}
+
+/// Tasks of the open task tabs that share the same `group`, with their aggregate status.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TaskGroup {
+    name: SharedString,
+    running: usize,
+    succeeded: usize,
+    failed: usize,
+    tasks: Vec<(SharedString, TaskStatus)>,
+}
+
+impl TaskGroup {
+    /// Same as the whole indicator: failed if any task failed, else running if any task is running, else succeeded.
+    fn status(&self) -> TaskStatus {
+        if self.failed > 0 {
+            TaskStatus::Failed
+        } else if self.running > 0 {
+            TaskStatus::Running
+        } else {
+            TaskStatus::Succeeded
+        }
+    }
+
+    fn header_label(&self) -> String {
+        let counts = [
+            (self.running, "running"),
+            (self.failed, "failed"),
+            (self.succeeded, "succeeded"),
+        ]
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, status)| format!("{count} {status}"))
+        .collect::<Vec<_>>();
+        format!("{} ({})", self.name, counts.join(", "))
+    }
+}
+
+/// Groups the tasks by their `group`, sorted by the group name, with the ungrouped tasks in the last group.
+fn task_groups<'a>(
+    tasks: impl IntoIterator<Item = (Option<&'a str>, &'a str, TaskStatus)>,
+) -> Vec<TaskGroup> {
+    let mut groups = Vec::<TaskGroup>::new();
+    for (group, label, status) in tasks {
+        let name = group.unwrap_or(UNGROUPED_TASKS);
+        let group = match groups.iter_mut().position(|group| group.name == name) {
+            Some(ix) => &mut groups[ix],
+            None => {
+                groups.push(TaskGroup {
+                    name: SharedString::from(name.to_owned()),
+                    running: 0,
+                    succeeded: 0,
+                    failed: 0,
+                    tasks: Vec::new(),
+                });
+                groups.last_mut().unwrap()
+            }
+        };
+        match status {
+            TaskStatus::Failed => group.failed += 1,
+            TaskStatus::Running => group.running += 1,
+            TaskStatus::Succeeded => group.succeeded += 1,
+        }
+        group
+            .tasks
+            .push((SharedString::from(label.to_owned()), status));
+    }
+    groups.sort_by_key(|group| (group.name == UNGROUPED_TASKS, group.name.clone()));
+    groups
+}
+
 /// A status bar icon that surfaces the status of running tasks.
 /// It has a different color depending on the state of running tasks:
 /// - red if any open task tab failed
 /// - else, yellow if any open task tab is still running
 /// - else, green if there tasks tabs open, and they have all succeeded
 /// - else, no indicator if there are no open task tabs
+///
+/// While any tasks are running, a badge next to the icon shows how many of them there are.
+/// For a few seconds after a task completes, the icon turns into a green check or a red cross, depending on its exit code.
+/// When there are task tabs open, clicking it shows their statuses, grouped by the task `group`.
 pub struct TaskStatusIndicator {
     workspace: WeakView<Workspace>,
+    /// The exit code of the last completed task, until [`LAST_EXIT_STATUS_DURATION`] passes.
+    last_exit_code: Option<i32>,
+    last_seen_run: Option<usize>,
+    _reset_last_exit_code: Task<()>,
+    _inventory_subscription: Subscription,
 }
 
 impl TaskStatusIndicator {
-    pub fn new(workspace: WeakView<Workspace>, cx: &mut WindowContext) -> View<Self> {
-        cx.new_view(|_| Self { workspace })
+    pub fn new(workspace: &Workspace, cx: &mut WindowContext) -> View<Self> {
+        let inventory = workspace.project().read(cx).task_inventory().clone();
+        let workspace = workspace.weak_handle();
+        cx.new_view(|cx| Self {
+            workspace,
+            last_exit_code: None,
+            last_seen_run: inventory.read(cx).last_completed_run().map(|run| run.ix),
+            _reset_last_exit_code: Task::ready(()),
+            // The inventory is notified every time a task terminal is spawned and every time a task completes.
+            _inventory_subscription: cx.observe(&inventory, Self::inventory_updated),
+        })
     }
-    fn current_status(&self, cx: &mut WindowContext) -> Option<TaskStatus> {
-        self.workspace
-            .update(cx, |this, cx| {
-                let mut status = None;
-                let project = this.project().read(cx);
-
-                for handle in project.local_terminal_handles() {
-                    let Some(handle) = handle.upgrade() else {
-                        continue;
-                    };
-                    let handle = handle.read(cx);
-                    let task_state = handle.task();
-                    if let Some(state) = task_state {
-                        match state.status {
-                            terminal::TaskStatus::Running => {
-                                let _ = status.insert(TaskStatus::Running);
-                            }
-                            terminal::TaskStatus::Completed { success } => {
-                                if !success {
-                                    let _ = status.insert(TaskStatus::Failed);
-                                    return status;
-                                }
-                                status.get_or_insert(TaskStatus::Succeeded);
-                            }
-                            _ => {}
-                        };
-                    }
-                }
-                status
-            })
-            .ok()
-            .flatten()
+
+    fn inventory_updated(&mut self, inventory: Model<Inventory>, cx: &mut ViewContext<Self>) {
+        if let Some(run) = inventory.read(cx).last_completed_run() {
+            if self.last_seen_run != Some(run.ix) {
+                self.last_seen_run = Some(run.ix);
+                self.last_exit_code = Some(run.exit_code);
+                self._reset_last_exit_code = cx.spawn(|this, mut cx| async move {
+                    cx.background_executor()
+                        .timer(LAST_EXIT_STATUS_DURATION)
+                        .await;
+                    this.update(&mut cx, |this, cx| {
+                        this.last_exit_code = None;
+                        cx.notify();
+                    })
+                    .ok();
+                });
+            }
+        }
+        cx.notify();
     }
 }
 
+fn current_status(groups: &[TaskGroup]) -> Option<TaskStatus> {
+    groups
+        .iter()
+        .map(TaskGroup::status)
+        .min_by_key(|status| match status {
+            TaskStatus::Failed => 0,
+            TaskStatus::Running => 1,
+            TaskStatus::Succeeded => 2,
+        })
+}
+
+/// The number of the running tasks to show next to the indicator icon, if there are any.
+fn running_tasks_badge(groups: &[TaskGroup]) -> Option<SharedString> {
+    let running = groups.iter().map(|group| group.running).sum::<usize>();
+    (running > 0).then(|| SharedString::from(running.to_string()))
+}
+
+/// Groups the tasks of the open task tabs in the workspace, with the test results summaries of the tasks that report them.
+fn task_groups_in(workspace: &WeakView<Workspace>, cx: &mut WindowContext) -> Vec<TaskGroup> {
+    workspace
+        .update(cx, |this, cx| {
+            let project = this.project().read(cx);
+            let test_results = cx.try_global::<TaskTestResults>();
+            let terminals = project
+                .local_terminal_handles()
+                .iter()
+                .filter_map(|handle| handle.upgrade())
+                .collect::<Vec<_>>();
+            let tasks = terminals
+                .iter()
+                .filter_map(|terminal| {
+                    let state = terminal.read(cx).task()?;
+                    let status = match state.status {
+                        terminal::TaskStatus::Running => TaskStatus::Running,
+                        terminal::TaskStatus::Completed { success: true } => TaskStatus::Succeeded,
+                        terminal::TaskStatus::Completed { success: false } => TaskStatus::Failed,
+                        terminal::TaskStatus::Unknown => return None,
+                    };
+                    let label = match test_results.and_then(|results| results.0.get(&state.id)) {
+                        Some(summary) => format!("{} ({summary})", state.label),
+                        None => state.label.clone(),
+                    };
+                    Some((state.group.as_deref(), label, status))
+                })
+                .collect::<Vec<_>>();
+            task_groups(
+                tasks
+                    .iter()
+                    .map(|(group, label, status)| (*group, label.as_str(), *status)),
+            )
+        })
+        .unwrap_or_default()
+}
+
 impl Render for TaskStatusIndicator {
     fn render(&mut self, cx: &mut gpui::ViewContext<Self>) -> impl IntoElement {
         if !TaskSettings::get_global(cx).show_status_indicator {
             return div().into_any_element();
         }
-        let current_status = self.current_status(cx);
-        let color = current_status.map(|status| match status {
-            TaskStatus::Failed => Color::Error,
-            TaskStatus::Running => Color::Warning,
-            TaskStatus::Succeeded => Color::Success,
-        });
-        IconButton::new("tasks-activity-indicator", IconName::Play)
-            .when_some(color, |this, color| this.icon_color(color))
-            .on_click(cx.listener(|this, _, cx| {
-                this.workspace
-                    .update(cx, |this, cx| {
-                        crate::spawn_task_or_modal(this, &Spawn::modal(), cx)
-                    })
-                    .ok();
-            }))
-            .tooltip(|cx| Tooltip::for_action("Spawn tasks", &Spawn { task_name: None }, cx))
-            .into_any_element()
+        let groups = task_groups_in(&self.workspace, cx);
+        let current_status = current_status(&groups);
+        let (icon, color) = match self.last_exit_code {
+            Some(0) => (IconName::Check, Some(Color::Success)),
+            Some(_) => (IconName::XCircle, Some(Color::Error)),
+            None => (IconName::Play, current_status.map(TaskStatus::color)),
+        };
+        let button = IconButton::new("tasks-activity-indicator", icon)
+            .when_some(color, |this, color| this.icon_color(color));
+        if current_status.is_none() {
+            return button
+                .on_click(cx.listener(|this, _, cx| {
+                    this.workspace
+                        .update(cx, |this, cx| {
+                            crate::spawn_task_or_modal(this, &Spawn::modal(), cx)
+                        })
+                        .ok();
+                }))
+                .tooltip(|cx| Tooltip::for_action("Spawn tasks", &Spawn::modal(), cx))
+                .into_any_element();
+        }
+
+        let workspace = self.workspace.clone();
+        let popover = popover_menu("tasks-activity-popover")
+            .menu(move |cx| {
+                let groups = task_groups_in(&workspace, cx);
+                Some(cx.new_view(|cx| TaskStatusPopover::new(groups, cx)))
+            })
+            .anchor(AnchorCorner::BottomLeft);
+        match running_tasks_badge(&groups) {
+            Some(badge) => popover
+                .trigger(
+                    Button::new("tasks-activity-indicator", badge)
+                        .icon(icon)
+                        .icon_position(IconPosition::Start)
+                        .icon_size(IconSize::Small)
+                        .when_some(color, |this, color| this.icon_color(color))
+                        .label_size(LabelSize::Small)
+                        .tooltip(|cx| Tooltip::text("Running tasks", cx)),
+                )
+                .into_any_element(),
+            None => popover
+                .trigger(button.tooltip(|cx| Tooltip::text("Task statuses", cx)))
+                .into_any_element(),
+        }
     }
 }
 
@@ -96,3 +272,195 @@ impl StatusItemView for TaskStatusIndicator {
     ) {
     }
 }
+
+/// A popover with the statuses of the tasks in the open task tabs, in collapsible sections per task group.
+struct TaskStatusPopover {
+    groups: Vec<TaskGroup>,
+    collapsed_groups: HashSet<SharedString>,
+    focus_handle: FocusHandle,
+}
+
+impl TaskStatusPopover {
+    fn new(groups: Vec<TaskGroup>, cx: &mut ViewContext<Self>) -> Self {
+        Self {
+            groups,
+            collapsed_groups: HashSet::default(),
+            focus_handle: cx.focus_handle(),
+        }
+    }
+}
+
+impl EventEmitter<DismissEvent> for TaskStatusPopover {}
+
+impl FocusableView for TaskStatusPopover {
+    fn focus_handle(&self, _: &gpui::AppContext) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Render for TaskStatusPopover {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        v_flex()
+            .key_context("TaskStatusPopover")
+            .track_focus(&self.focus_handle)
+            .occlude()
+            .elevation_2(cx)
+            .min_w(rems(16.))
+            .py_1()
+            .children(self.groups.iter().map(|group| {
+                let collapsed = self.collapsed_groups.contains(&group.name);
+                let name = group.name.clone();
+                v_flex()
+                    .child(
+                        ListHeader::new(group.header_label())
+                            .toggle(!collapsed)
+                            .on_toggle(cx.listener(move |this, _, cx| {
+                                if !this.collapsed_groups.remove(&name) {
+                                    this.collapsed_groups.insert(name.clone());
+                                }
+                                cx.notify();
+                            }))
+                            .end_slot(Icon::new(IconName::Play).color(group.status().color())),
+                    )
+                    .when(!collapsed, |this| {
+                        this.children(group.tasks.iter().enumerate().map(
+                            |(ix, (label, status))| {
+                                ListItem::new(SharedString::from(format!(
+                                    "task-status-{}-{ix}",
+                                    group.name
+                                )))
+                                .inset(true)
+                                .spacing(ListItemSpacing::Sparse)
+                                .child(Label::new(label.clone()))
+                                .end_slot(
+                                    Label::new(match status {
+                                        TaskStatus::Failed => "failed",
+                                        TaskStatus::Running => "running",
+                                        TaskStatus::Succeeded => "succeeded",
+                                    })
+                                    .color(status.color()),
+                                )
+                            },
+                        ))
+                    })
+            }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gpui::{TestAppContext, VisualTestContext};
+    use project::{FakeFs, Project};
+    use serde_json::json;
+    use task::TaskId;
+
+    use crate::tests::init_test;
+
+    use super::*;
+
+    #[test]
+    fn test_task_groups() {
+        let groups = task_groups([
+            (Some("test"), "unit tests", TaskStatus::Running),
+            (None, "lint", TaskStatus::Succeeded),
+            (Some("build"), "build server", TaskStatus::Succeeded),
+            (Some("test"), "integration tests", TaskStatus::Failed),
+            (Some("test"), "doc tests", TaskStatus::Running),
+            (Some("build"), "build client", TaskStatus::Running),
+        ]);
+        assert_eq!(
+            groups
+                .iter()
+                .map(|group| (group.header_label(), group.status()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("build (1 running, 1 succeeded)".to_owned(), TaskStatus::Running),
+                ("test (2 running, 1 failed)".to_owned(), TaskStatus::Failed),
+                ("Other (1 succeeded)".to_owned(), TaskStatus::Succeeded),
+            ],
+            "Groups should be sorted by name with the ungrouped tasks last, and aggregate their tasks' statuses"
+        );
+        assert_eq!(
+            groups[1].tasks,
+            vec![
+                ("unit tests".into(), TaskStatus::Running),
+                ("integration tests".into(), TaskStatus::Failed),
+                ("doc tests".into(), TaskStatus::Running),
+            ],
+            "Tasks should be listed under their group header in the order of their tabs"
+        );
+    }
+
+    #[test]
+    fn test_running_tasks_badge() {
+        let groups = task_groups([
+            (Some("watch"), "css watcher", TaskStatus::Running),
+            (None, "lint", TaskStatus::Failed),
+            (Some("watch"), "js watcher", TaskStatus::Running),
+            (None, "server", TaskStatus::Running),
+        ]);
+        assert_eq!(
+            running_tasks_badge(&groups),
+            Some("3".into()),
+            "Running tasks of all groups should be counted"
+        );
+        let finished_groups = task_groups([
+            (None, "lint", TaskStatus::Failed),
+            (Some("build"), "build", TaskStatus::Succeeded),
+        ]);
+        assert_eq!(
+            running_tasks_badge(&finished_groups),
+            None,
+            "Badge should be hidden without running tasks"
+        );
+        assert_eq!(running_tasks_badge(&[]), None);
+    }
+
+    #[gpui::test]
+    async fn test_last_exit_status(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree("/dir", json!({ "a.ts": "a" })).await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        let indicator =
+            workspace.update(cx, |workspace, cx| TaskStatusIndicator::new(workspace, cx));
+        let complete_task = |exit_code: i32, cx: &mut VisualTestContext| {
+            project.update(cx, |project, cx| {
+                project.task_inventory().update(cx, |inventory, cx| {
+                    inventory.task_completed(TaskId("build".to_owned()), exit_code, cx)
+                })
+            });
+            cx.run_until_parked();
+        };
+        let last_exit_code = |cx: &mut VisualTestContext| {
+            indicator.update(cx, |indicator, _| indicator.last_exit_code)
+        };
+        assert_eq!(last_exit_code(cx), None);
+
+        complete_task(1, cx);
+        assert_eq!(
+            last_exit_code(cx),
+            Some(1),
+            "Exit code of the completed task should be shown"
+        );
+        cx.executor().advance_clock(LAST_EXIT_STATUS_DURATION / 2);
+        complete_task(1, cx);
+        cx.executor().advance_clock(LAST_EXIT_STATUS_DURATION / 2);
+        cx.run_until_parked();
+        assert_eq!(
+            last_exit_code(cx),
+            Some(1),
+            "Another run with the same exit code should restart the timer"
+        );
+        complete_task(0, cx);
+        assert_eq!(last_exit_code(cx), Some(0));
+        cx.executor().advance_clock(LAST_EXIT_STATUS_DURATION);
+        cx.run_until_parked();
+        assert_eq!(
+            last_exit_code(cx),
+            None,
+            "Indicator should revert to the neutral state after a while"
+        );
+    }
+}
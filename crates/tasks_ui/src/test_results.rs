@@ -0,0 +1,163 @@
+use std::time::Duration;
+
+use anyhow::Context as _;
+use collections::HashMap;
+use gpui::{Global, ViewContext};
+use project::RemoveOptions;
+use smol::stream::StreamExt as _;
+use task::{test_results::TestResultsSummary, SpawnInTerminal, TaskId};
+use workspace::{Toast, Workspace};
+
+const TASK_TEST_RESULTS_TOAST_ID: usize = 0x7a5c7e57;
+const TEST_RESULTS_STATUS_LATENCY: Duration = Duration::from_millis(100);
+
+/// Summaries of the test results reported by the tasks, by the task id, from their last finished runs.
+#[derive(Default)]
+pub(crate) struct TaskTestResults(pub(crate) HashMap<TaskId, TestResultsSummary>);
+
+impl Global for TaskTestResults {}
+
+/// Spawns the task and, if it reports test results, waits for its [`SpawnInTerminal::results_status_file`] to summarize them
+/// in a notification and in the task status indicator, once the task finishes.
+pub(crate) fn spawn_with_test_results(
+    workspace: &Workspace,
+    spawn_in_terminal: SpawnInTerminal,
+    spawn: impl FnOnce(&Workspace, SpawnInTerminal, &mut ViewContext<'_, Workspace>) + 'static,
+    cx: &mut ViewContext<'_, Workspace>,
+) {
+    let (Some(results_format), Some(status_file), Some(results_file)) = (
+        spawn_in_terminal.results_format,
+        spawn_in_terminal.results_status_file(),
+        spawn_in_terminal.test_results_file(),
+    ) else {
+        spawn(workspace, spawn_in_terminal, cx);
+        return;
+    };
+    cx.default_global::<TaskTestResults>()
+        .0
+        .remove(&spawn_in_terminal.id);
+    let fs = workspace.app_state().fs.clone();
+    cx.spawn(|workspace, mut cx| async move {
+        fs.remove_file(
+            &status_file,
+            RemoveOptions {
+                ignore_if_not_exists: true,
+                ..RemoveOptions::default()
+            },
+        )
+        .await
+        .with_context(|| format!("removing the previous task status file {status_file:?}"))?;
+        let mut status_changes = fs.watch(&status_file, TEST_RESULTS_STATUS_LATENCY).await;
+        let id = spawn_in_terminal.id.clone();
+        let label = spawn_in_terminal.label.clone();
+        workspace.update(&mut cx, |workspace, cx| {
+            spawn(workspace, spawn_in_terminal, cx)
+        })?;
+        while status_changes.next().await.is_some() {
+            let finished = fs
+                .load(&status_file)
+                .await
+                .map_or(false, |status| !status.trim().is_empty());
+            if !finished {
+                continue;
+            }
+            let results = fs.load(&results_file).await.unwrap_or_default();
+            let summary = TestResultsSummary::parse(results_format, &results);
+            workspace.update(&mut cx, |workspace, cx| {
+                cx.default_global::<TaskTestResults>().0.insert(id, summary);
+                workspace.show_toast(
+                    Toast::new(
+                        TASK_TEST_RESULTS_TOAST_ID,
+                        format!("Task \"{label}\" tests: {summary}"),
+                    ),
+                    cx,
+                );
+            })?;
+            break;
+        }
+        anyhow::Ok(())
+    })
+    .detach_and_log_err(cx);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, path::PathBuf, rc::Rc};
+
+    use gpui::{TestAppContext, VisualTestContext};
+    use project::{FakeFs, Fs as _, Project};
+    use serde_json::json;
+
+    use crate::{modal::Spawn, tests::init_test};
+
+    use super::*;
+
+    #[gpui::test]
+    async fn test_test_results_summary(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[{ "label": "unit tests", "command": "make", "args": ["test"], "results_format": "junit", "results_file": "reports/junit.xml" }]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        fs.create_dir(&std::env::temp_dir()).await.unwrap();
+        let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        let spawned_tasks = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let spawned_tasks = spawned_tasks.clone();
+            cx.subscribe(&workspace, move |_, event, _| {
+                if let workspace::Event::SpawnTask(spawn_in_terminal) = event {
+                    spawned_tasks.borrow_mut().push(spawn_in_terminal.clone());
+                }
+            })
+            .detach();
+        });
+
+        cx.dispatch_action(Spawn {
+            task_name: Some("unit tests".to_string()),
+            omit_history: false,
+            split: None,
+        });
+        cx.run_until_parked();
+        let spawned_task = spawned_tasks.borrow()[0].clone();
+        assert_eq!(
+            spawned_task.test_results_file(),
+            Some(PathBuf::from("/dir/reports/junit.xml")),
+            "Relative results file should be resolved against the task cwd"
+        );
+        let test_results = |cx: &mut VisualTestContext| {
+            cx.update(|cx| {
+                cx.try_global::<TaskTestResults>()
+                    .and_then(|results| results.0.get(&spawned_task.id).copied())
+            })
+        };
+        assert_eq!(test_results(cx), None);
+
+        fs.insert_tree(
+            "/dir/reports",
+            json!({
+                "junit.xml": r#"<testsuite><testcase name="a"/><testcase name="b"><failure/></testcase></testsuite>"#,
+            }),
+        )
+        .await;
+        fs.insert_file(spawned_task.results_status_file().unwrap(), b"1\n".to_vec())
+            .await;
+        cx.run_until_parked();
+        assert_eq!(
+            test_results(cx),
+            Some(TestResultsSummary {
+                passed: 1,
+                failed: 1,
+                skipped: 0,
+            }),
+            "Test results should be summarized once the task writes its exit status"
+        );
+    }
+}
@@ -0,0 +1,423 @@
+use std::{cell::RefCell, path::PathBuf, rc::Rc, time::Duration};
+
+use anyhow::Context as _;
+use collections::HashSet;
+use editor::{actions::MoveToEnd, Editor};
+use gpui::ViewContext;
+use project::InventoryEvent;
+use task::TaskId;
+use util::ResultExt;
+use workspace::Workspace;
+
+use crate::{schedule_task, task_context, task_cwd};
+
+const RERUN_ON_CHANGE_DEBOUNCE: Duration = Duration::from_millis(300);
+pub(crate) const PROJECT_OPEN_DEBOUNCE: Duration = Duration::from_millis(300);
+const TAIL_FILE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const TAIL_FILE_WAIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Reruns the watched task (if any) every time the task sources change, reusing its terminal.
+pub(crate) fn watch_task_definitions(workspace: &Workspace, cx: &mut ViewContext<Workspace>) {
+    let inventory = workspace.project().read(cx).task_inventory().clone();
+    let mut pending_rerun = None;
+    cx.subscribe(&inventory, move |_, inventory, _: &InventoryEvent, cx| {
+        let Some(task_id) = inventory.read(cx).watched_task().cloned() else {
+            pending_rerun = None;
+            return;
+        };
+        pending_rerun = Some(cx.spawn(|workspace, mut cx| async move {
+            cx.background_executor()
+                .timer(RERUN_ON_CHANGE_DEBOUNCE)
+                .await;
+            workspace
+                .update(&mut cx, |workspace, cx| {
+                    let tasks = workspace.project().update(cx, |project, cx| {
+                        project.task_inventory().update(cx, |inventory, cx| {
+                            if inventory.watched_task() != Some(&task_id) {
+                                return Vec::new();
+                            }
+                            inventory.list_tasks(None, None, false, cx)
+                        })
+                    });
+                    let Some((_, task)) = tasks.into_iter().find(|(_, task)| task.id() == &task_id)
+                    else {
+                        return;
+                    };
+                    let cwd = task_cwd(workspace, cx).log_err().flatten();
+                    let task_context = task_context(workspace, cwd, cx);
+                    schedule_task(workspace, &task, task_context, false, cx);
+                })
+                .ok();
+        }));
+    })
+    .detach();
+}
+
+/// Runs the tasks marked to be run on project open, once the project's task sources settle.
+/// Every task is run at most once per workspace, not rerun when its definitions get reloaded.
+pub(crate) fn run_tasks_on_project_open(workspace: &Workspace, cx: &mut ViewContext<Workspace>) {
+    let inventory = workspace.project().read(cx).task_inventory().clone();
+    let tasks_run = Rc::new(RefCell::new(HashSet::default()));
+    let mut pending_run = Some(schedule_project_open_tasks(tasks_run.clone(), cx));
+    cx.observe(&inventory, move |_, _, cx| {
+        pending_run = Some(schedule_project_open_tasks(tasks_run.clone(), cx));
+    })
+    .detach();
+}
+
+fn schedule_project_open_tasks(
+    tasks_run: Rc<RefCell<HashSet<TaskId>>>,
+    cx: &mut ViewContext<Workspace>,
+) -> gpui::Task<()> {
+    cx.spawn(|workspace, mut cx| async move {
+        cx.background_executor().timer(PROJECT_OPEN_DEBOUNCE).await;
+        workspace
+            .update(&mut cx, |workspace, cx| {
+                let tasks = workspace.project().update(cx, |project, cx| {
+                    project.task_inventory().update(cx, |inventory, cx| {
+                        inventory.list_tasks(None, None, false, cx)
+                    })
+                });
+                for (_, task) in tasks {
+                    if task.run_on_project_open()
+                        && tasks_run.borrow_mut().insert(task.id().clone())
+                    {
+                        let cwd = task_cwd(workspace, cx).log_err().flatten();
+                        let task_context = task_context(workspace, cwd, cx);
+                        schedule_task(workspace, &task, task_context, false, cx);
+                    }
+                }
+            })
+            .ok();
+    })
+}
+
+/// Opens the file once the task creates it, waiting for up to [`TAIL_FILE_WAIT_TIMEOUT`] and never creating
+/// it in the worktree itself, and keeps its editor scrolled to the end every time the file gets reloaded
+/// after the changes on disk: appended to, truncated or replaced.
+pub(crate) fn follow_file(abs_path: PathBuf, cx: &mut ViewContext<Workspace>) {
+    cx.spawn(|workspace, mut cx| async move {
+        let fs = workspace.update(&mut cx, |workspace, _| workspace.app_state().fs.clone())?;
+        let mut waited = Duration::ZERO;
+        while !fs.is_file(&abs_path).await {
+            if waited >= TAIL_FILE_WAIT_TIMEOUT {
+                log::info!("not following {abs_path:?}: the task did not create it");
+                return Ok(());
+            }
+            cx.background_executor()
+                .timer(TAIL_FILE_POLL_INTERVAL)
+                .await;
+            waited += TAIL_FILE_POLL_INTERVAL;
+        }
+        let item = workspace
+            .update(&mut cx, |workspace, cx| {
+                workspace.open_abs_path(abs_path.clone(), false, cx)
+            })?
+            .await?;
+        let editor = item
+            .downcast::<Editor>()
+            .with_context(|| format!("tailed file {abs_path:?} is not opened in an editor"))?;
+        workspace.update(&mut cx, |_, cx| {
+            editor.update(cx, |editor, cx| editor.move_to_end(&MoveToEnd, cx));
+            let Some(buffer) = editor.read(cx).buffer().read(cx).as_singleton() else {
+                return;
+            };
+            let editor = editor.downgrade();
+            cx.subscribe(&buffer, move |_, _, event, cx| {
+                if matches!(event, language::Event::Reloaded) {
+                    editor
+                        .update(cx, |editor, cx| editor.move_to_end(&MoveToEnd, cx))
+                        .ok();
+                }
+            })
+            .detach();
+        })?;
+        anyhow::Ok(())
+    })
+    .detach_and_log_err(cx);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use gpui::{TestAppContext, VisualTestContext};
+    use project::{FakeFs, Fs as _, Project};
+    use serde_json::json;
+
+    use crate::{
+        modal::{Spawn, ToggleRerunOnChange},
+        tests::init_test,
+    };
+
+    use super::*;
+
+    #[gpui::test]
+    async fn test_rerun_on_definition_change(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[{ "label": "example task", "command": "echo", "args": ["4"] }]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        let spawn_count = Rc::new(RefCell::new(0));
+        cx.update(|cx| {
+            let spawn_count = spawn_count.clone();
+            cx.subscribe(&workspace, move |_, event, _| {
+                if let workspace::Event::SpawnTask(_) = event {
+                    *spawn_count.borrow_mut() += 1;
+                }
+            })
+            .detach();
+        });
+        let last_scheduled_args = |cx: &mut VisualTestContext| {
+            project.update(cx, |project, cx| {
+                let (task, task_context) =
+                    project.task_inventory().read(cx).last_scheduled_task()?;
+                Some(task.prepare_exec(task_context)?.args)
+            })
+        };
+
+        cx.dispatch_action(Spawn {
+            task_name: Some("example task".to_string()),
+            omit_history: false,
+            split: None,
+        });
+        cx.run_until_parked();
+        assert_eq!(last_scheduled_args(cx), Some(vec!["4".to_string()]));
+
+        fs.insert_file(
+            "/dir/.zed/tasks.json",
+            r#"[{ "label": "example task", "command": "echo", "args": ["5"] }]"#
+                .as_bytes()
+                .to_vec(),
+        )
+        .await;
+        cx.executor().advance_clock(RERUN_ON_CHANGE_DEBOUNCE);
+        cx.run_until_parked();
+        assert_eq!(
+            last_scheduled_args(cx),
+            Some(vec!["4".to_string()]),
+            "Tasks should not be rerun on change unless opted in"
+        );
+
+        cx.dispatch_action(ToggleRerunOnChange);
+        fs.insert_file(
+            "/dir/.zed/tasks.json",
+            r#"[{ "label": "example task", "command": "echo", "args": ["6"] }]"#
+                .as_bytes()
+                .to_vec(),
+        )
+        .await;
+        cx.executor().advance_clock(RERUN_ON_CHANGE_DEBOUNCE);
+        cx.run_until_parked();
+        assert_eq!(
+            last_scheduled_args(cx),
+            Some(vec!["6".to_string()]),
+            "Watched task should be rerun with its updated definition"
+        );
+
+        let spawns_before_completion = *spawn_count.borrow();
+        project.update(cx, |project, cx| {
+            project.task_inventory().update(cx, |inventory, cx| {
+                let watched_task = inventory.watched_task().cloned().unwrap();
+                inventory.task_completed(watched_task, 0, cx);
+            })
+        });
+        cx.executor().advance_clock(RERUN_ON_CHANGE_DEBOUNCE);
+        cx.run_until_parked();
+        assert_eq!(
+            *spawn_count.borrow(),
+            spawns_before_completion,
+            "Finishing the watched task should not rerun it"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_run_on_project_open(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[
+                        { "label": "start services", "command": "docker", "args": ["compose", "up"], "on_project_open": true },
+                        { "label": "build", "command": "make" }
+                    ]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        let spawned_tasks = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let spawned_tasks = spawned_tasks.clone();
+            cx.subscribe(&workspace, move |_, event, _| {
+                if let workspace::Event::SpawnTask(spawn_in_terminal) = event {
+                    spawned_tasks
+                        .borrow_mut()
+                        .push(spawn_in_terminal.label.clone());
+                }
+            })
+            .detach();
+        });
+
+        cx.run_until_parked();
+        cx.executor().advance_clock(PROJECT_OPEN_DEBOUNCE);
+        cx.run_until_parked();
+        assert_eq!(
+            spawned_tasks.borrow().as_slice(),
+            ["start services".to_string()],
+            "Only the flagged task should be run on project open"
+        );
+
+        fs.insert_file(
+            "/dir/.zed/tasks.json",
+            r#"[
+                { "label": "start services", "command": "docker", "args": ["compose", "up"], "on_project_open": true },
+                { "label": "build", "command": "make", "args": ["all"] }
+            ]"#
+            .as_bytes()
+            .to_vec(),
+        )
+        .await;
+        cx.run_until_parked();
+        cx.executor().advance_clock(PROJECT_OPEN_DEBOUNCE);
+        cx.run_until_parked();
+        assert_eq!(
+            spawned_tasks.borrow().as_slice(),
+            ["start services".to_string()],
+            "Reloading the task definitions should not run the task again"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_tail_file(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[{ "label": "test", "command": "make", "args": ["test"], "tail_file": "reports/test.log" }]"#,
+                },
+                "reports": {
+                    "test.log": "started\n",
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        let spawned_tasks = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let spawned_tasks = spawned_tasks.clone();
+            cx.subscribe(&workspace, move |_, event, _| {
+                if let workspace::Event::SpawnTask(spawn_in_terminal) = event {
+                    spawned_tasks
+                        .borrow_mut()
+                        .push(spawn_in_terminal.tail_file.clone());
+                }
+            })
+            .detach();
+        });
+
+        cx.dispatch_action(Spawn {
+            task_name: Some("test".to_string()),
+            omit_history: false,
+            split: None,
+        });
+        cx.run_until_parked();
+        assert_eq!(
+            spawned_tasks.borrow().as_slice(),
+            &[Some(PathBuf::from("/dir/reports/test.log"))],
+            "Relative tailed file should be resolved against the task cwd"
+        );
+        let tailed_text = |cx: &mut VisualTestContext| {
+            workspace.update(cx, |workspace, cx| {
+                workspace
+                    .active_item_as::<Editor>(cx)
+                    .expect("Tailed file should be opened in an editor")
+                    .read(cx)
+                    .text(cx)
+            })
+        };
+        assert_eq!(tailed_text(cx), "started\n");
+
+        fs.insert_file("/dir/reports/test.log", b"started\npassed 1\n".to_vec())
+            .await;
+        cx.run_until_parked();
+        assert_eq!(
+            tailed_text(cx),
+            "started\npassed 1\n",
+            "Content appended to the tailed file should surface in its editor"
+        );
+
+        fs.insert_file("/dir/reports/test.log", b"rotated\n".to_vec())
+            .await;
+        cx.run_until_parked();
+        assert_eq!(
+            tailed_text(cx),
+            "rotated\n",
+            "Truncated tailed file should be followed from its new content"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_tail_file_not_created_by_zed(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[{ "label": "test", "command": "make", "args": ["test"], "tail_file": "test.log" }]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        let active_editor_text = |cx: &mut VisualTestContext| {
+            workspace.update(cx, |workspace, cx| {
+                workspace
+                    .active_item_as::<Editor>(cx)
+                    .map(|editor| editor.read(cx).text(cx))
+            })
+        };
+
+        cx.dispatch_action(Spawn {
+            task_name: Some("test".to_string()),
+            omit_history: false,
+            split: None,
+        });
+        cx.run_until_parked();
+        assert!(
+            !fs.is_file(Path::new("/dir/test.log")).await,
+            "Following a missing file should not create it in the worktree"
+        );
+        assert_eq!(active_editor_text(cx), None);
+
+        fs.insert_file("/dir/test.log", b"started\n".to_vec()).await;
+        cx.executor().advance_clock(TAIL_FILE_POLL_INTERVAL);
+        cx.run_until_parked();
+        assert_eq!(
+            active_editor_text(cx).as_deref(),
+            Some("started\n"),
+            "Tailed file should be opened once the task creates it"
+        );
+    }
+}
@@ -0,0 +1,494 @@
+use std::{path::PathBuf, sync::Arc};
+
+use fuzzy::{StringMatch, StringMatchCandidate};
+use gpui::{
+    rems, DismissEvent, EventEmitter, FocusableView, InteractiveElement, ParentElement, Render,
+    SharedString, Styled, Subscription, View, ViewContext, VisualContext, WeakView,
+};
+use picker::{highlighted_match_with_paths::HighlightedText, Picker, PickerDelegate};
+use task::{Task, VariableName};
+use ui::{
+    v_flex, Color, Label, LabelCommon, ListItem, ListItemSpacing, RenderOnce, Selectable,
+    WindowContext,
+};
+use util::ResultExt;
+use workspace::{ModalView, Workspace};
+
+use crate::{schedule_task, task_context, task_worktrees};
+
+/// A worktree to spawn the task in: its root name and absolute path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct WorktreeCandidate {
+    pub(crate) name: SharedString,
+    pub(crate) abs_path: PathBuf,
+}
+
+/// A modal to choose the worktree to spawn the task in, when its cwd cannot be determined from the active entry.
+pub(crate) struct WorktreePickerModal {
+    pub(crate) picker: View<Picker<WorktreePickerDelegate>>,
+    _subscription: Subscription,
+}
+
+impl WorktreePickerModal {
+    pub(crate) fn new(
+        task: Arc<dyn Task>,
+        omit_history: bool,
+        variable_overrides: Vec<(VariableName, String)>,
+        inline_args: Vec<String>,
+        worktrees: Vec<WorktreeCandidate>,
+        workspace: WeakView<Workspace>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let picker = cx.new_view(|cx| {
+            Picker::uniform_list(
+                WorktreePickerDelegate {
+                    task,
+                    omit_history,
+                    variable_overrides,
+                    inline_args,
+                    worktrees,
+                    matches: Vec::new(),
+                    selected_index: 0,
+                    workspace,
+                },
+                cx,
+            )
+        });
+        let _subscription = cx.subscribe(&picker, |_, _, _, cx| {
+            cx.emit(DismissEvent);
+        });
+        Self {
+            picker,
+            _subscription,
+        }
+    }
+}
+
+impl Render for WorktreePickerModal {
+    fn render(&mut self, _: &mut ViewContext<Self>) -> impl gpui::prelude::IntoElement {
+        v_flex()
+            .key_context("TaskWorktreePickerModal")
+            .w(rems(34.))
+            .child(self.picker.clone())
+    }
+}
+
+impl EventEmitter<DismissEvent> for WorktreePickerModal {}
+
+impl FocusableView for WorktreePickerModal {
+    fn focus_handle(&self, cx: &gpui::AppContext) -> gpui::FocusHandle {
+        self.picker.read(cx).focus_handle(cx)
+    }
+}
+
+impl ModalView for WorktreePickerModal {}
+
+pub(crate) struct WorktreePickerDelegate {
+    task: Arc<dyn Task>,
+    omit_history: bool,
+    /// Variables overridden in the tasks modal query, applied over the context of the chosen worktree.
+    variable_overrides: Vec<(VariableName, String)>,
+    /// Args typed after ` -- ` in the tasks modal query, appended to the args of the task.
+    inline_args: Vec<String>,
+    worktrees: Vec<WorktreeCandidate>,
+    matches: Vec<StringMatch>,
+    selected_index: usize,
+    workspace: WeakView<Workspace>,
+}
+
+impl PickerDelegate for WorktreePickerDelegate {
+    type ListItem = ListItem;
+
+    fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    fn selected_index(&self) -> usize {
+        self.selected_index
+    }
+
+    fn set_selected_index(&mut self, ix: usize, _: &mut ViewContext<Picker<Self>>) {
+        self.selected_index = ix;
+    }
+
+    fn placeholder_text(&self, _: &mut WindowContext) -> Arc<str> {
+        Arc::from(format!(
+            "Select a worktree to run \"{}\" in...",
+            self.task.name()
+        ))
+    }
+
+    fn update_matches(
+        &mut self,
+        query: String,
+        cx: &mut ViewContext<Picker<Self>>,
+    ) -> gpui::Task<()> {
+        let candidates = self
+            .worktrees
+            .iter()
+            .enumerate()
+            .map(|(id, worktree)| StringMatchCandidate {
+                id,
+                char_bag: worktree.name.chars().collect(),
+                string: worktree.name.to_string(),
+            })
+            .collect::<Vec<_>>();
+        cx.spawn(move |picker, mut cx| async move {
+            let matches = fuzzy::match_strings(
+                &candidates,
+                &query,
+                true,
+                1000,
+                &Default::default(),
+                cx.background_executor().clone(),
+            )
+            .await;
+            picker
+                .update(&mut cx, |picker, _| {
+                    let delegate = &mut picker.delegate;
+                    delegate.matches = matches;
+                    if delegate.matches.is_empty() {
+                        delegate.selected_index = 0;
+                    } else {
+                        delegate.selected_index =
+                            delegate.selected_index.min(delegate.matches.len() - 1);
+                    }
+                })
+                .log_err();
+        })
+    }
+
+    fn confirm(&mut self, omit_history_entry: bool, cx: &mut ViewContext<Picker<Self>>) {
+        let Some(worktree) = self
+            .matches
+            .get(self.selected_index)
+            .and_then(|hit| self.worktrees.get(hit.candidate_id))
+        else {
+            return;
+        };
+
+        self.workspace
+            .update(cx, |workspace, cx| {
+                let mut task_context = task_context(workspace, Some(worktree.abs_path.clone()), cx);
+                task_context.task_variables.insert(
+                    VariableName::WorktreeRoot,
+                    worktree.abs_path.to_string_lossy().into_owned(),
+                );
+                for (variable, value) in &self.variable_overrides {
+                    task_context
+                        .task_variables
+                        .insert(variable.clone(), value.clone());
+                }
+                task_context.args_override.append_args = self.inline_args.clone();
+                schedule_task(
+                    workspace,
+                    &self.task,
+                    task_context,
+                    omit_history_entry || self.omit_history,
+                    cx,
+                );
+            })
+            .ok();
+        cx.emit(DismissEvent);
+    }
+
+    fn dismissed(&mut self, cx: &mut ViewContext<Picker<Self>>) {
+        cx.emit(DismissEvent);
+    }
+
+    fn render_match(
+        &self,
+        ix: usize,
+        selected: bool,
+        cx: &mut ViewContext<Picker<Self>>,
+    ) -> Option<Self::ListItem> {
+        let hit = self.matches.get(ix)?;
+        let worktree = self.worktrees.get(hit.candidate_id)?;
+        let highlighted_name = HighlightedText {
+            text: hit.string.clone(),
+            highlight_positions: hit.positions.clone(),
+            char_count: hit.string.chars().count(),
+        };
+        Some(
+            ListItem::new(SharedString::from(format!("task-worktree-{ix}")))
+                .inset(true)
+                .spacing(ListItemSpacing::Sparse)
+                .selected(selected)
+                .end_slot(
+                    Label::new(worktree.abs_path.to_string_lossy().into_owned())
+                        .color(Color::Muted),
+                )
+                .child(highlighted_name.render(cx)),
+        )
+    }
+}
+
+/// Asks to choose the worktree to spawn the task in, for the cases when [`crate::task_cwd`] cannot decide on one.
+/// The chosen worktree is used as the cwd and `$ZED_WORKTREE_ROOT` of the task,
+/// the `variable_overrides` and `inline_args` typed into the tasks modal are applied on top of its context.
+pub(crate) fn schedule_task_in_chosen_worktree(
+    workspace: &mut Workspace,
+    task: Arc<dyn Task>,
+    omit_history: bool,
+    variable_overrides: Vec<(VariableName, String)>,
+    inline_args: Vec<String>,
+    cx: &mut ViewContext<Workspace>,
+) {
+    let worktrees = task_worktrees(workspace.project().read(cx), cx)
+        .into_iter()
+        .map(|worktree| {
+            let worktree = worktree.read(cx);
+            WorktreeCandidate {
+                name: SharedString::from(worktree.root_name().to_owned()),
+                abs_path: worktree.abs_path().to_path_buf(),
+            }
+        })
+        .collect();
+    let workspace_handle = workspace.weak_handle();
+    workspace.toggle_modal(cx, |cx| {
+        WorktreePickerModal::new(
+            task,
+            omit_history,
+            variable_overrides,
+            inline_args,
+            worktrees,
+            workspace_handle,
+            cx,
+        )
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use gpui::{TestAppContext, VisualTestContext};
+    use project::{FakeFs, Project};
+    use serde_json::json;
+
+    use super::*;
+
+    #[gpui::test]
+    async fn test_worktree_is_chosen_for_ambiguous_cwd(cx: &mut TestAppContext) {
+        crate::tests::init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[{ "label": "list", "command": "ls", "args": ["$ZED_WORKTREE_ROOT"] }]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        fs.insert_tree("/other", json!({ "b.ts": "b" })).await;
+        let project = Project::test(fs, ["/dir".as_ref(), "/other".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        let spawned_tasks = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let spawned_tasks = spawned_tasks.clone();
+            cx.subscribe(&workspace, move |_, event, _| {
+                if let workspace::Event::SpawnTask(spawn_in_terminal) = event {
+                    spawned_tasks.borrow_mut().push(spawn_in_terminal.clone());
+                }
+            })
+            .detach();
+        });
+        cx.run_until_parked();
+
+        cx.dispatch_action(crate::modal::Spawn {
+            task_name: Some("list".to_string()),
+            omit_history: false,
+            split: None,
+        });
+        cx.run_until_parked();
+        assert!(
+            spawned_tasks.borrow().is_empty(),
+            "Task should not be spawned before the worktree is chosen"
+        );
+        let picker = workspace.update(cx, |workspace, cx| {
+            workspace
+                .active_modal::<WorktreePickerModal>(cx)
+                .expect(
+                    "Worktree picker should be shown for multiple worktrees and no active entry",
+                )
+                .read(cx)
+                .picker
+                .clone()
+        });
+        assert_eq!(worktree_names(&picker, cx), vec!["dir", "other"]);
+
+        picker.update(cx, |picker, cx| {
+            picker.delegate.set_selected_index(1, cx);
+            picker.delegate.confirm(false, cx);
+        });
+        cx.run_until_parked();
+        let spawned_tasks = spawned_tasks.borrow();
+        assert_eq!(spawned_tasks.len(), 1);
+        assert_eq!(spawned_tasks[0].cwd, Some(PathBuf::from("/other")));
+        assert_eq!(
+            spawned_tasks[0]
+                .env
+                .get("ZED_WORKTREE_ROOT")
+                .map(String::as_str),
+            Some("/other"),
+            "Chosen worktree should be used as the worktree root"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_worktree_is_chosen_for_reevaluated_rerun(cx: &mut TestAppContext) {
+        crate::tests::init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[{ "label": "list", "command": "ls" }]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        fs.insert_tree("/other", json!({ "b.ts": "b" })).await;
+        let project = Project::test(fs, ["/dir".as_ref(), "/other".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        let spawned_cwds = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let spawned_cwds = spawned_cwds.clone();
+            cx.subscribe(&workspace, move |_, event, _| {
+                if let workspace::Event::SpawnTask(spawn_in_terminal) = event {
+                    spawned_cwds
+                        .borrow_mut()
+                        .push(spawn_in_terminal.cwd.clone());
+                }
+            })
+            .detach();
+        });
+        cx.run_until_parked();
+
+        cx.dispatch_action(crate::modal::Spawn {
+            task_name: Some("list".to_string()),
+            omit_history: false,
+            split: None,
+        });
+        cx.run_until_parked();
+        let picker = |cx: &mut VisualTestContext| {
+            workspace.update(cx, |workspace, cx| {
+                workspace
+                    .active_modal::<WorktreePickerModal>(cx)
+                    .expect("Worktree picker should be shown for the ambiguous cwd")
+                    .read(cx)
+                    .picker
+                    .clone()
+            })
+        };
+        picker(cx).update(cx, |picker, cx| picker.delegate.confirm(false, cx));
+        cx.run_until_parked();
+        assert_eq!(*spawned_cwds.borrow(), vec![Some(PathBuf::from("/dir"))]);
+
+        cx.dispatch_action(crate::modal::Rerun {
+            reevaluate_context: true,
+        });
+        cx.run_until_parked();
+        assert_eq!(
+            spawned_cwds.borrow().len(),
+            1,
+            "Rerun with the reevaluated context should wait for the worktree to be chosen"
+        );
+        picker(cx).update(cx, |picker, cx| {
+            picker.delegate.set_selected_index(1, cx);
+            picker.delegate.confirm(false, cx);
+        });
+        cx.run_until_parked();
+        assert_eq!(
+            *spawned_cwds.borrow(),
+            vec![Some(PathBuf::from("/dir")), Some(PathBuf::from("/other"))]
+        );
+    }
+
+    #[gpui::test]
+    async fn test_modal_overrides_apply_in_chosen_worktree(cx: &mut TestAppContext) {
+        crate::tests::init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".zed": {
+                    "tasks.json": r#"[{ "label": "list", "command": "ls", "args": ["${ZED_TARGET}"] }]"#,
+                },
+                "a.ts": "a",
+            }),
+        )
+        .await;
+        fs.insert_tree("/other", json!({ "b.ts": "b" })).await;
+        let project = Project::test(fs, ["/dir".as_ref(), "/other".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        let spawned_tasks = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let spawned_tasks = spawned_tasks.clone();
+            cx.subscribe(&workspace, move |_, event, _| {
+                if let workspace::Event::SpawnTask(spawn_in_terminal) = event {
+                    spawned_tasks.borrow_mut().push(spawn_in_terminal.clone());
+                }
+            })
+            .detach();
+        });
+        cx.run_until_parked();
+
+        cx.dispatch_action(crate::modal::Spawn::default());
+        let tasks_picker = workspace.update(cx, |workspace, cx| {
+            workspace
+                .active_modal::<crate::modal::TasksModal>(cx)
+                .unwrap()
+                .read(cx)
+                .picker
+                .clone()
+        });
+        cx.simulate_input("list TARGET=src -- -la");
+        tasks_picker.update(cx, |picker, cx| picker.delegate.confirm(false, cx));
+        cx.run_until_parked();
+        let picker = workspace.update(cx, |workspace, cx| {
+            workspace
+                .active_modal::<WorktreePickerModal>(cx)
+                .expect("Worktree picker should be shown for the ambiguous cwd")
+                .read(cx)
+                .picker
+                .clone()
+        });
+        picker.update(cx, |picker, cx| {
+            picker.delegate.set_selected_index(1, cx);
+            picker.delegate.confirm(false, cx);
+        });
+        cx.run_until_parked();
+
+        let spawned_tasks = spawned_tasks.borrow();
+        assert_eq!(spawned_tasks.len(), 1);
+        assert_eq!(spawned_tasks[0].cwd, Some(PathBuf::from("/other")));
+        assert_eq!(
+            spawned_tasks[0].args,
+            vec!["${ZED_TARGET}", "-la"],
+            "Inline args typed into the tasks modal should be appended in the chosen worktree"
+        );
+        assert_eq!(
+            spawned_tasks[0].env.get("ZED_TARGET").map(String::as_str),
+            Some("src"),
+            "Variables overridden in the tasks modal should reach the task spawned in the chosen worktree"
+        );
+    }
+
+    fn worktree_names(
+        picker: &View<Picker<WorktreePickerDelegate>>,
+        cx: &mut VisualTestContext,
+    ) -> Vec<String> {
+        picker.update(cx, |picker, _| {
+            picker
+                .delegate
+                .matches
+                .iter()
+                .map(|hit| hit.string.clone())
+                .collect()
+        })
+    }
+}
@@ -118,6 +118,11 @@ impl PtyProcessInfo {
         Some(info)
     }
 
+    /// Kills the foreground process of the pseudo-terminal, returns whether the kill signal was sent
+    pub fn kill_current_process(&mut self) -> bool {
+        self.refresh().map_or(false, |process| process.kill())
+    }
+
     /// Updates the cached process info, returns whether the Zed-relevant info has changed
     pub fn has_changed(&mut self) -> bool {
         let current = self.load();
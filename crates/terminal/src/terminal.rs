@@ -13,12 +13,14 @@ use alacritty_terminal::{
     selection::{Selection, SelectionRange, SelectionType},
     sync::FairMutex,
     term::{
-        cell::Cell,
+        cell::{Cell, Flags},
         search::{Match, RegexIter, RegexSearch},
         Config, RenderableCursor, TermMode,
     },
     tty::{self, setup_env},
-    vte::ansi::{ClearMode, Handler, NamedPrivateMode, PrivateMode, Rgb},
+    vte::ansi::{
+        ClearMode, Handler, NamedPrivateMode, PrivateMode, Processor, Rgb, StdSyncHandler,
+    },
     Term,
 };
 use anyhow::{bail, Result};
@@ -39,7 +41,11 @@ use pty_info::PtyProcessInfo;
 use serde::{Deserialize, Serialize};
 use settings::Settings;
 use smol::channel::{Receiver, Sender};
-use task::{static_source::RevealStrategy, TaskId};
+use task::{
+    problem_matcher::{Problem, ProblemParser},
+    static_source::RevealStrategy,
+    TaskId,
+};
 use terminal_settings::{AlternateScroll, Shell, TerminalBlink, TerminalSettings};
 use theme::{ActiveTheme, Theme};
 use util::truncate_and_trailoff;
@@ -48,9 +54,9 @@ use std::{
     cmp::{self, min},
     fmt::Display,
     ops::{Deref, Index, RangeInclusive},
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::Arc,
-    time::Duration,
+    time::{Duration, Instant},
 };
 use thiserror::Error;
 
@@ -92,6 +98,12 @@ pub enum Event {
     SelectionsChanged,
     NewNavigationTarget(Option<MaybeNavigationTarget>),
     Open(MaybeNavigationTarget),
+    /// The task of the terminal exited with an error code, successful if it is zero.
+    TaskCompleted {
+        exit_code: i32,
+    },
+    /// Problems found in the output of the task, since the previous event.
+    TaskProblems(Vec<Problem>),
 }
 
 #[derive(Clone, Debug)]
@@ -103,6 +115,56 @@ pub struct PathLikeTarget {
     pub terminal_dir: Option<PathBuf>,
 }
 
+/// A location in a file, printed into the task output as `path:row` or `path:row:column`, e.g. by compilers and test runners.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TaskOutputLocation {
+    pub path: PathBuf,
+    pub row: u32,
+    pub column: Option<u32>,
+}
+
+impl TaskOutputLocation {
+    /// Parses a word of the task output as a location, resolving the relative path against the task's cwd.
+    pub fn parse(word: &str, task_cwd: Option<&Path>) -> Option<Self> {
+        let word = word
+            .trim_start_matches(&['(', '"', '\''][..])
+            .trim_end_matches(&[')', '"', '\'', ',', '.', ':'][..]);
+        let (rest, last) = word.rsplit_once(':')?;
+        let last = last.parse::<u32>().ok()?;
+        let (path, row, column) = match rest
+            .rsplit_once(':')
+            .and_then(|(path, row)| Some((path, row.parse::<u32>().ok()?)))
+        {
+            Some((path, row)) => (path, row, Some(last)),
+            None => (rest, last, None),
+        };
+        if path.is_empty()
+            || path.contains("://")
+            || path.chars().all(|c| c.is_ascii_digit())
+            || row == 0
+        {
+            return None;
+        }
+
+        let path = Path::new(path);
+        let path = match task_cwd {
+            Some(task_cwd) if path.is_relative() => task_cwd.join(path),
+            _ => path.to_path_buf(),
+        };
+        Some(Self { path, row, column })
+    }
+}
+
+impl Display for TaskOutputLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.path.display(), self.row)?;
+        if let Some(column) = self.column {
+            write!(f, ":{column}")?;
+        }
+        Ok(())
+    }
+}
+
 /// A string inside terminal, potentially useful as a URI that can be opened.
 #[derive(Clone, Debug)]
 pub enum MaybeNavigationTarget {
@@ -289,6 +351,10 @@ impl Display for TerminalError {
 pub struct SpawnTask {
     pub id: TaskId,
     pub label: String,
+    pub group: Option<String>,
+    pub singleton_group: Option<String>,
+    pub show_summary: bool,
+    pub problem_parser: Option<ProblemParser>,
     pub command: String,
     pub args: Vec<String>,
     pub env: HashMap<String, String>,
@@ -595,10 +661,43 @@ pub struct Terminal {
 pub struct TaskState {
     pub id: TaskId,
     pub label: String,
+    pub group: Option<String>,
+    /// A group of the tasks, only one of which may run at a time.
+    pub singleton_group: Option<String>,
+    pub reveal: RevealStrategy,
+    /// A directory the task was spawned in, to resolve the relative locations in its output against.
+    pub cwd: Option<PathBuf>,
     pub status: TaskStatus,
+    /// Whether to print a summary line with the duration and the exit status into the terminal, after the task finishes.
+    pub show_summary: bool,
+    pub started_at: Instant,
+    pub finished_at: Option<Instant>,
+    /// A parser of the problems in the output, fed with the complete lines of it while the task runs.
+    pub problem_parser: Option<ProblemParser>,
+    /// How many lines of the output, counted from the top of the scrollback, the problem parser has been fed with.
+    pub scanned_lines: usize,
     pub completion_rx: Receiver<()>,
 }
 
+impl TaskState {
+    /// A line to print into the terminal after the task finishes, e.g. `✓ Done in 3.2s (exit 0)`.
+    fn summary(&self, exit_code: i32) -> String {
+        let duration = self
+            .finished_at
+            .unwrap_or_else(Instant::now)
+            .saturating_duration_since(self.started_at);
+        let outcome = match self.status {
+            TaskStatus::Completed { success: true } => "✓ Done",
+            TaskStatus::Completed { success: false } => "✗ Failed",
+            TaskStatus::Running | TaskStatus::Unknown => "Stopped",
+        };
+        format!(
+            "{outcome} in {:.1}s (exit {exit_code})",
+            duration.as_secs_f64()
+        )
+    }
+}
+
 /// A status of the current terminal tab's task.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TaskStatus {
@@ -666,6 +765,7 @@ impl Terminal {
             }
             AlacTermEvent::Wakeup => {
                 cx.emit(Event::Wakeup);
+                self.scan_task_problems(false, cx);
 
                 if self.pty_info.has_changed() {
                     cx.emit(Event::TitleChanged);
@@ -676,9 +776,19 @@ impl Terminal {
                     .push_back(InternalEvent::ColorRequest(*idx, fun_ptr.clone()));
             }
             AlacTermEvent::ChildExit(error_code) => {
+                self.scan_task_problems(true, cx);
                 if let Some(task) = &mut self.task {
                     task.status.register_task_exit(*error_code);
+                    task.finished_at = Some(Instant::now());
+                    if task.show_summary {
+                        let summary = task.summary(*error_code);
+                        self.write_output(summary.as_bytes());
+                        cx.emit(Event::Wakeup);
+                    }
                     self.completion_tx.try_send(()).ok();
+                    cx.emit(Event::TaskCompleted {
+                        exit_code: *error_code,
+                    });
                 }
             }
         }
@@ -866,10 +976,9 @@ impl Terminal {
                             let target = if is_url {
                                 MaybeNavigationTarget::Url(maybe_url_or_path)
                             } else {
-                                MaybeNavigationTarget::PathLike(PathLikeTarget {
-                                    maybe_path: maybe_url_or_path,
-                                    terminal_dir: self.get_cwd(),
-                                })
+                                MaybeNavigationTarget::PathLike(
+                                    self.path_like_target(maybe_url_or_path, cx),
+                                )
                             };
                             cx.emit(Event::Open(target));
                         } else {
@@ -921,14 +1030,31 @@ impl Terminal {
         let navigation_target = if is_url {
             MaybeNavigationTarget::Url(word)
         } else {
-            MaybeNavigationTarget::PathLike(PathLikeTarget {
-                maybe_path: word,
-                terminal_dir: self.get_cwd(),
-            })
+            MaybeNavigationTarget::PathLike(self.path_like_target(word, cx))
         };
         cx.emit(Event::NewNavigationTarget(Some(navigation_target)));
     }
 
+    /// For the task terminals, turns the `path:row:column` locations into the ones resolved against the task's cwd,
+    /// as the process printing them may have changed its cwd since.
+    fn path_like_target(&self, maybe_path: String, cx: &AppContext) -> PathLikeTarget {
+        if TerminalSettings::get_global(cx).task_output_links {
+            if let Some(task) = &self.task {
+                if let Some(location) = TaskOutputLocation::parse(&maybe_path, task.cwd.as_deref())
+                {
+                    return PathLikeTarget {
+                        maybe_path: location.to_string(),
+                        terminal_dir: task.cwd.clone(),
+                    };
+                }
+            }
+        }
+        PathLikeTarget {
+            maybe_path,
+            terminal_dir: self.get_cwd(),
+        }
+    }
+
     fn next_link_id(&mut self) -> usize {
         let res = self.next_link_id;
         self.next_link_id = self.next_link_id.wrapping_add(1);
@@ -1002,6 +1128,66 @@ impl Terminal {
         self.pty_tx.notify(input);
     }
 
+    /// Feeds the lines of the output printed since the previous scan into the problem parser of the task, reporting the problems found.
+    /// The line with the cursor may be incomplete, so it's only scanned once the task finishes.
+    fn scan_task_problems(&mut self, task_finished: bool, cx: &mut ModelContext<Self>) {
+        let Some(task) = &mut self.task else {
+            return;
+        };
+        let Some(parser) = &mut task.problem_parser else {
+            return;
+        };
+        let term = self.term.lock();
+        let grid = term.grid();
+        let history_size = grid.history_size();
+        let cursor_line = history_size + grid.cursor.point.line.0.max(0) as usize;
+        // The lines above the cursor may have been cleared since the previous scan.
+        if task.scanned_lines > cursor_line + 1 {
+            task.scanned_lines = cursor_line;
+        }
+        let mut scan_end = cursor_line + usize::from(task_finished);
+        let to_line = |ix: usize| Line(ix as i32 - history_size as i32);
+        // A line that wraps into the next one is not complete either.
+        while scan_end > task.scanned_lines
+            && grid[to_line(scan_end - 1)][grid.last_column()]
+                .flags
+                .contains(Flags::WRAPLINE)
+        {
+            scan_end -= 1;
+        }
+        let problems = if scan_end > task.scanned_lines {
+            let output = term.bounds_to_string(
+                AlacPoint::new(to_line(task.scanned_lines), Column(0)),
+                AlacPoint::new(to_line(scan_end - 1), grid.last_column()),
+            );
+            task.scanned_lines = scan_end;
+            output
+                .lines()
+                .filter_map(|line| parser.parse_line(line))
+                .collect::<Vec<_>>()
+        } else {
+            Vec::new()
+        };
+        drop(term);
+        if !problems.is_empty() {
+            cx.emit(Event::TaskProblems(problems));
+        }
+    }
+
+    /// Prints the text into the terminal on its own line, as if it was the output of the process.
+    fn write_output(&mut self, output: &[u8]) {
+        let mut term = self.term.lock();
+        let mut processor = Processor::<StdSyncHandler>::new();
+        let line_start: &[u8] = if term.grid().cursor.point.column.0 == 0 {
+            b""
+        } else {
+            b"\r\n"
+        };
+        for byte in line_start.iter().chain(output).chain(b"\r\n") {
+            processor.advance(&mut *term, *byte);
+        }
+    }
+
     pub fn input(&mut self, input: String) {
         self.events
             .push_back(InternalEvent::Scroll(AlacScroll::Bottom));
@@ -1409,6 +1595,15 @@ impl Terminal {
         self.task.as_ref()
     }
 
+    /// Kills the process of the task in this terminal, if it's still running.
+    pub fn kill_active_task(&mut self) {
+        if let Some(task) = self.task() {
+            if task.status == TaskStatus::Running {
+                self.pty_info.kill_current_process();
+            }
+        }
+    }
+
     pub fn wait_for_completed_task(&self, cx: &mut AppContext) -> Task<()> {
         if let Some(task) = self.task() {
             if task.status == TaskStatus::Running {
@@ -1570,10 +1765,48 @@ mod tests {
     use gpui::{point, size, Pixels};
     use rand::{distributions::Alphanumeric, rngs::ThreadRng, thread_rng, Rng};
 
+    use std::path::{Path, PathBuf};
+
     use crate::{
-        content_index_for_mouse, rgb_for_index, IndexedCell, TerminalContent, TerminalSize,
+        content_index_for_mouse, rgb_for_index, IndexedCell, TaskOutputLocation, TerminalContent,
+        TerminalSize,
     };
 
+    #[test]
+    fn test_task_output_location() {
+        let task_cwd = Some(Path::new("/project"));
+        assert_eq!(
+            TaskOutputLocation::parse("src/main.rs:10:5", task_cwd),
+            Some(TaskOutputLocation {
+                path: PathBuf::from("/project/src/main.rs"),
+                row: 10,
+                column: Some(5),
+            }),
+            "Relative location should be resolved against the task cwd"
+        );
+        assert_eq!(
+            TaskOutputLocation::parse("(/tmp/lib.rs:3):", task_cwd),
+            Some(TaskOutputLocation {
+                path: PathBuf::from("/tmp/lib.rs"),
+                row: 3,
+                column: None,
+            })
+        );
+        assert_eq!(
+            TaskOutputLocation::parse("src/main.rs:10:5", task_cwd)
+                .unwrap()
+                .to_string(),
+            "/project/src/main.rs:10:5"
+        );
+        for not_a_location in ["src/main.rs", "12:30", "http://localhost:8080", "main.rs:x"] {
+            assert_eq!(
+                TaskOutputLocation::parse(not_a_location, task_cwd),
+                None,
+                "{not_a_location} should not be parsed as a location"
+            );
+        }
+    }
+
     #[test]
     fn test_rgb_for_index() {
         // Test every possible value in the color cube.
@@ -42,6 +42,7 @@ pub struct TerminalSettings {
     pub detect_venv: VenvSettings,
     pub max_scroll_history_lines: Option<usize>,
     pub toolbar: Toolbar,
+    pub task_output_links: bool,
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
@@ -163,6 +164,11 @@ pub struct TerminalSettingsContent {
     pub max_scroll_history_lines: Option<usize>,
     /// Toolbar related settings
     pub toolbar: Option<ToolbarContent>,
+    /// Whether to resolve the `path:line:column` locations printed by the tasks against the task's working directory,
+    /// when they are opened from the task terminal. Turn it off to treat them as any other path instead.
+    ///
+    /// Default: true
+    pub task_output_links: Option<bool>,
 }
 
 impl settings::Settings for TerminalSettings {
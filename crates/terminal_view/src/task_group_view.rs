@@ -0,0 +1,247 @@
+use std::any::TypeId;
+
+use gpui::{
+    AnyElement, AnyView, AppContext, EventEmitter, FocusHandle, FocusableView, Subscription, View,
+};
+use terminal::TaskStatus;
+use ui::{prelude::*, Tab, TabBar, TabPosition};
+use workspace::{
+    item::{Item, ItemEvent},
+    searchable::SearchableItemHandle,
+};
+
+use crate::{task_status_icon, TerminalView};
+
+/// A terminal tab of a group of tasks, that shows the terminals of the tasks as its sub-tabs, one at a time.
+/// Closed once the terminal of its last task is closed.
+pub struct TaskGroupView {
+    group: String,
+    terminals: Vec<View<TerminalView>>,
+    active_terminal: usize,
+    _subscriptions: Vec<Subscription>,
+}
+
+impl TaskGroupView {
+    pub fn new(
+        group: String,
+        terminal_view: View<TerminalView>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let mut task_group_view = Self {
+            group,
+            terminals: Vec::new(),
+            active_terminal: 0,
+            _subscriptions: Vec::new(),
+        };
+        task_group_view.add_terminal(terminal_view, cx);
+        task_group_view
+    }
+
+    /// The name of the group, shown as the title of the tab.
+    pub fn group(&self) -> &str {
+        &self.group
+    }
+
+    /// The terminals of the group's tasks, in the order of their sub-tabs.
+    pub fn terminals(&self) -> &[View<TerminalView>] {
+        &self.terminals
+    }
+
+    pub fn active_terminal(&self) -> &View<TerminalView> {
+        &self.terminals[self.active_terminal]
+    }
+
+    /// Adds the terminal as the last sub-tab of the group, activating it.
+    pub fn add_terminal(&mut self, terminal_view: View<TerminalView>, cx: &mut ViewContext<Self>) {
+        self._subscriptions.extend([
+            cx.observe(&terminal_view, |_, _, cx| cx.notify()),
+            cx.subscribe(
+                &terminal_view,
+                |task_group_view, terminal_view, event: &ItemEvent, cx| match event {
+                    ItemEvent::CloseItem => task_group_view.remove_terminal(&terminal_view, cx),
+                    ItemEvent::UpdateTab => cx.emit(ItemEvent::UpdateTab),
+                    event => {
+                        if task_group_view.active_terminal() == &terminal_view {
+                            cx.emit(*event);
+                        }
+                    }
+                },
+            ),
+        ]);
+        self.terminals.push(terminal_view);
+        self.activate_terminal_at(self.terminals.len() - 1, cx);
+    }
+
+    /// Activates the sub-tab of the terminal, if it is one of the group's.
+    pub fn activate_terminal(
+        &mut self,
+        terminal_view: &View<TerminalView>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        if let Some(ix) = self
+            .terminals
+            .iter()
+            .position(|terminal| terminal == terminal_view)
+        {
+            self.activate_terminal_at(ix, cx);
+        }
+    }
+
+    fn activate_terminal_at(&mut self, ix: usize, cx: &mut ViewContext<Self>) {
+        let focused = self
+            .terminals
+            .get(self.active_terminal)
+            .is_some_and(|terminal| terminal.focus_handle(cx).contains_focused(cx));
+        self.active_terminal = ix;
+        if focused {
+            cx.focus_view(&self.terminals[ix]);
+        }
+        cx.emit(ItemEvent::UpdateTab);
+        cx.notify();
+    }
+
+    fn remove_terminal(&mut self, terminal_view: &View<TerminalView>, cx: &mut ViewContext<Self>) {
+        let Some(ix) = self
+            .terminals
+            .iter()
+            .position(|terminal| terminal == terminal_view)
+        else {
+            return;
+        };
+        if self.terminals.len() == 1 {
+            cx.emit(ItemEvent::CloseItem);
+            return;
+        }
+        self.terminals.remove(ix);
+        if self.active_terminal > ix || self.active_terminal == self.terminals.len() {
+            self.active_terminal -= 1;
+        }
+        cx.emit(ItemEvent::UpdateTab);
+        cx.notify();
+    }
+
+    /// The status shown for the whole group: running while any of its tasks runs, and failed if any of them failed.
+    fn status(&self, cx: &AppContext) -> Option<TaskStatus> {
+        let statuses = self
+            .terminals
+            .iter()
+            .filter_map(|terminal_view| {
+                let terminal = terminal_view.read(cx).terminal().read(cx);
+                terminal.task().map(|task| task.status)
+            })
+            .collect::<Vec<_>>();
+        [
+            TaskStatus::Running,
+            TaskStatus::Completed { success: false },
+            TaskStatus::Unknown,
+            TaskStatus::Completed { success: true },
+        ]
+        .into_iter()
+        .find(|status| statuses.contains(status))
+    }
+}
+
+impl EventEmitter<ItemEvent> for TaskGroupView {}
+
+impl FocusableView for TaskGroupView {
+    fn focus_handle(&self, cx: &AppContext) -> FocusHandle {
+        self.active_terminal().focus_handle(cx)
+    }
+}
+
+impl Render for TaskGroupView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let last_ix = self.terminals.len() - 1;
+        let tabs = self
+            .terminals
+            .iter()
+            .enumerate()
+            .map(|(ix, terminal_view)| {
+                let terminal = terminal_view.read(cx).terminal().read(cx);
+                let selected = ix == self.active_terminal;
+                Tab::new(ix)
+                    .position(if ix == 0 {
+                        TabPosition::First
+                    } else if ix == last_ix {
+                        TabPosition::Last
+                    } else {
+                        TabPosition::Middle(ix.cmp(&self.active_terminal))
+                    })
+                    .selected(selected)
+                    .on_click(cx.listener(move |task_group_view, _, cx| {
+                        task_group_view.activate_terminal_at(ix, cx)
+                    }))
+                    .start_slot(Icon::new(task_status_icon(
+                        terminal.task().map(|task| task.status),
+                    )))
+                    .child(Label::new(terminal.title(true)).color(if selected {
+                        Color::Default
+                    } else {
+                        Color::Muted
+                    }))
+            })
+            .collect::<Vec<_>>();
+        v_flex()
+            .size_full()
+            .child(TabBar::new("task_group_tabs").children(tabs))
+            .child(div().flex_1().child(self.active_terminal().clone()))
+    }
+}
+
+impl Item for TaskGroupView {
+    type Event = ItemEvent;
+
+    fn tab_tooltip_text(&self, _: &AppContext) -> Option<SharedString> {
+        Some(self.group.clone().into())
+    }
+
+    fn tab_content(
+        &self,
+        _detail: Option<usize>,
+        selected: bool,
+        cx: &WindowContext,
+    ) -> AnyElement {
+        h_flex()
+            .gap_2()
+            .child(Icon::new(task_status_icon(self.status(cx))))
+            .child(Label::new(self.group.clone()).color(if selected {
+                Color::Default
+            } else {
+                Color::Muted
+            }))
+            .into_any()
+    }
+
+    fn telemetry_event_text(&self) -> Option<&'static str> {
+        None
+    }
+
+    fn to_item_events(event: &Self::Event, mut f: impl FnMut(ItemEvent)) {
+        f(*event)
+    }
+
+    fn is_dirty(&self, cx: &AppContext) -> bool {
+        self.terminals
+            .iter()
+            .any(|terminal_view| terminal_view.read(cx).is_dirty(cx))
+    }
+
+    fn act_as_type<'a>(
+        &'a self,
+        type_id: TypeId,
+        self_handle: &'a View<Self>,
+        _: &'a AppContext,
+    ) -> Option<AnyView> {
+        if type_id == TypeId::of::<Self>() {
+            Some(self_handle.clone().into())
+        } else if type_id == TypeId::of::<TerminalView>() {
+            Some(self.active_terminal().clone().into())
+        } else {
+            None
+        }
+    }
+
+    fn as_searchable(&self, _: &View<Self>) -> Option<Box<dyn SearchableItemHandle>> {
+        Some(Box::new(self.active_terminal().clone()))
+    }
+}
@@ -1,6 +1,6 @@
 use std::{ops::ControlFlow, path::PathBuf, sync::Arc};
 
-use crate::TerminalView;
+use crate::{task_group_view::TaskGroupView, TerminalView};
 use collections::{HashMap, HashSet};
 use db::kvp::KEY_VALUE_STORE;
 use futures::future::join_all;
@@ -14,7 +14,11 @@ use project::{Fs, ProjectEntryId};
 use search::{buffer_search::DivRegistrar, BufferSearchBar};
 use serde::{Deserialize, Serialize};
 use settings::Settings;
-use task::{static_source::RevealStrategy, SpawnInTerminal, TaskId};
+use task::{
+    problem_matcher::ProblemParser,
+    static_source::{RevealStrategy, TaskDockPosition},
+    SpawnInTerminal, TaskId, TaskSplitDirection,
+};
 use terminal::{
     terminal_settings::{Shell, TerminalDockPosition, TerminalSettings},
     SpawnTask,
@@ -26,7 +30,7 @@ use workspace::{
     item::Item,
     pane,
     ui::IconName,
-    DraggedTab, NewTerminal, Pane, Workspace,
+    DraggedTab, NewTerminal, Pane, SplitDirection, Workspace,
 };
 
 use anyhow::Result;
@@ -58,6 +62,8 @@ pub struct TerminalPanel {
     pending_terminals_to_add: usize,
     _subscriptions: Vec<Subscription>,
     deferred_tasks: HashMap<TaskId, Task<()>>,
+    /// A dock requested by the last task spawned, overriding the one from the settings.
+    task_dock: Option<DockPosition>,
 }
 
 impl TerminalPanel {
@@ -114,7 +120,9 @@ impl TerminalPanel {
                         tab.pane.read(cx).item_for_index(tab.ix)
                     };
                     if let Some(item) = item {
-                        if item.downcast::<TerminalView>().is_some() {
+                        if item.downcast::<TerminalView>().is_some()
+                            || item.downcast::<TaskGroupView>().is_some()
+                        {
                             return ControlFlow::Continue(());
                         } else if let Some(project_path) = item.project_path(cx) {
                             if let Some(entry_path) = workspace
@@ -168,6 +176,7 @@ impl TerminalPanel {
             height: None,
             pending_terminals_to_add: 0,
             deferred_tasks: HashMap::default(),
+            task_dock: None,
             _subscriptions: subscriptions,
         };
         this
@@ -228,7 +237,12 @@ impl TerminalPanel {
                                 terminal_panel.spawn_task(spawn_in_terminal, cx);
                             };
                         },
-                    ))
+                    ));
+                    let queued_task_spawns =
+                        workspace.update(cx, |workspace, _| workspace.take_queued_task_spawns());
+                    for spawn_in_terminal in queued_task_spawns {
+                        panel.spawn_task(&spawn_in_terminal, cx);
+                    }
                 })
                 .ok();
         }
@@ -300,10 +314,19 @@ impl TerminalPanel {
         let mut spawn_task = SpawnTask {
             id: spawn_in_terminal.id.clone(),
             label: spawn_in_terminal.label.clone(),
+            group: spawn_in_terminal.group.clone(),
+            singleton_group: spawn_in_terminal.singleton_group.clone(),
+            show_summary: spawn_in_terminal.show_summary,
+            problem_parser: spawn_in_terminal
+                .problem_matcher
+                .as_ref()
+                .and_then(|matcher| {
+                    ProblemParser::new(matcher, spawn_in_terminal.problem_root()).log_err()
+                }),
             command: spawn_in_terminal.command.clone(),
             args: spawn_in_terminal.args.clone(),
             env: spawn_in_terminal.env.clone(),
-            reveal: spawn_in_terminal.reveal,
+            reveal: spawn_in_terminal.reveal.unwrap_or_default(),
         };
         // Set up shell args unconditionally, as tasks are always spawned inside of a shell.
         let Some((shell, mut user_args)) = (match TerminalSettings::get_global(cx).shell.clone() {
@@ -324,19 +347,31 @@ impl TerminalPanel {
         user_args.extend(["-i".to_owned(), "-c".to_owned(), command]);
         spawn_task.args = user_args;
         let reveal = spawn_task.reveal;
+        self.set_task_dock(spawn_in_terminal.dock, cx);
 
         let working_directory = spawn_in_terminal.cwd.clone();
         let allow_concurrent_runs = spawn_in_terminal.allow_concurrent_runs;
         let use_new_terminal = spawn_in_terminal.use_new_terminal;
+        if spawn_in_terminal.cancel_previous {
+            self.kill_task_runs(&spawn_in_terminal.id, cx);
+        }
+        if let Some(singleton_group) = &spawn_in_terminal.singleton_group {
+            self.kill_singleton_group_runs(singleton_group, cx);
+        }
+        if let Some(split) = spawn_in_terminal.split {
+            self.spawn_in_split(spawn_task, working_directory, split, cx);
+            return;
+        }
 
+        let terminal_group = spawn_in_terminal.terminal_group.clone();
         if allow_concurrent_runs && use_new_terminal {
-            self.spawn_in_new_terminal(spawn_task, working_directory, cx);
+            self.spawn_in_new_terminal(spawn_task, working_directory, terminal_group, cx);
             return;
         }
 
         let terminals_for_task = self.terminals_for_task(&spawn_in_terminal.id, cx);
         if terminals_for_task.is_empty() {
-            self.spawn_in_new_terminal(spawn_task, working_directory, cx);
+            self.spawn_in_new_terminal(spawn_task, working_directory, terminal_group, cx);
             return;
         }
         let (existing_item_index, existing_terminal) = terminals_for_task
@@ -356,6 +391,7 @@ impl TerminalPanel {
                 cx,
             );
         } else {
+            let terminal_to_reveal = existing_terminal.clone();
             self.deferred_tasks.insert(
                 spawn_in_terminal.id.clone(),
                 cx.spawn(|terminal_panel, mut cx| async move {
@@ -366,6 +402,7 @@ impl TerminalPanel {
                                 terminal_panel.spawn_in_new_terminal(
                                     spawn_task,
                                     working_directory,
+                                    terminal_group,
                                     cx,
                                 );
                             } else {
@@ -384,7 +421,7 @@ impl TerminalPanel {
 
             match reveal {
                 RevealStrategy::Always => {
-                    self.activate_terminal_view(existing_item_index, cx);
+                    self.activate_terminal_view(existing_item_index, &terminal_to_reveal, true, cx);
                     let task_workspace = self.workspace.clone();
                     cx.spawn(|_, mut cx| async move {
                         task_workspace
@@ -393,19 +430,43 @@ impl TerminalPanel {
                     })
                     .detach();
                 }
+                RevealStrategy::NoFocus => {
+                    self.activate_terminal_view(
+                        existing_item_index,
+                        &terminal_to_reveal,
+                        false,
+                        cx,
+                    );
+                    self.open_without_focus(cx);
+                }
                 RevealStrategy::Never => {}
             }
         }
     }
 
+    /// Moves the panel into the dock the task asks for, or back into the one from the settings, if the task has no preference.
+    fn set_task_dock(&mut self, dock: Option<TaskDockPosition>, cx: &mut ViewContext<Self>) {
+        let task_dock = dock.map(|dock| match dock {
+            TaskDockPosition::Left => DockPosition::Left,
+            TaskDockPosition::Bottom => DockPosition::Bottom,
+            TaskDockPosition::Right => DockPosition::Right,
+        });
+        if self.task_dock != task_dock {
+            self.task_dock = task_dock;
+            cx.emit(PanelEvent::ChangePosition);
+        }
+    }
+
+    /// Spawns the task in a new terminal tab, or in a new sub-tab of the tab of its terminal group, if it has one.
     fn spawn_in_new_terminal(
         &mut self,
         spawn_task: SpawnTask,
         working_directory: Option<PathBuf>,
+        terminal_group: Option<String>,
         cx: &mut ViewContext<Self>,
     ) {
         let reveal = spawn_task.reveal;
-        self.add_terminal(working_directory, Some(spawn_task), cx);
+        self.add_terminal_to_group(working_directory, Some(spawn_task), terminal_group, cx);
         match reveal {
             RevealStrategy::Always => {
                 let task_workspace = self.workspace.clone();
@@ -416,10 +477,51 @@ impl TerminalPanel {
                 })
                 .detach();
             }
+            RevealStrategy::NoFocus => self.open_without_focus(cx),
             RevealStrategy::Never => {}
         }
     }
 
+    /// Spawns the task in a new terminal, opened in a split of the active center pane instead of the panel.
+    fn spawn_in_split(
+        &mut self,
+        spawn_task: SpawnTask,
+        working_directory: Option<PathBuf>,
+        split: TaskSplitDirection,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let split_direction = match split {
+            TaskSplitDirection::Right => SplitDirection::Right,
+            TaskSplitDirection::Down => SplitDirection::Down,
+        };
+        let workspace = self.workspace.clone();
+        cx.spawn(|_, mut cx| async move {
+            workspace.update(&mut cx, |workspace, cx| {
+                let working_directory = working_directory.or_else(|| {
+                    let working_directory_strategy =
+                        TerminalSettings::get_global(cx).working_directory.clone();
+                    crate::get_working_directory(workspace, cx, working_directory_strategy)
+                });
+                let window = cx.window_handle();
+                let terminal = workspace.project().update(cx, |project, cx| {
+                    project.create_terminal(working_directory, Some(spawn_task), window, cx)
+                })?;
+                let terminal_view = Box::new(cx.new_view(|cx| {
+                    TerminalView::new(
+                        terminal,
+                        workspace.weak_handle(),
+                        workspace.database_id(),
+                        cx,
+                    )
+                }));
+                workspace.split_item(split_direction, terminal_view, cx);
+                anyhow::Ok(())
+            })??;
+            anyhow::Ok(())
+        })
+        .detach_and_log_err(cx);
+    }
+
     ///Create a new Terminal in the current working directory or the user's home directory
     fn new_terminal(
         workspace: &mut Workspace,
@@ -433,16 +535,59 @@ impl TerminalPanel {
         this.update(cx, |this, cx| this.add_terminal(None, None, cx))
     }
 
+    /// Kills the processes of the task in all of its terminals, dropping its deferred spawn, if any.
+    fn kill_task_runs(&mut self, id: &TaskId, cx: &mut ViewContext<Self>) {
+        self.deferred_tasks.remove(id);
+        for (_, terminal_view) in self.terminals_for_task(id, cx) {
+            terminal_view.update(cx, |terminal_view, cx| {
+                terminal_view
+                    .terminal()
+                    .update(cx, |terminal, _| terminal.kill_active_task())
+            });
+        }
+    }
+
+    /// Kills the runs of all tasks of the singleton group, to spawn another task of the group instead.
+    fn kill_singleton_group_runs(&mut self, singleton_group: &str, cx: &mut ViewContext<Self>) {
+        let task_ids = self
+            .terminal_views(cx)
+            .into_iter()
+            .filter_map(|(_, terminal_view)| {
+                let task_state = terminal_view.read(cx).terminal().read(cx).task()?;
+                (task_state.singleton_group.as_deref() == Some(singleton_group))
+                    .then(|| task_state.id.clone())
+            })
+            .collect::<HashSet<_>>();
+        for task_id in task_ids {
+            self.kill_task_runs(&task_id, cx);
+        }
+    }
+
+    /// The terminals of the panel with the indices of their tabs: the terminals of the task groups come with the indices of the groups' tabs.
+    fn terminal_views(&self, cx: &AppContext) -> Vec<(usize, View<TerminalView>)> {
+        self.pane
+            .read(cx)
+            .items()
+            .enumerate()
+            .flat_map(|(index, item)| {
+                let terminal_views = match item.downcast::<TaskGroupView>() {
+                    Some(task_group_view) => task_group_view.read(cx).terminals().to_vec(),
+                    None => item.act_as::<TerminalView>(cx).into_iter().collect(),
+                };
+                terminal_views
+                    .into_iter()
+                    .map(move |terminal_view| (index, terminal_view))
+            })
+            .collect()
+    }
+
     fn terminals_for_task(
         &self,
         id: &TaskId,
         cx: &mut AppContext,
     ) -> Vec<(usize, View<TerminalView>)> {
-        self.pane
-            .read(cx)
-            .items()
-            .enumerate()
-            .filter_map(|(index, item)| Some((index, item.act_as::<TerminalView>(cx)?)))
+        self.terminal_views(cx)
+            .into_iter()
             .filter_map(|(index, terminal_view)| {
                 let task_state = terminal_view.read(cx).terminal().read(cx).task()?;
                 if &task_state.id == id {
@@ -454,9 +599,24 @@ impl TerminalPanel {
             .collect()
     }
 
-    fn activate_terminal_view(&self, item_index: usize, cx: &mut WindowContext) {
+    /// Activates the tab of the terminal, and its sub-tab, if the terminal is one of a task group's.
+    fn activate_terminal_view(
+        &self,
+        item_index: usize,
+        terminal_view: &View<TerminalView>,
+        focus: bool,
+        cx: &mut WindowContext,
+    ) {
         self.pane.update(cx, |pane, cx| {
-            pane.activate_item(item_index, true, true, cx)
+            if let Some(task_group_view) = pane
+                .item_for_index(item_index)
+                .and_then(|item| item.downcast::<TaskGroupView>())
+            {
+                task_group_view.update(cx, |task_group_view, cx| {
+                    task_group_view.activate_terminal(terminal_view, cx)
+                });
+            }
+            pane.activate_item(item_index, focus, focus, cx)
         })
     }
 
@@ -465,6 +625,17 @@ impl TerminalPanel {
         working_directory: Option<PathBuf>,
         spawn_task: Option<SpawnTask>,
         cx: &mut ViewContext<Self>,
+    ) {
+        self.add_terminal_to_group(working_directory, spawn_task, None, cx);
+    }
+
+    /// Adds a new terminal, as a new tab, or as a new sub-tab of the tab of the terminal group given.
+    fn add_terminal_to_group(
+        &mut self,
+        working_directory: Option<PathBuf>,
+        spawn_task: Option<SpawnTask>,
+        terminal_group: Option<String>,
+        cx: &mut ViewContext<Self>,
     ) {
         let workspace = self.workspace.clone();
         self.pending_terminals_to_add += 1;
@@ -485,17 +656,22 @@ impl TerminalPanel {
                         .create_terminal(working_directory, spawn_task, window, cx)
                         .log_err()
                 }) {
-                    let terminal = Box::new(cx.new_view(|cx| {
+                    let terminal = cx.new_view(|cx| {
                         TerminalView::new(
                             terminal,
                             workspace.weak_handle(),
                             workspace.database_id(),
                             cx,
                         )
-                    }));
+                    });
                     pane.update(cx, |pane, cx| {
                         let focus = pane.has_focus(cx);
-                        pane.add_item(terminal, true, focus, None, cx);
+                        match terminal_group {
+                            Some(terminal_group) => {
+                                add_to_task_group(pane, terminal_group, terminal, focus, cx)
+                            }
+                            None => pane.add_item(Box::new(terminal), true, focus, None, cx),
+                        }
                     });
                 }
             })?;
@@ -577,7 +753,7 @@ impl TerminalPanel {
 
         match reveal {
             RevealStrategy::Always => {
-                self.activate_terminal_view(terminal_item_index, cx);
+                self.activate_terminal_view(terminal_item_index, &terminal_to_replace, true, cx);
                 let task_workspace = self.workspace.clone();
                 cx.spawn(|_, mut cx| async move {
                     task_workspace
@@ -586,11 +762,26 @@ impl TerminalPanel {
                 })
                 .detach();
             }
+            RevealStrategy::NoFocus => {
+                self.activate_terminal_view(terminal_item_index, &terminal_to_replace, false, cx);
+                self.open_without_focus(cx);
+            }
             RevealStrategy::Never => {}
         }
 
         Some(())
     }
+    /// Opens the panel's dock, if it's closed, keeping the focus where it was.
+    fn open_without_focus(&self, cx: &mut ViewContext<Self>) {
+        let task_workspace = self.workspace.clone();
+        cx.spawn(|_, mut cx| async move {
+            task_workspace
+                .update(&mut cx, |workspace, cx| workspace.open_panel::<Self>(cx))
+                .ok()
+        })
+        .detach();
+    }
+
     pub fn pane(&self) -> &View<Pane> {
         &self.pane
     }
@@ -612,6 +803,33 @@ async fn wait_for_terminals_tasks(
     let _: Vec<()> = join_all(pending_tasks).await;
 }
 
+/// Adds the terminal as a new sub-tab of the tab of the task group, opening a new tab for the group, if there's none.
+fn add_to_task_group(
+    pane: &mut Pane,
+    terminal_group: String,
+    terminal_view: View<TerminalView>,
+    focus: bool,
+    cx: &mut ViewContext<'_, Pane>,
+) {
+    let existing_group = pane.items().enumerate().find_map(|(index, item)| {
+        let task_group_view = item.downcast::<TaskGroupView>()?;
+        (task_group_view.read(cx).group() == terminal_group).then_some((index, task_group_view))
+    });
+    match existing_group {
+        Some((index, task_group_view)) => {
+            task_group_view.update(cx, |task_group_view, cx| {
+                task_group_view.add_terminal(terminal_view, cx)
+            });
+            pane.activate_item(index, true, focus, cx);
+        }
+        None => {
+            let task_group_view =
+                cx.new_view(|cx| TaskGroupView::new(terminal_group, terminal_view, cx));
+            pane.add_item(Box::new(task_group_view), true, focus, None, cx);
+        }
+    }
+}
+
 fn add_paths_to_terminal(pane: &mut Pane, paths: &[PathBuf], cx: &mut ViewContext<'_, Pane>) {
     if let Some(terminal_view) = pane
         .active_item()
@@ -656,6 +874,9 @@ impl FocusableView for TerminalPanel {
 
 impl Panel for TerminalPanel {
     fn position(&self, cx: &WindowContext) -> DockPosition {
+        if let Some(task_dock) = self.task_dock {
+            return task_dock;
+        }
         match TerminalSettings::get_global(cx).dock {
             TerminalDockPosition::Left => DockPosition::Left,
             TerminalDockPosition::Bottom => DockPosition::Bottom,
@@ -668,6 +889,7 @@ impl Panel for TerminalPanel {
     }
 
     fn set_position(&mut self, position: DockPosition, cx: &mut ViewContext<Self>) {
+        self.task_dock = None;
         settings::update_settings_file::<TerminalSettings>(self.fs.clone(), cx, move |settings| {
             let dock = match position {
                 DockPosition::Left => TerminalDockPosition::Left,
@@ -744,3 +966,409 @@ struct SerializedTerminalPanel {
     width: Option<Pixels>,
     height: Option<Pixels>,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use gpui::{Model, TestAppContext, VisualTestContext};
+    use project::Project;
+    use serde_json::json;
+    use settings::SettingsStore;
+    use task::{
+        problem_matcher::{ProblemMatcher, ProblemPattern},
+        static_source::ShowStrategy,
+    };
+    use terminal::{TaskStatus, Terminal};
+    use workspace::AppState;
+
+    use super::*;
+
+    #[gpui::test]
+    async fn test_cancel_previous_run(cx: &mut TestAppContext) {
+        let app_state = init_test(cx);
+        let project = Project::test(app_state.fs.clone(), [], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        let terminal_panel = workspace.update(cx, |workspace, cx| {
+            cx.new_view(|cx| TerminalPanel::new(workspace, cx))
+        });
+
+        let spawn_in_terminal = SpawnInTerminal {
+            cancel_previous: true,
+            ..sleeping_task("server")
+        };
+        terminal_panel.update(cx, |terminal_panel, cx| {
+            terminal_panel.spawn_task(&spawn_in_terminal, cx)
+        });
+        let first_run = wait_for_task_terminal(&terminal_panel, &spawn_in_terminal.id, None, cx);
+        assert_eq!(task_status(&first_run, cx), Some(TaskStatus::Running));
+
+        terminal_panel.update(cx, |terminal_panel, cx| {
+            terminal_panel.spawn_task(&spawn_in_terminal, cx)
+        });
+        let second_run =
+            wait_for_task_terminal(&terminal_panel, &spawn_in_terminal.id, Some(&first_run), cx);
+        assert_ne!(
+            task_status(&first_run, cx),
+            Some(TaskStatus::Running),
+            "Previous run should be killed before the new one is spawned"
+        );
+        assert_eq!(task_status(&second_run, cx), Some(TaskStatus::Running));
+        kill_tasks(&[&second_run], cx);
+    }
+
+    #[gpui::test]
+    async fn test_singleton_group(cx: &mut TestAppContext) {
+        let app_state = init_test(cx);
+        let project = Project::test(app_state.fs.clone(), [], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        let terminal_panel = workspace.update(cx, |workspace, cx| {
+            cx.new_view(|cx| TerminalPanel::new(workspace, cx))
+        });
+
+        let dev_server = |id: &str| SpawnInTerminal {
+            singleton_group: Some("dev server".to_owned()),
+            ..sleeping_task(id)
+        };
+        let (server_a, server_b, watcher) = (
+            dev_server("server_a"),
+            dev_server("server_b"),
+            sleeping_task("watcher"),
+        );
+        for task in [&server_a, &watcher] {
+            terminal_panel.update(cx, |terminal_panel, cx| terminal_panel.spawn_task(task, cx));
+        }
+        let server_a_run = wait_for_task_terminal(&terminal_panel, &server_a.id, None, cx);
+        let watcher_run = wait_for_task_terminal(&terminal_panel, &watcher.id, None, cx);
+        assert_eq!(task_status(&server_a_run, cx), Some(TaskStatus::Running));
+
+        terminal_panel.update(cx, |terminal_panel, cx| {
+            terminal_panel.spawn_task(&server_b, cx)
+        });
+        let server_b_run = wait_for_task_terminal(&terminal_panel, &server_b.id, None, cx);
+        assert_ne!(
+            task_status(&server_a_run, cx),
+            Some(TaskStatus::Running),
+            "Running task of the same singleton group should be killed"
+        );
+        assert_eq!(task_status(&server_b_run, cx), Some(TaskStatus::Running));
+        assert_eq!(
+            task_status(&watcher_run, cx),
+            Some(TaskStatus::Running),
+            "Tasks outside of the singleton group should keep running"
+        );
+        kill_tasks(&[&server_b_run, &watcher_run], cx);
+    }
+
+    #[gpui::test]
+    async fn test_terminal_group(cx: &mut TestAppContext) {
+        let app_state = init_test(cx);
+        let project = Project::test(app_state.fs.clone(), [], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        let terminal_panel = workspace.update(cx, |workspace, cx| {
+            cx.new_view(|cx| TerminalPanel::new(workspace, cx))
+        });
+
+        let grouped_task = |id: &str| SpawnInTerminal {
+            terminal_group: Some("dev".to_owned()),
+            ..sleeping_task(id)
+        };
+        let (backend, frontend, watcher) = (
+            grouped_task("backend"),
+            grouped_task("frontend"),
+            sleeping_task("watcher"),
+        );
+        let mut runs = Vec::new();
+        for task in [&backend, &frontend, &watcher] {
+            terminal_panel.update(cx, |terminal_panel, cx| terminal_panel.spawn_task(task, cx));
+            runs.push(wait_for_task_terminal(&terminal_panel, &task.id, None, cx));
+        }
+
+        terminal_panel.update(cx, |terminal_panel, cx| {
+            let pane = terminal_panel.pane().read(cx);
+            assert_eq!(
+                pane.items_len(),
+                2,
+                "Grouped tasks should share one tab, next to the tab of the task outside of the group"
+            );
+            let task_group_view = pane
+                .items()
+                .find_map(|item| item.downcast::<TaskGroupView>())
+                .expect("Grouped tasks should be spawned in a task group tab");
+            let task_group_view = task_group_view.read(cx);
+            assert_eq!(task_group_view.group(), "dev");
+            let task_ids = |terminal_views: &[View<TerminalView>]| {
+                terminal_views
+                    .iter()
+                    .filter_map(|terminal_view| {
+                        let terminal = terminal_view.read(cx).terminal().read(cx);
+                        terminal.task().map(|task| task.id.clone())
+                    })
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(
+                task_ids(task_group_view.terminals()),
+                vec![backend.id.clone(), frontend.id.clone()],
+                "Every grouped task should get its own sub-tab"
+            );
+            assert_eq!(
+                task_ids(std::slice::from_ref(task_group_view.active_terminal())),
+                vec![frontend.id.clone()],
+                "The sub-tab of the task spawned last should be active"
+            );
+            assert_eq!(
+                terminal_panel.terminals_for_task(&backend.id, cx).len(),
+                1,
+                "Terminals of the grouped tasks should be found as the terminals of their tasks"
+            );
+        });
+        kill_tasks(&runs.iter().collect::<Vec<_>>(), cx);
+    }
+
+    #[gpui::test]
+    async fn test_show_summary(cx: &mut TestAppContext) {
+        let app_state = init_test(cx);
+        let project = Project::test(app_state.fs.clone(), [], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        let terminal_panel = workspace.update(cx, |workspace, cx| {
+            cx.new_view(|cx| TerminalPanel::new(workspace, cx))
+        });
+
+        let spawn_in_terminal = SpawnInTerminal {
+            command: "sh".to_owned(),
+            args: vec!["-c".to_owned(), "echo building; exit 3".to_owned()],
+            show_summary: true,
+            ..sleeping_task("build")
+        };
+        terminal_panel.update(cx, |terminal_panel, cx| {
+            terminal_panel.spawn_task(&spawn_in_terminal, cx)
+        });
+        let terminal = wait_for_task_terminal(&terminal_panel, &spawn_in_terminal.id, None, cx);
+        for _ in 0..500 {
+            if task_status(&terminal, cx) != Some(TaskStatus::Running) {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+            cx.executor().advance_clock(Duration::from_millis(10));
+            cx.run_until_parked();
+        }
+        assert_eq!(
+            task_status(&terminal, cx),
+            Some(TaskStatus::Completed { success: false })
+        );
+
+        let lines = terminal.update(cx, |terminal, cx| {
+            terminal.sync(cx);
+            let content = terminal.last_content();
+            content
+                .cells
+                .chunks(content.size.num_columns())
+                .map(|line| line.iter().map(|cell| cell.c).collect::<String>())
+                .map(|line| line.trim_end().to_owned())
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(lines.len(), 2, "Unexpected terminal content: {lines:?}");
+        assert_eq!(lines[0], "building");
+        assert!(
+            lines[1].starts_with("✗ Failed in ") && lines[1].ends_with("s (exit 3)"),
+            "Unexpected summary line: {}",
+            lines[1]
+        );
+    }
+
+    #[gpui::test]
+    async fn test_problem_matcher(cx: &mut TestAppContext) {
+        let app_state = init_test(cx);
+        app_state
+            .fs
+            .as_fake()
+            .insert_tree(
+                "/dir",
+                json!({ "a.rs": "fn main() {\n    let x = 1;\n}\n" }),
+            )
+            .await;
+        let project = Project::test(app_state.fs.clone(), ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        let terminal_panel = workspace.update(cx, |workspace, cx| {
+            cx.new_view(|cx| TerminalPanel::new(workspace, cx))
+        });
+
+        let check_task = |output: &str| SpawnInTerminal {
+            command: "sh".to_owned(),
+            args: vec!["-c".to_owned(), format!("printf '{output}'; exit 1")],
+            env: HashMap::from_iter([("ZED_WORKTREE_ROOT".to_owned(), "/dir".to_owned())]),
+            problem_matcher: Some(ProblemMatcher::Custom(ProblemPattern {
+                regexp: r"^(\S+):(\d+):(\d+): (\w+): (.*)$".to_owned(),
+                file: 1,
+                line: 2,
+                column: 3,
+                message: 5,
+                severity: Some(4),
+            })),
+            ..sleeping_task("check")
+        };
+        let run_to_completion = |spawn_in_terminal: &SpawnInTerminal,
+                                 previous_terminal: Option<&Model<Terminal>>,
+                                 cx: &mut VisualTestContext| {
+            terminal_panel.update(cx, |terminal_panel, cx| {
+                terminal_panel.spawn_task(spawn_in_terminal, cx)
+            });
+            let terminal = wait_for_task_terminal(
+                &terminal_panel,
+                &spawn_in_terminal.id,
+                previous_terminal,
+                cx,
+            );
+            for _ in 0..500 {
+                if task_status(&terminal, cx) != Some(TaskStatus::Running) {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+                cx.executor().advance_clock(Duration::from_millis(10));
+                cx.run_until_parked();
+            }
+            terminal
+        };
+        let diagnostic_summary = |cx: &mut VisualTestContext| {
+            project.update(cx, |project, cx| {
+                let summary = project.diagnostic_summary(false, cx);
+                (summary.error_count, summary.warning_count)
+            })
+        };
+
+        let first_run = run_to_completion(
+            &check_task(
+                "a.rs:2:9: warning: unused variable\\n/outside/b.rs:1:1: error: not in the project\\n",
+            ),
+            None,
+            cx,
+        );
+        assert_eq!(
+            diagnostic_summary(cx),
+            (0, 1),
+            "Problems of the project files should be reported, relative to the worktree root"
+        );
+
+        run_to_completion(&check_task("nothing to report\\n"), Some(&first_run), cx);
+        assert_eq!(
+            diagnostic_summary(cx),
+            (0, 0),
+            "Problems of the previous run should be cleared on rerun"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_spawn_task_in_split(cx: &mut TestAppContext) {
+        let app_state = init_test(cx);
+        let project = Project::test(app_state.fs.clone(), [], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        let terminal_panel = workspace.update(cx, |workspace, cx| {
+            cx.new_view(|cx| TerminalPanel::new(workspace, cx))
+        });
+
+        let spawn_in_terminal = SpawnInTerminal {
+            split: Some(TaskSplitDirection::Right),
+            ..sleeping_task("watch")
+        };
+        terminal_panel.update(cx, |terminal_panel, cx| {
+            terminal_panel.spawn_task(&spawn_in_terminal, cx)
+        });
+        cx.run_until_parked();
+        let terminal = workspace.update(cx, |workspace, cx| {
+            assert_eq!(
+                workspace.panes().len(),
+                2,
+                "Active pane should be split for the task"
+            );
+            let terminal = workspace
+                .active_pane()
+                .read(cx)
+                .active_item()
+                .and_then(|item| item.act_as::<TerminalView>(cx))
+                .map(|terminal_view| terminal_view.read(cx).terminal().clone())
+                .expect("Task terminal should be opened in the new pane");
+            assert_eq!(
+                terminal.read(cx).task().map(|task| task.id.clone()),
+                Some(spawn_in_terminal.id.clone()),
+                "Task terminal should be opened in the new pane"
+            );
+            terminal
+        });
+        terminal_panel.update(cx, |terminal_panel, cx| {
+            assert!(
+                terminal_panel
+                    .terminals_for_task(&spawn_in_terminal.id, cx)
+                    .is_empty(),
+                "Task spawned in a split should not be added to the terminal panel"
+            );
+        });
+        kill_tasks(&[&terminal], cx);
+    }
+
+    fn init_test(cx: &mut TestAppContext) -> Arc<AppState> {
+        cx.executor().allow_parking();
+        let app_state = cx.update(AppState::test);
+        cx.update(|cx| {
+            theme::init(theme::LoadThemes::JustBase, cx);
+            Project::init_settings(cx);
+            language::init(cx);
+            editor::init_settings(cx);
+            terminal::init(cx);
+            cx.update_global::<SettingsStore, _>(|store, cx| {
+                store.update_user_settings::<TerminalSettings>(cx, |settings| {
+                    settings.shell = Some(Shell::Program("sh".to_owned()));
+                });
+            });
+        });
+        app_state
+    }
+
+    /// A task that keeps running in its terminal, without revealing it, until it's killed with [`kill_tasks`].
+    fn sleeping_task(id: &str) -> SpawnInTerminal {
+        SpawnInTerminal {
+            id: TaskId(id.to_owned()),
+            label: id.to_owned(),
+            command: "sleep".to_owned(),
+            args: vec!["60".to_owned()],
+            reveal: Some(RevealStrategy::Never),
+            combine_output: true,
+            show: ShowStrategy::Terminal,
+            ..SpawnInTerminal::default()
+        }
+    }
+
+    /// Kills the processes of the tasks still running in the terminals, so that the tests don't leave them behind.
+    fn kill_tasks(terminals: &[&Model<Terminal>], cx: &mut VisualTestContext) {
+        for terminal in terminals {
+            terminal.update(cx, |terminal, _| terminal.kill_active_task());
+        }
+    }
+
+    fn task_status(terminal: &Model<Terminal>, cx: &VisualTestContext) -> Option<TaskStatus> {
+        cx.read(|cx| terminal.read(cx).task().map(|task| task.status))
+    }
+
+    /// Waits for the real process to be spawned in a terminal for the task, other than `previous_terminal`.
+    fn wait_for_task_terminal(
+        terminal_panel: &View<TerminalPanel>,
+        id: &TaskId,
+        previous_terminal: Option<&Model<Terminal>>,
+        cx: &mut VisualTestContext,
+    ) -> Model<Terminal> {
+        for _ in 0..500 {
+            let terminal = terminal_panel.update(cx, |terminal_panel, cx| {
+                let (_, terminal_view) = terminal_panel.terminals_for_task(id, cx).pop()?;
+                let terminal = terminal_view.read(cx).terminal().clone();
+                Some(terminal).filter(|terminal| Some(terminal) != previous_terminal)
+            });
+            if let Some(terminal) = terminal {
+                return terminal;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+            cx.executor().advance_clock(Duration::from_millis(10));
+            cx.run_until_parked();
+        }
+        panic!("No new terminal was spawned for task {id:?}");
+    }
+}
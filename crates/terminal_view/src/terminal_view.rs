@@ -1,4 +1,5 @@
 mod persistence;
+pub mod task_group_view;
 pub mod terminal_element;
 pub mod terminal_panel;
 
@@ -589,6 +590,7 @@ fn subscribe_for_terminal_events(
             Event::BreadcrumbsChanged => cx.emit(ItemEvent::UpdateBreadcrumbs),
             Event::CloseTerminal => cx.emit(ItemEvent::CloseItem),
             Event::SelectionsChanged => cx.emit(SearchEvent::ActiveMatchChanged),
+            Event::TaskCompleted { .. } => cx.emit(ItemEvent::UpdateTab),
         });
     vec![terminal_subscription, terminal_events_subscription]
 }
@@ -776,6 +778,17 @@ impl Render for TerminalView {
     }
 }
 
+/// An icon of the terminal tab with a task of the status given, or with no task.
+fn task_status_icon(status: Option<TaskStatus>) -> IconName {
+    match status {
+        Some(TaskStatus::Unknown) => IconName::ExclamationTriangle,
+        Some(TaskStatus::Running) => IconName::Play,
+        Some(TaskStatus::Completed { success: true }) => IconName::Check,
+        Some(TaskStatus::Completed { success: false }) => IconName::XCircle,
+        None => IconName::Terminal,
+    }
+}
+
 impl Item for TerminalView {
     type Event = ItemEvent;
 
@@ -791,20 +804,7 @@ impl Item for TerminalView {
     ) -> AnyElement {
         let terminal = self.terminal().read(cx);
         let title = terminal.title(true);
-        let icon = match terminal.task() {
-            Some(terminal_task) => match &terminal_task.status {
-                TaskStatus::Unknown => IconName::ExclamationTriangle,
-                TaskStatus::Running => IconName::Play,
-                TaskStatus::Completed { success } => {
-                    if *success {
-                        IconName::Check
-                    } else {
-                        IconName::XCircle
-                    }
-                }
-            },
-            None => IconName::Terminal,
-        };
+        let icon = task_status_icon(terminal.task().map(|terminal_task| terminal_task.status));
         h_flex()
             .gap_2()
             .child(Icon::new(icon))
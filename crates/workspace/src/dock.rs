@@ -21,6 +21,8 @@ pub enum PanelEvent {
     ZoomOut,
     Activate,
     Close,
+    /// The panel's [`Panel::position`] has changed, so it should be moved into another dock.
+    ChangePosition,
 }
 
 pub trait Panel: FocusableView + EventEmitter<PanelEvent> {
@@ -344,42 +346,7 @@ impl Dock {
             cx.observe_global::<SettingsStore>({
                 let workspace = workspace.clone();
                 let panel = panel.clone();
-
-                move |this, cx| {
-                    let new_position = panel.read(cx).position(cx);
-                    if new_position == this.position {
-                        return;
-                    }
-
-                    let Ok(new_dock) = workspace.update(cx, |workspace, cx| {
-                        if panel.is_zoomed(cx) {
-                            workspace.zoomed_position = Some(new_position);
-                        }
-                        match new_position {
-                            DockPosition::Left => &workspace.left_dock,
-                            DockPosition::Bottom => &workspace.bottom_dock,
-                            DockPosition::Right => &workspace.right_dock,
-                        }
-                        .clone()
-                    }) else {
-                        return;
-                    };
-
-                    let was_visible = this.is_open()
-                        && this.visible_panel().map_or(false, |active_panel| {
-                            active_panel.panel_id() == Entity::entity_id(&panel)
-                        });
-
-                    this.remove_panel(&panel, cx);
-
-                    new_dock.update(cx, |new_dock, cx| {
-                        new_dock.add_panel(panel.clone(), workspace.clone(), cx);
-                        if was_visible {
-                            new_dock.set_open(true, cx);
-                            new_dock.activate_panel(new_dock.panels_len() - 1, cx);
-                        }
-                    });
-                }
+                move |this, cx| this.move_panel_to_its_position(&panel, &workspace, cx)
             }),
             cx.subscribe(&panel, move |this, panel, event, cx| match event {
                 PanelEvent::ZoomIn => {
@@ -419,6 +386,9 @@ impl Dock {
                         cx.focus_view(&panel);
                     }
                 }
+                PanelEvent::ChangePosition => {
+                    this.move_panel_to_its_position(&panel, &workspace, cx)
+                }
                 PanelEvent::Close => {
                     if this
                         .visible_panel()
@@ -456,6 +426,48 @@ impl Dock {
         cx.notify()
     }
 
+    /// Moves the panel into the dock at its [`Panel::position`], if that is not this dock anymore.
+    fn move_panel_to_its_position<T: Panel>(
+        &mut self,
+        panel: &View<T>,
+        workspace: &WeakView<Workspace>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let new_position = panel.read(cx).position(cx);
+        if new_position == self.position {
+            return;
+        }
+
+        let Ok(new_dock) = workspace.update(cx, |workspace, cx| {
+            if panel.is_zoomed(cx) {
+                workspace.zoomed_position = Some(new_position);
+            }
+            match new_position {
+                DockPosition::Left => &workspace.left_dock,
+                DockPosition::Bottom => &workspace.bottom_dock,
+                DockPosition::Right => &workspace.right_dock,
+            }
+            .clone()
+        }) else {
+            return;
+        };
+
+        let was_visible = self.is_open()
+            && self.visible_panel().map_or(false, |active_panel| {
+                active_panel.panel_id() == Entity::entity_id(panel)
+            });
+
+        self.remove_panel(panel, cx);
+
+        new_dock.update(cx, |new_dock, cx| {
+            new_dock.add_panel(panel.clone(), workspace.clone(), cx);
+            if was_visible {
+                new_dock.set_open(true, cx);
+                new_dock.activate_panel(new_dock.panels_len() - 1, cx);
+            }
+        });
+    }
+
     pub fn remove_panel<T: Panel>(&mut self, panel: &View<T>, cx: &mut ViewContext<Self>) {
         if let Some(panel_ix) = self
             .panel_entries
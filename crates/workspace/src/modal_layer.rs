@@ -131,6 +131,11 @@ impl ModalLayer {
     pub fn has_active_modal(&self) -> bool {
         self.active_modal.is_some()
     }
+
+    /// The focus handle that was focused before the active modal got opened, if any.
+    pub fn previous_focus_handle(&self) -> Option<&FocusHandle> {
+        self.active_modal.as_ref()?.previous_focus_handle.as_ref()
+    }
 }
 
 impl Render for ModalLayer {
@@ -571,6 +571,7 @@ pub struct Workspace {
     _schedule_serialize: Option<Task<()>>,
     pane_history_timestamp: Arc<AtomicUsize>,
     bounds: Bounds<Pixels>,
+    queued_task_spawns: Vec<SpawnInTerminal>,
 }
 
 impl EventEmitter<Event> for Workspace {}
@@ -830,6 +831,7 @@ impl Workspace {
             workspace_actions: Default::default(),
             // This data will be incorrect, but it will be overwritten by the time it needs to be used.
             bounds: Default::default(),
+            queued_task_spawns: Vec::new(),
         }
     }
 
@@ -984,6 +986,20 @@ impl Workspace {
         &self.app_state
     }
 
+    /// Queues a task to be spawned by the terminal panel once it gets loaded.
+    /// Unlike [`Event::SpawnTask`] that is only handled by the panels loaded already, this is suitable for the new workspaces.
+    pub fn queue_task_spawn(&mut self, spawn_in_terminal: SpawnInTerminal) {
+        self.queued_task_spawns.push(spawn_in_terminal);
+    }
+
+    pub fn queued_task_spawns(&self) -> &[SpawnInTerminal] {
+        &self.queued_task_spawns
+    }
+
+    pub fn take_queued_task_spawns(&mut self) -> Vec<SpawnInTerminal> {
+        std::mem::take(&mut self.queued_task_spawns)
+    }
+
     pub fn user_store(&self) -> &Model<UserStore> {
         &self.app_state.user_store
     }
@@ -2747,6 +2763,15 @@ impl Workspace {
         self.update_window_title(cx);
     }
 
+    /// Names of the worktrees of the project, as shown in the window title, e.g. `zed, docs`.
+    pub fn worktrees_title(&self, cx: &AppContext) -> String {
+        self.project()
+            .read(cx)
+            .worktree_root_names(cx)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
     fn update_window_title(&mut self, cx: &mut WindowContext) {
         let project = self.project().read(cx);
         let mut title = String::new();
@@ -2771,12 +2796,7 @@ impl Workspace {
             }
         }
 
-        for (i, name) in project.worktree_root_names(cx).enumerate() {
-            if i > 0 {
-                title.push_str(", ");
-            }
-            title.push_str(name);
-        }
+        title.push_str(&self.worktrees_title(cx));
 
         if title.is_empty() {
             title = "empty project".to_string();
@@ -3697,6 +3717,11 @@ impl Workspace {
         self.modal_layer.read(cx).active_modal()
     }
 
+    /// The focus handle that was focused before the active modal got opened, if there's a modal.
+    pub fn focus_before_modal(&self, cx: &AppContext) -> Option<FocusHandle> {
+        self.modal_layer.read(cx).previous_focus_handle().cloned()
+    }
+
     pub fn toggle_modal<V: ModalView, B>(&mut self, cx: &mut WindowContext, build: B)
     where
         B: FnOnce(&mut ViewContext<V>) -> V,